@@ -32,62 +32,177 @@
 //! });
 //! ```
 
+use std::ffi::OsString;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::mpsc;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, mpsc};
 use std::thread;
 use std::time::Duration;
 
-use ansi_escape_sequences::strip_ansi;
 use kitty_remote_bindings::command::options::Matcher;
 use kitty_remote_bindings::command::{CommandOutput, Ls, SendText};
 use kitty_remote_bindings::model::{OsWindows, WindowId};
 use termwiz::escape::csi::KittyKeyboardFlags;
 use termwiz::input::{KeyCode, KeyCodeEncodeModes, KeyboardEncoding, Modifiers};
-use utils::window::{should_use_panel, wait_for_window};
+use utils::ansi::clean_trailing_whitespace_with;
+use utils::chunk::split_text;
+use utils::keys::kitty_key_name;
+use utils::mouse::parse_pixel_size;
+use utils::report::{StepLog, panic_message};
+use utils::window::{close_failed_launch, parse_launched_window_id, should_use_panel, verify_remote_control_with};
 
 pub mod utils;
 #[cfg(test)]
 use insta as _;
+#[cfg(feature = "apng")]
+pub use utils::animation::{AnimationRecorder, write_apng};
+pub use utils::ansi::{CleanOptions, clean_trailing_whitespace, strip_ansi};
+pub use utils::capture::{CaptureExtent, CaptureOptions, CaptureTarget, ScreenCapture};
+pub use utils::cast::{CastEvent, CastEventKind, CastRecorder};
+pub use utils::chunk::SendChunking;
+pub use utils::contrast::{ContrastIssue, assert_min_contrast, assert_min_contrast_with, contrast_ratio, find_low_contrast};
+pub use utils::deadline::{DEADLINE_ENV, Deadline};
+pub use utils::diff::{CellChange, CompareOptions, RowDiff, ScreenDiff, assert_screens_match, diff_screens, diff_screens_masked};
 pub use utils::env::require_kitty;
-pub use utils::keys::{common as keys, type_and_execute, type_string};
-pub use utils::log::{cleanup_test_log, create_test_log, read_test_log, wait_for_log_line};
+pub use utils::error::HarnessError;
+pub use utils::expect::{ScreenExpectation, expect_screen};
+pub use utils::export::{ansi_to_html, ansi_to_svg, grid_to_html, grid_to_svg};
+pub use utils::frames::{Frame, FrameTimeline, ScreenWatcher, assert_animating, assert_static, assert_static_outside, frames_to_script};
+pub use utils::golden::{GOLDEN_DIR_ENV, UPDATE_GOLDEN_ENV, golden_dir};
+pub use utils::keys::{
+	TypingProfile, common as keys, encode_kitty_key, format_key, parse_key, parse_vim_keys, send_vim_keys, type_and_execute, type_string, type_string_paced,
+};
+pub use utils::latency::{BenchStats, Latency, bench_scenario, measure_keys_latency, measure_latency, measure_text_latency};
+pub use utils::launch::{FocusPolicy, LaunchCommand, LaunchOptions};
+pub use utils::layout::{BoxRegion, BoxStyle, Table, TableRow, find_boxes, parse_table};
+pub use utils::log::{cleanup_test_log, create_test_log, read_test_log, wait_for_log_line, wait_for_log_line_with};
+pub use utils::mask::{Mask, MaskSet, mask_capture};
+pub use utils::matrix::{COMMON_SIZES, common_themes, run_at_sizes, run_with_themes};
+pub use utils::modes::{MouseReporting, TerminalModes};
 pub use utils::mouse::{
-	MouseButton, ScrollDirection, encode_mouse_drag, encode_mouse_move, encode_mouse_press, encode_mouse_release, encode_mouse_scroll, send_mouse_click,
-	send_mouse_drag, send_mouse_drag_with_steps, send_mouse_move, send_mouse_press, send_mouse_release, send_mouse_scroll,
+	ClickTiming, MouseButton, ScrollAmount, ScrollDirection, cell_center_pixels, encode_mouse_drag, encode_mouse_drag_pixels, encode_mouse_move,
+	encode_mouse_move_pixels, encode_mouse_press, encode_mouse_press_pixels, encode_mouse_release, encode_mouse_release_pixels, encode_mouse_scroll,
+	encode_mouse_scroll_repeat, send_mouse_click, send_mouse_click_cell_pixels, send_mouse_click_pixels, send_mouse_double_click, send_mouse_drag,
+	send_mouse_drag_with_steps, send_mouse_move, send_mouse_multi_click, send_mouse_press, send_mouse_release, send_mouse_scroll, send_mouse_scroll_amount,
+	send_mouse_triple_click,
 };
-pub use utils::patterns::{create_env_wrapper, create_mock_executable, parse_mock_log, wait_for_file};
-pub use utils::replay::{ReplayEvent, ReplayTiming, parse_recording, replay};
+pub use utils::normalize::{Normalizer, NormalizerChain, Replace};
+pub use utils::patterns::{create_env_wrapper, create_mock_executable, parse_mock_log, wait_for_file, wait_for_file_with};
+pub use utils::pool::{KittyPool, PooledHarness};
+pub use utils::ready::{READY_CHANNEL_ENV, ReadyChannel, wait_for_ready_event};
+pub use utils::render::{Image, ImageDiff, compare_images, render_grid};
+pub use utils::replay::{ReplayEvent, ReplayTiming, format_recording, parse_recording, replay};
+pub use utils::report::{REPORT_DIR_ENV, Step, StepAction, TestReport};
 pub use utils::resize::resize_window;
 pub use utils::screen::{
-	AnsiColor, HORIZONTAL_SEPARATOR, VERTICAL_SEPARATOR, extract_row_colors, extract_row_colors_parsed, fg_color_at_text, find_horizontal_separator_row,
-	find_separator_cols_at_row, find_separator_rows_at_col, find_vertical_separator_col,
+	AnsiColor, Cell, CellAttrs, Cursor, GraphicsPlacement, HORIZONTAL_SEPARATOR, Hyperlink, Rect, ScreenGrid, VERTICAL_SEPARATOR, attrs_at_text, display_col,
+	display_width, extract_row_colors, extract_row_colors_parsed, fg_color_at_text, find_horizontal_separator_row, find_separator_cols_at_row,
+	find_separator_rows_at_col, find_vertical_separator_col, parse_cursor, parse_graphics_placements,
 };
+#[cfg(feature = "screenshot")]
+pub use utils::screenshot::{ImageTolerance, SCREENSHOT_COMMAND_ENV, UPDATE_SCREENSHOTS_ENV, assert_screenshot_matches, read_png, write_png};
+pub use utils::script::{InputScript, STEP_TIMEOUT, ScriptStep};
+pub use utils::session::{SessionInfo, sessions};
+pub use utils::signal::Signal;
+pub use utils::snapshot::{grid_snapshot, styled_snapshot};
+pub use utils::state::{KittyState, OsWindowState, ProcessState, TabState, WindowState};
+pub use utils::style::{ColorSpec, TextStyle, UnderlineStyle, assert_no_text_with_style, assert_text_style, find_text_with_style, style_at_text};
+pub use utils::theme::{Palette, Rgb, TerminalColors, Theme};
+pub use utils::timing::{PollDelays, Timing};
 pub use utils::wait::{
-	WaitTimeout, sample_screen_rapidly, wait_for_clean_contains, wait_for_ready_marker, wait_for_screen_text, wait_for_screen_text_clean,
-	wait_for_screen_text_clean_or_timeout, wait_for_screen_text_or_timeout,
+	LogOrScreen, RowChange, ScreenChange, ScreenCheckpoint, WaitCondition, WaitTimeout, run_command, sample_frames, sample_screen_rapidly, try_wait_for,
+	try_wait_for_clean_contains, try_wait_for_row, try_wait_for_screen_text, try_wait_for_screen_text_clean, wait_all, wait_any, wait_for_cell,
+	wait_for_clean_contains, wait_for_grid, wait_for_last_row, wait_for_log_or_screen, wait_for_prompt, wait_for_ready_marker, wait_for_row,
+	wait_for_row_or_timeout, wait_for_screen_change, wait_for_screen_text, wait_for_screen_text_clean, wait_for_screen_text_clean_or_timeout,
+	wait_for_screen_text_or_timeout, wait_for_styled_text, wait_for_window_state,
 };
+pub use utils::window::{KittyWindow, RemoteControlError, verify_remote_control};
 
 /// Drive a kitty window via remote control and capture its contents.
 pub struct KittyHarness {
+	session: String,
 	socket_addr: String,
 	window_id: WindowId,
+	masks: Mutex<MaskSet>,
+	normalizer: Mutex<NormalizerChain>,
+	cleaning: Mutex<CleanOptions>,
+	chunking: Mutex<SendChunking>,
+	key_modes: Mutex<KeyCodeEncodeModes>,
+	timing: Mutex<Timing>,
+	deadline: Mutex<Option<Deadline>>,
+	recorder: Mutex<Option<CastRecorder>>,
+	steps: Mutex<StepLog>,
+	focus_blocker: Mutex<Option<WindowId>>,
+	keep_open: AtomicBool,
+	close_scope: CloseScope,
+	clipboard: bool,
+	artifact_subdir: Option<String>,
+	env: Vec<(OsString, OsString)>,
+}
+
+/// Which windows a harness closes when dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CloseScope {
+	/// Every window of the kitty instance, which then exits.
+	Instance,
+	/// Only the tab holding the harness window (used by pooled instances).
+	Tab,
+	/// Nothing; the instance is owned by someone else.
+	Nothing,
 }
 
 impl KittyHarness {
-	/// Launch a background kitty panel running the provided shell command.
-	pub fn launch(working_dir: &Path, command: &str) -> Self {
+	/// Launch a background kitty panel running the provided command.
+	///
+	/// `command` is either a shell command line (`&str`/`String`) or an argv
+	/// vector (see [`LaunchCommand`]).
+	pub fn launch(working_dir: &Path, command: impl Into<LaunchCommand>) -> Self {
+		Self::launch_with(working_dir, command, &LaunchOptions::default())
+	}
+
+	/// Like [`KittyHarness::launch`], but returns an error instead of panicking.
+	pub fn try_launch(working_dir: &Path, command: impl Into<LaunchCommand>) -> Result<Self, HarnessError> {
+		Self::try_launch_with(working_dir, command, &LaunchOptions::default())
+	}
+
+	/// Launch a background kitty panel running `command`, applying launch `options`.
+	pub fn launch_with(working_dir: &Path, command: impl Into<LaunchCommand>, options: &LaunchOptions) -> Self {
+		Self::try_launch_with(working_dir, command, options).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Launch a background kitty panel running `command` with extra environment variables.
+	///
+	/// Shorthand for [`KittyHarness::launch_with`] with [`LaunchOptions::envs`].
+	pub fn launch_with_env(working_dir: &Path, command: impl Into<LaunchCommand>, env: &[(&str, &str)]) -> Self {
+		Self::launch_with(working_dir, command, &LaunchOptions::new().envs(env.iter().copied()))
+	}
+
+	/// Like [`KittyHarness::launch_with`], but returns an error instead of panicking.
+	///
+	/// Fails if kitty cannot be spawned or exits unsuccessfully, if the
+	/// generated config cannot be written, or if remote control never
+	/// becomes usable on the session socket.
+	pub fn try_launch_with(working_dir: &Path, command: impl Into<LaunchCommand>, options: &LaunchOptions) -> Result<Self, HarnessError> {
+		let command = command.into();
 		let session = next_session_name();
-		let socket = working_dir.join(format!("{session}.sock"));
-		let socket_addr = format!("unix:{}", socket.display());
+		let (socket, socket_addr) = match options.listen_address() {
+			Some(address) => (address.strip_prefix("unix:").map(PathBuf::from), address.to_string()),
+			None => {
+				let socket = working_dir.join(format!("{session}.sock"));
+				let socket_addr = format!("unix:{}", socket.display());
+				(Some(socket), socket_addr)
+			}
+		};
+		let artifact_dir = utils::session::artifact_root().join(&session);
 
-		if socket.exists() {
-			let _ = std::fs::remove_file(&socket);
+		if let Some(socket) = socket.as_ref().filter(|socket| socket.exists()) {
+			let _ = std::fs::remove_file(socket);
 		}
 
 		// Panel requires Wayland with layer-shell protocol support
-		let use_panel = should_use_panel();
+		let use_panel = !options.forces_normal_window() && should_use_panel();
 
 		// Build environment passthrough for the launched command so it can talk back to this kitty.
 		let mut base_env = vec![("KITTY_LISTEN_ON".to_string(), socket_addr.clone())];
@@ -95,7 +210,34 @@ impl KittyHarness {
 			base_env.push(("KITTY_REMOTE_BIN".to_string(), bin));
 		}
 
-		let command_with_env = command.to_string();
+		// Config fragments are merged into an isolated config stored with the session artifacts.
+		let config_path = match options.render_config() {
+			Some(config) => {
+				std::fs::create_dir_all(&artifact_dir).map_err(|err| HarnessError::io(format!("create artifact dir {}", artifact_dir.display()), err))?;
+				let path = artifact_dir.join("kitty.conf");
+				std::fs::write(&path, config).map_err(|err| HarnessError::io(format!("write generated kitty config {}", path.display()), err))?;
+				Some(path)
+			}
+			None => None,
+		};
+
+		let mut kitty_args: Vec<OsString> = vec!["--listen-on".into(), socket_addr.clone().into(), "--class".into(), session.clone().into()];
+		if let Some(path) = &config_path {
+			kitty_args.push("--config".into());
+			kitty_args.push(path.clone().into());
+		}
+		kitty_args.extend(["-o", "allow_remote_control=yes"].map(OsString::from));
+		if options.allows_clipboard() {
+			kitty_args.extend(["-o", utils::clipboard::CLIPBOARD_CONTROL].map(OsString::from));
+		}
+		for option in options.overrides() {
+			kitty_args.push("-o".into());
+			kitty_args.push(option.into());
+		}
+		kitty_args.push("--detach".into());
+		kitty_args.extend(options.env_prefix());
+		kitty_args.extend(["bash", "--noprofile", "--norc", "-lc"].map(OsString::from));
+		kitty_args.extend(command.bash_args());
 
 		if use_panel {
 			// Try to launch as a background panel (requires Wayland layer-shell)
@@ -103,29 +245,18 @@ impl KittyHarness {
 			for (k, v) in &base_env {
 				cmd.env(k, v);
 			}
-			let status = cmd
+			// The detached kitty redirects its streams, so capturing output does not wait for it to exit.
+			let output = cmd
 				.current_dir(working_dir)
-				.args([
-					"+kitten",
-					"panel",
-					"--focus-policy=not-allowed",
-					"--edge=background",
-					"--listen-on",
-					&socket_addr,
-					"--class",
-					&session,
-					"-o",
-					"allow_remote_control=yes",
-					"--detach",
-					"bash",
-					"--noprofile",
-					"--norc",
-					"-lc",
-					&command_with_env,
-				])
-				.status()
-				.expect("kitty panel launch should run");
-			assert!(status.success(), "kitty panel should launch");
+				.args(["+kitten", "panel", "--edge=background"])
+				.arg(format!("--focus-policy={}", options.panel_focus_policy().as_str()))
+				.args(&kitty_args)
+				.stdin(Stdio::null())
+				.output()
+				.map_err(|err| HarnessError::spawn("kitty +kitten panel", err))?;
+			if !output.status.success() {
+				return Err(HarnessError::failed("kitty +kitten panel", &output));
+			}
 		} else {
 			// Use a normal window instead of a panel (e.g., WSL/X11)
 			let mut cmd = Command::new("kitty");
@@ -142,32 +273,216 @@ impl KittyHarness {
 				cmd.env(k, v);
 			}
 
-			let status = cmd
+			let output = cmd
 				.current_dir(working_dir)
-				.args([
-					"--listen-on",
-					&socket_addr,
-					"--class",
-					&session,
-					"-o",
-					"allow_remote_control=yes",
-					"--detach",
-					"bash",
-					"--noprofile",
-					"--norc",
-					"-lc",
-					&command_with_env,
-				])
-				.status()
-				.expect("kitty launch should run");
-			assert!(status.success(), "kitty window should launch");
+				.args(&kitty_args)
+				.stdin(Stdio::null())
+				.output()
+				.map_err(|err| HarnessError::spawn("kitty", err))?;
+			if !output.status.success() {
+				return Err(HarnessError::failed("kitty", &output));
+			}
 			// Give kitty a moment to create the socket
 			thread::sleep(Duration::from_millis(300));
 		}
 
-		let window_id = wait_for_window(&socket_addr);
+		let window_id = verify_remote_control_with(&socket_addr, &options.harness_timing().unwrap_or_default())
+			.inspect_err(|_| close_failed_launch(&socket_addr, &session, socket.as_deref()))?;
+
+		utils::session::register(SessionInfo {
+			name: session.clone(),
+			listen_on: socket_addr.clone(),
+			socket_path: socket,
+			log_paths: Vec::new(),
+			artifact_dir,
+			config_path,
+		});
+
+		let harness = Self::from_parts(session, socket_addr, window_id)
+			.with_clipboard(options.allows_clipboard())
+			.with_env(options.env_vars().to_vec());
+		if let Some(modes) = options.key_encode_modes() {
+			harness.set_key_modes(modes);
+		}
+		if let Some(timing) = options.harness_timing() {
+			harness.set_timing(timing);
+		}
+		if options.records() {
+			harness.try_start_recording()?;
+		}
+		Ok(harness)
+	}
+
+	fn from_parts(session: String, socket_addr: String, window_id: WindowId) -> Self {
+		Self {
+			session,
+			socket_addr,
+			window_id,
+			masks: Mutex::new(MaskSet::new()),
+			normalizer: Mutex::new(NormalizerChain::new()),
+			cleaning: Mutex::new(CleanOptions::default()),
+			chunking: Mutex::new(SendChunking::default()),
+			key_modes: Mutex::new(default_key_modes()),
+			timing: Mutex::new(Timing::default()),
+			deadline: Mutex::new(Deadline::from_env()),
+			recorder: Mutex::new(None),
+			steps: Mutex::new(StepLog::default()),
+			focus_blocker: Mutex::new(None),
+			keep_open: AtomicBool::new(false),
+			close_scope: CloseScope::Instance,
+			clipboard: false,
+			artifact_subdir: None,
+			env: Vec::new(),
+		}
+	}
+
+	pub(crate) fn with_close_scope(mut self, close_scope: CloseScope) -> Self {
+		self.close_scope = close_scope;
+		self
+	}
+
+	pub(crate) fn with_clipboard(mut self, clipboard: bool) -> Self {
+		self.clipboard = clipboard;
+		self
+	}
+
+	/// Environment variables from [`LaunchOptions::env`] applied to windows opened with [`KittyHarness::launch_window`].
+	pub(crate) fn with_env(mut self, env: Vec<(OsString, OsString)>) -> Self {
+		self.env = env;
+		self
+	}
+
+	/// Keep this harness's artifacts in `subdir` of the session artifact directory (used by pooled harnesses sharing a session).
+	pub(crate) fn with_artifact_subdir(mut self, subdir: String) -> Self {
+		self.artifact_subdir = Some(subdir);
+		self
+	}
+
+	/// Returns true if dropping this harness will leave its windows open.
+	pub(crate) fn is_kept_open(&self) -> bool {
+		self.keep_open.load(Ordering::Relaxed) || (thread::panicking() && keep_on_failure())
+	}
+
+	/// List harness sessions in `dir` whose kitty instance is still reachable.
+	///
+	/// Sessions are discovered from their `kitty-test-*.sock` sockets, so this
+	/// also finds windows left open by another process (see
+	/// [`KittyHarness::keep_open`] and `KITTY_TEST_KEEP_ON_FAILURE`).
+	pub fn list_sessions(dir: &Path) -> Vec<SessionInfo> {
+		let Ok(entries) = std::fs::read_dir(dir) else {
+			return Vec::new();
+		};
+
+		let mut sessions: Vec<SessionInfo> = entries
+			.filter_map(|entry| entry.ok())
+			.filter_map(|entry| {
+				let path = entry.path();
+				let name = path.file_name()?.to_str()?.strip_suffix(".sock")?.to_string();
+				if !name.starts_with("kitty-test-") {
+					return None;
+				}
+				let socket_addr = format!("unix:{}", path.display());
+				try_list_windows_at(&socket_addr)?;
+
+				let known = utils::session::lookup(&name);
+				let artifact_dir = utils::session::artifact_root().join(&name);
+				let config_path = Some(artifact_dir.join("kitty.conf")).filter(|p| p.exists());
+				Some(SessionInfo {
+					log_paths: known.map(|info| info.log_paths).unwrap_or_default(),
+					name,
+					listen_on: socket_addr,
+					socket_path: Some(path),
+					artifact_dir,
+					config_path,
+				})
+			})
+			.collect();
+		sessions.sort_by(|a, b| a.name.cmp(&b.name));
+		sessions
+	}
+
+	/// Reconnect to an existing session (e.g. one returned by [`KittyHarness::list_sessions`]).
+	///
+	/// The adopted harness drives the session's first window and closes it on
+	/// drop like a freshly launched one, unless [`KittyHarness::keep_open`] is called.
+	pub fn adopt(session: &SessionInfo) -> Self {
+		Self::try_adopt(session).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::adopt`], but returns an error instead of panicking.
+	pub fn try_adopt(session: &SessionInfo) -> Result<Self, HarnessError> {
+		let window_id = verify_remote_control(&session.listen_on)?;
+		if utils::session::lookup(&session.name).is_none() {
+			utils::session::register(session.clone());
+		}
+		Ok(Self::from_parts(session.name.clone(), session.listen_on.clone(), window_id))
+	}
+
+	/// Drive a kitty instance that was started outside the harness.
+	///
+	/// `address` is a kitty remote control address such as
+	/// `tcp:192.168.122.10:45678` or `unix:/run/user/1000/kitty.sock`, so a
+	/// kitty running on another machine or inside a VM can be tested from
+	/// the host. The harness drives the instance's first window and never
+	/// closes windows on drop, since it does not own the instance.
+	pub fn connect_existing(address: &str) -> Self {
+		Self::try_connect_existing(address).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::connect_existing`], but returns an error instead of panicking.
+	pub fn try_connect_existing(address: &str) -> Result<Self, HarnessError> {
+		let window_id = verify_remote_control(address)?;
+		let session = next_session_name();
+		utils::session::register(SessionInfo {
+			name: session.clone(),
+			listen_on: address.to_string(),
+			socket_path: address.strip_prefix("unix:").map(PathBuf::from),
+			log_paths: Vec::new(),
+			artifact_dir: utils::session::artifact_root().join(&session),
+			config_path: None,
+		});
+		Ok(Self::from_parts(session, address.to_string(), window_id)
+			.with_close_scope(CloseScope::Nothing)
+			.with_clipboard(true))
+	}
+
+	/// Leave the kitty windows open when this harness is dropped.
+	///
+	/// Setting `KITTY_TEST_KEEP_ON_FAILURE=1` does the same automatically when
+	/// the test panics, so the failed state can be inspected or adopted.
+	pub fn keep_open(&self) {
+		self.keep_open.store(true, Ordering::Relaxed);
+	}
+
+	/// Return the unique session name of this harness.
+	pub fn session_name(&self) -> &str {
+		&self.session
+	}
+
+	/// Return the resources (socket, logs, artifact dir) created for this harness.
+	///
+	/// Pooled harnesses share their instance's session, but each gets its own
+	/// `tab-<window id>` artifact subdirectory.
+	pub fn session_info(&self) -> SessionInfo {
+		let mut info = utils::session::lookup(&self.session).expect("harness session should be registered");
+		if let Some(subdir) = &self.artifact_subdir {
+			info.artifact_dir.push(subdir);
+		}
+		info
+	}
 
-		Self { socket_addr, window_id }
+	/// Create the artifact directory for this session if needed and return its path.
+	pub fn artifact_dir(&self) -> PathBuf {
+		let dir = self.session_info().artifact_dir;
+		std::fs::create_dir_all(&dir).expect("create artifact dir");
+		dir
+	}
+
+	/// Create a test log owned by this session (see [`create_test_log`]).
+	pub fn create_log(&self) -> PathBuf {
+		let path = create_test_log();
+		utils::session::add_log(&self.session, path.clone());
+		path
 	}
 
 	/// Return the socket address used for kitty remote control.
@@ -182,10 +497,7 @@ impl KittyHarness {
 
 	/// Best-effort list of kitty windows managed by this harness.
 	pub fn try_list_windows(&self) -> Option<OsWindows> {
-		let ls = Ls::new().to(self.socket_addr.clone());
-		let mut cmd: Command = (&ls).into();
-		let output = cmd.output().ok()?;
-		Ls::result(&output).ok()
+		try_list_windows_at(&self.socket_addr)
 	}
 
 	/// List kitty windows managed by this harness.
@@ -198,13 +510,771 @@ impl KittyHarness {
 		all_window_ids(&self.list_windows())
 	}
 
+	/// Return the full `kitty @ ls` window tree of this instance.
+	pub fn state(&self) -> KittyState {
+		self.try_state().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::state`], but returns an error instead of panicking.
+	pub fn try_state(&self) -> Result<KittyState, HarnessError> {
+		let output = self.try_remote(["ls"])?;
+		serde_json::from_slice(&output.stdout).map_err(|err| HarnessError::Parse {
+			command: "kitty @ ls".into(),
+			message: err.to_string(),
+		})
+	}
+
+	/// Return the harness window's size in cells as `(cols, rows)`, read from `kitty @ ls`.
+	///
+	/// Use it to check the effective geometry after a resize or to compute
+	/// mouse coordinates relative to the real window size.
+	pub fn size(&self) -> (u16, u16) {
+		self.size_for_window(self.window_id)
+	}
+
+	/// Like [`KittyHarness::size`], but returns an error instead of panicking.
+	pub fn try_size(&self) -> Result<(u16, u16), HarnessError> {
+		self.try_size_for_window(self.window_id)
+	}
+
+	/// Return the size of `window_id` in cells as `(cols, rows)`.
+	pub fn size_for_window(&self, window_id: WindowId) -> (u16, u16) {
+		self.try_size_for_window(window_id).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::size_for_window`], but returns an error instead of panicking.
+	pub fn try_size_for_window(&self, window_id: WindowId) -> Result<(u16, u16), HarnessError> {
+		let state = self.try_state()?;
+		let window = state.window(window_id.0).ok_or_else(|| HarnessError::Parse {
+			command: "kitty @ ls".into(),
+			message: format!("window {} not found", window_id.0),
+		})?;
+		Ok((clamp_u16(window.columns), clamp_u16(window.lines)))
+	}
+
+	/// Return the harness window's size in pixels as `(width, height)`.
+	///
+	/// kitty does not report pixel geometry over remote control, so this runs
+	/// `kitty +kitten icat --print-window-size` in a short-lived overlay.
+	pub fn pixel_size(&self) -> (u32, u32) {
+		self.try_pixel_size().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::pixel_size`], but returns an error instead of panicking.
+	pub fn try_pixel_size(&self) -> Result<(u32, u32), HarnessError> {
+		self.try_pixel_size_for_window(self.window_id)
+	}
+
+	/// Like [`KittyHarness::try_pixel_size`], for a specific window.
+	pub fn try_pixel_size_for_window(&self, window_id: WindowId) -> Result<(u32, u32), HarnessError> {
+		let output = utils::overlay::capture(self, window_id, "window-size", "kitty +kitten icat --print-window-size")?;
+		parse_pixel_size(&output).ok_or_else(|| HarnessError::Parse {
+			command: "kitty +kitten icat --print-window-size".into(),
+			message: format!("expected WIDTHxHEIGHT, got {:?}", output.trim()),
+		})
+	}
+
+	/// Return the size of one cell in pixels as `(width, height)`, used to place pixel-mode mouse events.
+	pub fn cell_size(&self) -> (u32, u32) {
+		self.try_cell_size().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::cell_size`], but returns an error instead of panicking.
+	pub fn try_cell_size(&self) -> Result<(u32, u32), HarnessError> {
+		self.try_cell_size_for_window(self.window_id)
+	}
+
+	/// Like [`KittyHarness::try_cell_size`], for a specific window.
+	pub fn try_cell_size_for_window(&self, window_id: WindowId) -> Result<(u32, u32), HarnessError> {
+		let (width, height) = self.try_pixel_size_for_window(window_id)?;
+		let (cols, rows) = self.try_size_for_window(window_id)?;
+		Ok((width / u32::from(cols.max(1)), height / u32::from(rows.max(1))))
+	}
+
+	/// Give the harness window keyboard focus via `kitty @ focus-window`.
+	///
+	/// Undoes [`KittyHarness::unfocus`]. Applications with focus reporting
+	/// (mode 1004) receive `CSI I`.
+	pub fn focus(&self) {
+		self.try_focus().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::focus`], but returns an error instead of panicking.
+	pub fn try_focus(&self) -> Result<(), HarnessError> {
+		let blocker = self.focus_blocker.lock().unwrap_or_else(|err| err.into_inner()).take();
+		if let Some(blocker) = blocker {
+			self.try_remote(["close-window", "--match", &format!("id:{}", blocker.0)])?;
+		}
+		self.try_remote(["focus-window", "--match", &format!("id:{}", self.window_id.0)]).map(|_| ())
+	}
+
+	/// Take keyboard focus away from the harness window.
+	///
+	/// kitty has no "unfocus" command, so this opens an idle overlay window on
+	/// top of the harness window and focuses it. The harness window keeps its
+	/// size and contents, and applications with focus reporting (mode 1004)
+	/// receive `CSI O`. Call [`KittyHarness::focus`] to remove the overlay.
+	pub fn unfocus(&self) {
+		self.try_unfocus().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::unfocus`], but returns an error instead of panicking.
+	pub fn try_unfocus(&self) -> Result<(), HarnessError> {
+		let mut blocker = self.focus_blocker.lock().unwrap_or_else(|err| err.into_inner());
+		if blocker.is_some() {
+			return Ok(());
+		}
+		let output = self.try_remote([
+			"launch",
+			"--type=overlay",
+			"--match",
+			&format!("id:{}", self.window_id.0),
+			"bash",
+			"--noprofile",
+			"--norc",
+			"-c",
+			"while :; do sleep 60; done",
+		])?;
+		*blocker = Some(parse_launched_window_id(&output.stdout).ok_or_else(|| HarnessError::Parse {
+			command: "kitty @ launch".into(),
+			message: format!("expected a window id, got {:?}", String::from_utf8_lossy(&output.stdout).trim()),
+		})?);
+		Ok(())
+	}
+
+	/// Returns true if kitty reports the harness window as focused.
+	pub fn is_focused(&self) -> bool {
+		self.try_is_focused().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::is_focused`], but returns an error instead of panicking.
+	pub fn try_is_focused(&self) -> Result<bool, HarnessError> {
+		Ok(self.try_state()?.window(self.window_id.0).is_some_and(|window| window.is_focused))
+	}
+
+	/// Send `signal` to the foreground process of the harness window via `kitty @ signal-child`.
+	pub fn signal_child(&self, signal: Signal) {
+		self.try_signal_child(signal).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::signal_child`], but returns an error instead of panicking.
+	pub fn try_signal_child(&self, signal: Signal) -> Result<(), HarnessError> {
+		self.try_signal_child_for_window(self.window_id, signal)
+	}
+
+	/// Like [`KittyHarness::try_signal_child`], for a specific window.
+	pub fn try_signal_child_for_window(&self, window_id: WindowId, signal: Signal) -> Result<(), HarnessError> {
+		self.try_remote(["signal-child", "--match", &format!("id:{}", window_id.0), signal.name()])
+			.map(|_| ())
+	}
+
+	/// Apply `theme` to every window of this instance at runtime via `kitty @ set-colors`.
+	pub fn set_colors(&self, theme: &Theme) {
+		self.try_set_colors(theme).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::set_colors`], but returns an error instead of panicking.
+	pub fn try_set_colors(&self, theme: &Theme) -> Result<(), HarnessError> {
+		let mut args = vec!["set-colors".to_string(), "--all".to_string(), "--configured".to_string()];
+		args.extend(theme.settings().into_iter().map(|(key, value)| format!("{key}={value}")));
+		self.try_remote(args).map(|_| ())
+	}
+
+	/// Return the colors the harness window is currently drawn with, via `kitty @ get-colors`.
+	///
+	/// Unlike a [`Theme`] applied at launch, this reflects runtime changes
+	/// made by [`KittyHarness::set_colors`] or by the application (OSC 4/10/11).
+	pub fn get_colors(&self) -> TerminalColors {
+		self.try_get_colors().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::get_colors`], but returns an error instead of panicking.
+	pub fn try_get_colors(&self) -> Result<TerminalColors, HarnessError> {
+		let output = self.try_remote(["get-colors".to_string(), "--match".to_string(), format!("id:{}", self.window_id.0)])?;
+		TerminalColors::parse(&String::from_utf8_lossy(&output.stdout))
+	}
+
+	/// Set the font size of every OS window of this instance to `points` via `kitty @ set-font-size`.
+	pub fn set_font_size(&self, points: f32) {
+		self.try_set_font_size(points).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::set_font_size`], but returns an error instead of panicking.
+	pub fn try_set_font_size(&self, points: f32) -> Result<(), HarnessError> {
+		self.try_remote(["set-font-size".to_string(), "--all".to_string(), points.to_string()])
+			.map(|_| ())
+	}
+
+	/// Return the title of the harness window.
+	///
+	/// Titles set by the application with OSC 0/2 escape sequences show up
+	/// here, so editors that put the current filename in the title can be
+	/// checked directly.
+	pub fn title(&self) -> String {
+		self.title_for_window(self.window_id)
+	}
+
+	/// Like [`KittyHarness::title`], but returns an error instead of panicking.
+	pub fn try_title(&self) -> Result<String, HarnessError> {
+		self.try_title_for_window(self.window_id)
+	}
+
+	/// Return the title of `window_id`.
+	pub fn title_for_window(&self, window_id: WindowId) -> String {
+		self.try_title_for_window(window_id).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::title_for_window`], but returns an error instead of panicking.
+	pub fn try_title_for_window(&self, window_id: WindowId) -> Result<String, HarnessError> {
+		let state = self.try_state()?;
+		state.window(window_id.0).map(|window| window.title.clone()).ok_or_else(|| HarnessError::Parse {
+			command: "kitty @ ls".into(),
+			message: format!("window {} not found", window_id.0),
+		})
+	}
+
+	/// Set the title of the harness window.
+	///
+	/// The title is set with `--temporary`, so the application can still
+	/// replace it with its own OSC 0/2 title afterwards.
+	pub fn set_title(&self, title: &str) {
+		self.set_title_for_window(self.window_id, title)
+	}
+
+	/// Like [`KittyHarness::set_title`], but returns an error instead of panicking.
+	pub fn try_set_title(&self, title: &str) -> Result<(), HarnessError> {
+		self.try_set_title_for_window(self.window_id, title)
+	}
+
+	/// Set the title of `window_id` (see [`KittyHarness::set_title`]).
+	pub fn set_title_for_window(&self, window_id: WindowId, title: &str) {
+		self.try_set_title_for_window(window_id, title).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::set_title_for_window`], but returns an error instead of panicking.
+	pub fn try_set_title_for_window(&self, window_id: WindowId, title: &str) -> Result<(), HarnessError> {
+		self.try_remote(["set-window-title", "--temporary", "--match", &format!("id:{}", window_id.0), title])
+			.map(|_| ())
+	}
+
+	/// Wait until the window title satisfies `predicate`, returning the matching title.
+	///
+	/// # Panics
+	///
+	/// Panics with the last observed title if `timeout` elapses first.
+	pub fn wait_for_title(&self, timeout: Duration, predicate: impl Fn(&str) -> bool) -> String {
+		self.try_wait_for_title(timeout, predicate).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::wait_for_title`], but returns an error instead of panicking.
+	///
+	/// A timeout is reported as [`HarnessError::Io`] with
+	/// [`std::io::ErrorKind::TimedOut`], naming the last observed title.
+	pub fn try_wait_for_title(&self, timeout: Duration, predicate: impl Fn(&str) -> bool) -> Result<String, HarnessError> {
+		let timeout = self.clamp_timeout(timeout);
+		let start = std::time::Instant::now();
+		let mut delays = self.timing().poll_delays();
+		loop {
+			let title = self.try_title()?;
+			if predicate(&title) {
+				return Ok(title);
+			}
+			if start.elapsed() >= timeout {
+				return Err(HarnessError::io(
+					format!("timed out after {timeout:?} waiting for window title"),
+					std::io::Error::new(std::io::ErrorKind::TimedOut, format!("last title: {title:?}")),
+				));
+			}
+			delays.sleep();
+		}
+	}
+
+	/// Replace the clipboard contents with `text`.
+	///
+	/// Runs `kitty +kitten clipboard` in a short-lived overlay (see
+	/// [`utils::clipboard`]), so the application under test can paste it.
+	/// Requires a harness launched with [`LaunchOptions::clipboard`]; this
+	/// overwrites the system clipboard.
+	pub fn set_clipboard(&self, text: &str) {
+		self.try_set_clipboard(text).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::set_clipboard`], but returns an error instead of panicking.
+	pub fn try_set_clipboard(&self, text: &str) -> Result<(), HarnessError> {
+		self.require_clipboard("set clipboard")?;
+		utils::clipboard::set_clipboard(self, text)
+	}
+
+	/// Read the clipboard contents, e.g. after the application copied text.
+	///
+	/// Requires a harness launched with [`LaunchOptions::clipboard`].
+	pub fn get_clipboard(&self) -> String {
+		self.try_get_clipboard().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::get_clipboard`], but returns an error instead of panicking.
+	pub fn try_get_clipboard(&self) -> Result<String, HarnessError> {
+		self.require_clipboard("get clipboard")?;
+		utils::clipboard::get_clipboard(self)
+	}
+
+	fn require_clipboard(&self, context: &str) -> Result<(), HarnessError> {
+		if self.clipboard {
+			return Ok(());
+		}
+		Err(HarnessError::io(
+			context,
+			std::io::Error::new(
+				std::io::ErrorKind::Unsupported,
+				"clipboard access is off; launch with LaunchOptions::clipboard()",
+			),
+		))
+	}
+
+	/// Open another window in this harness's kitty instance running `command`.
+	///
+	/// The window opens in the active tab, starts in the harness working
+	/// directory with the environment from [`LaunchOptions::env`], and is
+	/// closed with the rest of the harness on drop.
+	pub fn launch_window(&self, command: impl Into<LaunchCommand>) -> KittyWindow<'_> {
+		self.try_launch_window(command).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::launch_window`], but returns an error instead of panicking.
+	pub fn try_launch_window(&self, command: impl Into<LaunchCommand>) -> Result<KittyWindow<'_>, HarnessError> {
+		let mut args: Vec<OsString> = ["launch", "--type=window"].map(OsString::from).to_vec();
+		args.extend(utils::launch::remote_env_args(&self.env));
+		args.extend(["bash", "--noprofile", "--norc", "-lc"].map(OsString::from));
+		args.extend(command.into().bash_args());
+		let output = self.try_remote(args)?;
+		let id = parse_launched_window_id(&output.stdout).ok_or_else(|| HarnessError::Parse {
+			command: "kitty @ launch".into(),
+			message: format!("expected a window id, got {:?}", String::from_utf8_lossy(&output.stdout).trim()),
+		})?;
+		Ok(KittyWindow::new(self, id))
+	}
+
+	/// Return a handle to an existing window, e.g. one the application opened.
+	pub fn window(&self, window_id: WindowId) -> KittyWindow<'_> {
+		KittyWindow::new(self, window_id)
+	}
+
+	/// Run an arbitrary `kitty @` subcommand against this harness and parse its output.
+	///
+	/// `args` starts with the subcommand name, e.g. `&["ls"]` or
+	/// `&["set-spacing", "padding=0"]`; the `--to` address is added
+	/// automatically. JSON output is parsed, empty output becomes
+	/// [`serde_json::Value::Null`], and any other text is returned as a
+	/// [`serde_json::Value::String`].
+	pub fn remote(&self, args: &[&str]) -> Result<serde_json::Value, HarnessError> {
+		let output = self.try_remote(args)?;
+		Ok(parse_remote_output(&output.stdout))
+	}
+
+	/// Like [`KittyHarness::remote`], but targets `window_id` by inserting `--match id:<window_id>` after the subcommand.
+	pub fn remote_for_window(&self, window_id: WindowId, args: &[&str]) -> Result<serde_json::Value, HarnessError> {
+		let matcher = format!("id:{}", window_id.0);
+		let mut full: Vec<&str> = Vec::with_capacity(args.len() + 2);
+		full.extend(args.first());
+		full.extend(["--match", matcher.as_str()]);
+		full.extend(args.iter().skip(1));
+		self.remote(&full)
+	}
+
+	/// Run `kitty @ --to <socket> <args>`, returning its output if it succeeded.
+	pub(crate) fn try_remote<I, S>(&self, args: I) -> Result<std::process::Output, HarnessError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<std::ffi::OsStr>,
+	{
+		self.try_remote_with_input(args, None)
+	}
+
+	/// Run `kitty @ --to <socket> <args>`, optionally writing `input` to its stdin.
+	pub(crate) fn try_remote_with_input<I, S>(&self, args: I, input: Option<&[u8]>) -> Result<std::process::Output, HarnessError>
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<std::ffi::OsStr>,
+	{
+		let args: Vec<OsString> = args.into_iter().map(|arg| arg.as_ref().to_os_string()).collect();
+		let label = format!("kitty @ {}", args.first().map(|arg| arg.to_string_lossy()).unwrap_or_default());
+		let mut cmd = Command::new("kitty");
+		cmd.args(["@", "--to", &self.socket_addr]).args(&args);
+		let output = match input {
+			None => cmd.output(),
+			Some(input) => cmd
+				.stdin(Stdio::piped())
+				.stdout(Stdio::piped())
+				.stderr(Stdio::piped())
+				.spawn()
+				.and_then(|mut child| {
+					// Dropping stdin after the write signals EOF to kitty.
+					child.stdin.take().expect("stdin is piped").write_all(input)?;
+					child.wait_with_output()
+				}),
+		}
+		.map_err(|err| HarnessError::spawn(label.clone(), err))?;
+		if !output.status.success() {
+			return Err(HarnessError::failed(label, &output));
+		}
+		Ok(output)
+	}
+
 	/// Send raw text to a specific kitty window (e.g., escape sequences for arrows).
 	pub fn send_text_to_window(&self, window_id: WindowId, text: &str) {
-		let send = SendText::new(text.to_string()).to(self.socket_addr.clone()).matcher(Matcher::Id(window_id));
-		let mut cmd: Command = (&send).into();
-		let output = cmd.output().expect("kitty send-text should run");
-		std::thread::sleep(Duration::from_millis(20));
-		SendText::result(&output).expect("kitty send-text should succeed");
+		self.try_send_text_to_window(window_id, text).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_text_to_window`], but returns an error instead of panicking.
+	pub fn try_send_text_to_window(&self, window_id: WindowId, text: &str) -> Result<(), HarnessError> {
+		self.record_input(window_id, text);
+		let chunking = self.send_chunking();
+		for (idx, chunk) in split_text(text, chunking.max_bytes).into_iter().enumerate() {
+			if idx > 0 {
+				std::thread::sleep(chunking.delay);
+			}
+			let send = SendText::new(chunk.to_string()).to(self.socket_addr.clone()).matcher(Matcher::Id(window_id));
+			let mut cmd: Command = (&send).into();
+			let output = cmd.output().map_err(|err| HarnessError::spawn("kitty @ send-text", err))?;
+			SendText::result(&output).map_err(|_| HarnessError::failed("kitty @ send-text", &output))?;
+		}
+		std::thread::sleep(self.timing().post_send_delay);
+		Ok(())
+	}
+
+	/// Set the key encoding used by [`send_keys`], [`send_key_events`], the
+	/// [`kitty_send_keys!`] macro and replays for this harness.
+	///
+	/// Match this to the keyboard protocol the application under test
+	/// enables, e.g. `kitty.set_key_modes(kitty_key_modes(KittyKeyboardFlags::all()))`.
+	pub fn set_key_modes(&self, modes: KeyCodeEncodeModes) {
+		*self.key_modes.lock().unwrap_or_else(|err| err.into_inner()) = modes;
+	}
+
+	/// The key encoding currently used for this harness (see [`KittyHarness::set_key_modes`]).
+	pub fn key_modes(&self) -> KeyCodeEncodeModes {
+		*self.key_modes.lock().unwrap_or_else(|err| err.into_inner())
+	}
+
+	/// Configure poll intervals, backoff and the post-send delay (see [`Timing`]).
+	pub fn set_timing(&self, timing: Timing) {
+		*self.timing.lock().unwrap_or_else(|err| err.into_inner()) = timing;
+	}
+
+	/// The timing currently used by waits and sends.
+	pub fn timing(&self) -> Timing {
+		*self.timing.lock().unwrap_or_else(|err| err.into_inner())
+	}
+
+	/// Share `deadline` across all waits of this harness (see [`utils::deadline`]).
+	pub fn set_deadline(&self, deadline: Deadline) {
+		*self.deadline.lock().unwrap_or_else(|err| err.into_inner()) = Some(deadline);
+	}
+
+	/// Remove the deadline, including one picked up from [`DEADLINE_ENV`].
+	pub fn clear_deadline(&self) {
+		*self.deadline.lock().unwrap_or_else(|err| err.into_inner()) = None;
+	}
+
+	/// The deadline waits are currently clamped to, if any.
+	pub fn deadline(&self) -> Option<Deadline> {
+		*self.deadline.lock().unwrap_or_else(|err| err.into_inner())
+	}
+
+	/// Shorten a wait `timeout` so it ends no later than [`KittyHarness::deadline`].
+	pub fn clamp_timeout(&self, timeout: Duration) -> Duration {
+		self.deadline().map_or(timeout, |deadline| deadline.clamp(timeout))
+	}
+
+	/// Sample the screen every `interval` in the background and save `screen.apng` if the test fails.
+	///
+	/// See [`utils::animation`]; requires the `apng` feature.
+	#[cfg(feature = "apng")]
+	pub fn record_animation(&self, interval: Duration) -> AnimationRecorder {
+		AnimationRecorder::start(self, interval)
+	}
+
+	/// Save a pixel screenshot of the harness OS window to `path` as PNG.
+	///
+	/// See [`utils::screenshot`] for how the capture command is chosen;
+	/// requires the `screenshot` feature.
+	#[cfg(feature = "screenshot")]
+	pub fn screenshot_png(&self, path: &Path) {
+		self.try_screenshot_png(path).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::screenshot_png`], but returns an error instead of panicking.
+	#[cfg(feature = "screenshot")]
+	pub fn try_screenshot_png(&self, path: &Path) -> Result<(), HarnessError> {
+		utils::screenshot::capture(self, path)
+	}
+
+	/// Start recording inputs and screen captures of the harness window as an asciicast.
+	///
+	/// Replaces any recording in progress. If the harness is dropped while
+	/// the test panics, the recording is written to `recording.cast` in the
+	/// artifact directory.
+	pub fn start_recording(&self) {
+		self.try_start_recording().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::start_recording`], but returns an error instead of panicking.
+	pub fn try_start_recording(&self) -> Result<(), HarnessError> {
+		let (cols, rows) = self.try_size()?;
+		*self.recorder.lock().unwrap_or_else(|err| err.into_inner()) = Some(CastRecorder::new(cols, rows));
+		Ok(())
+	}
+
+	/// Stop recording and return what was recorded, if a recording was running.
+	pub fn stop_recording(&self) -> Option<CastRecorder> {
+		self.recorder.lock().unwrap_or_else(|err| err.into_inner()).take()
+	}
+
+	/// Write the recording so far to `path` as an asciicast v2 file, keeping it running.
+	///
+	/// Returns `Ok(false)` if nothing is being recorded.
+	pub fn write_cast(&self, path: &Path) -> Result<bool, HarnessError> {
+		match self.recorder.lock().unwrap_or_else(|err| err.into_inner()).as_ref() {
+			Some(recorder) => recorder.write(path).map(|()| true),
+			None => Ok(false),
+		}
+	}
+
+	/// Run `body` as a named step of the test report.
+	///
+	/// Input sent to and captures taken of the harness window while `body`
+	/// runs are grouped under the step, and the screen is captured when it
+	/// finishes. A panic inside `body` marks the step as failed, with its
+	/// message and the screen at that point, and is then resumed. Steps may
+	/// be nested; see [`KittyHarness::report`].
+	pub fn step<T>(&self, name: &str, body: impl FnOnce() -> T) -> T {
+		self.steps.lock().unwrap_or_else(|err| err.into_inner()).begin(name);
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(body));
+		let failure = result.as_ref().err().map(|payload| panic_message(payload.as_ref()));
+		// The closing capture is stored with the step, not logged as one of its actions.
+		let capture = self
+			.fetch_screen_text(self.window_id)
+			.and_then(|raw| Ok(ScreenCapture::new(raw, self.try_size_for_window(self.window_id)?)))
+			.ok();
+		self.steps.lock().unwrap_or_else(|err| err.into_inner()).end(failure, capture);
+		result.unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+	}
+
+	/// The steps finished so far, as a report for the test `name`.
+	pub fn report(&self, name: &str) -> TestReport {
+		TestReport::new(name, self.steps.lock().unwrap_or_else(|err| err.into_inner()).finished().to_vec())
+	}
+
+	/// Write the report of the steps so far as `<name>.md` and `<name>.html` and return the Markdown path.
+	///
+	/// Reports go to [`REPORT_DIR_ENV`] when set, otherwise to the
+	/// [`KittyHarness::artifact_dir`]. Screens are rendered with the window's
+	/// current colors, or kitty's defaults if they cannot be read.
+	pub fn write_report(&self, name: &str) -> PathBuf {
+		self.try_write_report(name).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::write_report`], but returns an error instead of panicking.
+	pub fn try_write_report(&self, name: &str) -> Result<PathBuf, HarnessError> {
+		let dir = std::env::var_os(REPORT_DIR_ENV).map_or_else(|| self.session_info().artifact_dir, PathBuf::from);
+		let palette = self.try_get_colors().map_or_else(|_| Palette::kitty_default(), |colors| colors.palette);
+		let (markdown, _html) = self.report(name).write_to(&dir, &palette)?;
+		Ok(markdown)
+	}
+
+	/// Log input sent to the harness window in the recording and the running step.
+	fn record_input(&self, window_id: WindowId, text: &str) {
+		if window_id != self.window_id {
+			return;
+		}
+		if let Some(recorder) = self.recorder.lock().unwrap_or_else(|err| err.into_inner()).as_mut() {
+			recorder.input(text);
+		}
+		self.steps
+			.lock()
+			.unwrap_or_else(|err| err.into_inner())
+			.action(StepAction::Input(text.to_string()));
+	}
+
+	/// Log a capture of the harness window in the recording and the running step.
+	fn record_frame(&self, window_id: WindowId, text: &str) {
+		if window_id != self.window_id {
+			return;
+		}
+		if let Some(recorder) = self.recorder.lock().unwrap_or_else(|err| err.into_inner()).as_mut() {
+			recorder.frame(text);
+		}
+		self.steps
+			.lock()
+			.unwrap_or_else(|err| err.into_inner())
+			.action(StepAction::Capture(strip_ansi(text)));
+	}
+
+	/// Configure how large payloads are split by the `send_text`, `send_bytes` and `send_file` methods.
+	pub fn set_send_chunking(&self, chunking: SendChunking) {
+		*self.chunking.lock().unwrap_or_else(|err| err.into_inner()) = chunking;
+	}
+
+	/// The chunking currently applied to large payloads.
+	pub fn send_chunking(&self) -> SendChunking {
+		*self.chunking.lock().unwrap_or_else(|err| err.into_inner())
+	}
+
+	/// Stream the contents of the file at `path` into the kitty window.
+	///
+	/// The bytes are sent unchanged via [`KittyHarness::send_bytes`], chunked
+	/// according to [`KittyHarness::send_chunking`]. Chunks are cut at fixed
+	/// byte offsets, so a chunk boundary may fall inside a UTF-8 character or
+	/// an escape sequence; use [`KittyHarness::send_text`] for text that must
+	/// arrive in whole pieces.
+	pub fn send_file(&self, path: impl AsRef<Path>) {
+		self.try_send_file(path).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_file`], but returns an error instead of panicking.
+	pub fn try_send_file(&self, path: impl AsRef<Path>) -> Result<(), HarnessError> {
+		let path = path.as_ref();
+		let bytes = std::fs::read(path).map_err(|err| HarnessError::io(format!("read {}", path.display()), err))?;
+		self.try_send_bytes(&bytes)
+	}
+
+	/// Send arbitrary bytes to the kitty window, exactly as given.
+	///
+	/// Unlike [`KittyHarness::send_text`], the payload need not be valid UTF-8,
+	/// so tests can feed invalid UTF-8, truncated escape sequences or binary
+	/// garbage to the application's input parser. The bytes are piped to
+	/// `kitty @ send-text --stdin`, which forwards them base64-encoded and does
+	/// not interpret escapes. Large payloads are chunked at fixed byte offsets
+	/// per [`KittyHarness::send_chunking`], without regard for character or
+	/// escape boundaries.
+	pub fn send_bytes(&self, bytes: &[u8]) {
+		self.try_send_bytes(bytes).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_bytes`], but returns an error instead of panicking.
+	pub fn try_send_bytes(&self, bytes: &[u8]) -> Result<(), HarnessError> {
+		self.try_send_bytes_to_window(self.window_id, bytes)
+	}
+
+	/// Send arbitrary bytes to a specific kitty window.
+	pub fn send_bytes_to_window(&self, window_id: WindowId, bytes: &[u8]) {
+		self.try_send_bytes_to_window(window_id, bytes).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_bytes_to_window`], but returns an error instead of panicking.
+	pub fn try_send_bytes_to_window(&self, window_id: WindowId, bytes: &[u8]) -> Result<(), HarnessError> {
+		self.record_input(window_id, &String::from_utf8_lossy(bytes));
+		let chunking = self.send_chunking();
+		self.try_broadcast(["--match".to_string(), format!("id:{}", window_id.0)], bytes.chunks(chunking.max_bytes))
+	}
+
+	/// Send `text` to every window in the harness window's tab, like kitty's broadcast kitten.
+	///
+	/// Helper windows opened with [`KittyHarness::launch_window`] share the
+	/// tab, so they all receive the same input. Text is delivered verbatim.
+	pub fn send_text_all(&self, text: &str) {
+		self.try_send_text_all(text).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_text_all`], but returns an error instead of panicking.
+	pub fn try_send_text_all(&self, text: &str) -> Result<(), HarnessError> {
+		self.record_input(self.window_id, text);
+		let chunking = self.send_chunking();
+		self.try_broadcast(
+			["--match-tab".to_string(), format!("window_id:{}", self.window_id.0)],
+			split_text(text, chunking.max_bytes).into_iter().map(str::as_bytes),
+		)
+	}
+
+	/// Send `text` to every window matching a kitty match expression, e.g. `"title:editor or id:3"`.
+	///
+	/// kitty evaluates the expression, so the harness cannot tell whether its
+	/// own window is among the targets and the text is not logged in the
+	/// recording or step report. Use [`KittyHarness::send_text_to_windows`]
+	/// when it should be.
+	pub fn send_text_matching(&self, matcher: &str, text: &str) {
+		self.try_send_text_matching(matcher, text).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_text_matching`], but returns an error instead of panicking.
+	pub fn try_send_text_matching(&self, matcher: &str, text: &str) -> Result<(), HarnessError> {
+		let chunking = self.send_chunking();
+		self.try_broadcast(
+			["--match".to_string(), matcher.to_string()],
+			split_text(text, chunking.max_bytes).into_iter().map(str::as_bytes),
+		)
+	}
+
+	/// Send `text` to each of `window_ids`.
+	///
+	/// The text is logged like [`KittyHarness::send_text`] when `window_ids`
+	/// includes the harness window.
+	pub fn send_text_to_windows(&self, window_ids: &[WindowId], text: &str) {
+		self.try_send_text_to_windows(window_ids, text).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_text_to_windows`], but returns an error instead of panicking.
+	pub fn try_send_text_to_windows(&self, window_ids: &[WindowId], text: &str) -> Result<(), HarnessError> {
+		if window_ids.is_empty() {
+			return Ok(());
+		}
+		if window_ids.contains(&self.window_id) {
+			self.record_input(self.window_id, text);
+		}
+		let matcher = window_ids.iter().map(|id| format!("id:{}", id.0)).collect::<Vec<_>>().join(" or ");
+		self.try_send_text_matching(&matcher, text)
+	}
+
+	/// Pipe each of `chunks` to `kitty @ send-text <target> --stdin`, pausing
+	/// between them per [`KittyHarness::send_chunking`].
+	fn try_broadcast<'a>(&self, target: [String; 2], chunks: impl IntoIterator<Item = &'a [u8]>) -> Result<(), HarnessError> {
+		let chunking = self.send_chunking();
+		for (idx, chunk) in chunks.into_iter().enumerate() {
+			if idx > 0 {
+				std::thread::sleep(chunking.delay);
+			}
+			let mut args = vec!["send-text".to_string()];
+			args.extend(target.iter().cloned());
+			args.push("--stdin".into());
+			self.try_remote_with_input(args, Some(chunk))?;
+		}
+		std::thread::sleep(self.timing().post_send_delay);
+		Ok(())
+	}
+
+	/// Send a key press through kitty's native `send-key` command.
+	///
+	/// kitty encodes the key itself, honouring whatever keyboard protocol the
+	/// application enabled, so no termwiz encoding is involved. Requires a
+	/// kitty version that supports `kitty @ send-key`.
+	pub fn send_key(&self, key: impl Into<KeyPress>) {
+		self.try_send_key(key).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_key`], but returns an error instead of panicking.
+	pub fn try_send_key(&self, key: impl Into<KeyPress>) -> Result<(), HarnessError> {
+		let key = key.into();
+		let name = kitty_key_name(key).ok_or_else(|| HarnessError::Parse {
+			command: "kitty @ send-key".into(),
+			message: format!("no kitty key name for {key:?}"),
+		})?;
+		self.try_send_key_names(&[name.as_str()])
+	}
+
+	/// Send keys by their kitty names (e.g. `"ctrl+a"`, `"shift+f1"`) through `kitty @ send-key`.
+	pub fn send_key_names(&self, names: &[&str]) {
+		self.try_send_key_names(names).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::send_key_names`], but returns an error instead of panicking.
+	pub fn try_send_key_names(&self, names: &[&str]) -> Result<(), HarnessError> {
+		names.iter().for_each(|name| self.record_input(self.window_id, &format!("<{name}>")));
+		let matcher = format!("id:{}", self.window_id.0);
+		let mut args = vec!["send-key", "--match", matcher.as_str()];
+		args.extend(names);
+		self.try_remote(args)?;
+		std::thread::sleep(self.timing().post_send_delay);
+		Ok(())
 	}
 
 	/// Send raw text to the kitty window (e.g., escape sequences for arrows).
@@ -212,48 +1282,398 @@ impl KittyHarness {
 		self.send_text_to_window(self.window_id, text)
 	}
 
+	/// Like [`KittyHarness::send_text`], but returns an error instead of panicking.
+	pub fn try_send_text(&self, text: &str) -> Result<(), HarnessError> {
+		self.try_send_text_to_window(self.window_id, text)
+	}
+
 	/// Capture the current screen contents as ANSI text with trailing whitespace trimmed.
 	pub fn screen_text_for_window(&self, window_id: WindowId) -> String {
-		let output = Command::new("kitty")
-			.args([
-				"@",
-				"--to",
-				&self.socket_addr,
-				"get-text",
-				"--match",
-				&format!("id:{}", window_id.0),
-				"--ansi",
-				"--extent",
-				"screen",
-			])
-			.output()
-			.expect("kitty get-text should run");
-		assert!(
-			output.status.success(),
-			"kitty get-text failed: stdout: {} stderr: {}",
-			String::from_utf8_lossy(&output.stdout),
-			String::from_utf8_lossy(&output.stderr)
-		);
-		let raw = String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n");
-		clean_trailing_whitespace(&raw)
+		self.try_screen_text_for_window(window_id).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::screen_text_for_window`], but returns an error instead of panicking.
+	pub fn try_screen_text_for_window(&self, window_id: WindowId) -> Result<String, HarnessError> {
+		let text = self.fetch_screen_text(window_id)?;
+		self.record_frame(window_id, &text);
+		Ok(text)
+	}
+
+	/// Run `kitty @ get-text` for `window_id` without logging the result as a frame.
+	fn fetch_screen_text(&self, window_id: WindowId) -> Result<String, HarnessError> {
+		let output = run_get_text(&self.socket_addr, window_id).map_err(|err| HarnessError::spawn("kitty @ get-text", err))?;
+		if !output.status.success() {
+			return Err(HarnessError::failed("kitty @ get-text", &output));
+		}
+		Ok(normalize_capture(&output.stdout, &self.capture_cleaning()))
+	}
+
+	/// Capture the scrollback history followed by the screen, as ANSI text.
+	///
+	/// Unlike [`KittyHarness::screen_text`], this includes lines that have
+	/// scrolled off the top. While an application is on the alternate screen
+	/// kitty reports that screen's (history-less) contents instead.
+	pub fn scrollback_text(&self) -> String {
+		self.try_scrollback_text().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::scrollback_text`], but returns an error instead of panicking.
+	pub fn try_scrollback_text(&self) -> Result<String, HarnessError> {
+		let output =
+			run_get_text_with(&self.socket_addr, self.window_id, "all", &[]).map_err(|err| HarnessError::spawn("kitty @ get-text --extent all", err))?;
+		if !output.status.success() {
+			return Err(HarnessError::failed("kitty @ get-text --extent all", &output));
+		}
+		Ok(normalize_capture(&output.stdout, &self.capture_cleaning()))
+	}
+
+	/// Scroll kitty's view of the harness window by `lines` (negative scrolls up into the scrollback).
+	pub fn scroll_lines(&self, lines: i32) {
+		self.try_scroll_window(&scroll_amount(lines, "")).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Scroll kitty's view of the harness window by `pages` (negative scrolls up).
+	pub fn scroll_pages(&self, pages: i32) {
+		self.try_scroll_window(&scroll_amount(pages, "p")).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Scroll to the top of the scrollback.
+	pub fn scroll_to_start(&self) {
+		self.try_scroll_window("start").unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Scroll back to the live screen.
+	pub fn scroll_to_end(&self) {
+		self.try_scroll_window("end").unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Run `kitty @ scroll-window` on the harness window with a raw amount such as `"30-"`, `"2p+"` or `"start"`.
+	pub fn try_scroll_window(&self, amount: &str) -> Result<(), HarnessError> {
+		self.try_remote(["scroll-window", "--match", &format!("id:{}", self.window_id.0), amount])
+			.map(|_| ())
 	}
 
 	/// Capture the current screen contents as ANSI text with trailing whitespace trimmed.
+	///
+	/// The trimming follows [`KittyHarness::set_capture_cleaning`].
 	pub fn screen_text(&self) -> String {
 		self.screen_text_for_window(self.window_id)
 	}
 
+	/// Like [`KittyHarness::screen_text`], but returns an error instead of panicking.
+	pub fn try_screen_text(&self) -> Result<String, HarnessError> {
+		self.try_screen_text_for_window(self.window_id)
+	}
+
+	/// Capture the screen of a window as a [`ScreenCapture`], with its current size.
+	pub fn capture_for_window(&self, window_id: WindowId) -> ScreenCapture {
+		self.try_capture_for_window(window_id).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::capture_for_window`], but returns an error instead of panicking.
+	pub fn try_capture_for_window(&self, window_id: WindowId) -> Result<ScreenCapture, HarnessError> {
+		let raw = self.try_screen_text_for_window(window_id)?;
+		let size = self.try_size_for_window(window_id)?;
+		Ok(ScreenCapture::new(raw, size))
+	}
+
+	/// Capture the screen of the harness window as a [`ScreenCapture`], with its current size.
+	pub fn capture(&self) -> ScreenCapture {
+		self.capture_for_window(self.window_id)
+	}
+
+	/// Like [`KittyHarness::capture`], but returns an error instead of panicking.
+	pub fn try_capture(&self) -> Result<ScreenCapture, HarnessError> {
+		self.try_capture_for_window(self.window_id)
+	}
+
+	/// Capture with explicit `kitty @ get-text` options (extent, cursor, wrap markers, styles, target window).
+	///
+	/// The output is kept as kitty prints it, without the trailing-whitespace
+	/// cleanup of [`KittyHarness::screen_text`], so cursor sequences and wrap
+	/// markers survive. The size is that of the target window.
+	pub fn capture_with(&self, options: &CaptureOptions) -> ScreenCapture {
+		self.try_capture_with(options).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::capture_with`], but returns an error instead of panicking.
+	///
+	/// Fails if no window matches a [`CaptureTarget::Match`] expression.
+	pub fn try_capture_with(&self, options: &CaptureOptions) -> Result<ScreenCapture, HarnessError> {
+		let window_id = match options.capture_target() {
+			CaptureTarget::Harness => self.window_id,
+			CaptureTarget::Window(id) => *id,
+			CaptureTarget::Match(expression) => {
+				let output = self.try_remote(["ls", "--match", expression])?;
+				let state: KittyState = serde_json::from_slice(&output.stdout).map_err(|err| HarnessError::Parse {
+					command: "kitty @ ls".into(),
+					message: err.to_string(),
+				})?;
+				let window = state.windows().next().ok_or_else(|| HarnessError::Parse {
+					command: "kitty @ ls".into(),
+					message: format!("no window matches {expression:?}"),
+				})?;
+				WindowId(window.id)
+			}
+		};
+		let output = self.try_remote(options.get_text_args(window_id))?;
+		let raw = String::from_utf8_lossy(&output.stdout).into_owned();
+		let size = self.try_size_for_window(window_id)?;
+		Ok(ScreenCapture::new(raw, size))
+	}
+
 	/// Capture the screen text and a variant with ANSI escapes stripped.
+	///
+	/// The `raw` and `clean` fields of [`KittyHarness::capture_for_window`], without querying the window size.
 	pub fn screen_text_clean_for_window(&self, window_id: WindowId) -> (String, String) {
-		let raw = self.screen_text_for_window(window_id);
+		self.try_screen_text_clean_for_window(window_id).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::screen_text_clean_for_window`], but returns an error instead of panicking.
+	pub fn try_screen_text_clean_for_window(&self, window_id: WindowId) -> Result<(String, String), HarnessError> {
+		let raw = self.try_screen_text_for_window(window_id)?;
 		let clean = strip_ansi(&raw);
-		(raw, clean)
+		Ok((raw, clean))
 	}
 
 	/// Capture the screen text and a variant with ANSI escapes stripped.
 	pub fn screen_text_clean(&self) -> (String, String) {
 		self.screen_text_clean_for_window(self.window_id)
 	}
+
+	/// Like [`KittyHarness::screen_text_clean`], but returns an error instead of panicking.
+	pub fn try_screen_text_clean(&self) -> Result<(String, String), HarnessError> {
+		self.try_screen_text_clean_for_window(self.window_id)
+	}
+
+	/// Capture the current screen as a [`ScreenGrid`] of styled cells.
+	pub fn screen_grid(&self) -> ScreenGrid {
+		ScreenGrid::parse(&self.screen_text())
+	}
+
+	/// Like [`KittyHarness::screen_grid`], but returns an error instead of panicking.
+	pub fn try_screen_grid(&self) -> Result<ScreenGrid, HarnessError> {
+		self.try_screen_text().map(|raw| ScreenGrid::parse(&raw))
+	}
+
+	/// Left-click the first on-screen occurrence of `needle`.
+	///
+	/// The screen is captured, `needle` is located (rows top to bottom), and
+	/// an SGR click is sent at its middle character, so tests keep working
+	/// when the layout shifts. Returns the clicked `(col, row)`.
+	///
+	/// # Panics
+	///
+	/// Panics if `needle` is not on screen.
+	pub fn click_text(&self, needle: &str) -> (u16, u16) {
+		self.try_click_text(needle).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::click_text`], but returns an error instead of panicking.
+	pub fn try_click_text(&self, needle: &str) -> Result<(u16, u16), HarnessError> {
+		self.try_click_text_with(needle, MouseButton::Left)
+	}
+
+	/// Like [`KittyHarness::try_click_text`], clicking with `button`.
+	pub fn try_click_text_with(&self, needle: &str, button: MouseButton) -> Result<(u16, u16), HarnessError> {
+		let grid = self.try_screen_grid()?;
+		let (row, col) = grid.find(needle).ok_or_else(|| HarnessError::Parse {
+			command: "kitty @ get-text".into(),
+			message: format!("{needle:?} is not on screen"),
+		})?;
+		let line = grid.row_text(row);
+		let start = line.char_indices().nth(col).map_or(line.len(), |(idx, _)| idx);
+		let col = display_col(&line, start) + display_width(needle).saturating_sub(1) / 2;
+		let col = u16::try_from(col).unwrap_or(u16::MAX);
+		let row = u16::try_from(row).unwrap_or(u16::MAX);
+		send_mouse_click(self, button, col, row);
+		Ok((col, row))
+	}
+
+	/// Return the cursor position and visibility of the harness window.
+	pub fn cursor(&self) -> Cursor {
+		self.try_cursor().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::cursor`], but returns an error instead of panicking.
+	pub fn try_cursor(&self) -> Result<Cursor, HarnessError> {
+		self.try_cursor_for_window(self.window_id)
+	}
+
+	/// Return the cursor position and visibility of `window_id`.
+	///
+	/// Rows and columns are 0-based, relative to the visible screen, matching
+	/// the lines and display columns (see [`utils::screen::display_col`]) of
+	/// [`KittyHarness::screen_text_clean`].
+	pub fn cursor_for_window(&self, window_id: WindowId) -> Cursor {
+		self.try_cursor_for_window(window_id).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyHarness::cursor_for_window`], but returns an error instead of panicking.
+	pub fn try_cursor_for_window(&self, window_id: WindowId) -> Result<Cursor, HarnessError> {
+		let output = run_get_text_with(&self.socket_addr, window_id, "screen", &["--add-cursor"])
+			.map_err(|err| HarnessError::spawn("kitty @ get-text --add-cursor", err))?;
+		if !output.status.success() {
+			return Err(HarnessError::failed("kitty @ get-text --add-cursor", &output));
+		}
+		parse_cursor(&String::from_utf8_lossy(&output.stdout)).ok_or_else(|| HarnessError::Parse {
+			command: "kitty @ get-text --add-cursor".into(),
+			message: "no cursor position sequence in output".into(),
+		})
+	}
+
+	/// Capture the current screen as a checkpoint for [`KittyHarness::assert_changed_since`] and friends.
+	pub fn checkpoint(&self) -> ScreenCheckpoint {
+		utils::wait::checkpoint(self)
+	}
+
+	/// Assert that the screen changes from `checkpoint` shortly, returning the changed capture.
+	pub fn assert_changed_since(&self, checkpoint: &ScreenCheckpoint) -> String {
+		utils::wait::assert_changed_since(self, checkpoint)
+	}
+
+	/// Wait until the screen differs from `checkpoint`, returning the new capture and changed rows.
+	pub fn wait_for_screen_change(&self, checkpoint: &ScreenCheckpoint, timeout: Duration) -> Result<ScreenChange, WaitTimeout> {
+		utils::wait::wait_for_screen_change(self, checkpoint, timeout)
+	}
+
+	/// Diff the current screen against `checkpoint`, with the harness masks applied to both.
+	pub fn diff_since(&self, checkpoint: &ScreenCheckpoint) -> ScreenDiff {
+		let (_raw, clean) = self.screen_text_clean();
+		diff_screens(&self.apply_masks(&strip_ansi(&checkpoint.raw)), &self.apply_masks(&clean))
+	}
+
+	/// Assert that the screen does not change from `checkpoint` during a short settle window.
+	pub fn assert_unchanged_since(&self, checkpoint: &ScreenCheckpoint) {
+		utils::wait::assert_unchanged_since(self, checkpoint)
+	}
+
+	/// Start sampling the screen every `interval` on a background thread.
+	///
+	/// Each new frame is passed to `callback` as `(elapsed, raw, clean)` while
+	/// the caller keeps driving input. Sampling stops when the returned
+	/// [`ScreenWatcher`] is stopped or dropped, or when the window goes away.
+	pub fn watch_screen(&self, interval: Duration, callback: impl FnMut(Duration, &str, &str) + Send + 'static) -> ScreenWatcher {
+		ScreenWatcher::spawn(self.socket_addr.clone(), self.window_id, self.capture_cleaning(), interval, callback)
+	}
+
+	/// Like [`KittyHarness::watch_screen`], but delivers frames over a channel.
+	pub fn watch_screen_channel(&self, interval: Duration) -> (ScreenWatcher, mpsc::Receiver<(Duration, String, String)>) {
+		let (tx, rx) = mpsc::channel();
+		let watcher = self.watch_screen(interval, move |at, raw, clean| {
+			let _ = tx.send((at, raw.to_string(), clean.to_string()));
+		});
+		(watcher, rx)
+	}
+
+	/// Configure masks for the rest of this harness's lifetime.
+	///
+	/// Masked regions are blanked by [`KittyHarness::snapshot_text`] and
+	/// ignored when comparing against a checkpoint
+	/// ([`KittyHarness::assert_changed_since`],
+	/// [`KittyHarness::assert_unchanged_since`],
+	/// [`KittyHarness::wait_for_screen_change`] and
+	/// [`KittyHarness::diff_since`]), so a clock or spinner does not count as
+	/// a change.
+	pub fn set_masks(&self, masks: MaskSet) {
+		*self.masks.lock().unwrap_or_else(|err| err.into_inner()) = masks;
+	}
+
+	/// Apply the harness masks to a capture.
+	pub(crate) fn apply_masks(&self, capture: &str) -> String {
+		self.masks.lock().unwrap_or_else(|err| err.into_inner()).apply(capture)
+	}
+
+	/// Configure the trailing-whitespace cleanup applied to [`KittyHarness::screen_text`] and every capture built on it.
+	///
+	/// [`CleanOptions::none`] keeps captures exactly as kitty prints them, so
+	/// styled trailing spaces can be asserted on.
+	pub fn set_capture_cleaning(&self, options: CleanOptions) {
+		*self.cleaning.lock().unwrap_or_else(|err| err.into_inner()) = options;
+	}
+
+	/// The trailing-whitespace cleanup applied to captures (see [`KittyHarness::set_capture_cleaning`]).
+	pub fn capture_cleaning(&self) -> CleanOptions {
+		*self.cleaning.lock().unwrap_or_else(|err| err.into_inner())
+	}
+
+	/// Configure the normalizers applied by [`KittyHarness::snapshot_text`] after the masks.
+	pub fn set_normalizer(&self, normalizer: NormalizerChain) {
+		*self.normalizer.lock().unwrap_or_else(|err| err.into_inner()) = normalizer;
+	}
+
+	/// Capture the raw screen text with the harness masks and normalizers applied, ready for snapshotting.
+	pub fn snapshot_text(&self) -> String {
+		let raw = self.screen_text();
+		let masked = self.apply_masks(&raw);
+		self.normalizer.lock().unwrap_or_else(|err| err.into_inner()).apply(&masked)
+	}
+
+	/// Like [`KittyHarness::snapshot_text`], but in the styled snapshot format of [`utils::snapshot`].
+	pub fn styled_snapshot_text(&self) -> String {
+		styled_snapshot(&self.snapshot_text())
+	}
+}
+
+fn run_get_text(socket_addr: &str, window_id: WindowId) -> std::io::Result<std::process::Output> {
+	run_get_text_with(socket_addr, window_id, "screen", &[])
+}
+
+fn run_get_text_with(socket_addr: &str, window_id: WindowId, extent: &str, extra: &[&str]) -> std::io::Result<std::process::Output> {
+	Command::new("kitty")
+		.args([
+			"@",
+			"--to",
+			socket_addr,
+			"get-text",
+			"--match",
+			&format!("id:{}", window_id.0),
+			"--ansi",
+			"--extent",
+			extent,
+		])
+		.args(extra)
+		.output()
+}
+
+/// Format a signed `scroll-window` amount: `"3+"` scrolls down, `"3-"` up.
+fn scroll_amount(count: i32, unit: &str) -> String {
+	let direction = if count < 0 { '-' } else { '+' };
+	format!("{}{unit}{direction}", count.unsigned_abs())
+}
+
+fn clamp_u16(value: u32) -> u16 {
+	u16::try_from(value).unwrap_or(u16::MAX)
+}
+
+fn parse_remote_output(stdout: &[u8]) -> serde_json::Value {
+	let text = String::from_utf8_lossy(stdout);
+	if text.trim().is_empty() {
+		return serde_json::Value::Null;
+	}
+	serde_json::from_str(&text).unwrap_or_else(|_| serde_json::Value::String(text.into_owned()))
+}
+
+fn normalize_capture(stdout: &[u8], cleaning: &CleanOptions) -> String {
+	let raw = String::from_utf8_lossy(stdout).replace("\r\n", "\n");
+	clean_trailing_whitespace_with(&raw, cleaning)
+}
+
+/// Capture a window's screen without borrowing a harness, for background samplers.
+pub(crate) fn try_capture_screen(socket_addr: &str, window_id: WindowId, cleaning: &CleanOptions) -> Option<String> {
+	let output = run_get_text(socket_addr, window_id).ok()?;
+	output.status.success().then(|| normalize_capture(&output.stdout, cleaning))
+}
+
+fn try_list_windows_at(socket_addr: &str) -> Option<OsWindows> {
+	let ls = Ls::new().to(socket_addr.to_string());
+	let mut cmd: Command = (&ls).into();
+	let output = cmd.output().ok()?;
+	Ls::result(&output).ok()
+}
+
+fn keep_on_failure() -> bool {
+	std::env::var("KITTY_TEST_KEEP_ON_FAILURE").is_ok_and(|val| val == "1" || val.eq_ignore_ascii_case("true"))
 }
 
 fn all_window_ids(ls: &OsWindows) -> Vec<WindowId> {
@@ -274,6 +1694,50 @@ fn next_session_name() -> String {
 
 impl Drop for KittyHarness {
 	fn drop(&mut self) {
+		if thread::panicking()
+			&& let Some(recorder) = self.stop_recording()
+		{
+			// Avoid `session_info()`: panicking again here would abort the test binary.
+			let mut dir = utils::session::lookup(&self.session).map_or_else(|| utils::session::artifact_root().join(&self.session), |info| info.artifact_dir);
+			if let Some(subdir) = &self.artifact_subdir {
+				dir.push(subdir);
+			}
+			let path = dir.join("recording.cast");
+			match recorder.write(&path) {
+				Ok(()) => eprintln!("wrote session recording to {}", path.display()),
+				Err(err) => eprintln!("failed to write session recording: {err}"),
+			}
+		}
+
+		if self.is_kept_open() {
+			eprintln!("keeping kitty session {} open at {}", self.session, self.socket_addr);
+			return;
+		}
+
+		if let Some(blocker) = self.focus_blocker.lock().unwrap_or_else(|err| err.into_inner()).take() {
+			let _ = Command::new("kitty")
+				.args(["@", "--to", &self.socket_addr, "close-window", "--match", &format!("id:{}", blocker.0)])
+				.status();
+		}
+
+		match self.close_scope {
+			CloseScope::Instance => {}
+			CloseScope::Nothing => return,
+			CloseScope::Tab => {
+				let _ = Command::new("kitty")
+					.args([
+						"@",
+						"--to",
+						&self.socket_addr,
+						"close-tab",
+						"--match",
+						&format!("window_id:{}", self.window_id.0),
+					])
+					.status();
+				return;
+			}
+		}
+
 		let mut window_ids = self.try_list_windows().map(|ls| all_window_ids(&ls)).unwrap_or_default();
 
 		if window_ids.is_empty() {
@@ -289,7 +1753,7 @@ impl Drop for KittyHarness {
 }
 
 /// A key press plus optional modifier to encode for kitty.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct KeyPress {
 	/// Key code to encode and send.
 	pub key: KeyCode,
@@ -309,13 +1773,140 @@ impl From<(KeyCode, Modifiers)> for KeyPress {
 	}
 }
 
-fn encode_key(key: KeyPress, modes: KeyCodeEncodeModes) -> String {
-	key.key.encode(key.mods, modes, true).expect("termwiz should encode key")
+/// Serialized as its key name in the replay format (`"C-x"`, `"F2"`), see [`utils::keys::format_key`].
+#[cfg(feature = "serde")]
+impl serde::Serialize for KeyPress {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match utils::keys::format_key(*self) {
+			Some(name) => serializer.serialize_str(&name),
+			None => Err(serde::ser::Error::custom(format!("{self:?} has no key name in the replay format"))),
+		}
+	}
+}
+
+/// Deserialized from a key name in the replay format, see [`utils::keys::parse_key`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for KeyPress {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let name = String::deserialize(deserializer)?;
+		utils::keys::parse_key(&name).ok_or_else(|| serde::de::Error::custom(format!("unknown key name {name:?}")))
+	}
+}
+
+/// Whether a key event is a press, an auto-repeat, or a release.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyEventKind {
+	/// The key went down.
+	#[default]
+	Press,
+	/// The key is held and auto-repeating.
+	Repeat,
+	/// The key went up.
+	Release,
+}
+
+/// A key event: a key, its modifiers, and whether it was pressed, repeated or released.
+///
+/// Repeat and release events are only distinguishable when the application
+/// enabled the kitty keyboard protocol's "report event types" flag (pass
+/// modes with [`KittyKeyboardFlags::REPORT_EVENT_TYPES`]). Under other
+/// encodings a repeat is sent as another press and a release sends nothing,
+/// matching what a real terminal does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+	/// Key code to encode and send.
+	pub key: KeyCode,
+	/// Modifier flags to encode alongside the key.
+	pub mods: Modifiers,
+	/// Event type.
+	pub kind: KeyEventKind,
+}
+
+impl KeyEvent {
+	/// A press of `key`.
+	pub fn press(key: impl Into<KeyPress>) -> Self {
+		Self::new(key, KeyEventKind::Press)
+	}
+
+	/// An auto-repeat of `key`.
+	pub fn repeat(key: impl Into<KeyPress>) -> Self {
+		Self::new(key, KeyEventKind::Repeat)
+	}
+
+	/// A release of `key`.
+	pub fn release(key: impl Into<KeyPress>) -> Self {
+		Self::new(key, KeyEventKind::Release)
+	}
+
+	fn new(key: impl Into<KeyPress>, kind: KeyEventKind) -> Self {
+		let KeyPress { key, mods } = key.into();
+		Self { key, mods, kind }
+	}
+
+	fn key_press(self) -> KeyPress {
+		KeyPress {
+			key: self.key,
+			mods: self.mods,
+		}
+	}
+}
+
+impl From<KeyPress> for KeyEvent {
+	fn from(key: KeyPress) -> Self {
+		Self::press(key)
+	}
+}
+
+fn encode_key(key: KeyPress, modes: KeyCodeEncodeModes) -> Result<String, HarnessError> {
+	// termwiz only knows legacy encodings; keys without one (keypad, media,
+	// F13+, modifier-only) are sent the way kitty does once the application
+	// enables the matching protocol flags.
+	if let KeyboardEncoding::Kitty(flags) = modes.encoding {
+		let report_all = flags.contains(KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES);
+		let disambiguate = report_all || flags.contains(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES);
+		if disambiguate
+			&& (report_all || !key.key.is_modifier())
+			&& let Some(encoded) = utils::keys::encode_kitty_key(key)
+		{
+			return Ok(encoded);
+		}
+	}
+	let no_encoding = |reason: String| HarnessError::Parse {
+		command: "key encoding".into(),
+		message: format!("cannot encode {key:?}: {reason}"),
+	};
+	match key.key.encode(key.mods, modes, true) {
+		Ok(encoded) if encoded.is_empty() => Err(no_encoding("no legacy encoding; enable the matching kitty keyboard protocol flags".into())),
+		Ok(encoded) => Ok(encoded),
+		Err(err) => Err(no_encoding(err.to_string())),
+	}
+}
+
+/// Encode a key event; releases encode to nothing unless the application asked for event types.
+fn encode_key_event(event: KeyEvent, modes: KeyCodeEncodeModes) -> Result<String, HarnessError> {
+	let reports_event_types = matches!(modes.encoding, KeyboardEncoding::Kitty(flags) if flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES));
+	match event.kind {
+		KeyEventKind::Press => encode_key(event.key_press(), modes),
+		KeyEventKind::Repeat | KeyEventKind::Release if reports_event_types => {
+			utils::keys::encode_kitty_key_event(event.key_press(), event.kind).ok_or_else(|| HarnessError::Parse {
+				command: "key encoding".into(),
+				message: format!("cannot encode {:?} {:?} with the kitty keyboard protocol", event.kind, event.key_press()),
+			})
+		}
+		KeyEventKind::Repeat => encode_key(event.key_press(), modes),
+		KeyEventKind::Release => Ok(String::new()),
+	}
+}
+
+/// The key encoding a harness starts with: the kitty encoding with no protocol flags enabled.
+pub fn default_key_modes() -> KeyCodeEncodeModes {
+	kitty_key_modes(KittyKeyboardFlags::empty())
 }
 
-fn default_key_modes() -> KeyCodeEncodeModes {
+/// Key encoding modes for an application that enabled the given kitty keyboard protocol flags.
+pub fn kitty_key_modes(flags: KittyKeyboardFlags) -> KeyCodeEncodeModes {
 	KeyCodeEncodeModes {
-		encoding: KeyboardEncoding::Kitty(KittyKeyboardFlags::empty()),
+		encoding: KeyboardEncoding::Kitty(flags),
 		application_cursor_keys: false,
 		newline_mode: false,
 		modify_other_keys: None,
@@ -323,23 +1914,89 @@ fn default_key_modes() -> KeyCodeEncodeModes {
 }
 
 /// Encode and send a sequence of key presses with custom key modes.
+///
+/// # Panics
+///
+/// Panics if a key has no encoding under `modes` or sending fails.
 pub fn send_keys_with_modes(kitty: &KittyHarness, modes: KeyCodeEncodeModes, keys: &[KeyPress]) {
+	try_send_keys_with_modes(kitty, modes, keys).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Like [`send_keys_with_modes`], but returns an error instead of panicking.
+///
+/// Keys are sent in order until one cannot be encoded or sent.
+pub fn try_send_keys_with_modes(kitty: &KittyHarness, modes: KeyCodeEncodeModes, keys: &[KeyPress]) -> Result<(), HarnessError> {
 	for key in keys {
-		kitty.send_text(&encode_key(*key, modes));
+		kitty.try_send_text(&encode_key(*key, modes)?)?;
 	}
+	Ok(())
 }
 
-/// Encode and send key presses with default kitty modes.
+/// Encode and send key presses with the harness's key modes (see [`KittyHarness::set_key_modes`]).
 pub fn send_keys(kitty: &KittyHarness, keys: &[KeyPress]) {
-	send_keys_with_modes(kitty, default_key_modes(), keys)
+	send_keys_with_modes(kitty, kitty.key_modes(), keys)
+}
+
+/// Like [`send_keys`], but returns an error instead of panicking.
+pub fn try_send_keys(kitty: &KittyHarness, keys: &[KeyPress]) -> Result<(), HarnessError> {
+	try_send_keys_with_modes(kitty, kitty.key_modes(), keys)
+}
+
+/// Encode and send a sequence of key events (presses, repeats and releases) with custom key modes.
+///
+/// Releases are skipped unless `modes` report event types.
+///
+/// # Panics
+///
+/// Panics if an event has no encoding under `modes` or sending fails.
+pub fn send_key_events_with_modes(kitty: &KittyHarness, modes: KeyCodeEncodeModes, events: &[KeyEvent]) {
+	try_send_key_events_with_modes(kitty, modes, events).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Like [`send_key_events_with_modes`], but returns an error instead of panicking.
+pub fn try_send_key_events_with_modes(kitty: &KittyHarness, modes: KeyCodeEncodeModes, events: &[KeyEvent]) -> Result<(), HarnessError> {
+	for event in events {
+		let encoded = encode_key_event(*event, modes)?;
+		if !encoded.is_empty() {
+			kitty.try_send_text(&encoded)?;
+		}
+	}
+	Ok(())
+}
+
+/// Encode and send key events with the harness's key modes (see [`KittyHarness::set_key_modes`]).
+pub fn send_key_events(kitty: &KittyHarness, events: &[KeyEvent]) {
+	send_key_events_with_modes(kitty, kitty.key_modes(), events)
+}
+
+/// Like [`send_key_events`], but returns an error instead of panicking.
+pub fn try_send_key_events(kitty: &KittyHarness, events: &[KeyEvent]) -> Result<(), HarnessError> {
+	try_send_key_events_with_modes(kitty, kitty.key_modes(), events)
 }
 
 /// Launch kitty, run `command`, and let the caller drive interactions to produce a result.
-pub fn with_kitty_capture<T>(working_dir: &Path, command: &str, driver: impl FnOnce(&KittyHarness) -> T) -> T {
+pub fn with_kitty_capture<T>(working_dir: &Path, command: impl Into<LaunchCommand>, driver: impl FnOnce(&KittyHarness) -> T) -> T {
 	let harness = KittyHarness::launch(working_dir, command);
 	driver(&harness)
 }
 
+/// Like [`with_kitty_capture`], but launches kitty with the given options.
+pub fn with_kitty_capture_options<T>(
+	working_dir: &Path,
+	command: impl Into<LaunchCommand>,
+	options: &LaunchOptions,
+	driver: impl FnOnce(&KittyHarness) -> T,
+) -> T {
+	let harness = KittyHarness::launch_with(working_dir, command, options);
+	driver(&harness)
+}
+
+/// Like [`with_kitty_capture`], but runs `command` in a warm instance from [`KittyPool::shared`].
+pub fn with_pooled_kitty_capture<T>(working_dir: &Path, command: impl Into<LaunchCommand>, driver: impl FnOnce(&KittyHarness) -> T) -> T {
+	let harness = KittyPool::shared().acquire(working_dir, command);
+	driver(&harness)
+}
+
 /// Run a closure and panic if it exceeds the given timeout.
 pub fn run_with_timeout<T, F>(timeout: Duration, f: F) -> T
 where
@@ -394,6 +2051,25 @@ macro_rules! __kitty_key {
 }
 
 /// Define a kitty snapshot test with a provided working directory binding.
+///
+/// An optional `masks = <MaskSet>` argument blanks volatile regions of the
+/// output before it is snapshotted, and `normalize = <NormalizerChain>`
+/// rewrites it (timestamps, PIDs, temp paths); a chain can include a
+/// `MaskSet` to do both. The bare `scrub` argument applies the built-in
+/// scrubbers of `NormalizerChain::scrub_volatile`, and `styled` snapshots a
+/// raw capture in the [`utils::snapshot`] format so color regressions fail
+/// the test too.
+///
+/// Instead of a body, `cmd = <command>, steps = [...]` launches the command
+/// and runs the steps of [`utils::script::steps`] (`keys`, `text`,
+/// `wait_contains`, `pause_ms`, `snapshot`) in order. Each `snapshot()`
+/// records [`KittyHarness::snapshot_text`]; several are named `<test>-1`,
+/// `<test>-2`, ..., and without any the screen is snapshotted once at the end.
+/// The test returns early when [`require_kitty`] is false.
+///
+/// ```ignore
+/// kitty_snapshot_test!(open_menu, cmd = "my-app", steps = [keys("F2"), wait_contains("Menu"), snapshot()]);
+/// ```
 #[macro_export]
 macro_rules! kitty_snapshot_test {
 	($name:ident, |$dir:ident| $body:block) => {
@@ -404,85 +2080,136 @@ macro_rules! kitty_snapshot_test {
 			insta::assert_snapshot!(stringify!($name), output);
 		}
 	};
-}
-
-fn clean_trailing_whitespace(input: &str) -> String {
-	let mut cleaned_lines = Vec::new();
-
-	for line in input.lines() {
-		let tokens = split_tokens(line);
-		let mut keep_until = 0usize;
-		for (idx, token) in tokens.iter().enumerate() {
-			if matches!(token.kind, TokenKind::Text) && !token.text.trim_end().is_empty() {
-				keep_until = idx + 1;
-			}
+	($name:ident, masks = $masks:expr, |$dir:ident| $body:block) => {
+		#[test]
+		fn $name() {
+			let $dir = $crate::manifest_dir();
+			let output: String = { $body };
+			let masks: $crate::MaskSet = $masks;
+			insta::assert_snapshot!(stringify!($name), masks.apply(&output));
 		}
-		let mut kept = String::new();
-		for token in tokens.iter().take(keep_until) {
-			kept.push_str(&token.raw);
+	};
+	($name:ident, styled, |$dir:ident| $body:block) => {
+		#[test]
+		fn $name() {
+			let $dir = $crate::manifest_dir();
+			let output: String = { $body };
+			insta::assert_snapshot!(stringify!($name), $crate::styled_snapshot(&output));
 		}
-		cleaned_lines.push(kept);
-	}
-
-	while let Some(last) = cleaned_lines.last() {
-		if strip_ansi(last).trim().is_empty() {
-			cleaned_lines.pop();
-		} else {
-			break;
+	};
+	($name:ident, scrub, |$dir:ident| $body:block) => {
+		$crate::kitty_snapshot_test!($name, normalize = $crate::NormalizerChain::new().scrub_volatile(), |$dir| $body);
+	};
+	($name:ident, normalize = $normalizer:expr, |$dir:ident| $body:block) => {
+		#[test]
+		fn $name() {
+			let $dir = $crate::manifest_dir();
+			let output: String = { $body };
+			let normalizer: $crate::NormalizerChain = $normalizer;
+			insta::assert_snapshot!(stringify!($name), normalizer.apply(&output));
 		}
-	}
-
-	cleaned_lines.join("\n")
-}
-
-#[derive(Clone, Debug)]
-struct Token {
-	kind: TokenKind,
-	raw: String,
-	text: String,
-}
-
-#[derive(Clone, Debug, PartialEq, Eq)]
-enum TokenKind {
-	Text,
-	Escape,
+	};
+	($name:ident, cmd = $cmd:expr, steps = [$($step:expr),* $(,)?]) => {
+		#[test]
+		fn $name() {
+			if !$crate::require_kitty() {
+				return;
+			}
+			let script: $crate::InputScript = {
+				#[allow(unused_imports)]
+				use $crate::utils::script::steps::*;
+				let steps: ::std::vec::Vec<$crate::ScriptStep> = ::std::vec![$($step),*];
+				steps.into_iter().collect()
+			};
+			let snapshots = $crate::with_kitty_capture(&$crate::manifest_dir(), $cmd, |kitty| {
+				let mut snapshots = script.run_snapshots(kitty);
+				if snapshots.is_empty() {
+					snapshots.push(kitty.snapshot_text());
+				}
+				snapshots
+			});
+			let count = snapshots.len();
+			for (idx, output) in snapshots.into_iter().enumerate() {
+				let snapshot_name = if count == 1 {
+					stringify!($name).to_string()
+				} else {
+					format!("{}-{}", stringify!($name), idx + 1)
+				};
+				insta::assert_snapshot!(snapshot_name, output);
+			}
+		}
+	};
 }
 
-fn split_tokens(line: &str) -> Vec<Token> {
-	let mut out = Vec::new();
-	let mut chars = line.chars().peekable();
-
-	while let Some(ch) = chars.next() {
-		if ch == '\u{1b}' {
-			let mut raw = String::from(ch);
-			while let Some(&next) = chars.peek() {
-				raw.push(next);
-				chars.next();
-				if next.is_ascii_alphabetic() || next == '~' {
-					break;
-				}
+/// Define a snapshot test that runs the same driver at several terminal sizes.
+///
+/// `cmd` is launched in a fresh harness for each `(cols, rows)` entry of
+/// `sizes` (see [`run_at_sizes`]), the body produces the output from the
+/// harness bound to the closure parameter, and every size is snapshotted as
+/// `<test>-<cols>x<rows>`. [`COMMON_SIZES`] covers 80x24, 120x40 and 200x60.
+/// The test returns early when [`require_kitty`] is false.
+///
+/// ```ignore
+/// kitty_size_matrix_test!(main_layout, cmd = "my-app", sizes = [(80, 24), (120, 40)], |kitty| {
+///     wait_for_clean_contains(kitty, Duration::from_secs(2), "Ready");
+///     kitty.snapshot_text()
+/// });
+/// ```
+#[macro_export]
+macro_rules! kitty_size_matrix_test {
+	($name:ident, cmd = $cmd:expr, sizes = $sizes:expr, |$kitty:ident| $body:block) => {
+		#[test]
+		fn $name() {
+			if !$crate::require_kitty() {
+				return;
 			}
-			out.push(Token {
-				kind: TokenKind::Escape,
-				raw,
-				text: String::new(),
+			let sizes: &[(u16, u16)] = &$sizes;
+			let captures = $crate::run_at_sizes(&$crate::manifest_dir(), $cmd, &$crate::LaunchOptions::new(), sizes, |$kitty| -> String {
+				$body
 			});
-		} else {
-			let mut raw = String::from(ch);
-			while let Some(&next) = chars.peek() {
-				if next == '\u{1b}' {
-					break;
-				}
-				raw.push(next);
-				chars.next();
+			for ((cols, rows), output) in captures {
+				insta::assert_snapshot!(format!("{}-{}x{}", stringify!($name), cols, rows), output);
 			}
-			out.push(Token {
-				kind: TokenKind::Text,
-				text: raw.clone(),
-				raw,
-			});
 		}
-	}
+	};
+}
 
-	out
+/// Define a snapshot test that runs the same driver under several color themes.
+///
+/// `cmd` is launched in a fresh harness for each `(name, Theme)` entry of
+/// `themes` (see [`run_with_themes`]), and every theme is snapshotted as
+/// `<test>-<name>`. The body can also bind the [`Theme`] to check contrast
+/// against its palette. [`common_themes`] covers dark, light and high
+/// contrast. The test returns early when [`require_kitty`] is false.
+///
+/// ```ignore
+/// kitty_theme_matrix_test!(status_bar, cmd = "my-app", themes = common_themes(), |kitty, theme| {
+///     assert_min_contrast_with(&kitty.screen_grid(), &theme.palette(), 4.5);
+///     kitty.styled_snapshot_text()
+/// });
+/// ```
+#[macro_export]
+macro_rules! kitty_theme_matrix_test {
+	($name:ident, cmd = $cmd:expr, themes = $themes:expr, |$kitty:ident| $body:block) => {
+		$crate::kitty_theme_matrix_test!($name, cmd = $cmd, themes = $themes, |$kitty, _theme| $body);
+	};
+	($name:ident, cmd = $cmd:expr, themes = $themes:expr, |$kitty:ident, $theme:ident| $body:block) => {
+		#[test]
+		fn $name() {
+			if !$crate::require_kitty() {
+				return;
+			}
+			let themes: &[(&str, $crate::Theme)] = &$themes;
+			let captures = $crate::run_with_themes(
+				&$crate::manifest_dir(),
+				$cmd,
+				&$crate::LaunchOptions::new(),
+				themes,
+				|$kitty, $theme| -> String { $body },
+			);
+			for (theme_name, output) in captures {
+				insta::assert_snapshot!(format!("{}-{}", stringify!($name), theme_name), output);
+			}
+		}
+	};
 }
@@ -0,0 +1,166 @@
+//! Animated PNG artifacts of sampled frames (requires the `apng` feature).
+//!
+//! [`write_apng`] renders each frame of a [`FrameTimeline`] with
+//! [`crate::utils::render`] and writes an animated PNG that plays back with
+//! the recorded timing in any browser. [`AnimationRecorder`] samples the
+//! screen in the background during a test and saves `screen.apng` to the
+//! session artifact directory (or one set with
+//! [`AnimationRecorder::artifact_dir`]) when the test fails.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! # fn demo(kitty: &kitty_test_harness::KittyHarness) {
+//! let _recorder = kitty.record_animation(Duration::from_millis(50));
+//! // ... drive the application; a panic below leaves screen.apng behind.
+//! # }
+//! ```
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::KittyHarness;
+use crate::utils::error::HarnessError;
+use crate::utils::frames::{FrameTimeline, ScreenWatcher};
+use crate::utils::render::{Image, render_grid};
+use crate::utils::screen::ScreenGrid;
+use crate::utils::theme::Theme;
+
+/// How long the last frame of an animation is shown.
+const LAST_FRAME_DELAY: Duration = Duration::from_millis(500);
+
+/// Render `timeline` as an animated PNG at `path` using `theme` colors.
+///
+/// The image covers the widest row and tallest frame seen in the timeline.
+pub fn write_apng(timeline: &FrameTimeline, path: &Path, theme: &Theme) -> Result<(), HarnessError> {
+	let frames = timeline.frames();
+	if frames.is_empty() {
+		return Err(HarnessError::io(
+			format!("write {}", path.display()),
+			std::io::Error::new(std::io::ErrorKind::InvalidInput, "timeline has no frames"),
+		));
+	}
+	let grids: Vec<_> = frames.iter().map(|frame| ScreenGrid::parse(&frame.raw)).collect();
+	let cols = grids.iter().flat_map(|grid| grid.rows().iter().map(Vec::len)).max().unwrap_or(1).max(1);
+	let rows = grids.iter().map(ScreenGrid::height).max().unwrap_or(1).max(1);
+	let images: Vec<Image> = grids.iter().map(|grid| render_grid(grid, cols, rows, theme)).collect();
+
+	let io_err = |err: png::EncodingError| HarnessError::io(format!("write {}", path.display()), std::io::Error::other(err));
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent).map_err(|err| HarnessError::io(format!("create {}", parent.display()), err))?;
+	}
+	let file = File::create(path).map_err(|err| HarnessError::io(format!("create {}", path.display()), err))?;
+	let mut encoder = png::Encoder::new(BufWriter::new(file), images[0].width as u32, images[0].height as u32);
+	encoder.set_color(png::ColorType::Rgb);
+	encoder.set_depth(png::BitDepth::Eight);
+	encoder.set_animated(images.len() as u32, 0).map_err(io_err)?;
+	let mut writer = encoder.write_header().map_err(io_err)?;
+
+	for (idx, image) in images.iter().enumerate() {
+		let delay = match frames.get(idx + 1) {
+			Some(next) => next.at.saturating_sub(frames[idx].at),
+			None => LAST_FRAME_DELAY,
+		};
+		writer
+			.set_frame_delay(delay.as_millis().clamp(1, u128::from(u16::MAX)) as u16, 1000)
+			.map_err(io_err)?;
+		writer.write_image_data(&image.to_rgb_bytes()).map_err(io_err)?;
+	}
+	writer.finish().map_err(io_err)
+}
+
+/// Background frame recorder that saves an animated PNG when a test fails.
+///
+/// Created by [`crate::KittyHarness::record_animation`]. Sampling stops when
+/// the recorder is stopped or dropped; if it is dropped while the thread is
+/// panicking, the animation is written to `screen.apng` in the artifact
+/// directory.
+pub struct AnimationRecorder {
+	watcher: Option<ScreenWatcher>,
+	timeline: Arc<Mutex<FrameTimeline>>,
+	dir: PathBuf,
+	theme: Theme,
+}
+
+impl AnimationRecorder {
+	pub(crate) fn start(kitty: &KittyHarness, interval: Duration) -> Self {
+		let timeline = Arc::new(Mutex::new(FrameTimeline::new()));
+		let sink = Arc::clone(&timeline);
+		let watcher = kitty.watch_screen(interval, move |at, raw, clean| {
+			sink.lock().unwrap_or_else(|err| err.into_inner()).push(at, raw.to_string(), clean.to_string());
+		});
+		Self {
+			watcher: Some(watcher),
+			timeline,
+			dir: kitty.session_info().artifact_dir,
+			theme: Theme::xterm(),
+		}
+	}
+
+	/// Save failure artifacts to `dir` instead of the session artifact directory.
+	pub fn artifact_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+		self.dir = dir.into();
+		self
+	}
+
+	/// Render with `theme` (default [`Theme::xterm`]).
+	pub fn theme(mut self, theme: Theme) -> Self {
+		self.theme = theme;
+		self
+	}
+
+	/// A copy of the frames recorded so far.
+	pub fn timeline(&self) -> FrameTimeline {
+		self.timeline.lock().unwrap_or_else(|err| err.into_inner()).clone()
+	}
+
+	/// Write the frames recorded so far to `path`.
+	pub fn save(&self, path: &Path) -> Result<(), HarnessError> {
+		write_apng(&self.timeline(), path, &self.theme)
+	}
+
+	/// Stop sampling and return the recorded frames without writing anything.
+	pub fn stop(mut self) -> FrameTimeline {
+		self.watcher.take();
+		self.timeline()
+	}
+}
+
+impl Drop for AnimationRecorder {
+	fn drop(&mut self) {
+		let Some(watcher) = self.watcher.take() else {
+			return;
+		};
+		watcher.stop();
+		if std::thread::panicking() {
+			let path = self.dir.join("screen.apng");
+			match self.save(&path) {
+				Ok(()) => eprintln!("wrote screen animation to {}", path.display()),
+				Err(err) => eprintln!("failed to write screen animation: {err}"),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn writes_one_png_frame_per_timeline_frame() {
+		let mut timeline = FrameTimeline::new();
+		timeline.push(Duration::ZERO, "a".into(), "a".into());
+		timeline.push(Duration::from_millis(120), "\x1b[7mb\x1b[0m".into(), "b".into());
+		let path = std::env::temp_dir().join(format!("kitty-test-animation-{}.apng", std::process::id()));
+		write_apng(&timeline, &path, &Theme::xterm()).unwrap();
+
+		let decoder = png::Decoder::new(File::open(&path).unwrap());
+		let reader = decoder.read_info().unwrap();
+		let control = reader.info().animation_control.unwrap();
+		assert_eq!(control.num_frames, 2);
+		let _ = std::fs::remove_file(&path);
+	}
+}
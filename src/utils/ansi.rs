@@ -0,0 +1,317 @@
+//! Escape-sequence tokenizing of raw captures.
+//!
+//! [`split_tokens`] runs captures through the VT state machine that termwiz
+//! uses (`vtparse`), so every sequence type is delimited correctly: CSI, OSC
+//! (titles, hyperlinks) terminated by BEL or ST, DCS and APC strings (kitty
+//! graphics payloads) and plain two-byte escapes. Printable characters and
+//! C0 controls outside sequences are text; everything else, including an
+//! unterminated sequence at the end of the input, is an escape token.
+//!
+//! [`strip_ansi`] builds on it to produce the clean text of
+//! [`crate::KittyHarness::screen_text_clean`]: no fragments of titles,
+//! hyperlink targets or image payloads survive, and control characters other
+//! than newline and tab are dropped.
+//!
+//! [`clean_trailing_whitespace`] is the cleanup applied to every capture by
+//! [`crate::KittyHarness::screen_text`]; [`CleanOptions`] relax it, per call
+//! or per harness with [`crate::KittyHarness::set_capture_cleaning`].
+//!
+//! ```
+//! use kitty_test_harness::strip_ansi;
+//!
+//! let raw = "\x1b]8;;file:///tmp/a\x1b\\a\x1b]8;;\x1b\\ \x1b_Ga=T;AAAA\x1b\\\x1b[1mb\x1b[0m\x07";
+//! assert_eq!(strip_ansi(raw), "a b");
+//! ```
+
+use vtparse::{CsiParam, VTActor, VTParser};
+
+/// A run of text or a single escape sequence.
+#[derive(Clone, Debug)]
+pub(crate) struct Token {
+	pub(crate) kind: TokenKind,
+	pub(crate) raw: String,
+	pub(crate) text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+	Text,
+	Escape,
+}
+
+/// Records whether the bytes fed since the last reset formed (part of) a sequence.
+#[derive(Default)]
+struct Classifier {
+	sequence: bool,
+}
+
+impl VTActor for Classifier {
+	fn print(&mut self, _: char) {}
+
+	fn execute_c0_or_c1(&mut self, _: u8) {}
+
+	fn dcs_hook(&mut self, _: u8, _: &[i64], _: &[u8], _: bool) {
+		self.sequence = true;
+	}
+
+	fn dcs_put(&mut self, _: u8) {
+		self.sequence = true;
+	}
+
+	fn dcs_unhook(&mut self) {
+		self.sequence = true;
+	}
+
+	fn esc_dispatch(&mut self, _: &[i64], _: &[u8], _: bool, _: u8) {
+		self.sequence = true;
+	}
+
+	fn csi_dispatch(&mut self, _: &[CsiParam], _: bool, _: u8) {
+		self.sequence = true;
+	}
+
+	fn osc_dispatch(&mut self, _: &[&[u8]]) {
+		self.sequence = true;
+	}
+
+	fn apc_dispatch(&mut self, _: Vec<u8>) {
+		self.sequence = true;
+	}
+}
+
+/// Split `input` into text runs and escape sequences.
+///
+/// Concatenating the `raw` fields of the result reproduces `input`.
+pub(crate) fn split_tokens(input: &str) -> Vec<Token> {
+	let mut out: Vec<Token> = Vec::new();
+	let mut parser = VTParser::new();
+	let mut classifier = Classifier::default();
+	let bytes = input.as_bytes();
+	let mut start = 0;
+
+	for (idx, &byte) in bytes.iter().enumerate() {
+		parser.parse_byte(byte, &mut classifier);
+		// Sequences and multi-byte characters leave the ground state until they end.
+		if !parser.is_ground() {
+			continue;
+		}
+		let raw = &input[start..=idx];
+		let kind = if classifier.sequence || raw.starts_with('\u{1b}') {
+			TokenKind::Escape
+		} else {
+			TokenKind::Text
+		};
+		push_token(&mut out, kind, raw);
+		classifier.sequence = false;
+		start = idx + 1;
+	}
+	if start < bytes.len() {
+		push_token(&mut out, TokenKind::Escape, &input[start..]);
+	}
+
+	out
+}
+
+/// Remove every escape sequence and non-printing control character (except `\n` and `\t`) from `raw`.
+pub fn strip_ansi(raw: &str) -> String {
+	split_tokens(raw)
+		.iter()
+		.filter(|token| token.kind == TokenKind::Text)
+		.flat_map(|token| token.text.chars())
+		.filter(|&ch| !ch.is_control() || ch == '\n' || ch == '\t')
+		.collect()
+}
+
+/// What [`clean_trailing_whitespace_with`] keeps.
+///
+/// The default drops the whitespace and escape sequences after the last
+/// visible text run of every line and blank lines at the end, as harness
+/// captures always have; whitespace at the end of that run itself is kept
+/// unless [`CleanOptions::trim_text_runs`] is set. [`CleanOptions::none`] keeps everything.
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::utils::ansi::{CleanOptions, clean_trailing_whitespace, clean_trailing_whitespace_with};
+///
+/// let raw = "ok\x1b[41m   \x1b[0m\n\n";
+/// assert_eq!(clean_trailing_whitespace(raw), "ok");
+/// assert_eq!(clean_trailing_whitespace_with(raw, &CleanOptions::default().preserve_trailing_spaces()), "ok\x1b[41m   \x1b[0m");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CleanOptions {
+	/// Keep whitespace and escape sequences after the last visible character of a line.
+	pub preserve_trailing_spaces: bool,
+	/// Keep blank lines at the end of the capture.
+	pub preserve_blank_lines: bool,
+	/// Keep the escape sequences of lines without visible text, and do not drop such lines at the end.
+	pub keep_escape_only_lines: bool,
+	/// Also trim whitespace at the end of the last visible text run, e.g. `"foo   "` before a style reset.
+	pub trim_text_runs: bool,
+}
+
+impl CleanOptions {
+	/// Keep the capture exactly as kitty printed it.
+	pub fn none() -> Self {
+		Self {
+			preserve_trailing_spaces: true,
+			preserve_blank_lines: true,
+			keep_escape_only_lines: true,
+			trim_text_runs: false,
+		}
+	}
+
+	/// Keep trailing whitespace, e.g. background-colored padding.
+	pub fn preserve_trailing_spaces(mut self) -> Self {
+		self.preserve_trailing_spaces = true;
+		self
+	}
+
+	/// Keep blank lines at the end.
+	pub fn preserve_blank_lines(mut self) -> Self {
+		self.preserve_blank_lines = true;
+		self
+	}
+
+	/// Keep lines that only hold escape sequences.
+	pub fn keep_escape_only_lines(mut self) -> Self {
+		self.keep_escape_only_lines = true;
+		self
+	}
+
+	/// Trim the whitespace a line's last visible text run ends with, not only what follows it.
+	pub fn trim_text_runs(mut self) -> Self {
+		self.trim_text_runs = true;
+		self
+	}
+}
+
+/// Trim trailing whitespace from every line and drop trailing blank lines, keeping styling intact.
+pub fn clean_trailing_whitespace(input: &str) -> String {
+	clean_trailing_whitespace_with(input, &CleanOptions::default())
+}
+
+/// Like [`clean_trailing_whitespace`], keeping what `options` ask for.
+pub fn clean_trailing_whitespace_with(input: &str, options: &CleanOptions) -> String {
+	let is_visible = |token: &Token| token.kind == TokenKind::Text && !token.text.trim_end().is_empty();
+	let mut cleaned_lines = Vec::new();
+	let lines: Vec<&str> = if options.preserve_trailing_spaces || options.preserve_blank_lines {
+		input.split('\n').collect()
+	} else {
+		input.lines().collect()
+	};
+
+	for line in lines {
+		let tokens = split_tokens(line);
+		let kept: String = if options.preserve_trailing_spaces {
+			line.to_string()
+		} else if options.keep_escape_only_lines && !tokens.iter().any(is_visible) {
+			tokens
+				.iter()
+				.filter(|token| token.kind == TokenKind::Escape)
+				.map(|token| token.raw.as_str())
+				.collect()
+		} else {
+			let last = tokens.iter().rposition(is_visible);
+			tokens
+				.iter()
+				.enumerate()
+				.take(last.map_or(0, |idx| idx + 1))
+				.map(|(idx, token)| {
+					if options.trim_text_runs && Some(idx) == last {
+						token.raw.trim_end()
+					} else {
+						token.raw.as_str()
+					}
+				})
+				.collect()
+		};
+		cleaned_lines.push(kept);
+	}
+
+	if !options.preserve_blank_lines {
+		while let Some(last) = cleaned_lines.last() {
+			let tokens = split_tokens(last);
+			let blank = tokens.iter().all(|token| token.text.trim().is_empty());
+			let escapes = tokens.iter().any(|token| token.kind == TokenKind::Escape);
+			if blank && !(options.keep_escape_only_lines && escapes) {
+				cleaned_lines.pop();
+			} else {
+				break;
+			}
+		}
+	}
+
+	cleaned_lines.join("\n")
+}
+
+/// Append `raw`, merging consecutive text into one token.
+fn push_token(out: &mut Vec<Token>, kind: TokenKind, raw: &str) {
+	if kind == TokenKind::Text
+		&& let Some(last) = out.last_mut()
+		&& last.kind == TokenKind::Text
+	{
+		last.raw.push_str(raw);
+		last.text.push_str(raw);
+		return;
+	}
+	out.push(Token {
+		text: if kind == TokenKind::Text { raw.to_string() } else { String::new() },
+		kind,
+		raw: raw.to_string(),
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kinds(input: &str) -> Vec<(TokenKind, String)> {
+		split_tokens(input).into_iter().map(|token| (token.kind, token.raw)).collect()
+	}
+
+	#[test]
+	fn delimits_every_sequence_type() {
+		use TokenKind::{Escape, Text};
+
+		let input = "\x1b[1;31mred\x1b]0;title\x07 \x1b]8;;https://x.y/a~b\x1b\\link\x1b]8;;\x1b\\\x1b_Gf=100;aGk=\x1b\\é\x1bPq#0;1\x1b\\\x1b7end";
+		assert_eq!(
+			kinds(input),
+			vec![
+				(Escape, "\x1b[1;31m".into()),
+				(Text, "red".into()),
+				(Escape, "\x1b]0;title\x07".into()),
+				(Text, " ".into()),
+				(Escape, "\x1b]8;;https://x.y/a~b\x1b\\".into()),
+				(Text, "link".into()),
+				(Escape, "\x1b]8;;\x1b\\".into()),
+				(Escape, "\x1b_Gf=100;aGk=\x1b\\".into()),
+				(Text, "é".into()),
+				(Escape, "\x1bPq#0;1\x1b\\".into()),
+				(Escape, "\x1b7".into()),
+				(Text, "end".into()),
+			]
+		);
+		assert_eq!(strip_ansi(input), "red link\u{e9}end");
+		assert_eq!(kinds("a\x1b]2;unterminated"), vec![(Text, "a".into()), (Escape, "\x1b]2;unterminated".into())]);
+	}
+
+	#[test]
+	fn cleaning_options_keep_what_they_name() {
+		let raw = "a \x1b[41m  \x1b[0m\n\x1b[0m\n  \n";
+		assert_eq!(clean_trailing_whitespace(raw), "a ");
+		assert_eq!(clean_trailing_whitespace_with(raw, &CleanOptions::default().trim_text_runs()), "a");
+		// Whitespace inside the last styled run is kept by default.
+		assert_eq!(clean_trailing_whitespace("\x1b[1mfoo   \x1b[0m\nbar   "), "\x1b[1mfoo   \nbar   ");
+		assert_eq!(
+			clean_trailing_whitespace_with(raw, &CleanOptions::default().preserve_trailing_spaces()),
+			"a \x1b[41m  \x1b[0m"
+		);
+		assert_eq!(clean_trailing_whitespace_with(raw, &CleanOptions::default().preserve_blank_lines()), "a \n\n\n");
+		assert_eq!(
+			clean_trailing_whitespace_with(raw, &CleanOptions::default().keep_escape_only_lines()),
+			"a \n\x1b[0m"
+		);
+		assert_eq!(clean_trailing_whitespace_with(raw, &CleanOptions::none()), raw);
+	}
+}
@@ -0,0 +1,294 @@
+//! Typed screen captures.
+//!
+//! A [`ScreenCapture`] keeps the raw and clean text of one capture together
+//! with when it was taken and the window size at the time, so code that
+//! inspects, waits on, snapshots or diffs captures can pass one value around
+//! instead of `(raw, clean)` tuples. [`crate::KittyHarness::capture`] takes
+//! one; [`crate::KittyHarness::screen_text_clean`] still returns the tuple.
+//!
+//! [`CaptureOptions`] choose what `kitty @ get-text` returns for
+//! [`crate::KittyHarness::capture_with`]: the extent (screen, scrollback,
+//! selection or shell-integration command output), cursor and wrap markers,
+//! whether styles are kept, and which window is read.
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::ScreenCapture;
+//!
+//! let capture = ScreenCapture::new("\x1b[1mName\x1b[0m\nready", (80, 24));
+//! assert_eq!(capture.line(0), "Name");
+//! assert!(capture.contains("ready"));
+//! assert!(capture.grid().cell(0, 0).unwrap().attrs.bold);
+//! assert_eq!((capture.cols(), capture.rows()), (80, 24));
+//! ```
+
+use std::time::SystemTime;
+
+use kitty_remote_bindings::model::WindowId;
+
+use crate::utils::ansi::strip_ansi;
+use crate::utils::diff::{ScreenDiff, diff_screens};
+use crate::utils::screen::{Cursor, ScreenGrid, parse_cursor};
+
+/// One screen capture: raw and clean text, timestamp and window size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScreenCapture {
+	/// The capture with ANSI escape sequences.
+	pub raw: String,
+	/// The capture with ANSI escape sequences stripped.
+	pub clean: String,
+	/// When the capture was taken.
+	pub captured_at: SystemTime,
+	/// Window size in cells as `(cols, rows)`.
+	pub size: (u16, u16),
+}
+
+impl ScreenCapture {
+	/// Build a capture from raw text and the window size, timestamped now.
+	pub fn new(raw: impl Into<String>, size: (u16, u16)) -> Self {
+		let raw = raw.into();
+		Self {
+			clean: strip_ansi(&raw),
+			raw,
+			captured_at: SystemTime::now(),
+			size,
+		}
+	}
+
+	/// Window width in cells.
+	pub fn cols(&self) -> u16 {
+		self.size.0
+	}
+
+	/// Window height in cells.
+	pub fn rows(&self) -> u16 {
+		self.size.1
+	}
+
+	/// Lines of the clean text.
+	pub fn lines(&self) -> impl Iterator<Item = &str> {
+		self.clean.lines()
+	}
+
+	/// Clean text of row `row`, or `""` past the last captured line.
+	pub fn line(&self, row: usize) -> &str {
+		self.clean.lines().nth(row).unwrap_or_default()
+	}
+
+	/// Returns true if the clean text contains `needle`.
+	pub fn contains(&self, needle: &str) -> bool {
+		self.clean.contains(needle)
+	}
+
+	/// Parse the raw text into a [`ScreenGrid`] of styled cells.
+	pub fn grid(&self) -> ScreenGrid {
+		ScreenGrid::parse(&self.raw)
+	}
+
+	/// Rows and cells that changed between this capture and `after`.
+	pub fn diff(&self, after: &ScreenCapture) -> ScreenDiff {
+		diff_screens(&self.clean, &after.clean)
+	}
+
+	/// The cursor reported in the raw text, for captures taken with [`CaptureOptions::add_cursor`].
+	pub fn cursor(&self) -> Option<Cursor> {
+		parse_cursor(&self.raw)
+	}
+
+	/// Split into the `(raw, clean)` pair returned by [`crate::KittyHarness::screen_text_clean`].
+	pub fn into_parts(self) -> (String, String) {
+		(self.raw, self.clean)
+	}
+}
+
+/// The part of a window's contents `kitty @ get-text --extent` returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CaptureExtent {
+	/// The visible screen.
+	#[default]
+	Screen,
+	/// The scrollback followed by the screen.
+	All,
+	/// The current selection.
+	Selection,
+	/// Output of the first command on screen (needs shell integration).
+	FirstCmdOutputOnScreen,
+	/// Output of the last command (needs shell integration).
+	LastCmdOutput,
+	/// Output of the last command scrolled to (needs shell integration).
+	LastVisitedCmdOutput,
+	/// Output of the last command that produced any (needs shell integration).
+	LastNonEmptyOutput,
+}
+
+impl CaptureExtent {
+	/// The value passed to `kitty @ get-text --extent`.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			CaptureExtent::Screen => "screen",
+			CaptureExtent::All => "all",
+			CaptureExtent::Selection => "selection",
+			CaptureExtent::FirstCmdOutputOnScreen => "first_cmd_output_on_screen",
+			CaptureExtent::LastCmdOutput => "last_cmd_output",
+			CaptureExtent::LastVisitedCmdOutput => "last_visited_cmd_output",
+			CaptureExtent::LastNonEmptyOutput => "last_non_empty_output",
+		}
+	}
+}
+
+/// The window a capture reads.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CaptureTarget {
+	/// The harness window.
+	#[default]
+	Harness,
+	/// A window by id, e.g. the [`crate::KittyWindow::id`] of a helper window.
+	Window(WindowId),
+	/// The first window matching a kitty match expression, e.g. `"title:^Help"`.
+	Match(String),
+}
+
+/// Options for [`crate::KittyHarness::capture_with`].
+///
+/// The defaults match [`crate::KittyHarness::screen_text`]: the visible
+/// screen of the harness window, with styles.
+///
+/// # Example
+///
+/// ```no_run
+/// use kitty_test_harness::{CaptureExtent, CaptureOptions};
+///
+/// # let kitty = kitty_test_harness::KittyHarness::launch(&kitty_test_harness::manifest_dir(), "my-app");
+/// let output = kitty.capture_with(&CaptureOptions::new().extent(CaptureExtent::LastCmdOutput).styles(false));
+/// let cursor = kitty.capture_with(&CaptureOptions::new().add_cursor()).cursor();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureOptions {
+	extent: CaptureExtent,
+	styles: bool,
+	add_cursor: bool,
+	add_wrap_markers: bool,
+	target: CaptureTarget,
+}
+
+impl Default for CaptureOptions {
+	fn default() -> Self {
+		Self {
+			extent: CaptureExtent::Screen,
+			styles: true,
+			add_cursor: false,
+			add_wrap_markers: false,
+			target: CaptureTarget::Harness,
+		}
+	}
+}
+
+impl CaptureOptions {
+	/// The visible screen of the harness window, with styles.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Capture `extent` instead of the visible screen.
+	pub fn extent(mut self, extent: CaptureExtent) -> Self {
+		self.extent = extent;
+		self
+	}
+
+	/// Keep (`true`, the default) or drop SGR styling and hyperlinks (`--ansi`).
+	pub fn styles(mut self, styles: bool) -> Self {
+		self.styles = styles;
+		self
+	}
+
+	/// Append the cursor position and visibility (`--add-cursor`); read it with [`ScreenCapture::cursor`].
+	pub fn add_cursor(mut self) -> Self {
+		self.add_cursor = true;
+		self
+	}
+
+	/// Mark soft-wrapped line ends with `\r` (`--add-wrap-markers`).
+	pub fn add_wrap_markers(mut self) -> Self {
+		self.add_wrap_markers = true;
+		self
+	}
+
+	/// Read `target` instead of the harness window.
+	pub fn target(mut self, target: CaptureTarget) -> Self {
+		self.target = target;
+		self
+	}
+
+	/// Read the first window matching the kitty match expression `expression`.
+	pub fn match_window(self, expression: impl Into<String>) -> Self {
+		self.target(CaptureTarget::Match(expression.into()))
+	}
+
+	/// The configured target window.
+	pub fn capture_target(&self) -> &CaptureTarget {
+		&self.target
+	}
+
+	/// Arguments for `kitty @`, reading `window_id` (the resolved target).
+	pub(crate) fn get_text_args(&self, window_id: WindowId) -> Vec<String> {
+		let mut args = vec![
+			"get-text".to_string(),
+			"--match".to_string(),
+			format!("id:{}", window_id.0),
+			"--extent".to_string(),
+			self.extent.as_str().to_string(),
+		];
+		for (set, flag) in [
+			(self.styles, "--ansi"),
+			(self.add_cursor, "--add-cursor"),
+			(self.add_wrap_markers, "--add-wrap-markers"),
+		] {
+			if set {
+				args.push(flag.to_string());
+			}
+		}
+		args
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn line_access_and_diff() {
+		let before = ScreenCapture::new("File  Edit\n\x1b[32mready\x1b[0m", (40, 10));
+		let after = ScreenCapture::new("File  Edit\nbusy", (40, 10));
+		assert_eq!(before.lines().collect::<Vec<_>>(), ["File  Edit", "ready"]);
+		assert_eq!(before.line(5), "");
+		assert_eq!(before.diff(&after).changed_rows(), vec![1]);
+		assert_eq!(after.into_parts(), ("File  Edit\nbusy".to_string(), "File  Edit\nbusy".to_string()));
+	}
+
+	#[test]
+	fn options_build_get_text_arguments() {
+		assert_eq!(
+			CaptureOptions::new().get_text_args(WindowId(3)),
+			["get-text", "--match", "id:3", "--extent", "screen", "--ansi"]
+		);
+		let options = CaptureOptions::new()
+			.extent(CaptureExtent::LastCmdOutput)
+			.styles(false)
+			.add_cursor()
+			.add_wrap_markers();
+		assert_eq!(
+			options.get_text_args(WindowId(3)),
+			[
+				"get-text",
+				"--match",
+				"id:3",
+				"--extent",
+				"last_cmd_output",
+				"--add-cursor",
+				"--add-wrap-markers"
+			]
+		);
+	}
+}
@@ -0,0 +1,141 @@
+//! Recording harness sessions as asciicast v2 files.
+//!
+//! While recording (see [`crate::KittyHarness::start_recording`]), every
+//! input sent to the harness window is stored as an `"i"` event and every
+//! distinct screen capture as an `"o"` event that redraws the whole screen.
+//! The result plays back with `asciinema play session.cast`. A harness that
+//! is dropped during a panic writes its recording to `recording.cast` in the
+//! session artifact directory.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::utils::error::HarnessError;
+
+/// Clear-screen and cursor-home prefix written before every captured frame.
+const FRAME_PREFIX: &str = "\x1b[H\x1b[2J";
+
+/// Kind of an asciicast event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastEventKind {
+	/// Data sent to the terminal (`"i"`).
+	Input,
+	/// A screen capture (`"o"`).
+	Output,
+}
+
+impl CastEventKind {
+	fn code(self) -> &'static str {
+		match self {
+			CastEventKind::Input => "i",
+			CastEventKind::Output => "o",
+		}
+	}
+}
+
+/// A single recorded event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CastEvent {
+	/// Time since recording started.
+	pub at: Duration,
+	/// Input or output.
+	pub kind: CastEventKind,
+	/// Event payload.
+	pub data: String,
+}
+
+/// Collects input and screen captures for an asciicast v2 file.
+#[derive(Debug, Clone)]
+pub struct CastRecorder {
+	started: Instant,
+	width: u16,
+	height: u16,
+	events: Vec<CastEvent>,
+	last_frame: Option<String>,
+}
+
+impl CastRecorder {
+	/// Start a recording for a `width` x `height` cell terminal.
+	pub fn new(width: u16, height: u16) -> Self {
+		Self {
+			started: Instant::now(),
+			width,
+			height,
+			events: Vec::new(),
+			last_frame: None,
+		}
+	}
+
+	/// Record input sent to the terminal.
+	pub fn input(&mut self, data: &str) {
+		self.push(CastEventKind::Input, data.to_string());
+	}
+
+	/// Record a raw screen capture; captures identical to the previous one are skipped.
+	pub fn frame(&mut self, raw: &str) {
+		if self.last_frame.as_deref() == Some(raw) {
+			return;
+		}
+		self.last_frame = Some(raw.to_string());
+		self.push(CastEventKind::Output, format!("{FRAME_PREFIX}{}", raw.replace('\n', "\r\n")));
+	}
+
+	/// Recorded events in order.
+	pub fn events(&self) -> &[CastEvent] {
+		&self.events
+	}
+
+	/// Render the recording as asciicast v2 (a JSON header line followed by one JSON array per event).
+	pub fn render(&self) -> String {
+		let header = serde_json::json!({
+			"version": 2,
+			"width": self.width,
+			"height": self.height,
+			"env": { "TERM": "xterm-kitty" },
+		});
+		let mut cast = format!("{header}\n");
+		for event in &self.events {
+			let line = serde_json::json!([event.at.as_secs_f64(), event.kind.code(), event.data]);
+			cast.push_str(&format!("{line}\n"));
+		}
+		cast
+	}
+
+	/// Write the recording to `path`, creating parent directories as needed.
+	pub fn write(&self, path: &Path) -> Result<(), HarnessError> {
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent).map_err(|err| HarnessError::io(format!("create {}", parent.display()), err))?;
+		}
+		std::fs::write(path, self.render()).map_err(|err| HarnessError::io(format!("write cast {}", path.display()), err))
+	}
+
+	fn push(&mut self, kind: CastEventKind, data: String) {
+		self.events.push(CastEvent {
+			at: self.started.elapsed(),
+			kind,
+			data,
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn renders_header_and_deduplicated_events() {
+		let mut recorder = CastRecorder::new(80, 24);
+		recorder.frame("$ ");
+		recorder.input("ls\r");
+		recorder.frame("$ ");
+		recorder.frame("$ ls\nfile");
+		let cast = recorder.render();
+		let lines: Vec<serde_json::Value> = cast.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+		assert_eq!(lines[0]["version"], 2);
+		assert_eq!((lines[0]["width"].as_u64(), lines[0]["height"].as_u64()), (Some(80), Some(24)));
+		let kinds: Vec<_> = lines[1..].iter().map(|line| line[1].as_str().unwrap()).collect();
+		assert_eq!(kinds, vec!["o", "i", "o"]);
+		assert_eq!(lines[3][2], format!("{FRAME_PREFIX}$ ls\r\nfile"));
+	}
+}
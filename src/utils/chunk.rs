@@ -0,0 +1,97 @@
+//! Chunking of large input payloads.
+//!
+//! Some kitty versions truncate or reject `send-text` payloads of several
+//! kilobytes. The harness therefore splits large text into chunks and pauses
+//! briefly between them, giving the application time to drain its input.
+//! Chunks end on character and escape sequence boundaries, so the
+//! application never reads half a sequence.
+//! Tune or disable this per harness with
+//! [`crate::KittyHarness::set_send_chunking`].
+
+use std::time::Duration;
+
+use crate::utils::ansi::{TokenKind, split_tokens};
+
+/// How [`crate::KittyHarness::send_text`] and friends split large payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendChunking {
+	/// Maximum bytes per `send-text` call.
+	pub max_bytes: usize,
+	/// Pause between consecutive chunks.
+	pub delay: Duration,
+}
+
+impl SendChunking {
+	/// Split payloads into chunks of at most `max_bytes`, pausing `delay` between them.
+	///
+	/// # Panics
+	///
+	/// Panics if `max_bytes` is zero.
+	pub fn new(max_bytes: usize, delay: Duration) -> Self {
+		assert!(max_bytes > 0, "chunk size must be positive");
+		Self { max_bytes, delay }
+	}
+
+	/// Send every payload in a single call.
+	pub fn disabled() -> Self {
+		Self {
+			max_bytes: usize::MAX,
+			delay: Duration::ZERO,
+		}
+	}
+}
+
+impl Default for SendChunking {
+	/// 1 KiB chunks with a 10ms pause.
+	fn default() -> Self {
+		Self::new(1024, Duration::from_millis(10))
+	}
+}
+
+/// Split `text` into pieces of at most `max_bytes`, never splitting a character or escape sequence.
+///
+/// A single character or escape sequence longer than `max_bytes` becomes its own piece.
+pub(crate) fn split_text(text: &str, max_bytes: usize) -> Vec<&str> {
+	if text.len() <= max_bytes {
+		return vec![text];
+	}
+	let mut pieces = Vec::new();
+	let mut piece_start = 0;
+	let mut offset = 0;
+	for token in split_tokens(text) {
+		let atoms: Vec<usize> = match token.kind {
+			TokenKind::Escape => vec![token.raw.len()],
+			TokenKind::Text => token.raw.chars().map(char::len_utf8).collect(),
+		};
+		for len in atoms {
+			if offset + len - piece_start > max_bytes && offset > piece_start {
+				pieces.push(&text[piece_start..offset]);
+				piece_start = offset;
+			}
+			offset += len;
+		}
+	}
+	pieces.push(&text[piece_start..]);
+	pieces
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn split_text_respects_char_boundaries() {
+		assert_eq!(split_text("abcdef", 4), vec!["abcd", "ef"]);
+		assert_eq!(split_text("aé€b", 3), vec!["aé", "€", "b"]);
+		assert_eq!(split_text("€", 1), vec!["€"]);
+		assert_eq!(split_text("", 4), vec![""]);
+		assert_eq!(split_text("abc", usize::MAX), vec!["abc"]);
+	}
+
+	#[test]
+	fn split_text_keeps_escape_sequences_whole() {
+		assert_eq!(split_text("ab\x1b[31mcd", 4), vec!["ab", "\x1b[31m", "cd"]);
+		assert_eq!(split_text("a\x1b[1mb", 5), vec!["a\x1b[1m", "b"]);
+		assert_eq!(split_text("\x1b]0;title\x07x", 3), vec!["\x1b]0;title\x07", "x"]);
+	}
+}
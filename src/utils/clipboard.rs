@@ -0,0 +1,31 @@
+//! Clipboard access for kitty harnesses.
+//!
+//! kitty has no remote control command for the clipboard, so the harness
+//! runs `kitty +kitten clipboard` in a short-lived overlay window on top of
+//! the harness window (see [`crate::utils::overlay`]). The kitten talks to
+//! kitty over OSC 52, which means the result is exactly what an application
+//! running in kitty would see.
+//!
+//! Clipboard access is opt-in with [`crate::LaunchOptions::clipboard`]. Such
+//! harnesses are launched with `clipboard_control` allowing reads without a
+//! confirmation prompt, so OSC 52 reads by the application work too. kitty
+//! uses the system clipboard, so tests that touch it overwrite the user's
+//! clipboard and must not run in parallel (e.g. put them in one test or
+//! serialize them with a shared lock).
+
+use crate::KittyHarness;
+use crate::utils::error::HarnessError;
+use crate::utils::overlay;
+
+/// `clipboard_control` value passed to harness kitty instances launched with [`crate::LaunchOptions::clipboard`].
+pub(crate) const CLIPBOARD_CONTROL: &str = "clipboard_control=write-clipboard write-primary read-clipboard read-primary";
+
+/// Replace the clipboard contents with `text`.
+pub(crate) fn set_clipboard(kitty: &KittyHarness, text: &str) -> Result<(), HarnessError> {
+	overlay::feed(kitty, kitty.window_id(), "clipboard-set", "kitty +kitten clipboard", text)
+}
+
+/// Read the current clipboard contents.
+pub(crate) fn get_clipboard(kitty: &KittyHarness) -> Result<String, HarnessError> {
+	overlay::capture(kitty, kitty.window_id(), "clipboard-get", "kitty +kitten clipboard --get-clipboard")
+}
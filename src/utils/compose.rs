@@ -0,0 +1,142 @@
+//! Simulation of composed (IME and dead-key) input.
+//!
+//! Terminal applications never see an input method's pre-edit state; kitty
+//! draws it itself and only writes the committed text to the pty. What
+//! differs between input paths is how that text is chunked: an IME commits
+//! a whole phrase in one write, while other paths deliver one character or
+//! one grapheme cluster at a time, which can split multi-codepoint sequences
+//! (combining marks, ZWJ emoji, Hangul jamo) across reads. The helpers here
+//! reproduce each of these deliveries, plus the output of dead-key
+//! composition.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use kitty_test_harness::utils::compose::{DeadKey, send_committed, send_dead_key, send_per_codepoint};
+//!
+//! send_committed(kitty, "日本語");
+//! send_per_codepoint(kitty, "e\u{301}", Duration::from_millis(20));
+//! send_dead_key(kitty, DeadKey::Acute, 'e'); // sends "é"
+//! ```
+
+use std::time::Duration;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::KittyHarness;
+
+/// Send `text` at once, the way an IME delivers a committed phrase.
+///
+/// This is one `send-text` call unless `text` exceeds the harness
+/// [`crate::SendChunking`] limit, in which case it is split into chunks.
+pub fn send_committed(kitty: &KittyHarness, text: &str) {
+	kitty.send_text(text);
+}
+
+/// Send `text` one Unicode scalar value at a time, pausing `delay` after each.
+///
+/// Combining sequences and emoji are split across writes, exposing readers
+/// that assume every read ends on a character boundary.
+pub fn send_per_codepoint(kitty: &KittyHarness, text: &str, delay: Duration) {
+	let mut buf = [0u8; 4];
+	for ch in text.chars() {
+		kitty.send_text(ch.encode_utf8(&mut buf));
+		std::thread::sleep(delay);
+	}
+}
+
+/// Send `text` one extended grapheme cluster at a time, pausing `delay` after each.
+pub fn send_per_grapheme(kitty: &KittyHarness, text: &str, delay: Duration) {
+	for grapheme in text.graphemes(true) {
+		kitty.send_text(grapheme);
+		std::thread::sleep(delay);
+	}
+}
+
+/// A dead key that combines with the following key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeadKey {
+	/// `´` (U+0301 when combining).
+	Acute,
+	/// `` ` `` (U+0300 when combining).
+	Grave,
+	/// `^` (U+0302 when combining).
+	Circumflex,
+	/// `~` (U+0303 when combining).
+	Tilde,
+	/// `¨` (U+0308 when combining).
+	Diaeresis,
+	/// `¸` (U+0327 when combining).
+	Cedilla,
+}
+
+impl DeadKey {
+	/// The combining mark this dead key adds.
+	pub fn combining_mark(self) -> char {
+		match self {
+			DeadKey::Acute => '\u{301}',
+			DeadKey::Grave => '\u{300}',
+			DeadKey::Circumflex => '\u{302}',
+			DeadKey::Tilde => '\u{303}',
+			DeadKey::Diaeresis => '\u{308}',
+			DeadKey::Cedilla => '\u{327}',
+		}
+	}
+
+	/// The standalone accent produced by pressing the dead key followed by space.
+	pub fn spacing_char(self) -> char {
+		match self {
+			DeadKey::Acute => '´',
+			DeadKey::Grave => '`',
+			DeadKey::Circumflex => '^',
+			DeadKey::Tilde => '~',
+			DeadKey::Diaeresis => '¨',
+			DeadKey::Cedilla => '¸',
+		}
+	}
+
+	fn table(self) -> (&'static str, &'static str) {
+		match self {
+			DeadKey::Acute => ("aeiouyAEIOUY", "áéíóúýÁÉÍÓÚÝ"),
+			DeadKey::Grave => ("aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+			DeadKey::Circumflex => ("aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+			DeadKey::Tilde => ("anoANO", "ãñõÃÑÕ"),
+			DeadKey::Diaeresis => ("aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+			DeadKey::Cedilla => ("cC", "çÇ"),
+		}
+	}
+}
+
+/// The text a dead-key sequence commits.
+///
+/// Common Latin letters compose to their precomposed form (`é`), a space
+/// yields the spacing accent, and other bases fall back to the base followed
+/// by the combining mark.
+pub fn dead_key_output(dead_key: DeadKey, base: char) -> String {
+	if base == ' ' {
+		return dead_key.spacing_char().to_string();
+	}
+	let (bases, composed) = dead_key.table();
+	match bases.chars().position(|ch| ch == base) {
+		Some(idx) => composed.chars().nth(idx).map(String::from).unwrap_or_default(),
+		None => format!("{base}{}", dead_key.combining_mark()),
+	}
+}
+
+/// Send the result of pressing `dead_key` followed by `base`.
+pub fn send_dead_key(kitty: &KittyHarness, dead_key: DeadKey, base: char) {
+	kitty.send_text(&dead_key_output(dead_key, base));
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn dead_keys_compose() {
+		assert_eq!(dead_key_output(DeadKey::Acute, 'e'), "é");
+		assert_eq!(dead_key_output(DeadKey::Tilde, 'N'), "Ñ");
+		assert_eq!(dead_key_output(DeadKey::Circumflex, ' '), "^");
+		assert_eq!(dead_key_output(DeadKey::Diaeresis, 'x'), "x\u{308}");
+	}
+}
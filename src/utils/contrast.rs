@@ -0,0 +1,173 @@
+//! WCAG contrast checks over styled captures.
+//!
+//! [`contrast_ratio`] implements the WCAG 2 formula (1.0 for identical
+//! colors up to 21.0 for black on white). [`find_low_contrast`] resolves
+//! every visible cell of a [`ScreenGrid`] against a [`Palette`], applying
+//! reverse video and dim the way the terminal draws them, and reports cells
+//! below a minimum ratio. WCAG AA asks for 4.5 for normal text.
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::utils::contrast::{assert_min_contrast_with, find_low_contrast};
+//! use kitty_test_harness::{Palette, ScreenGrid};
+//!
+//! let grid = ScreenGrid::parse("readable \x1b[38;5;236mfaint\x1b[0m");
+//! let palette = Palette::kitty_default();
+//! assert_eq!(find_low_contrast(&grid, &palette, 4.5).len(), 5);
+//! assert_min_contrast_with(&ScreenGrid::parse("readable"), &palette, 4.5);
+//! ```
+
+use crate::utils::screen::{ScreenGrid, display_width};
+use crate::utils::theme::{Palette, Rgb, hex};
+
+/// A visible cell whose text does not stand out enough from its background.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContrastIssue {
+	/// 0-based row.
+	pub row: usize,
+	/// 0-based display column, counting wide characters before it as two columns.
+	pub col: usize,
+	/// The character drawn.
+	pub ch: char,
+	/// Effective text color.
+	pub fg: Rgb,
+	/// Effective background color.
+	pub bg: Rgb,
+	/// Contrast ratio between `fg` and `bg`.
+	pub ratio: f64,
+}
+
+/// WCAG 2 contrast ratio between two colors, from 1.0 to 21.0.
+///
+/// ```
+/// use kitty_test_harness::utils::contrast::contrast_ratio;
+///
+/// assert!((contrast_ratio((0, 0, 0), (255, 255, 255)) - 21.0).abs() < 1e-9);
+/// assert_eq!(contrast_ratio((10, 20, 30), (10, 20, 30)), 1.0);
+/// ```
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+	let (la, lb) = (relative_luminance(a), relative_luminance(b));
+	let (light, dark) = if la >= lb { (la, lb) } else { (lb, la) };
+	(light + 0.05) / (dark + 0.05)
+}
+
+/// WCAG relative luminance of an sRGB color.
+fn relative_luminance((r, g, b): Rgb) -> f64 {
+	let channel = |value: u8| {
+		let c = f64::from(value) / 255.0;
+		if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+	};
+	0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+/// Return every non-whitespace cell of `grid` whose contrast is below `min_ratio`.
+pub fn find_low_contrast(grid: &ScreenGrid, palette: &Palette, min_ratio: f64) -> Vec<ContrastIssue> {
+	let mut issues = Vec::new();
+	for (row, cells) in grid.rows().iter().enumerate() {
+		let mut col = 0;
+		for cell in cells {
+			let cell_col = col;
+			col += char_width(cell.ch);
+			if cell.ch.is_whitespace() {
+				continue;
+			}
+			let (fg, bg) = cell.effective_colors(palette);
+			let ratio = contrast_ratio(fg, bg);
+			if ratio < min_ratio {
+				issues.push(ContrastIssue {
+					row,
+					col: cell_col,
+					ch: cell.ch,
+					fg,
+					bg,
+					ratio,
+				});
+			}
+		}
+	}
+	issues
+}
+
+/// Assert that all visible text in `grid` has at least `min_ratio` contrast under kitty's default colors.
+///
+/// Use [`assert_min_contrast_with`] when the harness runs with another theme.
+///
+/// # Panics
+///
+/// Panics listing each offending run of text with its colors and ratio.
+pub fn assert_min_contrast(grid: &ScreenGrid, min_ratio: f64) {
+	assert_min_contrast_with(grid, &Palette::kitty_default(), min_ratio)
+}
+
+/// Assert that all visible text in `grid` has at least `min_ratio` contrast against `palette`.
+///
+/// # Panics
+///
+/// Panics listing each offending run of text with its colors and ratio.
+pub fn assert_min_contrast_with(grid: &ScreenGrid, palette: &Palette, min_ratio: f64) {
+	let issues = find_low_contrast(grid, palette, min_ratio);
+	if issues.is_empty() {
+		return;
+	}
+
+	let mut report = String::new();
+	let mut previous: Option<&ContrastIssue> = None;
+	for issue in &issues {
+		let continues =
+			previous.is_some_and(|prev| prev.row == issue.row && prev.col + char_width(prev.ch) == issue.col && (prev.fg, prev.bg) == (issue.fg, issue.bg));
+		if !continues {
+			report.push_str(&format!(
+				"\n  row {}, col {} ({:.2}:1, fg {} on bg {}): ",
+				issue.row,
+				issue.col,
+				issue.ratio,
+				hex(issue.fg),
+				hex(issue.bg)
+			));
+		}
+		report.push(issue.ch);
+		previous = Some(issue);
+	}
+	panic!("{} character(s) below contrast ratio {min_ratio}:1:{report}", issues.len());
+}
+
+/// Display columns taken by `ch`.
+fn char_width(ch: char) -> usize {
+	display_width(ch.encode_utf8(&mut [0; 4]))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reverse_and_dim_change_effective_contrast() {
+		let palette = Palette::kitty_default();
+		// Reverse video of default colors is still high contrast.
+		assert!(find_low_contrast(&ScreenGrid::parse("\x1b[7mselected"), &palette, 4.5).is_empty());
+		// Blue on black is below AA, and dimming makes white on black fail a strict threshold.
+		let issues = find_low_contrast(&ScreenGrid::parse("\x1b[34mb\x1b[0m \x1b[2;97mw"), &palette, 7.0);
+		assert_eq!(issues.iter().map(|issue| (issue.col, issue.ch)).collect::<Vec<_>>(), vec![(0, 'b'), (2, 'w')]);
+		assert_eq!(issues[1].fg, (0x7f, 0x7f, 0x7f));
+	}
+
+	#[test]
+	fn columns_count_wide_characters() {
+		let issues = find_low_contrast(&ScreenGrid::parse("日本\x1b[38;5;240m語x"), &Palette::kitty_default(), 4.5);
+		assert_eq!(issues.iter().map(|issue| (issue.col, issue.ch)).collect::<Vec<_>>(), vec![(4, '語'), (6, 'x')]);
+	}
+
+	#[test]
+	fn wide_runs_are_reported_together() {
+		let payload = std::panic::catch_unwind(|| assert_min_contrast(&ScreenGrid::parse("日本\x1b[38;5;240m語x"), 4.5)).unwrap_err();
+		let message = payload.downcast_ref::<String>().unwrap();
+		assert!(message.ends_with("row 0, col 4 (2.95:1, fg #585858 on bg #000000): 語x"), "{message}");
+	}
+
+	#[test]
+	#[should_panic(expected = "row 0, col 4")]
+	fn assertion_reports_runs() {
+		assert_min_contrast(&ScreenGrid::parse("fine\x1b[38;5;240mgrey"), 4.5);
+	}
+}
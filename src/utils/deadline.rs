@@ -0,0 +1,75 @@
+//! Overall time budget shared by every wait of a test.
+//!
+//! Individual waits take their own timeout, so a test with five 5-second
+//! waits can hang for 25 seconds. A harness with a [`Deadline`] clamps each
+//! wait to the time left in the budget instead. Every harness picks up
+//! [`DEADLINE_ENV`] (seconds, e.g. `KITTY_TEST_DEADLINE=10`) when created;
+//! override it with [`crate::KittyHarness::set_deadline`].
+
+use std::time::{Duration, Instant};
+
+/// Environment variable holding the per-harness budget in seconds.
+pub const DEADLINE_ENV: &str = "KITTY_TEST_DEADLINE";
+
+/// A point in time after which waits give up immediately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+	started: Instant,
+	budget: Duration,
+}
+
+impl Deadline {
+	/// A deadline `budget` from now.
+	pub fn new(budget: Duration) -> Self {
+		Self {
+			started: Instant::now(),
+			budget,
+		}
+	}
+
+	/// A deadline built from [`DEADLINE_ENV`], or `None` when it is unset or invalid.
+	pub fn from_env() -> Option<Self> {
+		parse_budget(&std::env::var(DEADLINE_ENV).ok()?).map(Self::new)
+	}
+
+	/// The total budget.
+	pub fn budget(&self) -> Duration {
+		self.budget
+	}
+
+	/// Time left before the deadline (zero once expired).
+	pub fn remaining(&self) -> Duration {
+		self.budget.saturating_sub(self.started.elapsed())
+	}
+
+	/// Whether the budget is used up.
+	pub fn is_expired(&self) -> bool {
+		self.remaining().is_zero()
+	}
+
+	/// Shorten `timeout` so it does not run past the deadline.
+	pub fn clamp(&self, timeout: Duration) -> Duration {
+		timeout.min(self.remaining())
+	}
+}
+
+fn parse_budget(value: &str) -> Option<Duration> {
+	Duration::try_from_secs_f64(value.trim().parse().ok()?).ok().filter(|budget| !budget.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clamps_to_remaining_budget() {
+		let deadline = Deadline::new(Duration::from_secs(10));
+		assert!(deadline.clamp(Duration::from_secs(30)) <= Duration::from_secs(10));
+		assert_eq!(deadline.clamp(Duration::from_millis(5)), Duration::from_millis(5));
+		assert!(Deadline::new(Duration::ZERO).is_expired());
+
+		assert_eq!(parse_budget(" 2.5 "), Some(Duration::from_millis(2500)));
+		assert_eq!(parse_budget("0"), None);
+		assert_eq!(parse_budget("soon"), None);
+	}
+}
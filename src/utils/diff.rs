@@ -0,0 +1,333 @@
+//! Row and cell diffs between two screen captures.
+//!
+//! [`diff_screens`] compares two clean captures and reports every row whose
+//! text changed, with the old and new text and the individual display
+//! columns that differ. Trailing whitespace is not significant, so a row
+//! missing from one capture equals a blank row in the other.
+//!
+//! [`diff_screens_masked`] blanks [`MaskSet`] regions (a clock widget, a
+//! spinner) in both captures first, and [`crate::KittyHarness::diff_since`]
+//! diffs against a checkpoint with the harness masks applied.
+//!
+//! [`CompareOptions`] selects how strict a comparison is: exact, or
+//! ignoring colors and attributes, trailing whitespace, or the width of
+//! runs of spaces. [`assert_screens_match`] applies it per assertion, so a
+//! theme tweak or a column of alignment drift does not fail a check that
+//! is only about content.
+//!
+//! [`ScreenDiff`] implements [`std::fmt::Display`] as a unified-style listing
+//! with the changed columns marked, for use in assertion messages:
+//!
+//! ```text
+//! 1 row changed:
+//!   row 23:
+//!     - NORMAL  main.rs
+//!     + INSERT  main.rs [+]
+//!       ^^^^^^          ^^^
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::utils::diff::diff_screens;
+//!
+//! let before = "fn main() {}\n\nNORMAL  main.rs";
+//! let after = "fn main() {}\n\nINSERT  main.rs";
+//! let diff = diff_screens(before, after);
+//! assert_eq!(diff.changed_rows(), vec![2]);
+//! assert_eq!(diff.rows[0].cells[0].before, "N");
+//! diff.assert_only_rows(&[2]);
+//! ```
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use crate::utils::ansi::{CleanOptions, clean_trailing_whitespace_with, strip_ansi};
+use crate::utils::mask::MaskSet;
+use crate::utils::normalize::{Normalizer, Replace};
+use crate::utils::screen::cells;
+
+/// A display column whose content differs between two captures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellChange {
+	/// 0-based display column.
+	pub col: usize,
+	/// Grapheme drawn before (`" "` past the end of the row, `""` for the second half of a wide character).
+	pub before: String,
+	/// Grapheme drawn after.
+	pub after: String,
+}
+
+/// A row whose text differs between two captures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RowDiff {
+	/// 0-based row index.
+	pub row: usize,
+	/// Row text before, with trailing whitespace trimmed.
+	pub before: String,
+	/// Row text after, with trailing whitespace trimmed.
+	pub after: String,
+	/// Changed display columns, left to right.
+	pub cells: Vec<CellChange>,
+}
+
+/// Every changed row between two captures, top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScreenDiff {
+	/// Changed rows in order.
+	pub rows: Vec<RowDiff>,
+}
+
+impl ScreenDiff {
+	/// Returns true if both captures show the same text.
+	pub fn is_empty(&self) -> bool {
+		self.rows.is_empty()
+	}
+
+	/// Indices of the changed rows.
+	pub fn changed_rows(&self) -> Vec<usize> {
+		self.rows.iter().map(|diff| diff.row).collect()
+	}
+
+	/// Return the diff of `row`, or `None` if it did not change.
+	pub fn row(&self, row: usize) -> Option<&RowDiff> {
+		self.rows.iter().find(|diff| diff.row == row)
+	}
+
+	/// Assert that no rows other than `rows` changed.
+	///
+	/// Rows listed in `rows` may also be unchanged; use
+	/// [`ScreenDiff::changed_rows`] to require a change.
+	///
+	/// # Panics
+	///
+	/// Panics with the unexpected part of the diff.
+	pub fn assert_only_rows(&self, rows: &[usize]) {
+		let unexpected = ScreenDiff {
+			rows: self.rows.iter().filter(|diff| !rows.contains(&diff.row)).cloned().collect(),
+		};
+		if !unexpected.is_empty() {
+			panic!("expected only rows {rows:?} to change, but {unexpected}");
+		}
+	}
+}
+
+impl fmt::Display for ScreenDiff {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.rows.is_empty() {
+			return f.write_str("no rows changed");
+		}
+		let noun = if self.rows.len() == 1 { "row" } else { "rows" };
+		write!(f, "{} {noun} changed:", self.rows.len())?;
+		for diff in &self.rows {
+			write!(f, "\n  row {}:\n    - {}\n    + {}", diff.row, diff.before, diff.after)?;
+			let width = diff.cells.last().map_or(0, |cell| cell.col + 1);
+			let mut markers = vec![' '; width];
+			for cell in &diff.cells {
+				markers[cell.col] = '^';
+			}
+			write!(f, "\n      {}", markers.into_iter().collect::<String>())?;
+		}
+		Ok(())
+	}
+}
+
+/// Compare two clean captures row by row and column by column.
+pub fn diff_screens(before: &str, after: &str) -> ScreenDiff {
+	let before_lines: Vec<&str> = before.lines().collect();
+	let after_lines: Vec<&str> = after.lines().collect();
+	let count = before_lines.len().max(after_lines.len());
+
+	let rows = (0..count)
+		.filter_map(|row| {
+			let old = before_lines.get(row).copied().unwrap_or_default().trim_end();
+			let new = after_lines.get(row).copied().unwrap_or_default().trim_end();
+			(old != new).then(|| RowDiff {
+				row,
+				before: old.to_string(),
+				after: new.to_string(),
+				cells: diff_cells(old, new),
+			})
+		})
+		.collect();
+	ScreenDiff { rows }
+}
+
+/// Like [`diff_screens`], but with the regions covered by `masks` blanked in both captures first.
+///
+/// A clock or spinner inside a masked rectangle then never shows up as a change:
+///
+/// ```
+/// use kitty_test_harness::utils::diff::diff_screens_masked;
+/// use kitty_test_harness::utils::mask::MaskSet;
+///
+/// let masks = MaskSet::new().rect(0, 10, 5, 1);
+/// assert!(diff_screens_masked("status    12:00", "status    12:01", &masks).is_empty());
+/// assert_eq!(diff_screens_masked("a\nx", "b\nx", &masks).changed_rows(), vec![0]);
+/// ```
+pub fn diff_screens_masked(before: &str, after: &str, masks: &MaskSet) -> ScreenDiff {
+	diff_screens(&masks.apply(before), &masks.apply(after))
+}
+
+/// How strictly two captures are compared by [`assert_screens_match`].
+///
+/// The default is exact. Each option removes one kind of difference from
+/// both sides before comparing:
+///
+/// ```
+/// use kitty_test_harness::utils::diff::CompareOptions;
+///
+/// let loose = CompareOptions::exact().ignore_style().collapse_spaces();
+/// assert!(loose.matches("\x1b[31mName:\x1b[0m   Ada", "Name: Ada  "));
+/// assert!(!CompareOptions::exact().matches("a  b", "a b"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompareOptions {
+	/// Strip escape sequences, comparing only text.
+	pub ignore_style: bool,
+	/// Ignore whitespace at the end of lines and blank lines at the end.
+	pub ignore_trailing_whitespace: bool,
+	/// Treat any run of spaces as a single space (also ignores trailing whitespace).
+	pub collapse_spaces: bool,
+}
+
+impl CompareOptions {
+	/// Compare captures byte for byte.
+	pub fn exact() -> Self {
+		Self::default()
+	}
+
+	/// Ignore colors and text attributes.
+	pub fn ignore_style(mut self) -> Self {
+		self.ignore_style = true;
+		self
+	}
+
+	/// Ignore trailing whitespace on each line and trailing blank lines.
+	pub fn ignore_trailing_whitespace(mut self) -> Self {
+		self.ignore_trailing_whitespace = true;
+		self
+	}
+
+	/// Collapse runs of spaces, so alignment padding of any width compares equal.
+	pub fn collapse_spaces(mut self) -> Self {
+		self.collapse_spaces = true;
+		self
+	}
+
+	/// Return `capture` with the ignored differences removed.
+	pub fn normalize(&self, capture: &str) -> String {
+		let mut text = if self.ignore_style { strip_ansi(capture) } else { capture.to_string() };
+		if self.collapse_spaces {
+			static SPACE_RUNS: LazyLock<Replace> = LazyLock::new(|| Replace::new(" {2,}", " "));
+			text = SPACE_RUNS.normalize(&text);
+		}
+		if self.ignore_trailing_whitespace || self.collapse_spaces {
+			text = clean_trailing_whitespace_with(&text, &CleanOptions::default().trim_text_runs());
+		}
+		text
+	}
+
+	/// Returns true if `a` and `b` are equal under these options.
+	pub fn matches(&self, a: &str, b: &str) -> bool {
+		self.normalize(a) == self.normalize(b)
+	}
+}
+
+impl Normalizer for CompareOptions {
+	fn normalize(&self, capture: &str) -> String {
+		CompareOptions::normalize(self, capture)
+	}
+}
+
+/// Assert that `actual` equals `expected` under `options`.
+///
+/// # Panics
+///
+/// Panics with the [`ScreenDiff`] of the normalized captures, or with both
+/// captures escaped when they differ only in styling.
+pub fn assert_screens_match(expected: &str, actual: &str, options: CompareOptions) {
+	let (expected, actual) = (options.normalize(expected), options.normalize(actual));
+	if expected == actual {
+		return;
+	}
+	let diff = diff_screens(&strip_ansi(&expected), &strip_ansi(&actual));
+	if diff.is_empty() {
+		panic!("captures differ only in styling ({options:?})\n--- expected ---\n{expected:?}\n--- actual ---\n{actual:?}");
+	}
+	panic!("captures differ ({options:?}): {diff}");
+}
+
+fn diff_cells(before: &str, after: &str) -> Vec<CellChange> {
+	let (old, new) = (cells(before), cells(after));
+	(0..old.len().max(new.len()))
+		.filter_map(|col| {
+			let a = old.get(col).copied().unwrap_or(" ");
+			let b = new.get(col).copied().unwrap_or(" ");
+			(a != b).then(|| CellChange {
+				col,
+				before: a.to_string(),
+				after: b.to_string(),
+			})
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reports_changed_cells_and_ignores_trailing_blanks() {
+		let diff = diff_screens("ab  \nsame\n日x", "aB\nsame\n\nextra");
+		assert_eq!(diff.changed_rows(), vec![0, 2, 3]);
+		assert_eq!(
+			diff.row(0).unwrap().cells,
+			vec![CellChange {
+				col: 1,
+				before: "b".into(),
+				after: "B".into()
+			}]
+		);
+		let wide: Vec<usize> = diff.row(2).unwrap().cells.iter().map(|cell| cell.col).collect();
+		assert_eq!(wide, vec![0, 1, 2]);
+		assert!(diff_screens("x\n", "x   \n\n").is_empty());
+	}
+
+	#[test]
+	fn display_marks_changed_columns() {
+		let diff = diff_screens("top\nNORMAL  a", "top\nINSERT  a+");
+		assert_eq!(
+			diff.to_string(),
+			"1 row changed:\n  row 1:\n    - NORMAL  a\n    + INSERT  a+\n      ^^^^^^   ^"
+		);
+	}
+
+	#[test]
+	fn compare_options_remove_selected_differences() {
+		let expected = "\x1b[32mok\x1b[0m   done\n";
+		assert_screens_match(expected, "ok done", CompareOptions::exact().ignore_style().collapse_spaces());
+		assert!(CompareOptions::exact().ignore_trailing_whitespace().matches("a  \n\n", "a"));
+		assert!(!CompareOptions::exact().ignore_trailing_whitespace().matches("a  b", "a b"));
+		// Collapsing spaces keeps styling when it is not ignored.
+		assert_eq!(
+			CompareOptions::exact().collapse_spaces().normalize("\x1b[1ma\x1b[0m    b  "),
+			"\x1b[1ma\x1b[0m b"
+		);
+	}
+
+	#[test]
+	#[should_panic(expected = "captures differ only in styling")]
+	fn style_only_mismatch_is_reported() {
+		assert_screens_match("\x1b[31mred", "\x1b[32mred", CompareOptions::exact());
+	}
+
+	#[test]
+	#[should_panic(expected = "expected only rows [1] to change, but 1 row changed:\n  row 0:")]
+	fn assert_only_rows_reports_unexpected_rows() {
+		diff_screens("a\nb", "A\nB").assert_only_rows(&[1]);
+	}
+}
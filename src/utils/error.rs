@@ -0,0 +1,136 @@
+//! Error type for fallible harness operations.
+//!
+//! The `try_*` methods on [`crate::KittyHarness`] return [`HarnessError`]
+//! instead of panicking, so tests can skip or retry when kitty is missing,
+//! misconfigured, or a remote command fails. The non-`try` methods keep
+//! their panicking behaviour and use this type's `Display` as the message.
+
+use std::error::Error;
+use std::process::{ExitStatus, Output};
+use std::{fmt, io};
+
+use crate::utils::window::RemoteControlError;
+
+/// Failure of a harness operation, with the context needed to diagnose it.
+#[derive(Debug)]
+pub enum HarnessError {
+	/// A kitty process could not be started.
+	Spawn {
+		/// Short description of the command (e.g. `kitty @ get-text`).
+		command: String,
+		/// Underlying spawn error.
+		source: io::Error,
+	},
+	/// A kitty process ran but reported failure.
+	CommandFailed {
+		/// Short description of the command (e.g. `kitty @ send-text`).
+		command: String,
+		/// Exit status of the process.
+		status: ExitStatus,
+		/// Captured stdout (empty when output was not captured).
+		stdout: String,
+		/// Captured stderr (empty when output was not captured).
+		stderr: String,
+	},
+	/// Remote control never became usable after launch.
+	RemoteControl(RemoteControlError),
+	/// A command succeeded but its output could not be interpreted.
+	Parse {
+		/// Short description of the command whose output failed to parse.
+		command: String,
+		/// What went wrong.
+		message: String,
+	},
+	/// A filesystem operation (config or artifact files) failed.
+	Io {
+		/// What the harness was doing.
+		context: String,
+		/// Underlying I/O error.
+		source: io::Error,
+	},
+}
+
+impl HarnessError {
+	/// Build a [`HarnessError::CommandFailed`] from captured process output.
+	pub(crate) fn failed(command: impl Into<String>, output: &Output) -> Self {
+		HarnessError::CommandFailed {
+			command: command.into(),
+			status: output.status,
+			stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+			stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+		}
+	}
+
+	/// Build a [`HarnessError::Spawn`].
+	pub(crate) fn spawn(command: impl Into<String>, source: io::Error) -> Self {
+		HarnessError::Spawn {
+			command: command.into(),
+			source,
+		}
+	}
+
+	/// Build a [`HarnessError::Io`].
+	pub(crate) fn io(context: impl Into<String>, source: io::Error) -> Self {
+		HarnessError::Io {
+			context: context.into(),
+			source,
+		}
+	}
+}
+
+impl fmt::Display for HarnessError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			HarnessError::Spawn { command, source } => write!(f, "{command} could not be run: {source}"),
+			HarnessError::CommandFailed {
+				command,
+				status,
+				stdout,
+				stderr,
+			} => write!(f, "{command} failed ({status}): stdout: {} stderr: {}", stdout.trim(), stderr.trim()),
+			HarnessError::RemoteControl(err) => err.fmt(f),
+			HarnessError::Parse { command, message } => write!(f, "could not parse output of {command}: {message}"),
+			HarnessError::Io { context, source } => write!(f, "{context}: {source}"),
+		}
+	}
+}
+
+impl Error for HarnessError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			HarnessError::Spawn { source, .. } | HarnessError::Io { source, .. } => Some(source),
+			HarnessError::RemoteControl(err) => Some(err),
+			HarnessError::CommandFailed { .. } | HarnessError::Parse { .. } => None,
+		}
+	}
+}
+
+impl From<RemoteControlError> for HarnessError {
+	fn from(err: RemoteControlError) -> Self {
+		HarnessError::RemoteControl(err)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::process::Command;
+
+	use super::*;
+
+	#[test]
+	fn command_failed_includes_captured_output() {
+		let output = Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]).output().unwrap();
+		let err = HarnessError::failed("kitty @ get-text", &output);
+		let message = err.to_string();
+		assert!(message.starts_with("kitty @ get-text failed"));
+		assert!(message.contains("stdout: out"));
+		assert!(message.contains("stderr: err"));
+	}
+
+	#[test]
+	fn remote_control_error_is_the_source() {
+		let err = HarnessError::from(RemoteControlError::NoWindow);
+		assert!(err.source().is_some());
+		assert_eq!(err.to_string(), RemoteControlError::NoWindow.to_string());
+	}
+}
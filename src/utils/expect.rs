@@ -0,0 +1,247 @@
+//! Fluent assertions over a single screen capture.
+//!
+//! [`expect_screen`] captures the screen and cursor once and returns a
+//! [`ScreenExpectation`] whose matchers can be chained. Each matcher checks
+//! immediately and, on failure, panics with what was expected, the offending
+//! row and the whole capture with row numbers, instead of the bare
+//! `assertion failed: clean.contains(..)` of a plain `assert!`.
+//!
+//! ```ignore
+//! use kitty_test_harness::expect_screen;
+//!
+//! expect_screen(&kitty)
+//!     .contains("Saved").at_row(23)
+//!     .not_contains("ERROR")
+//!     .cursor_at(5, 10);
+//! ```
+//!
+//! A failing `at_row` reads:
+//!
+//! ```text
+//! expected "Saved" at row 23
+//!   row 23: "-- INSERT --"
+//!   found at: row 0, col 3
+//! --- screen (cursor at row 5, col 10) ---
+//!  0 | a  Saved file.txt
+//! ...
+//! ```
+
+use regex::Regex;
+
+use crate::KittyHarness;
+use crate::utils::ansi::strip_ansi;
+use crate::utils::screen::{Cursor, find_all_positions};
+
+/// A captured screen with chainable assertions; see [`expect_screen`].
+#[derive(Debug, Clone)]
+pub struct ScreenExpectation {
+	raw: String,
+	clean: String,
+	cursor: Option<Cursor>,
+	last: Option<String>,
+}
+
+/// Capture the screen and cursor of the harness window for chained assertions.
+pub fn expect_screen(kitty: &KittyHarness) -> ScreenExpectation {
+	let raw = kitty.screen_text();
+	ScreenExpectation::from_capture(&raw, Some(kitty.cursor()))
+}
+
+impl ScreenExpectation {
+	/// Build an expectation from an existing raw capture and, optionally, the cursor.
+	pub fn from_capture(raw: &str, cursor: Option<Cursor>) -> Self {
+		Self {
+			raw: raw.to_string(),
+			clean: strip_ansi(raw),
+			cursor,
+			last: None,
+		}
+	}
+
+	/// The raw capture being checked.
+	pub fn raw(&self) -> &str {
+		&self.raw
+	}
+
+	/// The capture with ANSI sequences stripped.
+	pub fn clean(&self) -> &str {
+		&self.clean
+	}
+
+	/// Expect `needle` somewhere on the screen.
+	///
+	/// Follow with [`ScreenExpectation::at_row`] or [`ScreenExpectation::at`]
+	/// to pin down where.
+	pub fn contains(mut self, needle: &str) -> Self {
+		if !self.clean.contains(needle) {
+			self.fail(&format!("expected screen to contain {needle:?}"));
+		}
+		self.last = Some(needle.to_string());
+		self
+	}
+
+	/// Expect `needle` nowhere on the screen.
+	pub fn not_contains(self, needle: &str) -> Self {
+		if let Some(&(row, col)) = find_all_positions(&self.clean, needle).first() {
+			self.fail(&format!("expected screen not to contain {needle:?}\n  found at: row {row}, col {col}"));
+		}
+		self
+	}
+
+	/// Expect a match of the regex `pattern` somewhere on the screen.
+	///
+	/// # Panics
+	///
+	/// Panics if `pattern` is not a valid regex.
+	pub fn matches(self, pattern: &str) -> Self {
+		let regex = Regex::new(pattern).unwrap_or_else(|err| panic!("invalid pattern {pattern:?}: {err}"));
+		if !regex.is_match(&self.clean) {
+			self.fail(&format!("expected screen to match /{pattern}/"));
+		}
+		self
+	}
+
+	/// Expect the text of the preceding [`ScreenExpectation::contains`] on `row`.
+	///
+	/// # Panics
+	///
+	/// Panics if no `contains` precedes it, or if the text is not on `row`.
+	pub fn at_row(self, row: usize) -> Self {
+		let needle = self.last_needle("at_row");
+		if !self.row_text(row).contains(needle) {
+			self.fail(&format!(
+				"expected {needle:?} at row {row}\n  row {row}: {:?}{}",
+				self.row_text(row),
+				self.found(needle)
+			));
+		}
+		self
+	}
+
+	/// Expect the text of the preceding [`ScreenExpectation::contains`] to start at (`row`, `col`).
+	///
+	/// `col` is a display column, like [`crate::KittyHarness::cursor`] reports.
+	///
+	/// # Panics
+	///
+	/// Panics if no `contains` precedes it, or if the text does not start there.
+	pub fn at(self, row: usize, col: usize) -> Self {
+		let needle = self.last_needle("at");
+		if !find_all_positions(&self.clean, needle).contains(&(row, col)) {
+			self.fail(&format!(
+				"expected {needle:?} at row {row}, col {col}\n  row {row}: {:?}{}",
+				self.row_text(row),
+				self.found(needle)
+			));
+		}
+		self
+	}
+
+	/// Expect row `row` to read exactly `text`, ignoring trailing whitespace.
+	pub fn row_eq(self, row: usize, text: &str) -> Self {
+		let actual = self.row_text(row);
+		if actual.trim_end() != text.trim_end() {
+			self.fail(&format!("expected row {row} to be {text:?}\n  row {row}: {actual:?}"));
+		}
+		self
+	}
+
+	/// Expect the cursor at (`row`, `col`).
+	///
+	/// # Panics
+	///
+	/// Panics if the cursor was not captured or is elsewhere.
+	pub fn cursor_at(self, row: usize, col: usize) -> Self {
+		match self.cursor {
+			Some(cursor) if (cursor.row, cursor.col) == (row, col) => {}
+			Some(cursor) => self.fail(&format!(
+				"expected cursor at row {row}, col {col}\n  cursor: row {}, col {}",
+				cursor.row, cursor.col
+			)),
+			None => self.fail("expected a cursor position, but none was captured"),
+		}
+		self
+	}
+
+	/// Expect the cursor to be shown (`true`) or hidden (`false`).
+	pub fn cursor_visible(self, visible: bool) -> Self {
+		if self.cursor.map(|cursor| cursor.visible) != Some(visible) {
+			let state = if visible { "visible" } else { "hidden" };
+			self.fail(&format!("expected cursor to be {state}"));
+		}
+		self
+	}
+
+	fn row_text(&self, row: usize) -> &str {
+		self.clean.lines().nth(row).unwrap_or_default()
+	}
+
+	fn last_needle(&self, matcher: &str) -> &str {
+		self.last.as_deref().unwrap_or_else(|| panic!("{matcher}() must follow contains()"))
+	}
+
+	/// Where `needle` was found instead, as a failure message line.
+	fn found(&self, needle: &str) -> String {
+		let positions: Vec<String> = find_all_positions(&self.clean, needle)
+			.iter()
+			.map(|(row, col)| format!("row {row}, col {col}"))
+			.collect();
+		if positions.is_empty() {
+			String::new()
+		} else {
+			format!("\n  found at: {}", positions.join("; "))
+		}
+	}
+
+	fn fail(&self, message: &str) -> ! {
+		let cursor = match self.cursor {
+			Some(cursor) => format!(
+				" (cursor at row {}, col {}{})",
+				cursor.row,
+				cursor.col,
+				if cursor.visible { "" } else { ", hidden" }
+			),
+			None => String::new(),
+		};
+		let lines: Vec<&str> = self.clean.lines().collect();
+		let width = lines.len().saturating_sub(1).to_string().len();
+		let screen: String = lines.iter().enumerate().map(|(row, line)| format!("\n{row:>width$} | {line}")).collect();
+		panic!("{message}\n--- screen{cursor} ---{screen}");
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn capture() -> ScreenExpectation {
+		ScreenExpectation::from_capture("a  \x1b[1mSaved\x1b[0m\n\n-- INSERT --", Some(Cursor { row: 2, col: 3, visible: true }))
+	}
+
+	#[test]
+	fn chained_matchers_pass() {
+		capture()
+			.contains("Saved")
+			.at_row(0)
+			.at(0, 3)
+			.not_contains("ERROR")
+			.matches(r"-- \w+ --")
+			.row_eq(2, "-- INSERT --")
+			.cursor_at(2, 3)
+			.cursor_visible(true);
+	}
+
+	#[test]
+	#[should_panic(
+		expected = "expected \"Saved\" at row 2\n  row 2: \"-- INSERT --\"\n  found at: row 0, col 3\n--- screen (cursor at row 2, col 3) ---\n0 | a  Saved\n1 | \n2 | -- INSERT --"
+	)]
+	fn at_row_failure_shows_row_and_capture() {
+		capture().contains("Saved").at_row(2);
+	}
+
+	#[test]
+	#[should_panic(expected = "expected cursor at row 0, col 0\n  cursor: row 2, col 3")]
+	fn cursor_failure_reports_actual_position() {
+		capture().cursor_at(0, 0);
+	}
+}
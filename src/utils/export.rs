@@ -0,0 +1,303 @@
+//! Standalone HTML and SVG export of styled captures.
+//!
+//! [`ansi_to_html`] turns a raw `get-text --ansi` capture into a complete
+//! HTML document with inline styles, so the colors and attributes that a
+//! plain-text snapshot loses can be attached to CI failure reports and
+//! opened in any browser. [`ansi_to_svg`] draws the same capture as an SVG
+//! picture on a fixed cell grid, for embedding terminal state in
+//! documentation without a screenshot pipeline.
+//!
+//! Colors are resolved against a [`Palette`] (use [`Palette::load`] for the
+//! kitty.conf the test runs with); reverse video and dim are applied the
+//! way kitty draws them.
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::Palette;
+//! use kitty_test_harness::utils::export::{ansi_to_html, ansi_to_svg};
+//!
+//! let html = ansi_to_html("plain \x1b[1;31merror\x1b[0m <tag>", &Palette::kitty_default());
+//! assert!(html.starts_with("<!DOCTYPE html>"));
+//! assert!(html.contains("<span style=\"color:#cc0403;font-weight:bold\">error</span> &lt;tag&gt;"));
+//!
+//! let svg = ansi_to_svg("\x1b[42mok\x1b[0m", &Palette::kitty_default());
+//! assert!(svg.starts_with("<svg"));
+//! ```
+
+use crate::utils::screen::{Cell, ScreenGrid, display_width};
+use crate::utils::style::{ColorSpec, UnderlineStyle};
+use crate::utils::theme::{Palette, Rgb, hex};
+
+/// Render a raw ANSI capture as a standalone HTML document.
+pub fn ansi_to_html(raw: &str, palette: &Palette) -> String {
+	grid_to_html(&ScreenGrid::parse(raw), palette)
+}
+
+/// Render a [`ScreenGrid`] as a standalone HTML document.
+///
+/// Runs of cells with the same style share one `<span>`; cells drawn in
+/// the default colors without attributes are emitted as bare text.
+pub fn grid_to_html(grid: &ScreenGrid, palette: &Palette) -> String {
+	let bg = hex(palette.background);
+	format!(
+		"<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>kitty capture</title>\n</head>\n<body style=\"margin:0;background:{bg}\">\n{}\n</body>\n</html>\n",
+		grid_to_html_pre(grid, palette)
+	)
+}
+
+/// Render a [`ScreenGrid`] as a styled `<pre>` element, for embedding in larger documents.
+pub(crate) fn grid_to_html_pre(grid: &ScreenGrid, palette: &Palette) -> String {
+	let mut body = String::new();
+	for (row, cells) in grid.rows().iter().enumerate() {
+		if row > 0 {
+			body.push('\n');
+		}
+		let mut run_style: Option<String> = None;
+		for cell in cells {
+			let style = css(cell, palette);
+			if run_style.as_deref() != Some(style.as_str()) {
+				if run_style.as_ref().is_some_and(|style| !style.is_empty()) {
+					body.push_str("</span>");
+				}
+				if !style.is_empty() {
+					body.push_str(&format!("<span style=\"{style}\">"));
+				}
+				run_style = Some(style);
+			}
+			escape_into(&mut body, cell.ch);
+		}
+		if run_style.is_some_and(|style| !style.is_empty()) {
+			body.push_str("</span>");
+		}
+	}
+
+	let (fg, bg) = (hex(palette.foreground), hex(palette.background));
+	format!("<pre style=\"margin:0;padding:8px;color:{fg};background:{bg};font-family:monospace;line-height:1.2\">{body}</pre>")
+}
+
+/// Inline CSS for `cell`, empty when it is drawn in the default style.
+fn css(cell: &Cell, palette: &Palette) -> String {
+	let (fg, bg) = cell.effective_colors(palette);
+	let attrs = cell.attrs;
+	let mut props = Vec::new();
+	if fg != palette.foreground {
+		props.push(format!("color:{}", hex(fg)));
+	}
+	if bg != palette.background {
+		props.push(format!("background:{}", hex(bg)));
+	}
+	if attrs.bold {
+		props.push("font-weight:bold".to_string());
+	}
+	if attrs.italic {
+		props.push("font-style:italic".to_string());
+	}
+	let mut lines = Vec::new();
+	if attrs.underline {
+		lines.push("underline");
+	}
+	if attrs.strikethrough {
+		lines.push("line-through");
+	}
+	if !lines.is_empty() {
+		props.push(format!("text-decoration:{}", lines.join(" ")));
+	}
+	if attrs.underline {
+		let style = match attrs.underline_style {
+			UnderlineStyle::Double => "double",
+			UnderlineStyle::Curly => "wavy",
+			UnderlineStyle::Dotted => "dotted",
+			UnderlineStyle::Dashed => "dashed",
+			UnderlineStyle::None | UnderlineStyle::Single => "solid",
+		};
+		if style != "solid" {
+			props.push(format!("text-decoration-style:{style}"));
+		}
+		if cell.underline_color != ColorSpec::Default {
+			props.push(format!("text-decoration-color:{}", hex(palette.resolve_fg(cell.underline_color))));
+		}
+	}
+	props.join(";")
+}
+
+/// Width of one cell in SVG user units.
+const SVG_CELL_WIDTH: usize = 9;
+/// Height of one cell in SVG user units.
+const SVG_CELL_HEIGHT: usize = 18;
+
+/// Render a raw ANSI capture as a standalone SVG picture.
+pub fn ansi_to_svg(raw: &str, palette: &Palette) -> String {
+	grid_to_svg(&ScreenGrid::parse(raw), palette)
+}
+
+/// Render a [`ScreenGrid`] as a standalone SVG picture.
+///
+/// Every cell is a 9x18 box on a fixed grid (wide characters take two), so
+/// glyphs line up exactly as in the terminal regardless of the viewer's
+/// monospace font. Backgrounds are drawn as rectangles, text in runs of
+/// equal style with per-character positions, and underlines (including
+/// double, curly, dotted and dashed styles and SGR 58 colors) and
+/// strikethrough as lines.
+pub fn grid_to_svg(grid: &ScreenGrid, palette: &Palette) -> String {
+	let (cw, ch) = (SVG_CELL_WIDTH, SVG_CELL_HEIGHT);
+	let mut backgrounds = String::new();
+	let mut text = String::new();
+	let mut lines = String::new();
+	let mut cols = 0;
+
+	for (row, cells) in grid.rows().iter().enumerate() {
+		let y = row * ch;
+		let mut col = 0;
+		let mut run: Option<(String, Vec<usize>, String)> = None;
+		let mut fill: Option<(usize, usize, Rgb)> = None;
+		for cell in cells {
+			let width = display_width(cell.ch.encode_utf8(&mut [0; 4])).max(1);
+			let (fg, bg) = cell.effective_colors(palette);
+			let x = col * cw;
+
+			if fill.is_some_and(|(_, _, color)| color != bg) {
+				let (start, end, color) = fill.take().unwrap_or_default();
+				backgrounds.push_str(&svg_rect(start, y, end - start, ch, color));
+			}
+			if bg != palette.background {
+				let start = fill.map_or(x, |(start, _, _)| start);
+				fill = Some((start, x + width * cw, bg));
+			}
+
+			let attrs = text_attrs(fg, cell);
+			if run.as_ref().is_some_and(|(run_attrs, _, _)| *run_attrs != attrs) {
+				push_text(&mut text, run.take(), y);
+			}
+			if !cell.ch.is_whitespace() {
+				let (_, xs, chars) = run.get_or_insert_with(|| (attrs, Vec::new(), String::new()));
+				xs.push(x);
+				escape_into(chars, cell.ch);
+			}
+
+			if cell.attrs.underline {
+				let color = if cell.underline_color == ColorSpec::Default {
+					fg
+				} else {
+					palette.resolve_fg(cell.underline_color)
+				};
+				lines.push_str(&underline(x, y + ch - 2, width * cw, cell.attrs.underline_style, color));
+			}
+			if cell.attrs.strikethrough {
+				lines.push_str(&format!(
+					"<line x1=\"{x}\" y1=\"{0}\" x2=\"{1}\" y2=\"{0}\" stroke=\"{2}\"/>\n",
+					y + ch / 2,
+					x + width * cw,
+					hex(fg)
+				));
+			}
+			col += width;
+		}
+		if let Some((start, end, color)) = fill {
+			backgrounds.push_str(&svg_rect(start, y, end - start, ch, color));
+		}
+		push_text(&mut text, run, y);
+		cols = cols.max(col);
+	}
+
+	let (width, height) = (cols * cw, grid.height() * ch);
+	format!(
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\" font-family=\"monospace\" font-size=\"15\">\n{}{backgrounds}{text}{lines}</svg>\n",
+		svg_rect(0, 0, width, height, palette.background)
+	)
+}
+
+fn svg_rect(x: usize, y: usize, width: usize, height: usize, color: Rgb) -> String {
+	format!("<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\" fill=\"{}\"/>\n", hex(color))
+}
+
+/// SVG presentation attributes of a text run.
+fn text_attrs(fg: Rgb, cell: &Cell) -> String {
+	let mut attrs = format!("fill=\"{}\"", hex(fg));
+	if cell.attrs.bold {
+		attrs.push_str(" font-weight=\"bold\"");
+	}
+	if cell.attrs.italic {
+		attrs.push_str(" font-style=\"italic\"");
+	}
+	attrs
+}
+
+fn push_text(out: &mut String, run: Option<(String, Vec<usize>, String)>, y: usize) {
+	let Some((attrs, xs, chars)) = run else {
+		return;
+	};
+	let xs: Vec<String> = xs.iter().map(usize::to_string).collect();
+	// Baseline roughly three quarters down the cell.
+	let baseline = y + SVG_CELL_HEIGHT * 3 / 4 + 1;
+	out.push_str(&format!("<text x=\"{}\" y=\"{baseline}\" {attrs}>{chars}</text>\n", xs.join(" ")));
+}
+
+fn underline(x: usize, y: usize, width: usize, style: UnderlineStyle, color: Rgb) -> String {
+	let color = hex(color);
+	let end = x + width;
+	match style {
+		UnderlineStyle::Double => format!(
+			"<line x1=\"{x}\" y1=\"{0}\" x2=\"{end}\" y2=\"{0}\" stroke=\"{color}\"/>\n<line x1=\"{x}\" y1=\"{1}\" x2=\"{end}\" y2=\"{1}\" stroke=\"{color}\"/>\n",
+			y - 2,
+			y + 1
+		),
+		UnderlineStyle::Curly => {
+			let waves = " q 1 -2 2 0 t 2 0".repeat(width / 4);
+			format!("<path d=\"M {x} {y}{waves}\" fill=\"none\" stroke=\"{color}\"/>\n")
+		}
+		UnderlineStyle::Dotted | UnderlineStyle::Dashed => {
+			let dashes = if style == UnderlineStyle::Dotted { "1 2" } else { "4 2" };
+			format!("<line x1=\"{x}\" y1=\"{y}\" x2=\"{end}\" y2=\"{y}\" stroke=\"{color}\" stroke-dasharray=\"{dashes}\"/>\n")
+		}
+		UnderlineStyle::None | UnderlineStyle::Single => format!("<line x1=\"{x}\" y1=\"{y}\" x2=\"{end}\" y2=\"{y}\" stroke=\"{color}\"/>\n"),
+	}
+}
+
+/// Escape `text` for use in HTML text and attribute values.
+pub(crate) fn escape_html(text: &str) -> String {
+	let mut out = String::with_capacity(text.len());
+	text.chars().for_each(|ch| escape_into(&mut out, ch));
+	out
+}
+
+fn escape_into(out: &mut String, ch: char) {
+	match ch {
+		'&' => out.push_str("&amp;"),
+		'<' => out.push_str("&lt;"),
+		'>' => out.push_str("&gt;"),
+		'"' => out.push_str("&quot;"),
+		ch => out.push(ch),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn spans_follow_style_runs_and_attributes() {
+		let palette = Palette::kitty_default();
+		let html = ansi_to_html("\x1b[7mab\x1b[27;3mc\x1b[0m\n\x1b[4:3;58;2;1;2;3mx\x1b[24;9my\x1b[0m", &palette);
+		let reverse = format!("color:{};background:{}", hex(palette.background), hex(palette.foreground));
+		assert!(html.contains(&format!("<span style=\"{reverse}\">ab</span><span style=\"font-style:italic\">c</span>\n")));
+		assert!(html.contains(
+			"<span style=\"text-decoration:underline;text-decoration-style:wavy;text-decoration-color:#010203\">x</span><span style=\"text-decoration:line-through\">y</span></pre>"
+		));
+	}
+
+	#[test]
+	fn svg_places_cells_on_a_fixed_grid() {
+		let palette = Palette::kitty_default();
+		let svg = ansi_to_svg("a\x1b[41m日 b\x1b[0m&\n\x1b[1;4mx", &palette);
+		assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"54\" height=\"36\""));
+		// One background rect covers the wide character and the space after it.
+		assert!(svg.contains(&format!(
+			"<rect x=\"9\" y=\"0\" width=\"36\" height=\"18\" fill=\"{}\"/>",
+			hex(palette.color(1))
+		)));
+		assert!(svg.contains("<text x=\"0 9 36 45\" y=\"14\" fill=\"#dddddd\">a日b&amp;</text>"));
+		assert!(svg.contains("<text x=\"0\" y=\"32\" fill=\"#dddddd\" font-weight=\"bold\">x</text>"));
+		assert!(svg.contains("<line x1=\"0\" y1=\"34\" x2=\"9\" y2=\"34\" stroke=\"#dddddd\"/>"));
+	}
+}
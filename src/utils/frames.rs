@@ -0,0 +1,374 @@
+//! Sampled screen frames: background watching and export.
+//!
+//! [`ScreenWatcher`] samples the screen on a background thread while a test
+//! keeps sending input (see [`crate::KittyHarness::watch_screen`]), enabling
+//! observe-while-driving checks such as "no flicker while typing".
+//!
+//! [`crate::sample_frames`] collects a deduplicated [`FrameTimeline`]: each
+//! distinct screen is stored once with the time it appeared and the time it
+//! was last seen, which keeps long sampling windows small and easy to query.
+//!
+//! Frames captured with [`crate::sample_screen_rapidly`] (or
+//! [`FrameTimeline::to_samples`]) can be written in
+//! the `script(1)` typescript format with a matching timing file, so a failed
+//! sampling window can be watched with the standard `scriptreplay` tool:
+//!
+//! ```text
+//! scriptreplay --timing=frames.typescript.timing frames.typescript
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use kitty_remote_bindings::model::WindowId;
+
+use crate::KittyHarness;
+use crate::utils::ansi::{CleanOptions, strip_ansi};
+use crate::utils::screen::{Cell, CellAttrs, Rect, ScreenGrid};
+use crate::utils::style::ColorSpec;
+
+/// Handle to a background screen sampler started by [`crate::KittyHarness::watch_screen`].
+///
+/// Dropping the handle stops sampling and waits for the thread to finish.
+pub struct ScreenWatcher {
+	stop: Arc<AtomicBool>,
+	handle: Option<JoinHandle<usize>>,
+}
+
+impl ScreenWatcher {
+	pub(crate) fn spawn(
+		socket_addr: String,
+		window_id: WindowId,
+		cleaning: CleanOptions,
+		interval: Duration,
+		mut callback: impl FnMut(Duration, &str, &str) + Send + 'static,
+	) -> Self {
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread_stop = Arc::clone(&stop);
+		let handle = std::thread::spawn(move || {
+			let start = Instant::now();
+			let mut delivered = 0usize;
+			while !thread_stop.load(Ordering::Relaxed) {
+				let Some(raw) = crate::try_capture_screen(&socket_addr, window_id, &cleaning) else {
+					break;
+				};
+				let clean = strip_ansi(&raw);
+				callback(start.elapsed(), &raw, &clean);
+				delivered += 1;
+				std::thread::sleep(interval);
+			}
+			delivered
+		});
+
+		Self { stop, handle: Some(handle) }
+	}
+
+	/// Stop sampling and return the number of frames delivered.
+	pub fn stop(mut self) -> usize {
+		self.finish()
+	}
+
+	fn finish(&mut self) -> usize {
+		self.stop.store(true, Ordering::Relaxed);
+		self.handle.take().map(|handle| handle.join().unwrap_or(0)).unwrap_or(0)
+	}
+}
+
+impl Drop for ScreenWatcher {
+	fn drop(&mut self) {
+		self.finish();
+	}
+}
+
+/// A distinct screen state in a [`FrameTimeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+	/// When the frame was first captured, relative to the start of sampling.
+	pub at: Duration,
+	/// When an identical capture was last seen.
+	pub last_seen: Duration,
+	/// Number of consecutive identical captures merged into this frame.
+	pub samples: usize,
+	/// Raw capture.
+	pub raw: String,
+	/// Capture with ANSI sequences stripped.
+	pub clean: String,
+}
+
+/// Consecutively deduplicated screen frames with timestamps.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FrameTimeline {
+	frames: Vec<Frame>,
+	captures: usize,
+}
+
+impl FrameTimeline {
+	/// An empty timeline.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Record a capture taken `at` after the start; identical consecutive captures extend the previous frame.
+	pub fn push(&mut self, at: Duration, raw: String, clean: String) {
+		self.captures += 1;
+		if let Some(last) = self.frames.last_mut()
+			&& last.raw == raw
+		{
+			last.last_seen = at;
+			last.samples += 1;
+			return;
+		}
+		self.frames.push(Frame {
+			at,
+			last_seen: at,
+			samples: 1,
+			raw,
+			clean,
+		});
+	}
+
+	/// Distinct frames in capture order.
+	pub fn frames(&self) -> &[Frame] {
+		&self.frames
+	}
+
+	/// Number of distinct frames.
+	pub fn len(&self) -> usize {
+		self.frames.len()
+	}
+
+	/// Whether no capture was recorded.
+	pub fn is_empty(&self) -> bool {
+		self.frames.is_empty()
+	}
+
+	/// Total number of captures, including merged duplicates.
+	pub fn captures(&self) -> usize {
+		self.captures
+	}
+
+	/// Frames that were on screen at some point in `[start, end]`.
+	pub fn frames_between(&self, start: Duration, end: Duration) -> &[Frame] {
+		let first = self.frames.partition_point(|frame| frame.last_seen < start);
+		let last = self.frames.partition_point(|frame| frame.at <= end);
+		&self.frames[first..last.max(first)]
+	}
+
+	/// Expand back into `(at, raw, clean)` samples, one per distinct frame, for [`frames_to_script`].
+	pub fn to_samples(&self) -> Vec<(Duration, String, String)> {
+		self.frames.iter().map(|frame| (frame.at, frame.raw.clone(), frame.clean.clone())).collect()
+	}
+
+	/// Times at which the cells inside `region` (text or style) changed.
+	pub fn region_changes(&self, region: Rect) -> Vec<Duration> {
+		self.changes(|row, col| region.contains(row, col))
+	}
+
+	/// Times at which any cell outside `region` changed.
+	pub fn changes_outside(&self, region: Rect) -> Vec<Duration> {
+		self.changes(|row, col| !region.contains(row, col))
+	}
+
+	/// Rate of changes inside `region` per second of sampling.
+	pub fn region_fps(&self, region: Rect) -> f64 {
+		let span = self.span().as_secs_f64();
+		if span == 0.0 {
+			return 0.0;
+		}
+		self.region_changes(region).len() as f64 / span
+	}
+
+	/// Time between the first capture and the last one.
+	pub fn span(&self) -> Duration {
+		match (self.frames.first(), self.frames.last()) {
+			(Some(first), Some(last)) => last.last_seen.saturating_sub(first.at),
+			_ => Duration::ZERO,
+		}
+	}
+
+	fn changes(&self, include: impl Fn(usize, usize) -> bool) -> Vec<Duration> {
+		let selected = |frame: &Frame| -> Vec<(usize, usize, Cell)> {
+			let grid = ScreenGrid::parse(&frame.raw);
+			grid.rows()
+				.iter()
+				.enumerate()
+				.flat_map(|(row, cells)| cells.iter().enumerate().map(move |(col, cell)| (row, col, *cell)))
+				.filter(|(row, col, cell)| include(*row, *col) && !is_blank(cell))
+				.collect()
+		};
+		let mut changes = Vec::new();
+		let mut previous = None;
+		for frame in &self.frames {
+			let cells = selected(frame);
+			if previous.as_ref().is_some_and(|previous| *previous != cells) {
+				changes.push(frame.at);
+			}
+			previous = Some(cells);
+		}
+		changes
+	}
+
+	/// Export as JSON: `{"captures": n, "frames": [{"at_ms", "last_seen_ms", "samples", "raw", "clean"}]}`.
+	pub fn to_json(&self) -> String {
+		let frames: Vec<_> = self
+			.frames
+			.iter()
+			.map(|frame| {
+				serde_json::json!({
+					"at_ms": frame.at.as_secs_f64() * 1000.0,
+					"last_seen_ms": frame.last_seen.as_secs_f64() * 1000.0,
+					"samples": frame.samples,
+					"raw": frame.raw,
+					"clean": frame.clean,
+				})
+			})
+			.collect();
+		serde_json::json!({ "captures": self.captures, "frames": frames }).to_string()
+	}
+}
+
+/// An unstyled space is equivalent to a cell missing from a trimmed row.
+fn is_blank(cell: &Cell) -> bool {
+	cell.ch == ' ' && cell.fg == ColorSpec::Default && cell.bg == ColorSpec::Default && cell.attrs == CellAttrs::default()
+}
+
+/// Sample the screen for `duration` and assert that `region` changes at least `min_fps` times per second.
+///
+/// Returns the timeline so further checks (e.g. [`assert_static`] on the
+/// rest of the screen) can reuse it without sampling again.
+pub fn assert_animating(kitty: &KittyHarness, region: Rect, min_fps: f64, duration: Duration) -> FrameTimeline {
+	let timeline = crate::sample_frames(kitty, duration);
+	let fps = timeline.region_fps(region);
+	assert!(
+		fps >= min_fps,
+		"expected {region:?} to animate at >= {min_fps} fps, saw {fps:.1} fps ({} changes over {:?})",
+		timeline.region_changes(region).len(),
+		timeline.span()
+	);
+	timeline
+}
+
+/// Sample the screen for `duration` and assert that nothing inside `region` changes.
+pub fn assert_static(kitty: &KittyHarness, region: Rect, duration: Duration) -> FrameTimeline {
+	let timeline = crate::sample_frames(kitty, duration);
+	let changes = timeline.region_changes(region);
+	assert!(changes.is_empty(), "expected {region:?} to stay static, but it changed at {changes:?}");
+	timeline
+}
+
+/// Sample the screen for `duration` and assert that nothing outside `region` changes (no flicker around a spinner).
+pub fn assert_static_outside(kitty: &KittyHarness, region: Rect, duration: Duration) -> FrameTimeline {
+	let timeline = crate::sample_frames(kitty, duration);
+	let changes = timeline.changes_outside(region);
+	assert!(
+		changes.is_empty(),
+		"expected the screen outside {region:?} to stay static, but it changed at {changes:?}"
+	);
+	timeline
+}
+
+/// Clear-screen and cursor-home prefix written before every frame.
+const FRAME_PREFIX: &str = "\x1b[H\x1b[2J";
+
+/// Render frames into typescript data plus timing lines.
+///
+/// Returns `(typescript, timing)`. The typescript starts with the header
+/// line that `scriptreplay` skips; each timing line is `<delay> <bytes>`
+/// for the next chunk of data.
+pub fn render_script(frames: &[(Duration, String, String)]) -> (String, String) {
+	let mut typescript = String::from("Script started on kitty-test-harness frame capture\n");
+	let mut timing = String::new();
+	let mut previous = Duration::ZERO;
+
+	for (at, raw, _clean) in frames {
+		let chunk = format!("{FRAME_PREFIX}{}", raw.replace('\n', "\r\n"));
+		let delay = at.saturating_sub(previous);
+		previous = *at;
+
+		timing.push_str(&format!("{:.6} {}\n", delay.as_secs_f64(), chunk.len()));
+		typescript.push_str(&chunk);
+	}
+
+	(typescript, timing)
+}
+
+/// Write frames as a typescript at `path` with timing data at `<path>.timing`.
+///
+/// Returns the path of the timing file.
+pub fn frames_to_script(frames: &[(Duration, String, String)], path: &Path) -> io::Result<PathBuf> {
+	let (typescript, timing) = render_script(frames);
+	let mut timing_path = path.as_os_str().to_owned();
+	timing_path.push(".timing");
+	let timing_path = PathBuf::from(timing_path);
+
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+	fs::write(path, typescript)?;
+	fs::write(&timing_path, timing)?;
+	Ok(timing_path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn render_script_emits_one_timing_line_per_frame() {
+		let frames = vec![
+			(Duration::from_millis(0), "a\nb".to_string(), "a\nb".to_string()),
+			(Duration::from_millis(250), "c".to_string(), "c".to_string()),
+		];
+		let (typescript, timing) = render_script(&frames);
+
+		let first = format!("{FRAME_PREFIX}a\r\nb");
+		let second = format!("{FRAME_PREFIX}c");
+		assert!(typescript.ends_with(&format!("{first}{second}")));
+		assert_eq!(timing, format!("0.000000 {}\n0.250000 {}\n", first.len(), second.len()));
+	}
+
+	#[test]
+	fn timeline_merges_consecutive_duplicates() {
+		let mut timeline = FrameTimeline::new();
+		for (ms, text) in [(0, "a"), (10, "a"), (20, "b"), (30, "a"), (40, "a")] {
+			timeline.push(Duration::from_millis(ms), text.into(), text.into());
+		}
+		assert_eq!((timeline.len(), timeline.captures()), (3, 5));
+		assert_eq!(timeline.frames()[0].last_seen, Duration::from_millis(10));
+		assert_eq!(timeline.frames()[2].samples, 2);
+
+		let between: Vec<_> = timeline
+			.frames_between(Duration::from_millis(15), Duration::from_millis(25))
+			.iter()
+			.map(|frame| frame.clean.as_str())
+			.collect();
+		assert_eq!(between, vec!["b"]);
+
+		let spinner = Rect::new(0, 0, 1, 1);
+		let mut spinning = FrameTimeline::new();
+		for (ms, text) in [(0, "| ok"), (100, "/ ok"), (200, "- ok"), (300, "- ok  ")] {
+			spinning.push(Duration::from_millis(ms), text.into(), text.into());
+		}
+		assert_eq!(spinning.region_changes(spinner), vec![Duration::from_millis(100), Duration::from_millis(200)]);
+		assert!(spinning.changes_outside(spinner).is_empty());
+		assert!((spinning.region_fps(spinner) - 20.0 / 3.0).abs() < 1e-9);
+
+		let json: serde_json::Value = serde_json::from_str(&timeline.to_json()).unwrap();
+		assert_eq!(json["frames"][1]["at_ms"], 20.0);
+	}
+
+	#[test]
+	fn frames_to_script_writes_both_files() {
+		let path = std::env::temp_dir()
+			.join(format!("kitty-test-frames-{}", std::process::id()))
+			.join("run.typescript");
+		let timing = frames_to_script(&[(Duration::ZERO, "x".into(), "x".into())], &path).unwrap();
+		assert!(path.exists());
+		assert_eq!(timing.file_name().unwrap(), "run.typescript.timing");
+		let _ = fs::remove_dir_all(path.parent().unwrap());
+	}
+}
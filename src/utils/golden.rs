@@ -0,0 +1,182 @@
+//! Golden-file assertions for screen captures, without insta.
+//!
+//! [`assert_matches`] compares a capture against `<name>.golden` in the
+//! golden directory: `tests/golden` under the crate being tested (from
+//! `CARGO_MANIFEST_DIR` at run time), or [`GOLDEN_DIR_ENV`] when set. On
+//! mismatch the capture is written next to the golden file as
+//! `<name>.golden.actual` and the assertion panics with a row and column
+//! diff. A missing golden file fails the same way, so a forgotten golden
+//! cannot pass silently in CI. Rerunning with [`UPDATE_GOLDEN_ENV`]`=1`
+//! creates or overwrites the golden files from the captures.
+//!
+//! Golden files hold the capture exactly as given, so raw captures keep
+//! their escape sequences; pass a clean, masked or
+//! [`crate::utils::snapshot::styled_snapshot`] capture for a more readable
+//! file.
+//!
+//! ```ignore
+//! use kitty_test_harness::utils::golden;
+//!
+//! let (_raw, clean) = kitty.screen_text_clean();
+//! golden::assert_matches("main_menu", &clean);
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::utils::ansi::strip_ansi;
+use crate::utils::diff::diff_screens;
+use crate::utils::error::HarnessError;
+
+/// Environment variable overriding the directory golden files are stored in.
+pub const GOLDEN_DIR_ENV: &str = "KITTY_TEST_GOLDEN_DIR";
+
+/// Set to `1` to overwrite golden files with the current captures instead of comparing.
+pub const UPDATE_GOLDEN_ENV: &str = "KITTY_TEST_UPDATE_GOLDEN";
+
+/// Directory holding golden files: [`GOLDEN_DIR_ENV`], or `tests/golden` under `CARGO_MANIFEST_DIR`.
+pub fn golden_dir() -> PathBuf {
+	if let Some(dir) = std::env::var_os(GOLDEN_DIR_ENV) {
+		return PathBuf::from(dir);
+	}
+	let root = std::env::var_os("CARGO_MANIFEST_DIR").map_or_else(|| PathBuf::from("."), PathBuf::from);
+	root.join("tests").join("golden")
+}
+
+/// Path of the golden file for `name`.
+pub fn golden_path(name: &str) -> PathBuf {
+	golden_dir().join(format!("{name}.golden"))
+}
+
+/// Assert that `capture` matches the golden file for `name`.
+///
+/// # Panics
+///
+/// Panics with a diff if the capture differs, if the golden file is missing,
+/// or if it cannot be read or written.
+pub fn assert_matches(name: &str, capture: &str) {
+	assert_matches_at(&golden_path(name), capture)
+}
+
+/// Assert that `capture` matches the golden file at `path`.
+///
+/// # Panics
+///
+/// Panics with a diff if the capture differs, if the golden file is missing,
+/// or if it cannot be read or written.
+pub fn assert_matches_at(path: &Path, capture: &str) {
+	if let Some(message) = check(path, capture).unwrap_or_else(|err| panic!("{err}")) {
+		panic!("{message}");
+	}
+}
+
+/// Compare `capture` against the golden file at `path`, creating or updating it as configured.
+///
+/// Returns the failure report on mismatch or when the golden file is
+/// missing, after writing the capture to `<path>.actual`, and `None` when the
+/// capture matches or was written because [`UPDATE_GOLDEN_ENV`] is set.
+pub fn check(path: &Path, capture: &str) -> Result<Option<String>, HarnessError> {
+	check_with(path, capture, std::env::var(UPDATE_GOLDEN_ENV).is_ok_and(|value| value == "1"))
+}
+
+fn check_with(path: &Path, capture: &str, update: bool) -> Result<Option<String>, HarnessError> {
+	let actual_path = actual_path(path);
+	if update {
+		write(path, capture)?;
+		let _ = std::fs::remove_file(&actual_path);
+		eprintln!("wrote golden file {}", path.display());
+		return Ok(None);
+	}
+	if !path.exists() {
+		write(&actual_path, capture)?;
+		return Ok(Some(format!(
+			"golden file {} does not exist\nactual output written to {}; rerun with {UPDATE_GOLDEN_ENV}=1 to create it",
+			path.display(),
+			actual_path.display()
+		)));
+	}
+
+	let expected = std::fs::read_to_string(path).map_err(|err| HarnessError::io(format!("read golden file {}", path.display()), err))?;
+	if expected == capture {
+		let _ = std::fs::remove_file(&actual_path);
+		return Ok(None);
+	}
+
+	write(&actual_path, capture)?;
+	Ok(Some(format!(
+		"capture differs from golden file {}\n{}\nactual output written to {}; rerun with {UPDATE_GOLDEN_ENV}=1 to accept it",
+		path.display(),
+		report(&expected, capture),
+		actual_path.display()
+	)))
+}
+
+/// Human-readable difference between the golden text and the capture.
+fn report(expected: &str, actual: &str) -> String {
+	let diff = diff_screens(&strip_ansi(expected), &strip_ansi(actual));
+	if !diff.is_empty() {
+		return diff.to_string();
+	}
+	// Same text: show the first line whose escape sequences differ.
+	let (expected_lines, actual_lines): (Vec<&str>, Vec<&str>) = (expected.split('\n').collect(), actual.split('\n').collect());
+	let row = (0..expected_lines.len().max(actual_lines.len()))
+		.find(|&row| expected_lines.get(row) != actual_lines.get(row))
+		.unwrap_or_default();
+	format!(
+		"text is identical, styling differs at row {row}:\n    - {:?}\n    + {:?}",
+		expected_lines.get(row).copied().unwrap_or_default(),
+		actual_lines.get(row).copied().unwrap_or_default()
+	)
+}
+
+fn actual_path(path: &Path) -> PathBuf {
+	let mut name = path.file_name().unwrap_or_default().to_os_string();
+	name.push(".actual");
+	path.with_file_name(name)
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), HarnessError> {
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent).map_err(|err| HarnessError::io(format!("create {}", parent.display()), err))?;
+	}
+	std::fs::write(path, contents).map_err(|err| HarnessError::io(format!("write {}", path.display()), err))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn creates_compares_and_reports() {
+		let dir = std::env::temp_dir().join(format!("kitty-test-golden-{}", std::process::id()));
+		let path = dir.join("menu.golden");
+		let _ = std::fs::remove_dir_all(&dir);
+
+		assert_eq!(check_with(&path, "File  Edit\nready", true).unwrap(), None);
+		assert_eq!(std::fs::read_to_string(&path).unwrap(), "File  Edit\nready");
+		assert_eq!(check_with(&path, "File  Edit\nready", false).unwrap(), None);
+
+		let message = check_with(&path, "File  Edit\nbusy", false).unwrap().unwrap();
+		assert!(message.contains("1 row changed:\n  row 1:\n    - ready\n    + busy"), "{message}");
+		assert_eq!(std::fs::read_to_string(dir.join("menu.golden.actual")).unwrap(), "File  Edit\nbusy");
+
+		std::fs::write(&path, "\x1b[1mx").unwrap();
+		let message = check_with(&path, "\x1b[2mx", false).unwrap().unwrap();
+		assert!(message.contains("styling differs at row 0"), "{message}");
+
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn missing_golden_file_fails() {
+		let dir = std::env::temp_dir().join(format!("kitty-test-golden-missing-{}", std::process::id()));
+		let path = dir.join("menu.golden");
+		let _ = std::fs::remove_dir_all(&dir);
+
+		let message = check_with(&path, "File  Edit", false).unwrap().unwrap();
+		assert!(message.contains("does not exist"), "{message}");
+		assert!(!path.exists());
+		assert_eq!(std::fs::read_to_string(dir.join("menu.golden.actual")).unwrap(), "File  Edit");
+
+		let _ = std::fs::remove_dir_all(&dir);
+	}
+}
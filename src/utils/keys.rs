@@ -47,10 +47,17 @@
 //!
 //! The harness defaults to kitty keyboard encoding with no flags enabled, which provides
 //! a middle ground of compatibility.
+//!
+//! To sidestep encoding entirely, [`crate::KittyHarness::send_key`] delivers keys through
+//! kitty's own `send-key` command, so kitty encodes them with whatever keyboard protocol the
+//! application negotiated, exactly as if they were typed.
+
+use std::time::Duration;
 
 use termwiz::input::{KeyCode, Modifiers};
 
-use crate::KeyPress;
+use crate::utils::timing::RestoreTiming;
+use crate::{KeyEventKind, KeyPress};
 
 /// Common key sequences that are useful for testing.
 pub mod common {
@@ -110,6 +117,139 @@ pub mod common {
 		key: KeyCode::Tab,
 		mods: Modifiers::SHIFT,
 	};
+
+	// The keys below have no legacy encoding (or, for F13-F24, only a rarely
+	// supported one). Under the kitty keyboard protocol they are sent as
+	// `CSI <code> u` sequences once the application enables the
+	// disambiguate flag; modifier-only presses additionally need the
+	// report-all-keys flag. See [`crate::KittyHarness::set_key_modes`].
+
+	/// F13.
+	pub const F13: KeyPress = plain(KeyCode::Function(13));
+
+	/// F14.
+	pub const F14: KeyPress = plain(KeyCode::Function(14));
+
+	/// F15.
+	pub const F15: KeyPress = plain(KeyCode::Function(15));
+
+	/// F16.
+	pub const F16: KeyPress = plain(KeyCode::Function(16));
+
+	/// F17.
+	pub const F17: KeyPress = plain(KeyCode::Function(17));
+
+	/// F18.
+	pub const F18: KeyPress = plain(KeyCode::Function(18));
+
+	/// F19.
+	pub const F19: KeyPress = plain(KeyCode::Function(19));
+
+	/// F20.
+	pub const F20: KeyPress = plain(KeyCode::Function(20));
+
+	/// F21.
+	pub const F21: KeyPress = plain(KeyCode::Function(21));
+
+	/// F22.
+	pub const F22: KeyPress = plain(KeyCode::Function(22));
+
+	/// F23.
+	pub const F23: KeyPress = plain(KeyCode::Function(23));
+
+	/// F24.
+	pub const F24: KeyPress = plain(KeyCode::Function(24));
+
+	/// Keypad 0.
+	pub const KP_0: KeyPress = plain(KeyCode::Numpad0);
+
+	/// Keypad 1.
+	pub const KP_1: KeyPress = plain(KeyCode::Numpad1);
+
+	/// Keypad 2.
+	pub const KP_2: KeyPress = plain(KeyCode::Numpad2);
+
+	/// Keypad 3.
+	pub const KP_3: KeyPress = plain(KeyCode::Numpad3);
+
+	/// Keypad 4.
+	pub const KP_4: KeyPress = plain(KeyCode::Numpad4);
+
+	/// Keypad 5.
+	pub const KP_5: KeyPress = plain(KeyCode::Numpad5);
+
+	/// Keypad 6.
+	pub const KP_6: KeyPress = plain(KeyCode::Numpad6);
+
+	/// Keypad 7.
+	pub const KP_7: KeyPress = plain(KeyCode::Numpad7);
+
+	/// Keypad 8.
+	pub const KP_8: KeyPress = plain(KeyCode::Numpad8);
+
+	/// Keypad 9.
+	pub const KP_9: KeyPress = plain(KeyCode::Numpad9);
+
+	/// Keypad `+`.
+	pub const KP_ADD: KeyPress = plain(KeyCode::Add);
+
+	/// Keypad `-`.
+	pub const KP_SUBTRACT: KeyPress = plain(KeyCode::Subtract);
+
+	/// Keypad `*`.
+	pub const KP_MULTIPLY: KeyPress = plain(KeyCode::Multiply);
+
+	/// Keypad `/`.
+	pub const KP_DIVIDE: KeyPress = plain(KeyCode::Divide);
+
+	/// Keypad decimal point.
+	pub const KP_DECIMAL: KeyPress = plain(KeyCode::Decimal);
+
+	/// Menu (context menu) key.
+	pub const MENU: KeyPress = plain(KeyCode::Menu);
+
+	/// Print Screen key.
+	pub const PRINT_SCREEN: KeyPress = plain(KeyCode::PrintScreen);
+
+	/// Pause key.
+	pub const PAUSE: KeyPress = plain(KeyCode::Pause);
+
+	/// Raise volume media key.
+	pub const VOLUME_UP: KeyPress = plain(KeyCode::VolumeUp);
+
+	/// Lower volume media key.
+	pub const VOLUME_DOWN: KeyPress = plain(KeyCode::VolumeDown);
+
+	/// Mute media key.
+	pub const VOLUME_MUTE: KeyPress = plain(KeyCode::VolumeMute);
+
+	/// Play/pause media key.
+	pub const MEDIA_PLAY_PAUSE: KeyPress = plain(KeyCode::MediaPlayPause);
+
+	/// Stop media key.
+	pub const MEDIA_STOP: KeyPress = plain(KeyCode::MediaStop);
+
+	/// Next track media key.
+	pub const MEDIA_NEXT_TRACK: KeyPress = plain(KeyCode::MediaNextTrack);
+
+	/// Previous track media key.
+	pub const MEDIA_PREV_TRACK: KeyPress = plain(KeyCode::MediaPrevTrack);
+
+	/// Left Shift pressed on its own.
+	pub const SHIFT: KeyPress = plain(KeyCode::LeftShift);
+
+	/// Left Control pressed on its own.
+	pub const CONTROL: KeyPress = plain(KeyCode::LeftControl);
+
+	/// Left Alt pressed on its own.
+	pub const ALT: KeyPress = plain(KeyCode::LeftAlt);
+
+	/// Left Super pressed on its own.
+	pub const SUPER: KeyPress = plain(KeyCode::LeftWindows);
+
+	const fn plain(key: KeyCode) -> KeyPress {
+		KeyPress { key, mods: Modifiers::NONE }
+	}
 }
 
 /// Type a string character by character.
@@ -127,6 +267,105 @@ pub fn type_string(kitty: &crate::KittyHarness, text: &str) {
 	}
 }
 
+/// Cadence used by [`type_string_paced`].
+///
+/// Each character is followed by `delay` plus a pseudo-random offset in
+/// `-jitter..=jitter`. The offsets come from a generator seeded with `seed`,
+/// so a run that exposes a race can be reproduced exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypingProfile {
+	/// Base pause after each character.
+	pub delay: Duration,
+	/// Maximum deviation from `delay` in either direction.
+	pub jitter: Duration,
+	/// Seed for the jitter sequence.
+	pub seed: u64,
+}
+
+impl TypingProfile {
+	/// A fixed `delay` after every character, without jitter.
+	pub fn steady(delay: Duration) -> Self {
+		Self {
+			delay,
+			jitter: Duration::ZERO,
+			seed: 0,
+		}
+	}
+
+	/// Roughly 60 words per minute: 100ms per key with up to 40ms of jitter.
+	pub fn human() -> Self {
+		Self {
+			delay: Duration::from_millis(100),
+			jitter: Duration::from_millis(40),
+			seed: 0x5eed,
+		}
+	}
+
+	/// Replace the jitter.
+	pub fn with_jitter(mut self, jitter: Duration) -> Self {
+		self.jitter = jitter;
+		self
+	}
+
+	/// Replace the jitter seed.
+	pub fn with_seed(mut self, seed: u64) -> Self {
+		self.seed = seed;
+		self
+	}
+
+	/// The pauses taken after each of `count` characters.
+	pub fn delays(&self, count: usize) -> Vec<Duration> {
+		let jitter = u64::try_from(self.jitter.as_micros()).unwrap_or(u64::MAX / 2);
+		let base = u64::try_from(self.delay.as_micros()).unwrap_or(u64::MAX / 2);
+		// xorshift64; the state must never be zero.
+		let mut state = self.seed | 1;
+		(0..count)
+			.map(|_| {
+				if jitter == 0 {
+					return self.delay;
+				}
+				state ^= state << 13;
+				state ^= state >> 7;
+				state ^= state << 17;
+				let offset = state % (2 * jitter + 1);
+				Duration::from_micros((base + offset).saturating_sub(jitter))
+			})
+			.collect()
+	}
+}
+
+impl Default for TypingProfile {
+	fn default() -> Self {
+		Self::human()
+	}
+}
+
+/// Type a string one character at a time with a realistic cadence.
+///
+/// Unlike [`type_string`], which sends characters back to back, this pauses
+/// after every character according to `profile`, which helps reproduce bugs
+/// that only appear when input trickles in. The harness post-send delay is
+/// disabled for the duration of the call, so the pauses come from `profile`
+/// alone.
+///
+/// # Example
+/// ```ignore
+/// type_string_paced(kitty, "hello", TypingProfile::human().with_seed(42));
+/// ```
+pub fn type_string_paced(kitty: &crate::KittyHarness, text: &str, profile: TypingProfile) {
+	let _restore = RestoreTiming::zero_post_send_delay(kitty);
+	type_paced(text, profile, |ch| kitty.send_text(ch), std::thread::sleep);
+}
+
+/// Send `text` one character at a time through `send`, pausing via `sleep` per `profile`.
+fn type_paced(text: &str, profile: TypingProfile, mut send: impl FnMut(&str), mut sleep: impl FnMut(Duration)) {
+	let delays = profile.delays(text.chars().count());
+	for (ch, delay) in text.chars().zip(delays) {
+		send(&ch.to_string());
+		sleep(delay);
+	}
+}
+
 /// Type a command string and execute it with Ctrl+J.
 ///
 /// This is a convenience for the common pattern of typing a command and executing it,
@@ -141,3 +380,573 @@ pub fn type_and_execute(kitty: &crate::KittyHarness, text: &str) {
 	type_string(kitty, text);
 	crate::send_keys(kitty, &[common::CTRL_J]);
 }
+
+/// Parse Vim-style key notation into key presses.
+///
+/// Plain characters stand for themselves; angle-bracket names describe
+/// special keys and modifiers, e.g. `"<C-w>j:wq<CR>"`. Supported names
+/// (case-insensitive) are `CR`/`Enter`/`Return`, `Esc`, `Tab`, `BS`, `Del`,
+/// `Insert`, `Home`, `End`, `PageUp`, `PageDown`, `Up`/`Down`/`Left`/`Right`,
+/// `Space`, `lt`, `Bar`, `Bslash`, `NL` and `F1`-`F35`, with any of the
+/// `C-`, `S-`, `A-`/`M-` and `D-` modifier prefixes. As in Vim, a `<` that
+/// does not start a recognised name is sent literally.
+///
+/// # Example
+/// ```
+/// use kitty_test_harness::utils::keys::parse_vim_keys;
+/// use termwiz::input::{KeyCode, Modifiers};
+///
+/// let keys = parse_vim_keys("<C-w>j<CR>");
+/// assert_eq!(keys[0], (KeyCode::Char('w'), Modifiers::CTRL).into());
+/// assert_eq!(keys[1], KeyCode::Char('j').into());
+/// assert_eq!(keys[2], KeyCode::Enter.into());
+/// ```
+pub fn parse_vim_keys(notation: &str) -> Vec<KeyPress> {
+	let mut keys = Vec::new();
+	let mut rest = notation;
+	while let Some(ch) = rest.chars().next() {
+		if ch == '<'
+			&& let Some(end) = rest.find('>')
+			&& let Some(key) = parse_vim_key(&rest[1..end])
+		{
+			keys.push(key);
+			rest = &rest[end + 1..];
+			continue;
+		}
+		keys.push(KeyCode::Char(ch).into());
+		rest = &rest[ch.len_utf8()..];
+	}
+	keys
+}
+
+/// Parse the inside of a single `<...>` Vim key name.
+fn parse_vim_key(name: &str) -> Option<KeyPress> {
+	let mut mods = Modifiers::NONE;
+	let mut rest = name;
+	while let Some((prefix, tail)) = rest.split_once('-') {
+		if tail.is_empty() {
+			// `<C-->` means Ctrl+minus.
+			break;
+		}
+		mods |= match prefix.to_ascii_uppercase().as_str() {
+			"C" => Modifiers::CTRL,
+			"S" => Modifiers::SHIFT,
+			"A" | "M" => Modifiers::ALT,
+			"D" => Modifiers::SUPER,
+			_ => return None,
+		};
+		rest = tail;
+	}
+
+	let mut chars = rest.chars();
+	let key = match (chars.next(), chars.next()) {
+		(Some(ch), None) if !mods.is_empty() => KeyCode::Char(ch),
+		_ => match rest.to_ascii_lowercase().as_str() {
+			"cr" | "enter" | "return" => KeyCode::Enter,
+			"esc" => KeyCode::Escape,
+			"tab" => KeyCode::Tab,
+			"bs" => KeyCode::Backspace,
+			"del" => KeyCode::Delete,
+			"insert" => KeyCode::Insert,
+			"home" => KeyCode::Home,
+			"end" => KeyCode::End,
+			"pageup" => KeyCode::PageUp,
+			"pagedown" => KeyCode::PageDown,
+			"up" => KeyCode::UpArrow,
+			"down" => KeyCode::DownArrow,
+			"left" => KeyCode::LeftArrow,
+			"right" => KeyCode::RightArrow,
+			"space" => KeyCode::Char(' '),
+			"lt" => KeyCode::Char('<'),
+			"bar" => KeyCode::Char('|'),
+			"bslash" => KeyCode::Char('\\'),
+			"nl" => KeyCode::Char('\n'),
+			lower => {
+				let n: u8 = lower.strip_prefix('f')?.parse().ok()?;
+				if !(1..=35).contains(&n) {
+					return None;
+				}
+				KeyCode::Function(n)
+			}
+		},
+	};
+	Some(KeyPress { key, mods })
+}
+
+/// Parse Vim-style key notation (see [`parse_vim_keys`]) and send the keys.
+///
+/// # Example
+/// ```ignore
+/// send_vim_keys(kitty, "ihello<Esc>:wq<CR>");
+/// ```
+pub fn send_vim_keys(kitty: &crate::KittyHarness, notation: &str) {
+	crate::send_keys(kitty, &parse_vim_keys(notation));
+}
+
+/// Parse a key name in the replay format's `C-A-S-<code>` notation into a key press.
+///
+/// `D-` marks the super modifier. Codes are single characters, `F1`-style function keys, or one of `esc`,
+/// `enter`/`ret`, `tab`, `backtab`, `backspace`/`bs`, `del`/`delete`,
+/// `insert`/`ins`, `home`, `end`, `pageup`, `pagedown`, `up`, `down`,
+/// `left`, `right`, `space` and `hash` (`#`, which would start a comment
+/// line in a recording). Returns `None` for anything else.
+///
+/// # Example
+/// ```
+/// use kitty_test_harness::utils::keys::parse_key;
+/// use termwiz::input::{KeyCode, Modifiers};
+///
+/// assert_eq!(parse_key("C-x"), Some((KeyCode::Char('x'), Modifiers::CTRL).into()));
+/// assert_eq!(parse_key("backtab"), Some((KeyCode::Tab, Modifiers::SHIFT).into()));
+/// ```
+pub fn parse_key(name: &str) -> Option<KeyPress> {
+	let mut remaining = name;
+	let mut mods = Modifiers::NONE;
+
+	// Parse modifier prefixes
+	loop {
+		if let Some(rest) = remaining.strip_prefix("C-") {
+			mods |= Modifiers::CTRL;
+			remaining = rest;
+		} else if let Some(rest) = remaining.strip_prefix("A-") {
+			mods |= Modifiers::ALT;
+			remaining = rest;
+		} else if let Some(rest) = remaining.strip_prefix("S-") {
+			mods |= Modifiers::SHIFT;
+			remaining = rest;
+		} else if let Some(rest) = remaining.strip_prefix("D-") {
+			mods |= Modifiers::SUPER;
+			remaining = rest;
+		} else {
+			break;
+		}
+	}
+
+	let keycode = match remaining {
+		"esc" => KeyCode::Escape,
+		"enter" | "ret" => KeyCode::Enter,
+		"tab" => KeyCode::Tab,
+		"backtab" => KeyCode::Tab, // backtab is shift+tab
+		"backspace" | "bs" => KeyCode::Backspace,
+		"del" | "delete" => KeyCode::Delete,
+		"insert" | "ins" => KeyCode::Insert,
+		"home" => KeyCode::Home,
+		"end" => KeyCode::End,
+		"pageup" => KeyCode::PageUp,
+		"pagedown" => KeyCode::PageDown,
+		"up" => KeyCode::UpArrow,
+		"down" => KeyCode::DownArrow,
+		"left" => KeyCode::LeftArrow,
+		"right" => KeyCode::RightArrow,
+		"space" => KeyCode::Char(' '),
+		"hash" => KeyCode::Char('#'),
+		s if s.chars().count() == 1 => KeyCode::Char(s.chars().next().unwrap()),
+		s if s.starts_with('F') || s.starts_with('f') => {
+			let n: u8 = s[1..].parse().ok()?;
+			KeyCode::Function(n)
+		}
+		_ => return None,
+	};
+
+	// backtab implies shift
+	if remaining == "backtab" {
+		mods |= Modifiers::SHIFT;
+	}
+
+	Some(KeyPress { key: keycode, mods })
+}
+
+/// Format a key press as a key name in `C-A-S-<code>` notation; the inverse of [`parse_key`].
+///
+/// Returns `None` for keys that have no name in the recording format,
+/// including keys with modifiers other than ctrl, alt, shift and super.
+pub fn format_key(key: KeyPress) -> Option<String> {
+	let code = match key.key {
+		KeyCode::Escape => "esc".to_string(),
+		KeyCode::Enter => "enter".to_string(),
+		KeyCode::Tab => "tab".to_string(),
+		KeyCode::Backspace => "backspace".to_string(),
+		KeyCode::Delete => "del".to_string(),
+		KeyCode::Insert => "insert".to_string(),
+		KeyCode::Home => "home".to_string(),
+		KeyCode::End => "end".to_string(),
+		KeyCode::PageUp => "pageup".to_string(),
+		KeyCode::PageDown => "pagedown".to_string(),
+		KeyCode::UpArrow => "up".to_string(),
+		KeyCode::DownArrow => "down".to_string(),
+		KeyCode::LeftArrow => "left".to_string(),
+		KeyCode::RightArrow => "right".to_string(),
+		KeyCode::Char(' ') => "space".to_string(),
+		KeyCode::Char('#') => "hash".to_string(),
+		KeyCode::Char('\n') | KeyCode::Char('\r') => "enter".to_string(),
+		KeyCode::Char('\t') => "tab".to_string(),
+		KeyCode::Char(ch) => ch.to_string(),
+		KeyCode::Function(n) => format!("F{n}"),
+		_ => return None,
+	};
+
+	if !(Modifiers::CTRL | Modifiers::ALT | Modifiers::SHIFT | Modifiers::SUPER).contains(key.mods) {
+		return None;
+	}
+
+	let mut name = String::new();
+	if key.mods.contains(Modifiers::SUPER) {
+		name.push_str("D-");
+	}
+	if key.mods.contains(Modifiers::CTRL) {
+		name.push_str("C-");
+	}
+	if key.mods.contains(Modifiers::ALT) {
+		name.push_str("A-");
+	}
+	if key.mods.contains(Modifiers::SHIFT) {
+		name.push_str("S-");
+	}
+	name.push_str(&code);
+	Some(name)
+}
+
+/// Return kitty's name for a key press (e.g. `ctrl+shift+a`, `page_up`, `f5`), as used by `kitty @ send-key`.
+///
+/// `+` is named `plus`, since kitty splits key specs on `+`, and uppercase
+/// letters become the lowercase key with `shift+`, the way kitty names
+/// them. Returns `None` for keys kitty has no name for.
+///
+/// # Example
+/// ```
+/// use kitty_test_harness::utils::keys::kitty_key_name;
+/// use termwiz::input::{KeyCode, Modifiers};
+///
+/// assert_eq!(kitty_key_name((KeyCode::Char('x'), Modifiers::CTRL | Modifiers::ALT).into()).as_deref(), Some("ctrl+alt+x"));
+/// assert_eq!(kitty_key_name(KeyCode::PageUp.into()).as_deref(), Some("page_up"));
+/// ```
+pub fn kitty_key_name(key: KeyPress) -> Option<String> {
+	let mut mods = key.mods;
+	let base = match key.key {
+		KeyCode::Char(' ') => "space".to_string(),
+		KeyCode::Char('+') => "plus".to_string(),
+		KeyCode::Char(ch) if ch.is_control() => return None,
+		KeyCode::Char(ch) if ch.is_uppercase() => {
+			mods |= Modifiers::SHIFT;
+			ch.to_lowercase().collect()
+		}
+		KeyCode::Char(ch) => ch.to_string(),
+		KeyCode::Enter => "enter".into(),
+		KeyCode::Escape => "escape".into(),
+		KeyCode::Tab => "tab".into(),
+		KeyCode::Backspace => "backspace".into(),
+		KeyCode::Delete => "delete".into(),
+		KeyCode::Insert => "insert".into(),
+		KeyCode::Home => "home".into(),
+		KeyCode::End => "end".into(),
+		KeyCode::PageUp => "page_up".into(),
+		KeyCode::PageDown => "page_down".into(),
+		KeyCode::UpArrow => "up".into(),
+		KeyCode::DownArrow => "down".into(),
+		KeyCode::LeftArrow => "left".into(),
+		KeyCode::RightArrow => "right".into(),
+		KeyCode::Function(n) if (1..=35).contains(&n) => format!("f{n}"),
+		KeyCode::Numpad0 => "kp_0".into(),
+		KeyCode::Numpad1 => "kp_1".into(),
+		KeyCode::Numpad2 => "kp_2".into(),
+		KeyCode::Numpad3 => "kp_3".into(),
+		KeyCode::Numpad4 => "kp_4".into(),
+		KeyCode::Numpad5 => "kp_5".into(),
+		KeyCode::Numpad6 => "kp_6".into(),
+		KeyCode::Numpad7 => "kp_7".into(),
+		KeyCode::Numpad8 => "kp_8".into(),
+		KeyCode::Numpad9 => "kp_9".into(),
+		KeyCode::Decimal => "kp_decimal".into(),
+		KeyCode::Divide => "kp_divide".into(),
+		KeyCode::Multiply => "kp_multiply".into(),
+		KeyCode::Subtract => "kp_subtract".into(),
+		KeyCode::Add => "kp_add".into(),
+		KeyCode::Menu => "menu".into(),
+		KeyCode::PrintScreen => "print_screen".into(),
+		KeyCode::Pause => "pause".into(),
+		KeyCode::MediaPlayPause => "media_play_pause".into(),
+		KeyCode::MediaStop => "media_stop".into(),
+		KeyCode::MediaNextTrack => "media_track_next".into(),
+		KeyCode::MediaPrevTrack => "media_track_previous".into(),
+		KeyCode::VolumeDown => "lower_volume".into(),
+		KeyCode::VolumeUp => "raise_volume".into(),
+		KeyCode::VolumeMute => "mute_volume".into(),
+		_ => return None,
+	};
+
+	let mut name = String::new();
+	for (modifier, prefix) in [
+		(Modifiers::CTRL, "ctrl+"),
+		(Modifiers::ALT, "alt+"),
+		(Modifiers::SHIFT, "shift+"),
+		(Modifiers::SUPER, "super+"),
+	] {
+		if mods.contains(modifier) {
+			name.push_str(prefix);
+		}
+	}
+	name.push_str(&base);
+	Some(name)
+}
+
+/// Encode a key event in the kitty keyboard protocol with event types reported.
+///
+/// This is the form kitty sends once an application enables the
+/// "report event types" progressive enhancement (flag `0b10`), e.g.
+/// `CSI 97;5:3u` for releasing Ctrl+A. Returns `None` for keys the protocol
+/// encoding here does not cover.
+pub fn encode_kitty_key_event(key: KeyPress, kind: KeyEventKind) -> Option<String> {
+	let mods = kitty_modifier_bits(key.mods);
+	let event = match kind {
+		KeyEventKind::Press => 1,
+		KeyEventKind::Repeat => 2,
+		KeyEventKind::Release => 3,
+	};
+	let params = format!("{}:{event}", mods + 1);
+
+	if let Some(code) = kitty_functional_code(key.key) {
+		return Some(format!("\x1b[{code};{params}u"));
+	}
+
+	let (number, terminator) = match key.key {
+		KeyCode::Char(ch) if !ch.is_control() => (u32::from(ch.to_lowercase().next().unwrap_or(ch)), 'u'),
+		KeyCode::Escape => (27, 'u'),
+		KeyCode::Enter => (13, 'u'),
+		KeyCode::Tab => (9, 'u'),
+		KeyCode::Backspace => (127, 'u'),
+		KeyCode::Insert => (2, '~'),
+		KeyCode::Delete => (3, '~'),
+		KeyCode::PageUp => (5, '~'),
+		KeyCode::PageDown => (6, '~'),
+		KeyCode::UpArrow => (1, 'A'),
+		KeyCode::DownArrow => (1, 'B'),
+		KeyCode::RightArrow => (1, 'C'),
+		KeyCode::LeftArrow => (1, 'D'),
+		KeyCode::Home => (1, 'H'),
+		KeyCode::End => (1, 'F'),
+		KeyCode::Function(1) => (1, 'P'),
+		KeyCode::Function(2) => (1, 'Q'),
+		KeyCode::Function(3) => (13, '~'),
+		KeyCode::Function(4) => (1, 'S'),
+		KeyCode::Function(n @ 5..=12) => ([15, 17, 18, 19, 20, 21, 23, 24][usize::from(n - 5)], '~'),
+		_ => return None,
+	};
+	Some(format!("\x1b[{number};{params}{terminator}"))
+}
+
+/// Encode a key that only the kitty keyboard protocol can express, as sent in disambiguate mode.
+///
+/// Covers F13-F35, keypad keys, lock keys, Menu, Print Screen, Pause, media
+/// keys and modifier-only presses, e.g. `CSI 57399u` for keypad 0. Returns
+/// `None` for keys that have a regular encoding.
+pub fn encode_kitty_key(key: KeyPress) -> Option<String> {
+	let code = kitty_functional_code(key.key)?;
+	let mods = kitty_modifier_bits(key.mods);
+	Some(if mods == 0 {
+		format!("\x1b[{code}u")
+	} else {
+		format!("\x1b[{code};{}u", mods + 1)
+	})
+}
+
+fn kitty_modifier_bits(mods: Modifiers) -> u8 {
+	[(Modifiers::SHIFT, 1), (Modifiers::ALT, 2), (Modifiers::CTRL, 4), (Modifiers::SUPER, 8)]
+		.into_iter()
+		.filter(|(modifier, _)| mods.contains(*modifier))
+		.fold(0, |bits, (_, bit)| bits | bit)
+}
+
+/// The kitty protocol's private-use key number for keys without a legacy encoding.
+fn kitty_functional_code(key: KeyCode) -> Option<u32> {
+	Some(match key {
+		KeyCode::CapsLock => 57358,
+		KeyCode::ScrollLock => 57359,
+		KeyCode::NumLock => 57360,
+		KeyCode::PrintScreen => 57361,
+		KeyCode::Pause => 57362,
+		KeyCode::Menu => 57363,
+		KeyCode::Function(n @ 13..=35) => 57376 + u32::from(n - 13),
+		KeyCode::Numpad0 => 57399,
+		KeyCode::Numpad1 => 57400,
+		KeyCode::Numpad2 => 57401,
+		KeyCode::Numpad3 => 57402,
+		KeyCode::Numpad4 => 57403,
+		KeyCode::Numpad5 => 57404,
+		KeyCode::Numpad6 => 57405,
+		KeyCode::Numpad7 => 57406,
+		KeyCode::Numpad8 => 57407,
+		KeyCode::Numpad9 => 57408,
+		KeyCode::Decimal => 57409,
+		KeyCode::Divide => 57410,
+		KeyCode::Multiply => 57411,
+		KeyCode::Subtract => 57412,
+		KeyCode::Add => 57413,
+		KeyCode::Separator => 57416,
+		KeyCode::KeyPadPageUp => 57421,
+		KeyCode::KeyPadPageDown => 57422,
+		KeyCode::KeyPadHome => 57423,
+		KeyCode::KeyPadEnd => 57424,
+		KeyCode::KeyPadBegin => 57427,
+		KeyCode::MediaPlayPause => 57430,
+		KeyCode::MediaStop => 57432,
+		KeyCode::MediaNextTrack => 57435,
+		KeyCode::MediaPrevTrack => 57436,
+		KeyCode::VolumeDown => 57438,
+		KeyCode::VolumeUp => 57439,
+		KeyCode::VolumeMute => 57440,
+		KeyCode::Shift | KeyCode::LeftShift => 57441,
+		KeyCode::Control | KeyCode::LeftControl => 57442,
+		KeyCode::Alt | KeyCode::LeftAlt => 57443,
+		KeyCode::Super | KeyCode::LeftWindows => 57444,
+		KeyCode::Hyper => 57445,
+		KeyCode::Meta => 57446,
+		KeyCode::RightShift => 57447,
+		KeyCode::RightControl => 57448,
+		KeyCode::RightAlt => 57449,
+		KeyCode::RightWindows => 57450,
+		_ => return None,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn typing_delays_stay_within_jitter() {
+		let profile = TypingProfile::steady(Duration::from_millis(50))
+			.with_jitter(Duration::from_millis(10))
+			.with_seed(7);
+		let delays = profile.delays(100);
+		assert!(delays.iter().all(|d| (Duration::from_millis(40)..=Duration::from_millis(60)).contains(d)));
+		assert!(delays.windows(2).any(|pair| pair[0] != pair[1]));
+		assert_eq!(delays, profile.delays(100));
+		assert_eq!(TypingProfile::steady(Duration::from_millis(5)).delays(2), vec![Duration::from_millis(5); 2]);
+	}
+
+	#[test]
+	fn paced_typing_sleeps_only_for_the_profile() {
+		let kitty = crate::KittyHarness::from_parts("paced".into(), "unix:/nonexistent".into(), kitty_remote_bindings::model::WindowId(1))
+			.with_close_scope(crate::CloseScope::Nothing);
+		let profile = TypingProfile::steady(Duration::from_millis(30))
+			.with_jitter(Duration::from_millis(10))
+			.with_seed(3);
+		let mut sent = Vec::new();
+		let mut slept = Vec::new();
+		{
+			let _restore = RestoreTiming::zero_post_send_delay(&kitty);
+			type_paced(
+				"abc",
+				profile,
+				|ch| sent.push((ch.to_string(), kitty.timing().post_send_delay)),
+				|delay| slept.push(delay),
+			);
+		}
+		assert_eq!(
+			sent,
+			[("a", Duration::ZERO), ("b", Duration::ZERO), ("c", Duration::ZERO)].map(|(ch, d)| (ch.to_string(), d))
+		);
+		assert_eq!(slept, profile.delays(3));
+		assert_eq!(kitty.timing(), crate::Timing::default());
+	}
+
+	#[test]
+	fn vim_notation() {
+		let keys = parse_vim_keys("<C-w>j:wq<CR>");
+		assert_eq!(keys.len(), 6);
+		assert_eq!(keys[0], (KeyCode::Char('w'), Modifiers::CTRL).into());
+		assert_eq!(keys[5], KeyCode::Enter.into());
+		assert_eq!(
+			parse_vim_keys("<S-Tab><M-x><c-s-f5>"),
+			vec![
+				(KeyCode::Tab, Modifiers::SHIFT).into(),
+				(KeyCode::Char('x'), Modifiers::ALT).into(),
+				(KeyCode::Function(5), Modifiers::CTRL | Modifiers::SHIFT).into(),
+			]
+		);
+		assert_eq!(
+			parse_vim_keys("<lt><C-->"),
+			vec![KeyCode::Char('<').into(), (KeyCode::Char('-'), Modifiers::CTRL).into()]
+		);
+		// Unrecognised names are typed literally.
+		assert_eq!(parse_vim_keys("a<b").len(), 3);
+		assert_eq!(parse_vim_keys("<nope>").len(), 6);
+	}
+
+	#[test]
+	fn key_names_round_trip() {
+		for name in [
+			"j", "f", "F", "C-f", "A-F", "C-x", "A-S-tab", "D-C-s", "esc", "F5", "space", "hash", "C-hash", "C-enter",
+		] {
+			let key = parse_key(name).expect("name should parse");
+			assert_eq!(format_key(key).as_deref(), Some(name));
+		}
+		assert_eq!(format_key((KeyCode::Char('a'), Modifiers::LEADER).into()), None);
+	}
+
+	#[test]
+	fn kitty_only_keys() {
+		assert_eq!(encode_kitty_key(common::KP_0).as_deref(), Some("\x1b[57399u"));
+		assert_eq!(encode_kitty_key(common::F13).as_deref(), Some("\x1b[57376u"));
+		assert_eq!(
+			encode_kitty_key((KeyCode::Function(24), Modifiers::CTRL).into()).as_deref(),
+			Some("\x1b[57387;5u")
+		);
+		assert_eq!(encode_kitty_key(common::SHIFT).as_deref(), Some("\x1b[57441u"));
+		assert_eq!(encode_kitty_key(common::ENTER), None);
+		assert_eq!(
+			encode_kitty_key_event(common::VOLUME_UP, KeyEventKind::Release).as_deref(),
+			Some("\x1b[57439;1:3u")
+		);
+		assert_eq!(kitty_key_name(common::MEDIA_PLAY_PAUSE).as_deref(), Some("media_play_pause"));
+	}
+
+	#[test]
+	fn unencodable_keys_are_errors() {
+		assert!(crate::encode_key(common::VOLUME_UP, crate::default_key_modes()).is_err());
+		assert!(crate::encode_key(KeyCode::Function(30).into(), crate::default_key_modes()).is_err());
+		assert_eq!(crate::encode_key(common::F13, crate::default_key_modes()).unwrap(), "\x1b[25~");
+
+		let modes = crate::kitty_key_modes(termwiz::escape::csi::KittyKeyboardFlags::REPORT_EVENT_TYPES);
+		assert!(crate::encode_key_event(crate::KeyEvent::release(KeyCode::Function(40)), modes).is_err());
+		assert_eq!(
+			crate::encode_key_event(crate::KeyEvent::release(KeyCode::Char('a')), crate::default_key_modes()).unwrap(),
+			""
+		);
+	}
+
+	#[test]
+	fn kitty_key_events() {
+		let ctrl_a = KeyPress::from((KeyCode::Char('a'), Modifiers::CTRL));
+		assert_eq!(encode_kitty_key_event(ctrl_a, KeyEventKind::Release).as_deref(), Some("\x1b[97;5:3u"));
+		assert_eq!(
+			encode_kitty_key_event(KeyCode::UpArrow.into(), KeyEventKind::Repeat).as_deref(),
+			Some("\x1b[1;1:2A")
+		);
+		assert_eq!(
+			encode_kitty_key_event(KeyCode::Function(5).into(), KeyEventKind::Press).as_deref(),
+			Some("\x1b[15;1:1~")
+		);
+		assert_eq!(encode_kitty_key_event(KeyCode::Char('\u{1}').into(), KeyEventKind::Press), None);
+	}
+
+	#[test]
+	fn kitty_key_names() {
+		assert_eq!(kitty_key_name(KeyCode::Char(' ').into()).as_deref(), Some("space"));
+		assert_eq!(kitty_key_name((KeyCode::Tab, Modifiers::SHIFT).into()).as_deref(), Some("shift+tab"));
+		assert_eq!(kitty_key_name(KeyCode::Function(12).into()).as_deref(), Some("f12"));
+		assert_eq!(kitty_key_name(KeyCode::Char('\u{7}').into()), None);
+		assert_eq!(kitty_key_name(KeyCode::Char('+').into()).as_deref(), Some("plus"));
+		assert_eq!(kitty_key_name((KeyCode::Char('+'), Modifiers::CTRL).into()).as_deref(), Some("ctrl+plus"));
+		assert_eq!(kitty_key_name(KeyCode::Char('A').into()).as_deref(), Some("shift+a"));
+		assert_eq!(
+			kitty_key_name((KeyCode::Char('A'), Modifiers::CTRL | Modifiers::SHIFT).into()).as_deref(),
+			Some("ctrl+shift+a")
+		);
+		assert_eq!(kitty_key_name((KeyCode::Char(','), Modifiers::CTRL).into()).as_deref(), Some("ctrl+,"));
+		assert_eq!(
+			kitty_key_name((KeyCode::Char('-'), Modifiers::CTRL | Modifiers::ALT).into()).as_deref(),
+			Some("ctrl+alt+-")
+		);
+	}
+}
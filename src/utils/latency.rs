@@ -0,0 +1,191 @@
+//! Input-to-render latency measurement.
+//!
+//! [`measure_latency`] timestamps an input action and captures the screen
+//! back-to-back until a predicate holds, returning the elapsed time. The
+//! harness post-send delay is disabled while measuring so it does not count
+//! towards the result.
+//!
+//! Resolution is bounded by one `kitty @ get-text` round trip (typically a
+//! few milliseconds), and the measurement includes it, so treat results as an
+//! upper bound. [`Latency`] reports both bounds.
+//!
+//! [`bench_scenario`] repeats a measurement and summarizes it as
+//! [`BenchStats`] (min/median/p95/max over the upper bounds), with the raw
+//! samples kept for criterion or CI trend tracking.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use kitty_test_harness::utils::latency::measure_keys_latency;
+//! use kitty_test_harness::{KeyPress, KittyHarness};
+//! use termwiz::input::KeyCode;
+//!
+//! # fn demo(kitty: &KittyHarness) {
+//! let keys = [KeyPress::from(KeyCode::Char('j'))];
+//! let latency = measure_keys_latency(kitty, &keys, |clean| clean.contains("line 2"), Duration::from_secs(1)).expect("editor redraw");
+//! assert!(latency.upper < Duration::from_millis(50), "keypress to redraw took {latency:?}");
+//! # }
+//! ```
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::utils::timing::RestoreTiming;
+use crate::utils::wait::WaitTimeout;
+use crate::{KeyPress, KittyHarness};
+
+/// Time from an input action until the screen satisfied a predicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latency {
+	/// Time until the last non-matching capture started; the update was not visible before then.
+	pub lower: Duration,
+	/// Time until the first matching capture returned; the update was visible by then.
+	pub upper: Duration,
+	/// Number of captures taken.
+	pub captures: usize,
+}
+
+/// Run `action`, then capture the screen repeatedly until `predicate` holds on the cleaned text.
+///
+/// Timing starts right before `action` runs.
+pub fn measure_latency(
+	kitty: &KittyHarness,
+	action: impl FnOnce(&KittyHarness),
+	predicate: impl Fn(&str) -> bool,
+	timeout: Duration,
+) -> Result<Latency, WaitTimeout> {
+	let start = {
+		let _restore = RestoreTiming::zero_post_send_delay(kitty);
+		let start = Instant::now();
+		action(kitty);
+		start
+	};
+
+	let timeout = kitty.clamp_timeout(timeout);
+	let mut captures = 0;
+	let mut previous_start = Duration::ZERO;
+	loop {
+		let capture_start = start.elapsed();
+		let (raw, clean) = kitty.screen_text_clean();
+		captures += 1;
+		let upper = start.elapsed();
+		if predicate(&clean) {
+			return Ok(Latency {
+				lower: previous_start,
+				upper,
+				captures,
+			});
+		}
+		previous_start = capture_start;
+		if upper > timeout {
+			return Err(WaitTimeout::clean(upper, timeout, raw, clean).described("screen update after input"));
+		}
+	}
+}
+
+/// Measure the latency of [`KittyHarness::send_text`] with `text`.
+pub fn measure_text_latency(kitty: &KittyHarness, text: &str, predicate: impl Fn(&str) -> bool, timeout: Duration) -> Result<Latency, WaitTimeout> {
+	measure_latency(kitty, |kitty| kitty.send_text(text), predicate, timeout)
+}
+
+/// Measure the latency of [`crate::send_keys`] with `keys`.
+pub fn measure_keys_latency(kitty: &KittyHarness, keys: &[KeyPress], predicate: impl Fn(&str) -> bool, timeout: Duration) -> Result<Latency, WaitTimeout> {
+	measure_latency(kitty, |kitty| crate::send_keys(kitty, keys), predicate, timeout)
+}
+
+/// Summary of repeated latency measurements from [`bench_scenario`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchStats {
+	/// Upper-bound latency of every iteration, in run order.
+	pub samples: Vec<Duration>,
+	/// Fastest iteration.
+	pub min: Duration,
+	/// Median iteration.
+	pub median: Duration,
+	/// 95th percentile (nearest rank).
+	pub p95: Duration,
+	/// Slowest iteration.
+	pub max: Duration,
+	/// Arithmetic mean.
+	pub mean: Duration,
+}
+
+impl BenchStats {
+	/// Summarize `samples`.
+	///
+	/// # Panics
+	///
+	/// Panics if `samples` is empty.
+	pub fn from_samples(samples: Vec<Duration>) -> Self {
+		assert!(!samples.is_empty(), "benchmark needs at least one sample");
+		let mut sorted = samples.clone();
+		sorted.sort();
+		let rank = |percent: usize| sorted[(sorted.len() * percent).div_ceil(100).saturating_sub(1)];
+		let total: Duration = sorted.iter().sum();
+		Self {
+			min: sorted[0],
+			median: rank(50),
+			p95: rank(95),
+			max: sorted[sorted.len() - 1],
+			mean: total / sorted.len() as u32,
+			samples,
+		}
+	}
+}
+
+impl fmt::Display for BenchStats {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} iterations: min {:?}, median {:?}, p95 {:?}, max {:?}, mean {:?}",
+			self.samples.len(),
+			self.min,
+			self.median,
+			self.p95,
+			self.max,
+			self.mean
+		)
+	}
+}
+
+/// Run `setup` then measure `action` until `predicate` holds, `iterations` times.
+///
+/// `setup` runs outside the measured window (e.g. to reset the application
+/// state); each measurement shares the semantics of [`measure_latency`].
+///
+/// # Panics
+///
+/// Panics if `iterations` is zero.
+pub fn bench_scenario(
+	kitty: &KittyHarness,
+	iterations: usize,
+	mut setup: impl FnMut(&KittyHarness),
+	mut action: impl FnMut(&KittyHarness),
+	predicate: impl Fn(&str) -> bool,
+	timeout: Duration,
+) -> Result<BenchStats, WaitTimeout> {
+	assert!(iterations > 0, "benchmark needs at least one iteration");
+	let mut samples = Vec::with_capacity(iterations);
+	for _ in 0..iterations {
+		setup(kitty);
+		samples.push(measure_latency(kitty, &mut action, &predicate, timeout)?.upper);
+	}
+	Ok(BenchStats::from_samples(samples))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stats_use_nearest_rank_percentiles() {
+		let samples: Vec<_> = (1..=20).rev().map(Duration::from_millis).collect();
+		let stats = BenchStats::from_samples(samples);
+		assert_eq!(stats.min, Duration::from_millis(1));
+		assert_eq!(stats.median, Duration::from_millis(10));
+		assert_eq!(stats.p95, Duration::from_millis(19));
+		assert_eq!(stats.max, Duration::from_millis(20));
+		assert_eq!(stats.mean, Duration::from_micros(10_500));
+		assert_eq!(stats.samples[0], Duration::from_millis(20));
+	}
+}
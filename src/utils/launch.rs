@@ -0,0 +1,525 @@
+//! Launch-time configuration for kitty harnesses.
+//!
+//! [`LaunchCommand`] describes the program to run, either as a shell command
+//! line or as an argv vector that is passed through without quoting.
+//!
+//! [`LaunchOptions`] collects settings that must be known before kitty starts,
+//! such as extra `kitty.conf` fragments and environment variables for the
+//! launched command. When any fragments are supplied (or
+//! [`LaunchOptions::isolated`] is requested), the harness writes an isolated
+//! config file into the session's artifact directory and launches kitty with
+//! `--config` pointing at it, so the test no longer inherits the developer's
+//! personal configuration. Individual settings can also be passed as `-o`
+//! overrides, which take precedence over any config file.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use kitty_test_harness::{KittyHarness, LaunchOptions};
+//!
+//! let options = LaunchOptions::new()
+//!     .config("scrollback_lines 500")
+//!     .config("map ctrl+shift+enter send_text all \\x1b[13;6u")
+//!     .override_option("cursor_blink_interval=0");
+//! let kitty = KittyHarness::launch_with(&kitty_test_harness::manifest_dir(), "my-app", &options);
+//! ```
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::Path;
+
+use termwiz::input::KeyCodeEncodeModes;
+
+/// The program a harness runs inside kitty.
+///
+/// Commands always run under `bash --noprofile --norc -l` so the login
+/// environment is the same either way. A [`LaunchCommand::Shell`] string is
+/// interpreted by bash, while a [`LaunchCommand::Argv`] vector is executed
+/// verbatim via `exec "$@"`, so arguments containing spaces, quotes, `$`, or
+/// non-UTF-8 bytes reach the program unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchCommand {
+	/// A shell command line interpreted by bash.
+	Shell(String),
+	/// A program and its arguments, passed through without shell interpretation.
+	Argv(Vec<OsString>),
+}
+
+impl LaunchCommand {
+	/// A shell command line interpreted by bash.
+	pub fn shell(command: impl Into<String>) -> Self {
+		LaunchCommand::Shell(command.into())
+	}
+
+	/// A program and its arguments, passed through without shell interpretation.
+	///
+	/// # Panics
+	///
+	/// Panics if `args` is empty.
+	pub fn argv<I, S>(args: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: AsRef<OsStr>,
+	{
+		let args: Vec<OsString> = args.into_iter().map(|arg| arg.as_ref().to_os_string()).collect();
+		assert!(!args.is_empty(), "argv launch command needs at least a program");
+		LaunchCommand::Argv(args)
+	}
+
+	/// Arguments that follow `bash --noprofile --norc -lc` on the kitty command line.
+	pub(crate) fn bash_args(&self) -> Vec<OsString> {
+		match self {
+			LaunchCommand::Shell(command) => vec![OsString::from(command)],
+			LaunchCommand::Argv(args) => {
+				let mut out = vec![OsString::from("exec \"$@\""), OsString::from("bash")];
+				out.extend(args.iter().cloned());
+				out
+			}
+		}
+	}
+}
+
+impl fmt::Display for LaunchCommand {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			LaunchCommand::Shell(command) => f.write_str(command),
+			LaunchCommand::Argv(args) => {
+				let quoted: Vec<String> = args.iter().map(|arg| shell_single_quote(&arg.to_string_lossy())).collect();
+				f.write_str(&quoted.join(" "))
+			}
+		}
+	}
+}
+
+impl From<&str> for LaunchCommand {
+	fn from(command: &str) -> Self {
+		LaunchCommand::shell(command)
+	}
+}
+
+impl From<String> for LaunchCommand {
+	fn from(command: String) -> Self {
+		LaunchCommand::Shell(command)
+	}
+}
+
+impl From<&String> for LaunchCommand {
+	fn from(command: &String) -> Self {
+		LaunchCommand::shell(command.as_str())
+	}
+}
+
+impl From<Vec<String>> for LaunchCommand {
+	fn from(args: Vec<String>) -> Self {
+		LaunchCommand::argv(args)
+	}
+}
+
+impl From<&[&str]> for LaunchCommand {
+	fn from(args: &[&str]) -> Self {
+		LaunchCommand::argv(args)
+	}
+}
+
+impl<const N: usize> From<[&str; N]> for LaunchCommand {
+	fn from(args: [&str; N]) -> Self {
+		LaunchCommand::argv(args)
+	}
+}
+
+/// `--env KEY=VALUE` arguments for `kitty @ launch`.
+pub(crate) fn remote_env_args(env: &[(OsString, OsString)]) -> Vec<OsString> {
+	let mut args = Vec::new();
+	for (key, value) in env {
+		let mut var = key.clone();
+		var.push("=");
+		var.push(value);
+		args.extend([OsString::from("--env"), var]);
+	}
+	args
+}
+
+/// Quote a value for safe interpolation into a POSIX shell command line.
+pub(crate) fn shell_single_quote(value: &str) -> String {
+	format!("'{}'", value.replace('\'', "'\"'\"'"))
+}
+
+/// Whether a harness launched as a background panel may take keyboard focus from the desktop.
+///
+/// Only panel launches (native Wayland, see `KITTY_TEST_USE_PANEL`) honour
+/// this; regular kitty windows are focused by the window manager as usual.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FocusPolicy {
+	/// Never take focus, so tests cannot steal focus from whatever you are doing.
+	#[default]
+	NotAllowed,
+	/// Take focus only when asked to, e.g. by [`crate::KittyHarness::focus`].
+	OnDemand,
+	/// Take focus exclusively while the panel is open.
+	Exclusive,
+}
+
+impl FocusPolicy {
+	/// The value passed to `kitty +kitten panel --focus-policy`.
+	pub fn as_str(self) -> &'static str {
+		match self {
+			FocusPolicy::NotAllowed => "not-allowed",
+			FocusPolicy::OnDemand => "on-demand",
+			FocusPolicy::Exclusive => "exclusive",
+		}
+	}
+}
+
+/// Options controlling how a harness launches kitty.
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+	config_fragments: Vec<String>,
+	listen_on: Option<String>,
+	env: Vec<(OsString, OsString)>,
+	overrides: Vec<String>,
+	isolated: bool,
+	key_modes: Option<KeyCodeEncodeModes>,
+	focus_policy: FocusPolicy,
+	timing: Option<crate::Timing>,
+	record: bool,
+	normal_window: bool,
+	clipboard: bool,
+}
+
+impl LaunchOptions {
+	/// Create options that match the behaviour of [`crate::KittyHarness::launch`].
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Append a `kitty.conf` fragment (one or more config lines).
+	pub fn config(mut self, fragment: impl Into<String>) -> Self {
+		self.config_fragments.push(fragment.into());
+		self
+	}
+
+	/// Append the contents of a `kitty.conf` fixture file.
+	///
+	/// # Panics
+	///
+	/// Panics if the file cannot be read.
+	pub fn config_file(self, path: &Path) -> Self {
+		let fragment = std::fs::read_to_string(path).unwrap_or_else(|err| panic!("read kitty config fixture {}: {err}", path.display()));
+		self.config(fragment)
+	}
+
+	/// Force a known color theme (see [`crate::Theme`]).
+	pub fn theme(self, theme: &crate::Theme) -> Self {
+		self.config(theme.to_config())
+	}
+
+	/// Use `family` as the font, so cell geometry does not depend on the local default font.
+	pub fn font_family(self, family: impl AsRef<str>) -> Self {
+		self.override_option(format!("font_family={}", family.as_ref()))
+	}
+
+	/// Use a font size of `points`.
+	pub fn font_size(self, points: f32) -> Self {
+		self.override_option(format!("font_size={points}"))
+	}
+
+	/// Open the window with `cols` x `rows` cells instead of the configured initial size.
+	///
+	/// Implies [`LaunchOptions::normal_window`]: the background panel used on
+	/// Wayland always fills the screen, so it would ignore the requested size.
+	pub fn size(self, cols: u16, rows: u16) -> Self {
+		self.normal_window()
+			.override_option(format!("initial_window_width={cols}c"))
+			.override_option(format!("initial_window_height={rows}c"))
+			.override_option("remember_window_size=no")
+	}
+
+	/// Launch a regular kitty window even where a background panel would be used.
+	///
+	/// A normal window can be sized and resized, at the cost of appearing on
+	/// the desktop (and possibly taking focus) on Wayland.
+	pub fn normal_window(mut self) -> Self {
+		self.normal_window = true;
+		self
+	}
+
+	/// Return whether [`LaunchOptions::normal_window`] was requested.
+	pub fn forces_normal_window(&self) -> bool {
+		self.normal_window
+	}
+
+	/// Let the harness and the application read and write the clipboard.
+	///
+	/// Required for [`crate::KittyHarness::set_clipboard`] and
+	/// [`crate::KittyHarness::get_clipboard`], and lets OSC 52 reads by the
+	/// application through without a confirmation prompt. kitty's clipboard
+	/// is the system clipboard, so tests using this clobber it and cannot
+	/// run in parallel with each other.
+	pub fn clipboard(mut self) -> Self {
+		self.clipboard = true;
+		self
+	}
+
+	/// Return whether [`LaunchOptions::clipboard`] was requested.
+	pub fn allows_clipboard(&self) -> bool {
+		self.clipboard
+	}
+
+	/// Ignore the personal kitty config even when no fragments are supplied.
+	///
+	/// The generated config then only enables remote control, so every
+	/// other setting uses kitty's built-in default.
+	pub fn isolated(mut self) -> Self {
+		self.isolated = true;
+		self
+	}
+
+	/// Pass a single `-o key=value` override to kitty, e.g. `"scrollback_lines=0"`.
+	pub fn override_option(mut self, option: impl Into<String>) -> Self {
+		self.overrides.push(option.into());
+		self
+	}
+
+	/// Return the configured `-o` overrides in order.
+	pub fn overrides(&self) -> &[String] {
+		&self.overrides
+	}
+
+	/// Listen for remote control on `address` instead of a per-session unix socket.
+	///
+	/// Accepts any kitty `--listen-on` address, e.g. `tcp:127.0.0.1:45678`
+	/// to drive the instance over TCP.
+	pub fn listen_on(mut self, address: impl Into<String>) -> Self {
+		self.listen_on = Some(address.into());
+		self
+	}
+
+	/// Return the configured remote control address, if one was set.
+	pub fn listen_address(&self) -> Option<&str> {
+		self.listen_on.as_deref()
+	}
+
+	/// Set an environment variable for the launched command.
+	///
+	/// Variables are passed as `env KEY=VALUE` arguments in front of the
+	/// command, so values need no shell quoting and kitty itself keeps its
+	/// own environment.
+	///
+	/// # Panics
+	///
+	/// Panics if `key` is empty or contains `=`.
+	pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+		let key = key.as_ref();
+		assert!(
+			!key.is_empty() && !key.as_encoded_bytes().contains(&b'='),
+			"invalid environment variable name {key:?}"
+		);
+		self.env.push((key.to_os_string(), value.as_ref().to_os_string()));
+		self
+	}
+
+	/// Set several environment variables for the launched command (see [`LaunchOptions::env`]).
+	pub fn envs<I, K, V>(self, vars: I) -> Self
+	where
+		I: IntoIterator<Item = (K, V)>,
+		K: AsRef<OsStr>,
+		V: AsRef<OsStr>,
+	{
+		vars.into_iter().fold(self, |options, (key, value)| options.env(key, value))
+	}
+
+	/// Encode keys for this harness with `modes` instead of [`crate::default_key_modes`].
+	///
+	/// Use this when the application under test enables kitty keyboard
+	/// protocol flags, e.g. `crate::kitty_key_modes(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES)`.
+	pub fn key_modes(mut self, modes: KeyCodeEncodeModes) -> Self {
+		self.key_modes = Some(modes);
+		self
+	}
+
+	/// Set whether a panel launch may take desktop focus (default [`FocusPolicy::NotAllowed`]).
+	///
+	/// Applications that react to focus changes only see real focus events
+	/// when kitty itself has focus, so focus tests on panels need
+	/// [`FocusPolicy::OnDemand`].
+	pub fn focus_policy(mut self, policy: FocusPolicy) -> Self {
+		self.focus_policy = policy;
+		self
+	}
+
+	/// Poll and post-send timing for this harness instead of [`crate::Timing::default`].
+	pub fn timing(mut self, timing: crate::Timing) -> Self {
+		self.timing = Some(timing);
+		self
+	}
+
+	/// Start an asciicast recording as soon as the harness is up (see [`crate::KittyHarness::start_recording`]).
+	pub fn record(mut self) -> Self {
+		self.record = true;
+		self
+	}
+
+	/// Return whether [`LaunchOptions::record`] was requested.
+	pub fn records(&self) -> bool {
+		self.record
+	}
+
+	/// Return the timing requested with [`LaunchOptions::timing`].
+	pub fn harness_timing(&self) -> Option<crate::Timing> {
+		self.timing
+	}
+
+	/// Return the panel focus policy (see [`LaunchOptions::focus_policy`]).
+	pub fn panel_focus_policy(&self) -> FocusPolicy {
+		self.focus_policy
+	}
+
+	/// Return the key encoding modes requested with [`LaunchOptions::key_modes`].
+	pub fn key_encode_modes(&self) -> Option<KeyCodeEncodeModes> {
+		self.key_modes
+	}
+
+	/// Return the configured environment variables in order.
+	pub fn env_vars(&self) -> &[(OsString, OsString)] {
+		&self.env
+	}
+
+	/// Arguments placed before the command to apply the environment variables.
+	pub(crate) fn env_prefix(&self) -> Vec<OsString> {
+		if self.env.is_empty() {
+			return Vec::new();
+		}
+		let mut args = vec![OsString::from("env")];
+		for (key, value) in &self.env {
+			let mut pair = key.clone();
+			pair.push("=");
+			pair.push(value);
+			args.push(pair);
+		}
+		args
+	}
+
+	/// Return the configured `kitty.conf` fragments in order.
+	pub fn config_fragments(&self) -> &[String] {
+		&self.config_fragments
+	}
+
+	/// Render the isolated config file, or `None` if no fragments were supplied and the options are not isolated.
+	///
+	/// Remote control is always enabled first so that fragments cannot
+	/// accidentally lock the harness out.
+	pub fn render_config(&self) -> Option<String> {
+		if self.config_fragments.is_empty() && !self.isolated {
+			return None;
+		}
+
+		let mut config = String::from("# generated by kitty-test-harness\nallow_remote_control yes\n");
+		for fragment in &self.config_fragments {
+			config.push_str(fragment.trim_end());
+			config.push('\n');
+		}
+		Some(config)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shell_command_is_passed_as_single_script() {
+		assert_eq!(LaunchCommand::from("echo $HOME").bash_args(), vec![OsString::from("echo $HOME")]);
+	}
+
+	#[test]
+	fn argv_command_is_passed_through_exec() {
+		let command = LaunchCommand::from(["my app", "--name=it's", "$HOME"]);
+		assert_eq!(
+			command.bash_args(),
+			["exec \"$@\"", "bash", "my app", "--name=it's", "$HOME"].map(OsString::from).to_vec()
+		);
+		assert_eq!(command.to_string(), "'my app' '--name=it'\"'\"'s' '$HOME'");
+	}
+
+	#[test]
+	#[should_panic(expected = "at least a program")]
+	fn empty_argv_is_rejected() {
+		let _ = LaunchCommand::argv(Vec::<String>::new());
+	}
+
+	#[test]
+	fn env_vars_become_env_prefix() {
+		let options = LaunchOptions::new().env("MY_APP_CONFIG", "/tmp/it's here").envs([("A", "1")]);
+		assert_eq!(
+			options.env_prefix(),
+			["env", "MY_APP_CONFIG=/tmp/it's here", "A=1"].map(OsString::from).to_vec()
+		);
+		assert!(LaunchOptions::new().env_prefix().is_empty());
+	}
+
+	#[test]
+	#[should_panic(expected = "invalid environment variable name")]
+	fn env_name_with_equals_is_rejected() {
+		let _ = LaunchOptions::new().env("A=B", "1");
+	}
+
+	#[test]
+	fn sized_launches_use_a_normal_window() {
+		assert!(!LaunchOptions::new().forces_normal_window());
+		let options = LaunchOptions::new().size(80, 24);
+		assert!(options.forces_normal_window());
+		assert_eq!(
+			options.overrides(),
+			["initial_window_width=80c", "initial_window_height=24c", "remember_window_size=no"]
+		);
+	}
+
+	#[test]
+	fn no_fragments_means_no_config() {
+		assert_eq!(LaunchOptions::new().render_config(), None);
+	}
+
+	#[test]
+	fn fragments_are_merged_in_order() {
+		let config = LaunchOptions::new()
+			.config("scrollback_lines 500\n")
+			.config("map f1 no_op\nmouse_map left click ungrabbed no_op")
+			.render_config()
+			.unwrap();
+		assert_eq!(
+			config,
+			"# generated by kitty-test-harness\nallow_remote_control yes\nscrollback_lines 500\nmap f1 no_op\nmouse_map left click ungrabbed no_op\n"
+		);
+	}
+
+	#[test]
+	fn font_options_become_overrides() {
+		let options = LaunchOptions::new().font_family("DejaVu Sans Mono").font_size(11.5);
+		assert_eq!(options.overrides(), &["font_family=DejaVu Sans Mono".to_string(), "font_size=11.5".to_string()]);
+	}
+
+	#[test]
+	fn size_sets_initial_window_cells() {
+		assert_eq!(
+			LaunchOptions::new().size(120, 40).overrides(),
+			["initial_window_width=120c", "initial_window_height=40c", "remember_window_size=no"].map(String::from)
+		);
+	}
+
+	#[test]
+	fn isolated_options_render_header_only() {
+		assert_eq!(
+			LaunchOptions::new().isolated().render_config().as_deref(),
+			Some("# generated by kitty-test-harness\nallow_remote_control yes\n")
+		);
+	}
+
+	#[test]
+	fn config_file_reads_fixture() {
+		let path = std::env::temp_dir().join(format!("kitty-test-launch-fixture-{}.conf", std::process::id()));
+		std::fs::write(&path, "cursor_blink_interval 0\n").unwrap();
+		let options = LaunchOptions::new().config_file(&path);
+		assert_eq!(options.config_fragments(), &["cursor_blink_interval 0\n".to_string()]);
+		let _ = std::fs::remove_file(&path);
+	}
+}
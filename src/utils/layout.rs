@@ -0,0 +1,359 @@
+//! Structure detection in clean captures.
+//!
+//! [`find_boxes`] locates rectangles drawn with box-drawing characters
+//! (popups, dialogs, bordered panes) and returns their position, border
+//! style, title and interior text. [`parse_table`] splits column-aligned
+//! output (file lists, `ps`-style tables, bordered tables) into rows and
+//! cells. Coordinates are 0-based display columns, like the rest of
+//! [`crate::utils::screen`].
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::utils::layout::{BoxStyle, find_boxes};
+//! use kitty_test_harness::utils::screen::Rect;
+//!
+//! let screen = "editor text\n  ╭─ Save? ─╮\n  │ yes  no │\n  ╰─────────╯";
+//! let boxes = find_boxes(screen);
+//! assert_eq!(boxes[0].rect, Rect::new(1, 2, 11, 3));
+//! assert_eq!(boxes[0].style, BoxStyle::Rounded);
+//! assert_eq!(boxes[0].title.as_deref(), Some("Save?"));
+//! assert_eq!(boxes[0].interior, " yes  no");
+//! ```
+
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::utils::screen::{Rect, cells};
+
+/// Line style of a box border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BoxStyle {
+	/// `┌─┐` light lines with square corners.
+	Single,
+	/// `╭─╮` light lines with rounded corners.
+	Rounded,
+	/// `╔═╗` double lines.
+	Double,
+	/// `┏━┓` heavy lines.
+	Heavy,
+}
+
+/// A box found by [`find_boxes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoxRegion {
+	/// Bounds including the border.
+	pub rect: Rect,
+	/// Border style, taken from the top-left corner.
+	pub style: BoxStyle,
+	/// Text embedded in the top border (e.g. `┌─ Title ─┐`), if any.
+	pub title: Option<String>,
+	/// Text inside the border, one line per row with trailing whitespace removed.
+	pub interior: String,
+}
+
+impl BoxRegion {
+	/// Bounds of the interior, excluding the border.
+	pub fn inner(&self) -> Rect {
+		Rect::new(self.rect.row + 1, self.rect.col + 1, self.rect.width - 2, self.rect.height - 2)
+	}
+}
+
+/// Border characters of one [`BoxStyle`].
+struct Border {
+	style: BoxStyle,
+	corners: [char; 4],
+	/// Characters allowed on the left and right sides, including junctions.
+	sides: &'static str,
+	/// Line characters of the top and bottom edges, blanked out of titles.
+	edges: &'static str,
+}
+
+const BORDERS: [Border; 4] = [
+	Border {
+		style: BoxStyle::Single,
+		corners: ['┌', '┐', '└', '┘'],
+		sides: "│├┤┼╞╡",
+		edges: "─┬┴┼╥╨",
+	},
+	Border {
+		style: BoxStyle::Rounded,
+		corners: ['╭', '╮', '╰', '╯'],
+		sides: "│├┤┼╞╡",
+		edges: "─┬┴┼╥╨",
+	},
+	Border {
+		style: BoxStyle::Double,
+		corners: ['╔', '╗', '╚', '╝'],
+		sides: "║╠╣╬╟╢",
+		edges: "═╦╩╬╤╧",
+	},
+	Border {
+		style: BoxStyle::Heavy,
+		corners: ['┏', '┓', '┗', '┛'],
+		sides: "┃┣┫╋┠┨",
+		edges: "━┳┻╋┯┷",
+	},
+];
+
+/// Find boxes drawn with box-drawing characters in clean screen text.
+///
+/// A box needs matching corners of one style and unbroken left and right
+/// sides; the top and bottom edges may carry text such as titles or key
+/// hints. Each top-left corner is paired with the nearest top-right corner
+/// that closes a box, so side-by-side and nested boxes are all reported,
+/// ordered by their top-left corner (top to bottom, then left to right).
+pub fn find_boxes(clean: &str) -> Vec<BoxRegion> {
+	let grid: Vec<Vec<&str>> = clean.lines().map(cells).collect();
+	let at = |row: usize, col: usize| grid.get(row).and_then(|cells| cells.get(col)).copied().unwrap_or("");
+	let is = |row: usize, col: usize, set: &str| {
+		let mut chars = at(row, col).chars();
+		chars.next().is_some_and(|ch| set.contains(ch)) && chars.next().is_none()
+	};
+
+	let mut boxes = Vec::new();
+	for (top, cells) in grid.iter().enumerate() {
+		for left in 0..cells.len() {
+			let Some(border) = BORDERS.iter().find(|border| is(top, left, &border.corners[0].to_string())) else {
+				continue;
+			};
+			let [_, top_right, bottom_left, bottom_right] = border.corners.map(String::from);
+			let found = (left + 2..cells.len()).filter(|&right| is(top, right, &top_right)).find_map(|right| {
+				let mut bottom = top + 1;
+				while is(bottom, left, border.sides) && is(bottom, right, border.sides) {
+					bottom += 1;
+				}
+				(is(bottom, left, &bottom_left) && is(bottom, right, &bottom_right)).then_some((right, bottom))
+			});
+			let Some((right, bottom)) = found else {
+				continue;
+			};
+
+			let title: String = grid[top][left + 1..right]
+				.iter()
+				.map(|cell| if cell.chars().all(|ch| border.edges.contains(ch)) { " " } else { cell })
+				.collect();
+			let title = title.trim();
+			let interior = (top + 1..bottom)
+				.map(|row| grid[row][left + 1..right].concat().trim_end().to_string())
+				.collect::<Vec<_>>()
+				.join("\n");
+			boxes.push(BoxRegion {
+				rect: Rect::new(top, left, right - left + 1, bottom - top + 1),
+				style: border.style,
+				title: (!title.is_empty()).then(|| title.to_string()),
+				interior,
+			});
+		}
+	}
+	boxes
+}
+
+/// Column separators recognized by [`parse_table`].
+const TABLE_SEPARATORS: [&str; 4] = ["│", "┃", "║", "|"];
+
+/// Characters that make up horizontal rules between table rows.
+const TABLE_RULES: &str = "─━═-=+┼╋╬╪┬┴├┤┌┐└┘╭╮╰╯│┃║|";
+
+/// A table parsed by [`parse_table`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Table {
+	/// Display columns covered by each table column (relative to the parsed region).
+	pub columns: Vec<Range<usize>>,
+	/// Parsed rows in screen order, the header (if any) first.
+	pub rows: Vec<TableRow>,
+}
+
+/// One row of a [`Table`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRow {
+	/// 0-based screen row the cells came from.
+	pub row: usize,
+	/// Trimmed cell text, one entry per table column.
+	pub cells: Vec<String>,
+}
+
+impl TableRow {
+	/// Text of cell `idx`.
+	pub fn get(&self, idx: usize) -> Option<&str> {
+		self.cells.get(idx).map(String::as_str)
+	}
+
+	/// Parse cell `idx` as `T`, or `None` if it is missing or does not parse.
+	pub fn parse<T: FromStr>(&self, idx: usize) -> Option<T> {
+		self.get(idx)?.parse().ok()
+	}
+}
+
+impl Table {
+	/// The first row, conventionally the column headers.
+	pub fn header(&self) -> Option<&TableRow> {
+		self.rows.first()
+	}
+
+	/// Rows after the header.
+	pub fn body(&self) -> &[TableRow] {
+		self.rows.get(1..).unwrap_or_default()
+	}
+
+	/// Index of the column whose header is `name`.
+	pub fn column(&self, name: &str) -> Option<usize> {
+		self.header()?.cells.iter().position(|cell| cell == name)
+	}
+
+	/// Cell of body row `idx` in the column headed `name`.
+	pub fn get(&self, idx: usize, name: &str) -> Option<&str> {
+		self.body().get(idx)?.get(self.column(name)?)
+	}
+
+	/// Cells of the column headed `name` for every body row.
+	pub fn column_values(&self, name: &str) -> Vec<&str> {
+		let Some(col) = self.column(name) else {
+			return Vec::new();
+		};
+		self.body().iter().filter_map(|row| row.get(col)).collect()
+	}
+}
+
+/// Split column-aligned text into a [`Table`], optionally limited to `region`.
+///
+/// Blank lines and horizontal rules (`───`, `-+-`, `═╪═`) are skipped. If
+/// every remaining line has a `│`/`|` separator at the same display column,
+/// columns are split there; otherwise a column boundary is any display
+/// column that is blank on every line, which handles left- and
+/// right-aligned fields alike. Columns that are empty on every row (such
+/// as outside a bordered table) are dropped.
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::utils::layout::parse_table;
+///
+/// let screen = "  PID TTY      CMD\n    1 pts/0    bash\n  842 pts/0    vim notes.md";
+/// let table = parse_table(screen, None);
+/// assert_eq!(table.header().unwrap().cells, ["PID", "TTY", "CMD"]);
+/// assert_eq!(table.body()[1].parse::<u32>(0), Some(842));
+/// assert_eq!(table.get(1, "CMD"), Some("vim notes.md"));
+/// ```
+pub fn parse_table(clean: &str, region: Option<Rect>) -> Table {
+	let lines: Vec<(usize, Vec<&str>)> = clean
+		.lines()
+		.map(cells)
+		.enumerate()
+		.filter_map(|(row, cells)| match region {
+			None => Some((row, cells)),
+			Some(rect) if rect.contains(row, rect.col) => {
+				let end = (rect.col + rect.width).min(cells.len());
+				Some((row, cells.get(rect.col..end).unwrap_or_default().to_vec()))
+			}
+			Some(_) => None,
+		})
+		.filter(|(_, cells)| !is_rule(cells))
+		.collect();
+	let width = lines.iter().map(|(_, cells)| cells.len()).max().unwrap_or(0);
+	let blank = |col: usize| {
+		lines
+			.iter()
+			.all(|(_, cells)| cells.get(col).is_none_or(|cell| cell.trim().is_empty() && !cell.is_empty()))
+	};
+
+	let separators: Vec<usize> = (0..width)
+		.filter(|&col| {
+			lines
+				.iter()
+				.all(|(_, cells)| cells.get(col).is_some_and(|cell| TABLE_SEPARATORS.contains(cell)))
+		})
+		.collect();
+	let mut ranges = Vec::new();
+	if separators.is_empty() {
+		let mut start = None;
+		for col in 0..=width {
+			match (start, col == width || blank(col)) {
+				(None, false) => start = Some(col),
+				(Some(from), true) => {
+					ranges.push(from..col);
+					start = None;
+				}
+				_ => {}
+			}
+		}
+	} else {
+		let mut from = 0;
+		for &sep in separators.iter().chain(std::iter::once(&width)) {
+			ranges.push(from..sep);
+			from = sep + 1;
+		}
+	}
+
+	let text = |cells: &[&str], range: &Range<usize>| {
+		let end = range.end.min(cells.len());
+		cells.get(range.start..end).unwrap_or_default().concat().trim().to_string()
+	};
+	ranges.retain(|range| lines.iter().any(|(_, cells)| !text(cells, range).is_empty()));
+	let rows = lines
+		.iter()
+		.map(|(row, cells)| TableRow {
+			row: *row,
+			cells: ranges.iter().map(|range| text(cells, range)).collect(),
+		})
+		.collect();
+	Table { columns: ranges, rows }
+}
+
+/// Whether a line is blank or a horizontal rule.
+fn is_rule(cells: &[&str]) -> bool {
+	cells.iter().all(|cell| cell.chars().all(|ch| ch == ' ' || TABLE_RULES.contains(ch)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_nested_and_side_by_side_boxes() {
+		let screen = "\
+┌──────────┐ ╔══╗
+│ ┏━━┓     │ ║日║
+│ ┃ok┃     │ ╚══╝
+│ ┗━━┛ ┌─  │
+└──────────┘
+┌─┐
+│ x";
+		let boxes = find_boxes(screen);
+		let found: Vec<_> = boxes.iter().map(|b| (b.style, b.rect)).collect();
+		assert_eq!(
+			found,
+			vec![
+				(BoxStyle::Single, Rect::new(0, 0, 12, 5)),
+				(BoxStyle::Double, Rect::new(0, 13, 4, 3)),
+				(BoxStyle::Heavy, Rect::new(1, 2, 4, 3)),
+			]
+		);
+		assert_eq!(boxes[1].interior, "日");
+		assert_eq!(boxes[2].interior, "ok");
+		assert_eq!(boxes[2].inner(), Rect::new(2, 3, 2, 1));
+		assert_eq!(boxes[0].title, None);
+	}
+
+	#[test]
+	fn parses_bordered_and_whitespace_tables() {
+		let screen = "\
+┌──────┬─────┐
+│ name │ 大小│
+├──────┼─────┤
+│ a b  │  10 │
+│      │   2 │
+└──────┴─────┘";
+		let table = parse_table(screen, None);
+		assert_eq!(table.header().unwrap().cells, ["name", "大小"]);
+		assert_eq!(table.body()[0].cells, ["a b", "10"]);
+		assert_eq!(table.body()[1].cells, ["", "2"]);
+		assert_eq!(table.body()[1].row, 4);
+		assert_eq!(table.column_values("大小"), ["10", "2"]);
+
+		let screen = "ignored\n  x  left   right\n  1  aa        3\n  22 b        44\n";
+		let table = parse_table(screen, Some(Rect::new(1, 2, 20, 3)));
+		assert_eq!(table.columns, vec![0..2, 3..7, 10..15]);
+		assert_eq!(table.get(1, "right").and_then(|v| v.parse::<u8>().ok()), Some(44));
+	}
+}
@@ -11,7 +11,8 @@
 //! 2. Pass the path to your application via environment variable
 //! 3. Have your application write debug messages to this file
 //! 4. Read back the log with [`read_test_log`] or wait for specific
-//!    patterns with [`wait_for_log_line`]
+//!    patterns with [`wait_for_log_line`] (or [`wait_for_log_line_with`]
+//!    to follow a harness's timing and deadline)
 //!
 //! # Example
 //!
@@ -38,6 +39,9 @@ use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
+use crate::KittyHarness;
+use crate::utils::timing::{PollDelays, Timing};
+
 static LOG_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 /// Creates a unique test log file and returns its path.
@@ -77,14 +81,21 @@ pub fn read_test_log(path: &Path) -> Vec<String> {
 /// Polls the file every 10ms until timeout is reached.
 /// Returns the first matching line, or `None` if timeout expires.
 pub fn wait_for_log_line(path: &Path, timeout: Duration, predicate: impl Fn(&str) -> bool) -> Option<String> {
+	poll_log_line(path, timeout, Timing::fixed(Duration::from_millis(10), Duration::ZERO).poll_delays(), predicate)
+}
+
+/// Like [`wait_for_log_line`], but polls with the [`crate::Timing`] of `kitty` and clamps `timeout` to its [`crate::Deadline`].
+pub fn wait_for_log_line_with(kitty: &KittyHarness, path: &Path, timeout: Duration, predicate: impl Fn(&str) -> bool) -> Option<String> {
+	poll_log_line(path, kitty.clamp_timeout(timeout), kitty.timing().poll_delays(), predicate)
+}
+
+fn poll_log_line(path: &Path, timeout: Duration, mut delays: PollDelays, predicate: impl Fn(&str) -> bool) -> Option<String> {
 	let start = std::time::Instant::now();
 	while start.elapsed() < timeout {
-		for line in read_test_log(path) {
-			if predicate(&line) {
-				return Some(line);
-			}
+		if let Some(line) = read_test_log(path).into_iter().find(|line| predicate(line)) {
+			return Some(line);
 		}
-		std::thread::sleep(Duration::from_millis(10));
+		delays.sleep();
 	}
 	None
 }
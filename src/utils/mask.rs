@@ -0,0 +1,222 @@
+//! Capture masking for stable snapshots.
+//!
+//! Screens often contain content that changes between runs (clocks, version
+//! strings, temp paths). Rather than giving up on whole-screen snapshots, a
+//! [`MaskSet`] blanks out configured rectangles or regex matches before the
+//! capture is compared.
+//!
+//! Masks work on both raw (ANSI) and clean captures: escape sequences are
+//! left untouched and only visible characters are replaced, so styling
+//! survives in raw snapshots. Rectangle columns are display columns, like
+//! the rest of [`crate::utils::screen`]; a masked wide character is replaced
+//! by one fill character per column it covers, so alignment is kept.
+//!
+//! The same masks apply to comparisons: [`crate::diff_screens_masked`] diffs
+//! two captures with the masked regions blanked, and masks installed with
+//! [`crate::KittyHarness::set_masks`] are honored by snapshots and by the
+//! checkpoint assertions and diffs of the harness.
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::utils::mask::MaskSet;
+//!
+//! let masks = MaskSet::new().pattern(r"\d{2}:\d{2}:\d{2}").fill('#');
+//! assert_eq!(masks.apply("status 12:34:56 ok"), "status ######## ok");
+//! ```
+
+use regex::Regex;
+
+use crate::utils::ansi::{TokenKind, split_tokens};
+use crate::utils::screen::{Rect, display_width};
+
+/// A single region of a capture to blank out.
+#[derive(Debug, Clone)]
+pub enum Mask {
+	/// Blank every cell inside the rectangle.
+	Rect(Rect),
+	/// Blank every match of the pattern (matched against visible text, per line).
+	Pattern(Regex),
+}
+
+impl Mask {
+	/// Mask a rectangle given its top-left corner and size.
+	pub fn rect(row: usize, col: usize, width: usize, height: usize) -> Self {
+		Mask::Rect(Rect::new(row, col, width, height))
+	}
+
+	/// Mask all matches of a regular expression.
+	///
+	/// # Panics
+	///
+	/// Panics if `pattern` is not a valid regex.
+	pub fn pattern(pattern: &str) -> Self {
+		Mask::Pattern(Regex::new(pattern).unwrap_or_else(|err| panic!("invalid mask pattern {pattern:?}: {err}")))
+	}
+}
+
+/// An ordered collection of masks plus the fill character used to blank cells.
+#[derive(Debug, Clone)]
+pub struct MaskSet {
+	masks: Vec<Mask>,
+	fill: char,
+}
+
+impl Default for MaskSet {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl MaskSet {
+	/// Create an empty mask set that fills with spaces.
+	pub fn new() -> Self {
+		Self { masks: Vec::new(), fill: ' ' }
+	}
+
+	/// Add a mask to the set.
+	pub fn with(mut self, mask: Mask) -> Self {
+		self.masks.push(mask);
+		self
+	}
+
+	/// Add a rectangular mask.
+	pub fn rect(self, row: usize, col: usize, width: usize, height: usize) -> Self {
+		self.with(Mask::rect(row, col, width, height))
+	}
+
+	/// Add a regex mask.
+	pub fn pattern(self, pattern: &str) -> Self {
+		self.with(Mask::pattern(pattern))
+	}
+
+	/// Set the character written over masked cells.
+	pub fn fill(mut self, fill: char) -> Self {
+		self.fill = fill;
+		self
+	}
+
+	/// Returns true if no masks are configured.
+	pub fn is_empty(&self) -> bool {
+		self.masks.is_empty()
+	}
+
+	/// Apply all masks to a raw or clean capture.
+	pub fn apply(&self, capture: &str) -> String {
+		if self.masks.is_empty() {
+			return capture.to_string();
+		}
+
+		capture
+			.split('\n')
+			.enumerate()
+			.map(|(row, line)| self.apply_line(row, line))
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+
+	fn apply_line(&self, row: usize, line: &str) -> String {
+		let tokens = split_tokens(line);
+		let visible: String = tokens.iter().filter(|t| t.kind == TokenKind::Text).map(|t| t.text.as_str()).collect();
+		// Display columns covered by each visible character.
+		let spans: Vec<std::ops::Range<usize>> = visible
+			.chars()
+			.scan(0, |col, ch| {
+				let start = *col;
+				*col += display_width(ch.encode_utf8(&mut [0; 4]));
+				Some(start..(*col).max(start + 1))
+			})
+			.collect();
+
+		let mut masked = vec![false; spans.len()];
+		for mask in &self.masks {
+			match mask {
+				Mask::Rect(rect) => {
+					for (slot, span) in masked.iter_mut().zip(&spans) {
+						if span.clone().any(|col| rect.contains(row, col)) {
+							*slot = true;
+						}
+					}
+				}
+				Mask::Pattern(regex) => {
+					for found in regex.find_iter(&visible) {
+						let start = visible[..found.start()].chars().count();
+						let len = found.as_str().chars().count();
+						for slot in masked.iter_mut().skip(start).take(len) {
+							*slot = true;
+						}
+					}
+				}
+			}
+		}
+
+		let mut out = String::with_capacity(line.len());
+		let mut idx = 0usize;
+		for token in &tokens {
+			match token.kind {
+				TokenKind::Escape => out.push_str(&token.raw),
+				TokenKind::Text => {
+					for ch in token.text.chars() {
+						if masked[idx] {
+							let width = display_width(ch.encode_utf8(&mut [0; 4]));
+							out.extend(std::iter::repeat_n(self.fill, width));
+						} else {
+							out.push(ch);
+						}
+						idx += 1;
+					}
+				}
+			}
+		}
+		out
+	}
+}
+
+/// Apply a mask set to a capture (convenience for `masks.apply(capture)`).
+pub fn mask_capture(capture: &str, masks: &MaskSet) -> String {
+	masks.apply(capture)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rect_mask_blanks_cells() {
+		let masks = MaskSet::new().rect(0, 6, 5, 1).fill('*');
+		assert_eq!(masks.apply("hello world\nhello world"), "hello *****\nhello world");
+	}
+
+	#[test]
+	fn pattern_mask_blanks_matches_on_every_line() {
+		let masks = MaskSet::new().pattern(r"v\d+\.\d+\.\d+").fill('#');
+		assert_eq!(masks.apply("app v1.2.3\nlib v10.0.0 ok"), "app ######\nlib ####### ok");
+	}
+
+	#[test]
+	fn masks_preserve_escape_sequences() {
+		let masks = MaskSet::new().rect(0, 0, 2, 1).fill('.');
+		let raw = "\x1b[31mab\x1b[0mcd";
+		assert_eq!(masks.apply(raw), "\x1b[31m..\x1b[0mcd");
+	}
+
+	#[test]
+	fn pattern_spans_styled_segments() {
+		let masks = MaskSet::new().pattern("12:34").fill('#');
+		let raw = "at \x1b[1m12\x1b[0m:34 now";
+		assert_eq!(masks.apply(raw), "at \x1b[1m##\x1b[0m### now");
+	}
+
+	#[test]
+	fn rect_columns_are_display_columns() {
+		let masks = MaskSet::new().rect(0, 5, 5, 1).fill('#');
+		assert_eq!(masks.apply("日本 12:00 x"), "日本 ##### x");
+		// A wide character partly inside the rect is blanked across both of its columns.
+		assert_eq!(MaskSet::new().rect(0, 3, 2, 1).fill('#').apply("日本 x"), "日###x");
+	}
+
+	#[test]
+	fn empty_set_is_identity() {
+		assert_eq!(MaskSet::new().apply("same\ntext"), "same\ntext");
+	}
+}
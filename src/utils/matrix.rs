@@ -0,0 +1,101 @@
+//! Running one scenario at several terminal sizes or color themes.
+//!
+//! Layout bugs often only show at some widths. [`run_at_sizes`] launches a
+//! fresh harness per size (so the application starts at that size rather
+//! than reacting to a resize) and runs the same driver in each. The
+//! [`crate::kitty_size_matrix_test!`] macro builds on it and snapshots every
+//! size separately, as `<test>-<cols>x<rows>`.
+//!
+//! Likewise, [`run_with_themes`] launches once per named [`Theme`] so text
+//! that is only unreadable in a light or dark theme gets caught; the driver
+//! receives the theme to check contrast against its palette, and
+//! [`crate::kitty_theme_matrix_test!`] snapshots each theme as `<test>-<name>`.
+//!
+//! ```no_run
+//! use kitty_test_harness::{LaunchOptions, manifest_dir, run_at_sizes};
+//!
+//! let captures = run_at_sizes(&manifest_dir(), "my-app", &LaunchOptions::new(), &[(80, 24), (120, 40)], |kitty| {
+//!     kitty.snapshot_text()
+//! });
+//! assert_eq!(captures.len(), 2);
+//! ```
+
+use std::path::Path;
+
+use crate::KittyHarness;
+use crate::utils::launch::{LaunchCommand, LaunchOptions};
+use crate::utils::resize::resize_window;
+use crate::utils::theme::Theme;
+
+/// Common terminal sizes as `(cols, rows)`: the classic 80x24, a laptop-sized 120x40 and a wide 200x60.
+pub const COMMON_SIZES: [(u16, u16); 3] = [(80, 24), (120, 40), (200, 60)];
+
+/// Dark ([`Theme::xterm`]), light ([`Theme::light`]) and high-contrast ([`Theme::high_contrast`]) themes with their names.
+pub fn common_themes() -> [(&'static str, Theme); 3] {
+	[("dark", Theme::xterm()), ("light", Theme::light()), ("high-contrast", Theme::high_contrast())]
+}
+
+/// Launch `command` once per `(cols, rows)` size with `options` and run `driver` in each harness.
+///
+/// Each harness is opened at its size through [`LaunchOptions::size`], which
+/// launches a normal window rather than the full-screen Wayland background
+/// panel; if the window manager overrides the size, the window is resized
+/// before `driver` runs.
+/// Results are returned with their sizes, in the order given.
+///
+/// # Panics
+///
+/// Panics if kitty fails to launch, or if a window cannot be brought to its size.
+pub fn run_at_sizes<T>(
+	working_dir: &Path,
+	command: impl Into<LaunchCommand>,
+	options: &LaunchOptions,
+	sizes: &[(u16, u16)],
+	mut driver: impl FnMut(&KittyHarness) -> T,
+) -> Vec<((u16, u16), T)> {
+	let command = command.into();
+	sizes
+		.iter()
+		.map(|&(cols, rows)| {
+			let kitty = KittyHarness::launch_with(working_dir, command.clone(), &options.clone().size(cols, rows));
+			if kitty.size() != (cols, rows) {
+				resize_window(&kitty, cols, rows);
+			}
+			let actual = kitty.size();
+			assert_eq!(
+				actual,
+				(cols, rows),
+				"kitty window is {}x{} instead of {cols}x{rows}; is a tiling window manager fixing its size?",
+				actual.0,
+				actual.1
+			);
+			((cols, rows), driver(&kitty))
+		})
+		.collect()
+}
+
+/// Launch `command` once per named theme with `options` and run `driver` in each harness.
+///
+/// The theme is applied with [`LaunchOptions::theme`] after any config in
+/// `options`, so it takes precedence. Results are returned with the theme
+/// names, in the order given.
+///
+/// # Panics
+///
+/// Panics if kitty fails to launch.
+pub fn run_with_themes<T>(
+	working_dir: &Path,
+	command: impl Into<LaunchCommand>,
+	options: &LaunchOptions,
+	themes: &[(&str, Theme)],
+	mut driver: impl FnMut(&KittyHarness, &Theme) -> T,
+) -> Vec<(String, T)> {
+	let command = command.into();
+	themes
+		.iter()
+		.map(|(name, theme)| {
+			let kitty = KittyHarness::launch_with(working_dir, command.clone(), &options.clone().theme(theme));
+			(name.to_string(), driver(&kitty, theme))
+		})
+		.collect()
+}
@@ -1,21 +1,95 @@
 //! Module for utility functions and helpers for the kitty test harness.
 
+/// Animated PNG artifacts of sampled frames.
+#[cfg(feature = "apng")]
+pub mod animation;
+/// Escape-sequence tokenizing of raw captures.
+pub mod ansi;
+/// Typed screen captures and `get-text` capture options.
+pub mod capture;
+/// asciicast v2 recording of harness sessions.
+pub mod cast;
+/// Splitting of large input payloads into chunks.
+pub mod chunk;
+/// Clipboard access through the clipboard kitten.
+pub mod clipboard;
+/// Composed input (IME commits, dead keys) simulation.
+pub mod compose;
+/// WCAG contrast checks over styled captures.
+pub mod contrast;
+/// Overall per-test time budget shared by waits.
+pub mod deadline;
+/// Row and cell diffs between two screen captures.
+pub mod diff;
 /// Helpers for environment detection and test gating.
 pub mod env;
+/// Error type returned by the fallible `try_*` harness operations.
+pub mod error;
+/// Fluent, chainable assertions over screen captures.
+pub mod expect;
+/// Standalone HTML and SVG export of styled captures.
+pub mod export;
+/// Export of sampled screen frames (typescript, etc.).
+pub mod frames;
+/// Golden-file assertions for captures, independent of insta.
+pub mod golden;
 /// Terminal key encoding helpers and common key constants.
 pub mod keys;
+/// Input-to-render latency measurement.
+pub mod latency;
+/// Launch-time options such as kitty.conf fragments.
+pub mod launch;
+/// Box, dialog and table detection in clean captures.
+pub mod layout;
 /// Test logging utilities for debugging.
 pub mod log;
+/// Capture masking for stable snapshots (rects and patterns).
+pub mod mask;
+/// Running a scenario across terminal sizes and color themes.
+pub mod matrix;
+/// Terminal mode tracking (alternate screen, mouse reporting, keyboard protocol) from output streams.
+pub mod modes;
 /// Mouse event encoding and sending.
 pub mod mouse;
+/// Capture normalizers (regex replacements, masks) for stable snapshots.
+pub mod normalize;
+/// Helper commands run in overlay windows (clipboard, pixel geometry).
+pub mod overlay;
 /// Common testing patterns (mock executables, env wrappers, etc.).
 pub mod patterns;
+/// Warm kitty instance pool shared across tests.
+pub mod pool;
+/// Event-driven readiness through a FIFO marker channel.
+pub mod ready;
+/// Rasterizing styled captures into images.
+pub mod render;
 /// Recording replay for automated session testing.
 pub mod replay;
+/// Named test steps and Markdown/HTML test reports.
+pub mod report;
 /// Window resize utilities.
 pub mod resize;
 /// Screen content parsing (separators, ANSI colors, etc.).
 pub mod screen;
+/// Window screenshots and PNG snapshot comparison.
+#[cfg(feature = "screenshot")]
+pub mod screenshot;
+/// Composable input scripts shared by replay and hand-written tests.
+pub mod script;
+/// Per-test resource tracking (sockets, logs, artifact directories).
+pub mod session;
+/// Signals for the program running in a window.
+pub mod signal;
+/// Styled snapshot format (text, style markers and legend).
+pub mod snapshot;
+/// Typed model of the `kitty @ ls` window tree.
+pub mod state;
+/// SGR style tracking (colors and text attributes) for raw captures.
+pub mod style;
+/// Deterministic color themes for launch and runtime.
+pub mod theme;
+/// Poll interval, backoff and post-send delay configuration.
+pub mod timing;
 /// Helpers for waiting for certain conditions in the kitty harness.
 pub mod wait;
 /// Helpers for managing kitty windows and panels.
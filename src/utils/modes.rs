@@ -0,0 +1,256 @@
+//! Terminal mode tracking from raw output streams.
+//!
+//! `get-text` shows what is on screen but not which modes the application
+//! switched on, so [`TerminalModes::parse`] replays the bytes a program wrote
+//! (for example recorded with `script -qfc my-app out.log`) and reports the
+//! state left at the end: alternate screen, bracketed paste, mouse
+//! reporting, focus events, cursor visibility and the kitty keyboard
+//! protocol flags. [`TerminalModes::assert_restored`] checks that an
+//! application put everything back on exit.
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::{MouseReporting, TerminalModes};
+//!
+//! let running = TerminalModes::parse(b"\x1b[?1049h\x1b[?2004h\x1b[?1002h\x1b[?1006h\x1b[>1u");
+//! assert!(running.alternate_screen && running.bracketed_paste);
+//! assert_eq!(running.mouse_reporting, MouseReporting::ButtonMotion);
+//! assert_eq!(running.keyboard_flags(), 1);
+//!
+//! let exited = TerminalModes::parse(b"\x1b[?1049h\x1b[?1002h\x1b[>1u\x1b[<u\x1b[?1002l\x1b[?1049l");
+//! exited.assert_restored();
+//! ```
+
+use std::path::Path;
+
+use termwiz::escape::csi::{DecPrivateMode, DecPrivateModeCode, Keyboard, KittyKeyboardMode, Mode};
+use termwiz::escape::parser::Parser;
+use termwiz::escape::{Action, CSI};
+
+use crate::utils::error::HarnessError;
+
+/// Mouse reporting mode requested with DECSET 1000, 1002 or 1003.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MouseReporting {
+	/// No mouse events are reported.
+	#[default]
+	Off,
+	/// Button presses and releases (1000).
+	Clicks,
+	/// Presses, releases and motion while a button is held (1002).
+	ButtonMotion,
+	/// All motion, with or without a button held (1003).
+	AnyMotion,
+}
+
+/// Terminal modes set by an application, as tracked from its output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TerminalModes {
+	/// The alternate screen is active (DECSET 47, 1047 or 1049).
+	pub alternate_screen: bool,
+	/// Bracketed paste is enabled (DECSET 2004).
+	pub bracketed_paste: bool,
+	/// Which mouse events are reported.
+	pub mouse_reporting: MouseReporting,
+	/// Mouse events use the SGR encoding (DECSET 1006).
+	pub sgr_mouse: bool,
+	/// Focus in/out events are reported (DECSET 1004).
+	pub focus_reporting: bool,
+	/// Cursor keys send application sequences (DECCKM, DECSET 1).
+	pub application_cursor_keys: bool,
+	/// The cursor is shown (DECTCEM, DECSET 25).
+	pub cursor_visible: bool,
+	/// Kitty keyboard protocol flag stacks for the main and alternate screens; the last entry is current.
+	keyboard: [Vec<u16>; 2],
+}
+
+impl Default for TerminalModes {
+	fn default() -> Self {
+		Self {
+			alternate_screen: false,
+			bracketed_paste: false,
+			mouse_reporting: MouseReporting::Off,
+			sgr_mouse: false,
+			focus_reporting: false,
+			application_cursor_keys: false,
+			cursor_visible: true,
+			keyboard: [vec![0], vec![0]],
+		}
+	}
+}
+
+impl TerminalModes {
+	/// The state of a freshly reset terminal.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Replay `stream` from a freshly reset terminal and return the modes left at its end.
+	pub fn parse(stream: &[u8]) -> Self {
+		let mut modes = Self::new();
+		modes.feed(stream);
+		modes
+	}
+
+	/// Replay a recorded output stream read from `path`.
+	pub fn from_file(path: &Path) -> Result<Self, HarnessError> {
+		let stream = std::fs::read(path).map_err(|err| HarnessError::io(format!("read {}", path.display()), err))?;
+		Ok(Self::parse(&stream))
+	}
+
+	/// Apply further output to the tracked state.
+	///
+	/// Sequences split across calls are not reassembled, so feed whole
+	/// recordings or chunks that end on a sequence boundary.
+	pub fn feed(&mut self, stream: &[u8]) {
+		Parser::new().parse(stream, |action| match action {
+			Action::CSI(CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(code)))) => self.set_dec_mode(code, true),
+			Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(DecPrivateMode::Code(code)))) => self.set_dec_mode(code, false),
+			Action::CSI(CSI::Keyboard(keyboard)) => self.apply_keyboard(keyboard),
+			_ => {}
+		});
+	}
+
+	/// Current kitty keyboard protocol flags (`0` when the protocol is off).
+	pub fn keyboard_flags(&self) -> u16 {
+		self.keyboard_stack().last().copied().unwrap_or_default()
+	}
+
+	/// Number of kitty keyboard protocol entries pushed and not popped on the current screen.
+	pub fn keyboard_stack_depth(&self) -> usize {
+		self.keyboard_stack().len() - 1
+	}
+
+	/// Names of the modes that differ from a freshly reset terminal, empty when everything was restored.
+	pub fn active(&self) -> Vec<&'static str> {
+		let mut active = Vec::new();
+		for (set, name) in [
+			(self.alternate_screen, "alternate screen"),
+			(self.bracketed_paste, "bracketed paste"),
+			(self.mouse_reporting == MouseReporting::Clicks, "mouse reporting (clicks)"),
+			(self.mouse_reporting == MouseReporting::ButtonMotion, "mouse reporting (button motion)"),
+			(self.mouse_reporting == MouseReporting::AnyMotion, "mouse reporting (any motion)"),
+			(self.sgr_mouse, "SGR mouse encoding"),
+			(self.focus_reporting, "focus reporting"),
+			(self.application_cursor_keys, "application cursor keys"),
+			(!self.cursor_visible, "hidden cursor"),
+			(self.keyboard.iter().any(|stack| stack.len() > 1 || stack[0] != 0), "kitty keyboard protocol"),
+		] {
+			if set {
+				active.push(name);
+			}
+		}
+		active
+	}
+
+	/// Returns true if every mode is back to its reset state.
+	pub fn is_restored(&self) -> bool {
+		self.active().is_empty()
+	}
+
+	/// Assert that the application restored the main screen and turned every mode it enabled back off.
+	///
+	/// # Panics
+	///
+	/// Panics listing the modes still enabled.
+	pub fn assert_restored(&self) {
+		let active = self.active();
+		if !active.is_empty() {
+			panic!("terminal modes left enabled: {}", active.join(", "));
+		}
+	}
+
+	fn set_dec_mode(&mut self, code: DecPrivateModeCode, enable: bool) {
+		match code {
+			DecPrivateModeCode::EnableAlternateScreen | DecPrivateModeCode::OptEnableAlternateScreen | DecPrivateModeCode::ClearAndEnableAlternateScreen => {
+				self.alternate_screen = enable
+			}
+			DecPrivateModeCode::BracketedPaste => self.bracketed_paste = enable,
+			DecPrivateModeCode::MouseTracking | DecPrivateModeCode::ButtonEventMouse | DecPrivateModeCode::AnyEventMouse if !enable => {
+				self.mouse_reporting = MouseReporting::Off
+			}
+			DecPrivateModeCode::MouseTracking => self.mouse_reporting = MouseReporting::Clicks,
+			DecPrivateModeCode::ButtonEventMouse => self.mouse_reporting = MouseReporting::ButtonMotion,
+			DecPrivateModeCode::AnyEventMouse => self.mouse_reporting = MouseReporting::AnyMotion,
+			DecPrivateModeCode::SGRMouse => self.sgr_mouse = enable,
+			DecPrivateModeCode::FocusTracking => self.focus_reporting = enable,
+			DecPrivateModeCode::ApplicationCursorKeys => self.application_cursor_keys = enable,
+			DecPrivateModeCode::ShowCursor => self.cursor_visible = enable,
+			_ => {}
+		}
+	}
+
+	fn apply_keyboard(&mut self, keyboard: Keyboard) {
+		let stack = &mut self.keyboard[usize::from(self.alternate_screen)];
+		match keyboard {
+			Keyboard::PushKittyState { flags, .. } => stack.push(flags.bits()),
+			Keyboard::PopKittyState(count) => {
+				// Popping every entry resets the flags, as kitty does.
+				if count as usize >= stack.len() - 1 {
+					*stack = vec![0];
+				} else {
+					stack.truncate(stack.len() - count as usize);
+				}
+			}
+			Keyboard::SetKittyState { flags, mode } => {
+				let current = stack.last_mut().expect("keyboard stack is never empty");
+				*current = match mode {
+					KittyKeyboardMode::AssignAll => flags.bits(),
+					KittyKeyboardMode::SetSpecified => *current | flags.bits(),
+					KittyKeyboardMode::ClearSpecified => *current & !flags.bits(),
+				};
+			}
+			Keyboard::QueryKittySupport | Keyboard::ReportKittyState(_) => {}
+		}
+	}
+
+	fn keyboard_stack(&self) -> &[u16] {
+		&self.keyboard[usize::from(self.alternate_screen)]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tracks_dec_modes_until_reset() {
+		let modes = TerminalModes::parse(b"\x1b[?1049h\x1b[?25l\x1b[?1h\x1b[?1003h\x1b[?1006h\x1b[?1004h\x1b[?2004h");
+		assert_eq!(
+			modes.active(),
+			[
+				"alternate screen",
+				"bracketed paste",
+				"mouse reporting (any motion)",
+				"SGR mouse encoding",
+				"focus reporting",
+				"application cursor keys",
+				"hidden cursor"
+			]
+		);
+
+		let mut modes = modes;
+		modes.feed(b"\x1b[?2004l\x1b[?1004l\x1b[?1006l\x1b[?1000l\x1b[?1l\x1b[?25h\x1b[?1049l");
+		assert!(modes.is_restored(), "{:?}", modes.active());
+	}
+
+	#[test]
+	fn keyboard_stacks_are_per_screen() {
+		let mut modes = TerminalModes::parse(b"\x1b[>1u\x1b[?1049h\x1b[>3u\x1b[=4;2u");
+		assert_eq!((modes.keyboard_flags(), modes.keyboard_stack_depth()), (7, 1));
+		modes.feed(b"\x1b[<u\x1b[?1049l");
+		assert_eq!((modes.keyboard_flags(), modes.keyboard_stack_depth()), (1, 1));
+		modes.feed(b"\x1b[<5u");
+		assert_eq!(modes.keyboard_flags(), 0);
+		modes.assert_restored();
+	}
+
+	#[test]
+	#[should_panic(expected = "terminal modes left enabled: alternate screen, kitty keyboard protocol")]
+	fn assert_restored_lists_leftover_modes() {
+		TerminalModes::parse(b"\x1b[?1049h\x1b[>1u\x1b[?2004h\x1b[?2004l").assert_restored();
+	}
+}
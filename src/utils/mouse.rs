@@ -8,27 +8,39 @@
 //!
 //! SGR mouse encoding uses the format: `\x1b[<Cb;Cx;CyM` for press and `\x1b[<Cb;Cx;Cym` for release
 //! Where:
-//! - Cb = button code (0=left, 1=middle, 2=right, 32+motion, 64+scroll)
+//! - Cb = button code (0=left, 1=middle, 2=right, 32+motion, 64/65 vertical scroll, 66/67 horizontal scroll)
 //! - Cx = column (1-based)
 //! - Cy = row (1-based)
 //! - M = press, m = release
 //!
+//! # Pixel Coordinates (mode 1016)
+//!
+//! Applications that enable SGR-pixel reporting (`CSI ? 1016 h`) receive the
+//! same sequences with pixel instead of cell coordinates. The `*_pixels`
+//! encoders take 0-based pixel positions; [`send_mouse_click_cell_pixels`]
+//! converts a cell to the pixel at its center using the window's cell size
+//! (see [`KittyHarness::cell_size`]).
+//!
 //! # Example
 //!
 //! ```ignore
-//! use kitty_test_harness::utils::mouse::{MouseButton, send_mouse_click, send_mouse_drag};
+//! use kitty_test_harness::utils::mouse::{MouseButton, ScrollDirection, send_mouse_click, send_mouse_drag, send_mouse_scroll};
 //!
 //! // Click at position (10, 5)
 //! send_mouse_click(kitty, MouseButton::Left, 10, 5);
 //!
 //! // Drag from (10, 5) to (20, 5)
 //! send_mouse_drag(kitty, MouseButton::Left, 10, 5, 20, 5);
+//!
+//! // Scroll a horizontally scrollable pane to the right
+//! send_mouse_scroll(kitty, ScrollDirection::Right, 10, 5);
 //! ```
 
 use crate::KittyHarness;
 
 /// Mouse button identifiers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseButton {
 	/// Left mouse button (button 0).
 	Left,
@@ -107,6 +119,51 @@ pub fn send_mouse_click(kitty: &KittyHarness, button: MouseButton, col: u16, row
 	kitty.send_text(&encode_mouse_release(button, col, row));
 }
 
+/// Timing of the clicks sent by [`send_mouse_multi_click`].
+///
+/// SGR reporting has no click count: applications detect double and triple
+/// clicks from the spacing of successive presses. `interval` must therefore
+/// stay below the application's multi-click threshold (commonly 300-500ms).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClickTiming {
+	/// How long each button is held between press and release.
+	pub hold: std::time::Duration,
+	/// Pause between a release and the next press.
+	pub interval: std::time::Duration,
+}
+
+impl Default for ClickTiming {
+	/// 10ms hold and 50ms between clicks.
+	fn default() -> Self {
+		Self {
+			hold: std::time::Duration::from_millis(10),
+			interval: std::time::Duration::from_millis(50),
+		}
+	}
+}
+
+/// Sends `clicks` consecutive clicks at the same position with the given timing.
+pub fn send_mouse_multi_click(kitty: &KittyHarness, button: MouseButton, col: u16, row: u16, clicks: u8, timing: ClickTiming) {
+	for idx in 0..clicks {
+		if idx > 0 {
+			std::thread::sleep(timing.interval);
+		}
+		kitty.send_text(&encode_mouse_press(button, col, row));
+		std::thread::sleep(timing.hold);
+		kitty.send_text(&encode_mouse_release(button, col, row));
+	}
+}
+
+/// Sends a double click (e.g. word selection) with [`ClickTiming::default`].
+pub fn send_mouse_double_click(kitty: &KittyHarness, button: MouseButton, col: u16, row: u16) {
+	send_mouse_multi_click(kitty, button, col, row, 2, ClickTiming::default());
+}
+
+/// Sends a triple click (e.g. line selection) with [`ClickTiming::default`].
+pub fn send_mouse_triple_click(kitty: &KittyHarness, button: MouseButton, col: u16, row: u16) {
+	send_mouse_multi_click(kitty, button, col, row, 3, ClickTiming::default());
+}
+
 /// Sends a mouse press event at the specified position.
 pub fn send_mouse_press(kitty: &KittyHarness, button: MouseButton, col: u16, row: u16) {
 	kitty.send_text(&encode_mouse_press(button, col, row));
@@ -162,8 +219,54 @@ pub fn send_mouse_drag_with_steps(kitty: &KittyHarness, button: MouseButton, sta
 	kitty.send_text(&encode_mouse_release(button, end_col, end_row));
 }
 
+/// Encodes a mouse press at pixel position `(x, y)` for SGR-pixel mode (1016).
+pub fn encode_mouse_press_pixels(button: MouseButton, x: u32, y: u32) -> String {
+	format!("\x1b[<{};{};{}M", button.code(), x + 1, y + 1)
+}
+
+/// Encodes a mouse release at pixel position `(x, y)` for SGR-pixel mode (1016).
+pub fn encode_mouse_release_pixels(button: MouseButton, x: u32, y: u32) -> String {
+	format!("\x1b[<{};{};{}m", button.code(), x + 1, y + 1)
+}
+
+/// Encodes a drag (motion with `button` held) to pixel position `(x, y)` for SGR-pixel mode (1016).
+pub fn encode_mouse_drag_pixels(button: MouseButton, x: u32, y: u32) -> String {
+	format!("\x1b[<{};{};{}M", button.code() + 32, x + 1, y + 1)
+}
+
+/// Encodes a mouse move (no button) to pixel position `(x, y)` for SGR-pixel mode (1016).
+pub fn encode_mouse_move_pixels(x: u32, y: u32) -> String {
+	format!("\x1b[<35;{};{}M", x + 1, y + 1)
+}
+
+/// Returns the pixel at the center of cell `(col, row)` for a `(width, height)` cell size.
+pub fn cell_center_pixels(col: u16, row: u16, cell_size: (u32, u32)) -> (u32, u32) {
+	let (width, height) = cell_size;
+	(u32::from(col) * width + width / 2, u32::from(row) * height + height / 2)
+}
+
+/// Sends a click (press + release) at pixel position `(x, y)` for SGR-pixel mode (1016).
+pub fn send_mouse_click_pixels(kitty: &KittyHarness, button: MouseButton, x: u32, y: u32) {
+	kitty.send_text(&encode_mouse_press_pixels(button, x, y));
+	std::thread::sleep(std::time::Duration::from_millis(10));
+	kitty.send_text(&encode_mouse_release_pixels(button, x, y));
+}
+
+/// Sends an SGR-pixel click at the center of cell `(col, row)`, using the window's cell size.
+pub fn send_mouse_click_cell_pixels(kitty: &KittyHarness, button: MouseButton, col: u16, row: u16) {
+	let (x, y) = cell_center_pixels(col, row, kitty.cell_size());
+	send_mouse_click_pixels(kitty, button, x, y);
+}
+
+/// Parses the `WIDTHxHEIGHT` pixel size printed by `kitty +kitten icat --print-window-size`.
+pub(crate) fn parse_pixel_size(output: &str) -> Option<(u32, u32)> {
+	let (width, height) = output.trim().split_once('x')?;
+	Some((width.parse().ok()?, height.parse().ok()?))
+}
+
 /// Scroll direction for mouse scroll events.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ScrollDirection {
 	/// Scroll up.
 	Up,
@@ -207,6 +310,62 @@ pub fn send_mouse_scroll(kitty: &KittyHarness, direction: ScrollDirection, col:
 	kitty.send_text(&encode_mouse_scroll(direction, col, row));
 }
 
+/// Amount of scrolling to deliver in a single gesture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollAmount {
+	/// A fixed number of wheel ticks (one SGR event each).
+	Lines(u16),
+	/// A high-resolution delta in pixels, as produced by touchpads.
+	///
+	/// SGR mouse reporting has no fractional wheel events, so the delta is
+	/// converted to whole ticks of `pixels_per_line`, rounding up so that any
+	/// non-zero movement produces at least one event.
+	Pixels {
+		/// Total scroll distance in pixels.
+		delta: u32,
+		/// Pixels that correspond to one wheel tick (usually the cell height).
+		pixels_per_line: u32,
+	},
+}
+
+impl ScrollAmount {
+	/// Number of wheel events this amount expands to.
+	pub fn ticks(self) -> u16 {
+		match self {
+			ScrollAmount::Lines(count) => count,
+			ScrollAmount::Pixels { delta, pixels_per_line } => {
+				let per_line = pixels_per_line.max(1);
+				u16::try_from(delta.div_ceil(per_line)).unwrap_or(u16::MAX)
+			}
+		}
+	}
+}
+
+/// Encodes `count` consecutive scroll events at the same position as one string.
+pub fn encode_mouse_scroll_repeat(direction: ScrollDirection, col: u16, row: u16, count: u16) -> String {
+	encode_mouse_scroll(direction, col, row).repeat(count as usize)
+}
+
+/// Sends a scroll gesture as a stream of wheel events.
+///
+/// Each tick is sent separately with `interval` between events, so
+/// applications that accelerate or smooth scrolling see a realistic stream
+/// rather than one burst. Use a zero interval to send all ticks at once.
+pub fn send_mouse_scroll_amount(kitty: &KittyHarness, direction: ScrollDirection, col: u16, row: u16, amount: ScrollAmount, interval: std::time::Duration) {
+	let ticks = amount.ticks();
+	if interval.is_zero() {
+		if ticks > 0 {
+			kitty.send_text(&encode_mouse_scroll_repeat(direction, col, row, ticks));
+		}
+		return;
+	}
+
+	for _ in 0..ticks {
+		kitty.send_text(&encode_mouse_scroll(direction, col, row));
+		std::thread::sleep(interval);
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -245,6 +404,17 @@ mod tests {
 		assert_eq!(encode_mouse_move(0, 0), "\x1b[<35;1;1M");
 	}
 
+	#[test]
+	fn test_encode_mouse_pixels() {
+		assert_eq!(encode_mouse_press_pixels(MouseButton::Left, 0, 0), "\x1b[<0;1;1M");
+		assert_eq!(encode_mouse_release_pixels(MouseButton::Right, 99, 41), "\x1b[<2;100;42m");
+		assert_eq!(encode_mouse_drag_pixels(MouseButton::Left, 9, 9), "\x1b[<32;10;10M");
+		assert_eq!(encode_mouse_move_pixels(9, 9), "\x1b[<35;10;10M");
+		assert_eq!(cell_center_pixels(2, 1, (10, 20)), (25, 30));
+		assert_eq!(parse_pixel_size("800x600\n"), Some((800, 600)));
+		assert_eq!(parse_pixel_size("oops"), None);
+	}
+
 	#[test]
 	fn test_encode_mouse_scroll() {
 		assert_eq!(encode_mouse_scroll(ScrollDirection::Up, 0, 0), "\x1b[<64;1;1M");
@@ -252,4 +422,25 @@ mod tests {
 		assert_eq!(encode_mouse_scroll(ScrollDirection::Left, 0, 0), "\x1b[<66;1;1M");
 		assert_eq!(encode_mouse_scroll(ScrollDirection::Right, 0, 0), "\x1b[<67;1;1M");
 	}
+
+	#[test]
+	fn test_encode_mouse_scroll_repeat() {
+		assert_eq!(encode_mouse_scroll_repeat(ScrollDirection::Down, 0, 0, 3), "\x1b[<65;1;1M".repeat(3));
+		assert_eq!(encode_mouse_scroll_repeat(ScrollDirection::Down, 0, 0, 0), "");
+	}
+
+	#[test]
+	fn test_scroll_amount_ticks() {
+		assert_eq!(ScrollAmount::Lines(4).ticks(), 4);
+		assert_eq!(
+			ScrollAmount::Pixels {
+				delta: 40,
+				pixels_per_line: 20
+			}
+			.ticks(),
+			2
+		);
+		assert_eq!(ScrollAmount::Pixels { delta: 5, pixels_per_line: 20 }.ticks(), 1);
+		assert_eq!(ScrollAmount::Pixels { delta: 0, pixels_per_line: 20 }.ticks(), 0);
+	}
 }
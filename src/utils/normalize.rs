@@ -0,0 +1,258 @@
+//! Capture normalization before assertions and snapshots.
+//!
+//! A [`Normalizer`] rewrites a capture so that content which legitimately
+//! differs between runs (timestamps, PIDs, temp paths, version strings)
+//! compares equal. A [`NormalizerChain`] runs several in order; it is what
+//! [`crate::KittyHarness::set_normalizer`] and the `normalize = ...` form of
+//! [`crate::kitty_snapshot_test!`] apply.
+//!
+//! Closures `Fn(&str) -> String` and [`crate::MaskSet`] are normalizers, and
+//! [`Replace`] substitutes regex matches. Like masks, replacements match the
+//! visible text of each line and leave escape sequences in place, so they
+//! work on raw captures without corrupting styling.
+//!
+//! Ready-made scrubbers cover the usual volatile terminal content:
+//! [`Replace::durations`], [`Replace::iso_dates`], [`Replace::temp_paths`]
+//! and [`Replace::session_names`], all added at once by
+//! [`NormalizerChain::scrub_volatile`] or the `scrub` form of
+//! [`crate::kitty_snapshot_test!`].
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::utils::normalize::NormalizerChain;
+//!
+//! let chain = NormalizerChain::new()
+//!     .replace(r"pid \d+", "pid [PID]")
+//!     .replace(r"/tmp/[\w.-]+", "[TMP]")
+//!     .with(|capture: &str| capture.replace("v1.2.3", "[VERSION]"));
+//! assert_eq!(chain.apply("server v1.2.3 (pid 4242) in /tmp/.tmpAb12"), "server [VERSION] (pid [PID]) in [TMP]");
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::utils::ansi::{TokenKind, split_tokens};
+use crate::utils::mask::MaskSet;
+
+/// A rewrite applied to captures before they are compared or snapshotted.
+pub trait Normalizer: Send + Sync {
+	/// Return the normalized form of `capture` (raw or clean).
+	fn normalize(&self, capture: &str) -> String;
+}
+
+impl<F> Normalizer for F
+where
+	F: Fn(&str) -> String + Send + Sync,
+{
+	fn normalize(&self, capture: &str) -> String {
+		self(capture)
+	}
+}
+
+impl Normalizer for MaskSet {
+	fn normalize(&self, capture: &str) -> String {
+		self.apply(capture)
+	}
+}
+
+/// Replace every regex match in the visible text of each line.
+///
+/// The replacement may refer to capture groups (`$1`, `${name}`). A match
+/// spanning several styled segments is replaced in the first one and the
+/// escape sequences inside it are kept.
+#[derive(Debug, Clone)]
+pub struct Replace {
+	pattern: Regex,
+	replacement: String,
+}
+
+impl Replace {
+	/// Replace matches of `pattern` with `replacement`.
+	///
+	/// # Panics
+	///
+	/// Panics if `pattern` is not a valid regex.
+	pub fn new(pattern: &str, replacement: &str) -> Self {
+		Self {
+			pattern: Regex::new(pattern).unwrap_or_else(|err| panic!("invalid normalizer pattern {pattern:?}: {err}")),
+			replacement: replacement.to_string(),
+		}
+	}
+
+	/// Replace durations such as `1.23s`, `450ms`, `1m30s` or `1h` with `[DURATION]`.
+	///
+	/// A bare `m` suffix is not a duration on its own (`256m` is as likely a
+	/// size), so minutes are only matched when seconds follow.
+	pub fn durations() -> Self {
+		Self::new(r"\b(?:\d+h)?(?:\d+m)?\d+(?:\.\d+)?(?:ns|µs|us|ms|s|h)\b", "[DURATION]")
+	}
+
+	/// Replace ISO 8601 dates and timestamps (`2024-05-01`, `2024-05-01T12:00:00.123Z`) with `[DATE]`.
+	pub fn iso_dates() -> Self {
+		Self::new(
+			r"\b\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2}(?:[.,]\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)?\b",
+			"[DATE]",
+		)
+	}
+
+	/// Replace absolute paths under `/tmp`, `/var/folders` or [`std::env::temp_dir`] with `[TMP]`.
+	///
+	/// A path must start the line or follow whitespace, a quote, `(` or `=`,
+	/// so `/tmp` inside a longer path such as `foo/tmp/x` is left alone.
+	/// Punctuation ending a sentence or list (`.`, `,`, `)`) is not taken as part of the path.
+	pub fn temp_paths() -> Self {
+		let temp_dir = std::env::temp_dir();
+		let temp_dir = regex::escape(temp_dir.to_string_lossy().trim_end_matches('/'));
+		Self::new(
+			&format!(r#"(^|[\s'"(=])(?:/tmp|/var/folders|{temp_dir})(?:/[^\s/'"(),;:]*[^\s/'"(),;:.])+"#),
+			"${1}[TMP]",
+		)
+	}
+
+	/// Replace harness session names such as `kitty-test-1234-0` (sockets, logs, window classes) with `kitty-test-[SESSION]`.
+	pub fn session_names() -> Self {
+		Self::new(r"kitty-test-\d+-\d+", "kitty-test-[SESSION]")
+	}
+
+	fn apply_line(&self, line: &str) -> String {
+		let tokens = split_tokens(line);
+		let visible: String = tokens.iter().filter(|t| t.kind == TokenKind::Text).map(|t| t.text.as_str()).collect();
+		let mut matches = Vec::new();
+		for caps in self.pattern.captures_iter(&visible) {
+			let found = caps.get(0).expect("group 0 always matches");
+			let mut replacement = String::new();
+			caps.expand(&self.replacement, &mut replacement);
+			matches.push((found.start(), found.end(), replacement));
+		}
+		if matches.is_empty() {
+			return line.to_string();
+		}
+
+		let mut out = String::with_capacity(line.len());
+		let (mut offset, mut next, mut covered) = (0, 0, 0);
+		for token in &tokens {
+			if token.kind == TokenKind::Escape {
+				out.push_str(&token.raw);
+				continue;
+			}
+			for ch in token.text.chars() {
+				while let Some((start, end, replacement)) = matches.get(next)
+					&& *start == offset
+				{
+					out.push_str(replacement);
+					covered = *end;
+					next += 1;
+				}
+				if offset >= covered {
+					out.push(ch);
+				}
+				offset += ch.len_utf8();
+			}
+		}
+		// Empty matches at the end of the line.
+		for (_, _, replacement) in &matches[next..] {
+			out.push_str(replacement);
+		}
+		out
+	}
+}
+
+impl Normalizer for Replace {
+	fn normalize(&self, capture: &str) -> String {
+		capture.split('\n').map(|line| self.apply_line(line)).collect::<Vec<_>>().join("\n")
+	}
+}
+
+/// An ordered list of normalizers applied one after another.
+#[derive(Clone, Default)]
+pub struct NormalizerChain {
+	steps: Vec<Arc<dyn Normalizer>>,
+}
+
+impl fmt::Debug for NormalizerChain {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("NormalizerChain").field("steps", &self.steps.len()).finish()
+	}
+}
+
+impl NormalizerChain {
+	/// An empty chain, which leaves captures unchanged.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Append a normalizer.
+	pub fn with(mut self, normalizer: impl Normalizer + 'static) -> Self {
+		self.steps.push(Arc::new(normalizer));
+		self
+	}
+
+	/// Append a [`Replace`] of `pattern` with `replacement`.
+	pub fn replace(self, pattern: &str, replacement: &str) -> Self {
+		self.with(Replace::new(pattern, replacement))
+	}
+
+	/// Append every built-in scrubber: session names, temp paths, ISO dates, then durations.
+	pub fn scrub_volatile(self) -> Self {
+		self.with(Replace::session_names())
+			.with(Replace::temp_paths())
+			.with(Replace::iso_dates())
+			.with(Replace::durations())
+	}
+
+	/// Returns true if the chain has no steps.
+	pub fn is_empty(&self) -> bool {
+		self.steps.is_empty()
+	}
+
+	/// Run `capture` through every step in order.
+	pub fn apply(&self, capture: &str) -> String {
+		self.steps.iter().fold(capture.to_string(), |text, step| step.normalize(&text))
+	}
+}
+
+impl Normalizer for NormalizerChain {
+	fn normalize(&self, capture: &str) -> String {
+		self.apply(capture)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn replace_keeps_escapes_and_expands_groups() {
+		let replace = Replace::new(r"(\d+)ms", "<$1>");
+		assert_eq!(replace.normalize("took \x1b[1m12\x1b[0mms\nand 3ms"), "took \x1b[1m<12>\x1b[0m\nand <3>");
+		// Digits inside escape sequences are never matched.
+		assert_eq!(Replace::new(r"\d", "#").normalize("\x1b[38;5;123mx1"), "\x1b[38;5;123mx#");
+	}
+
+	#[test]
+	fn chain_applies_steps_in_order() {
+		let chain = NormalizerChain::new()
+			.replace("a", "b")
+			.replace("b", "c")
+			.with(MaskSet::new().rect(0, 0, 1, 1).fill('*'));
+		assert_eq!(chain.apply("ab"), "*c");
+		assert!(NormalizerChain::new().is_empty());
+	}
+
+	#[test]
+	fn built_in_scrubbers_cover_volatile_content() {
+		let chain = NormalizerChain::new().scrub_volatile();
+		assert_eq!(
+			chain.apply("built in 1.23s (450ms) at 2024-05-01T12:00:00.123Z\nlog /tmp/kitty-test-4242-3.log, socket kitty-test-4242-3.sock"),
+			"built in [DURATION] ([DURATION]) at [DATE]\nlog [TMP], socket kitty-test-[SESSION].sock"
+		);
+		assert_eq!(chain.apply("v1.2.3 took 3 steps on 2024-05-01"), "v1.2.3 took 3 steps on [DATE]");
+		assert_eq!(chain.apply("saved to /tmp/out/a.txt."), "saved to [TMP].");
+		assert_eq!(chain.apply("/tmp/a '/tmp/b' (/tmp/c) --dir=/tmp/d"), "[TMP] '[TMP]' ([TMP]) --dir=[TMP]");
+		assert_eq!(chain.apply("kept foo/tmp/x and ~/tmp/y"), "kept foo/tmp/x and ~/tmp/y");
+		assert_eq!(chain.apply("256m heap after 1m30s and 2h"), "256m heap after [DURATION] and [DURATION]");
+	}
+}
@@ -0,0 +1,145 @@
+//! Short-lived helper commands run in overlay windows.
+//!
+//! Some information (clipboard contents, pixel geometry) is only available
+//! to programs running inside kitty. The harness runs such a program in an
+//! overlay window on top of the harness window and collects its output via
+//! a file in the session artifact directory. Overlays are launched with
+//! `--keep-focus` and do not resize the window underneath, so the
+//! application under test is not disturbed.
+
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use kitty_remote_bindings::model::WindowId;
+
+use crate::KittyHarness;
+use crate::utils::error::HarnessError;
+use crate::utils::launch::shell_single_quote;
+use crate::utils::window::parse_launched_window_id;
+
+/// How long to wait for an overlay command to finish.
+const OVERLAY_TIMEOUT: Duration = Duration::from_secs(5);
+
+static NEXT_TRANSFER: AtomicUsize = AtomicUsize::new(0);
+
+/// Run `command` (a shell snippet) in an overlay over `window_id` and return its stdout.
+pub(crate) fn capture(kitty: &KittyHarness, window_id: WindowId, kind: &str, command: &str) -> Result<String, HarnessError> {
+	let transfer = Transfer::new(kitty, kind)?;
+	transfer.run(kitty, window_id, command, '>')?;
+	std::fs::read_to_string(&transfer.data).map_err(|err| HarnessError::io(format!("read {kind} output {}", transfer.data.display()), err))
+}
+
+/// Run `command` in an overlay over `window_id` with `input` on its stdin.
+pub(crate) fn feed(kitty: &KittyHarness, window_id: WindowId, kind: &str, command: &str, input: &str) -> Result<(), HarnessError> {
+	let transfer = Transfer::new(kitty, kind)?;
+	std::fs::write(&transfer.data, input).map_err(|err| HarnessError::io(format!("write {kind} input {}", transfer.data.display()), err))?;
+	transfer.run(kitty, window_id, command, '<')
+}
+
+/// Files exchanged with one overlay command; they are removed again when the transfer is dropped.
+struct Transfer {
+	/// Redirected stdin or stdout of the command.
+	data: PathBuf,
+	stderr: PathBuf,
+	/// Exit status, written under a temporary name and renamed to `done` once the command has finished.
+	status: PathBuf,
+	done: PathBuf,
+}
+
+impl Transfer {
+	fn new(kitty: &KittyHarness, kind: &str) -> Result<Self, HarnessError> {
+		let dir = kitty.session_info().artifact_dir;
+		std::fs::create_dir_all(&dir).map_err(|err| HarnessError::io(format!("create artifact dir {}", dir.display()), err))?;
+		let idx = NEXT_TRANSFER.fetch_add(1, Ordering::Relaxed);
+		let path = |ext: &str| dir.join(format!("{kind}-{idx}.{ext}"));
+		Ok(Self {
+			data: path("data"),
+			stderr: path("err"),
+			status: path("status"),
+			done: path("done"),
+		})
+	}
+
+	/// Run `command` with its stdin or stdout (`redirect` is `<` or `>`) on the data file and wait for it to exit.
+	///
+	/// A non-zero exit is reported as soon as the command finishes, with its
+	/// stderr; on a timeout the overlay window is closed.
+	fn run(&self, kitty: &KittyHarness, window_id: WindowId, command: &str, redirect: char) -> Result<(), HarnessError> {
+		let quote = |path: &Path| shell_single_quote(&path.to_string_lossy());
+		let script = format!(
+			"( {command} ) {redirect} {data} 2> {stderr}; echo $? > {status} && mv {status} {done}",
+			data = quote(&self.data),
+			stderr = quote(&self.stderr),
+			status = quote(&self.status),
+			done = quote(&self.done)
+		);
+		let overlay = launch(kitty, window_id, &script)?;
+		if let Err(err) = wait_for_file(kitty, &self.done) {
+			let _ = kitty.try_remote(["close-window", "--match", &format!("id:{}", overlay.0)]);
+			return Err(err);
+		}
+
+		let status = std::fs::read_to_string(&self.done).map_err(|err| HarnessError::io(format!("read overlay status {}", self.done.display()), err))?;
+		let code: i32 = status.trim().parse().map_err(|_| HarnessError::Parse {
+			command: command.to_string(),
+			message: format!("unexpected overlay exit status {:?}", status.trim()),
+		})?;
+		if code != 0 {
+			return Err(HarnessError::CommandFailed {
+				command: command.to_string(),
+				status: ExitStatus::from_raw(code << 8),
+				stdout: String::new(),
+				stderr: std::fs::read_to_string(&self.stderr).unwrap_or_default(),
+			});
+		}
+		Ok(())
+	}
+}
+
+impl Drop for Transfer {
+	fn drop(&mut self) {
+		for path in [&self.data, &self.stderr, &self.status, &self.done] {
+			let _ = std::fs::remove_file(path);
+		}
+	}
+}
+
+/// Open an overlay over `window_id` running `script` and return the overlay's window id.
+fn launch(kitty: &KittyHarness, window_id: WindowId, script: &str) -> Result<WindowId, HarnessError> {
+	let output = kitty.try_remote([
+		"launch",
+		"--type=overlay",
+		"--keep-focus",
+		"--match",
+		&format!("id:{}", window_id.0),
+		"bash",
+		"--noprofile",
+		"--norc",
+		"-c",
+		script,
+	])?;
+	parse_launched_window_id(&output.stdout).ok_or_else(|| HarnessError::Parse {
+		command: "kitty @ launch".into(),
+		message: format!("expected a window id, got {:?}", String::from_utf8_lossy(&output.stdout).trim()),
+	})
+}
+
+/// Wait for `path` to appear, polling with the harness [`crate::Timing`] within its [`crate::Deadline`].
+fn wait_for_file(kitty: &KittyHarness, path: &Path) -> Result<(), HarnessError> {
+	let timeout = kitty.clamp_timeout(OVERLAY_TIMEOUT);
+	let start = Instant::now();
+	let mut delays = kitty.timing().poll_delays();
+	while !path.exists() {
+		if start.elapsed() >= timeout {
+			return Err(HarnessError::io(
+				format!("overlay command did not finish within {timeout:?}"),
+				std::io::Error::new(std::io::ErrorKind::TimedOut, path.display().to_string()),
+			));
+		}
+		delays.sleep();
+	}
+	Ok(())
+}
@@ -6,10 +6,10 @@
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-fn shell_single_quote(value: &str) -> String {
-	format!("'{}'", value.replace('\'', "'\"'\"'"))
-}
+use crate::KittyHarness;
+use crate::utils::launch::shell_single_quote;
 
 fn is_valid_env_key(key: &str) -> bool {
 	let mut chars = key.chars();
@@ -146,6 +146,23 @@ pub fn wait_for_file(path: &Path, retries: usize) -> bool {
 	path.exists()
 }
 
+/// Waits up to `timeout` for a file to exist, polling with the [`crate::Timing`] of `kitty`.
+///
+/// `timeout` is clamped to the harness [`crate::Deadline`]. Returns `true`
+/// if the file exists.
+pub fn wait_for_file_with(kitty: &KittyHarness, path: &Path, timeout: Duration) -> bool {
+	let timeout = kitty.clamp_timeout(timeout);
+	let start = Instant::now();
+	let mut delays = kitty.timing().poll_delays();
+	while !path.exists() {
+		if start.elapsed() >= timeout {
+			return false;
+		}
+		delays.sleep();
+	}
+	true
+}
+
 #[cfg(test)]
 mod tests {
 	use std::env::temp_dir;
@@ -0,0 +1,196 @@
+//! Warm kitty instance pool shared across tests.
+//!
+//! Starting kitty dominates the runtime of short tests. A [`KittyPool`]
+//! keeps kitty instances alive between tests and hands out a fresh tab per
+//! test instead. Each instance keeps a hidden keeper window in its first tab
+//! so it survives while no test is using it; the keeper exits together with
+//! the test binary, which lets kitty shut down on its own.
+//!
+//! A pooled test gets a brand-new window, so it starts with an empty screen
+//! and scrollback. When the [`PooledHarness`] is dropped its tab (including
+//! any windows opened with [`KittyHarness::launch_window`]) is closed and
+//! the instance returns to the pool. Pooled harnesses share their
+//! instance's session, so each writes its artifacts (recordings, reports,
+//! screenshots) to a `tab-<window id>` subdirectory of the session
+//! artifact directory.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use kitty_test_harness::{KittyPool, manifest_dir};
+//!
+//! let kitty = KittyPool::shared().acquire(&manifest_dir(), "my-app");
+//! kitty.send_text("q");
+//! ```
+
+use std::ffi::OsString;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use kitty_remote_bindings::model::WindowId;
+
+use crate::utils::error::HarnessError;
+use crate::utils::launch::{LaunchCommand, LaunchOptions, remote_env_args};
+use crate::utils::window::parse_launched_window_id;
+use crate::{CloseScope, KittyHarness};
+
+/// A pool of warm kitty instances.
+pub struct KittyPool {
+	options: LaunchOptions,
+	idle: Mutex<Vec<KittyHarness>>,
+}
+
+impl KittyPool {
+	/// Create an empty pool whose instances are launched with `options`.
+	///
+	/// The tab bar is always hidden so pooled windows get the full OS window.
+	pub fn new(options: LaunchOptions) -> Self {
+		Self {
+			options: options.config("tab_bar_style hidden"),
+			idle: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Return the pool shared by every test in the current test binary.
+	pub fn shared() -> &'static KittyPool {
+		static SHARED: OnceLock<KittyPool> = OnceLock::new();
+		SHARED.get_or_init(|| KittyPool::new(LaunchOptions::default()))
+	}
+
+	/// Number of instances currently waiting to be reused.
+	pub fn idle_count(&self) -> usize {
+		self.idle.lock().unwrap_or_else(|err| err.into_inner()).len()
+	}
+
+	/// Open a fresh window running `command` in a pooled instance.
+	///
+	/// A new instance is launched when none is idle, so concurrent tests each
+	/// get their own kitty.
+	pub fn acquire(&self, working_dir: &Path, command: impl Into<LaunchCommand>) -> PooledHarness<'_> {
+		self.try_acquire(working_dir, command).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyPool::acquire`], but returns an error instead of panicking.
+	pub fn try_acquire(&self, working_dir: &Path, command: impl Into<LaunchCommand>) -> Result<PooledHarness<'_>, HarnessError> {
+		let idle = self.idle.lock().unwrap_or_else(|err| err.into_inner()).pop();
+		let keeper = match idle {
+			Some(keeper) => keeper,
+			None => KittyHarness::try_launch_with(working_dir, keeper_command(), &self.options)?,
+		};
+
+		let window_id = match open_tab(&keeper, tab_launch_args(working_dir, &command.into(), &self.options)) {
+			Ok(window_id) => window_id,
+			Err(err) => {
+				// The instance itself is still usable; only this launch failed.
+				self.release(keeper);
+				return Err(err);
+			}
+		};
+
+		let harness = KittyHarness::from_parts(keeper.session_name().to_string(), keeper.socket_addr().to_string(), window_id)
+			.with_close_scope(CloseScope::Tab)
+			.with_clipboard(self.options.allows_clipboard())
+			.with_artifact_subdir(format!("tab-{}", window_id.0))
+			.with_env(self.options.env_vars().to_vec());
+		if let Some(modes) = self.options.key_encode_modes() {
+			harness.set_key_modes(modes);
+		}
+		Ok(PooledHarness {
+			pool: self,
+			keeper: Some(keeper),
+			harness: Some(harness),
+		})
+	}
+
+	fn release(&self, keeper: KittyHarness) {
+		self.idle.lock().unwrap_or_else(|err| err.into_inner()).push(keeper);
+	}
+}
+
+/// `kitty @ launch` arguments for a test tab running `command` with the environment from `options`.
+fn tab_launch_args(working_dir: &Path, command: &LaunchCommand, options: &LaunchOptions) -> Vec<OsString> {
+	let mut args: Vec<OsString> = vec!["launch".into(), "--type=tab".into(), "--cwd".into(), working_dir.into()];
+	args.extend(remote_env_args(options.env_vars()));
+	args.extend(["bash", "--noprofile", "--norc", "-lc"].map(OsString::from));
+	args.extend(command.bash_args());
+	args
+}
+
+/// Run `kitty @ launch` with `args` in the instance behind `keeper` and return the new window id.
+fn open_tab(keeper: &KittyHarness, args: Vec<OsString>) -> Result<WindowId, HarnessError> {
+	let output = keeper.try_remote(args)?;
+	parse_launched_window_id(&output.stdout).ok_or_else(|| HarnessError::Parse {
+		command: "kitty @ launch".into(),
+		message: format!("expected a window id, got {:?}", String::from_utf8_lossy(&output.stdout).trim()),
+	})
+}
+
+/// Keeper window command: stay alive until the test binary exits.
+fn keeper_command() -> LaunchCommand {
+	LaunchCommand::shell(format!("while kill -0 {} 2>/dev/null; do sleep 1; done", std::process::id()))
+}
+
+/// A harness borrowed from a [`KittyPool`]; dereferences to [`KittyHarness`].
+pub struct PooledHarness<'a> {
+	pool: &'a KittyPool,
+	keeper: Option<KittyHarness>,
+	harness: Option<KittyHarness>,
+}
+
+impl Deref for PooledHarness<'_> {
+	type Target = KittyHarness;
+
+	fn deref(&self) -> &KittyHarness {
+		self.harness.as_ref().expect("pooled harness is present until drop")
+	}
+}
+
+impl Drop for PooledHarness<'_> {
+	fn drop(&mut self) {
+		let kept = self.harness.as_ref().is_some_and(KittyHarness::is_kept_open);
+		drop(self.harness.take());
+		let Some(keeper) = self.keeper.take() else {
+			return;
+		};
+		if kept {
+			// The failed tab stays open for inspection; do not hand the instance to another test.
+			keeper.keep_open();
+		} else {
+			self.pool.release(keeper);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tab_launch_forwards_env_vars() {
+		let options = LaunchOptions::new().env("MY_APP_CONFIG", "/tmp/it's here").env("A", "1");
+		let args = tab_launch_args(Path::new("/work"), &LaunchCommand::from(["my-app"]), &options);
+		assert_eq!(
+			args,
+			[
+				"launch",
+				"--type=tab",
+				"--cwd",
+				"/work",
+				"--env",
+				"MY_APP_CONFIG=/tmp/it's here",
+				"--env",
+				"A=1",
+				"bash",
+				"--noprofile",
+				"--norc",
+				"-lc",
+				"exec \"$@\"",
+				"bash",
+				"my-app"
+			]
+			.map(OsString::from)
+			.to_vec()
+		);
+	}
+}
@@ -0,0 +1,162 @@
+//! Event-driven readiness signalling.
+//!
+//! Polling `get-text` works for anything that eventually shows up on screen,
+//! but every poll shells out to `kitty @` and the 50ms interval adds latency.
+//! A [`ReadyChannel`] is a named pipe that the shell or the application under
+//! test writes marker lines to; waits block on the pipe and wake as soon as
+//! a line arrives instead of repeatedly capturing the screen.
+//!
+//! Pass the pipe to the application with [`READY_CHANNEL_ENV`] and
+//! [`LaunchOptions::env`](crate::LaunchOptions::env), or let
+//! [`wait_for_ready_event`] inject a `printf` into the shell.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::KittyHarness;
+use crate::utils::error::HarnessError;
+use crate::utils::launch::shell_single_quote;
+
+/// Environment variable conventionally used to hand the channel path to the application under test.
+pub const READY_CHANNEL_ENV: &str = "KITTY_HARNESS_READY";
+
+static NEXT_CHANNEL: AtomicUsize = AtomicUsize::new(0);
+
+/// A named pipe that delivers marker lines written by the terminal side.
+///
+/// The pipe is removed when the channel is dropped.
+#[derive(Debug)]
+pub struct ReadyChannel {
+	path: PathBuf,
+	close_line: String,
+	lines: Mutex<Receiver<String>>,
+}
+
+impl ReadyChannel {
+	/// Create a channel backed by a fresh FIFO in the system temp directory.
+	pub fn create() -> Result<Self, HarnessError> {
+		let idx = NEXT_CHANNEL.fetch_add(1, Ordering::Relaxed);
+		let path = std::env::temp_dir().join(format!("kitty-harness-ready-{}-{idx}.fifo", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		let output = Command::new("mkfifo").arg(&path).output().map_err(|err| HarnessError::spawn("mkfifo", err))?;
+		if !output.status.success() {
+			return Err(HarnessError::failed("mkfifo", &output));
+		}
+
+		// Opening read+write never blocks and keeps a writer attached, so the
+		// reader does not see EOF between writers.
+		let fifo = std::fs::OpenOptions::new()
+			.read(true)
+			.write(true)
+			.open(&path)
+			.map_err(|err| HarnessError::io(format!("open ready channel {}", path.display()), err))?;
+		let close_line = format!("__KITTY_READY_CHANNEL_CLOSE_{}_{idx}__", std::process::id());
+		let (tx, rx) = mpsc::channel();
+		let close = close_line.clone();
+		std::thread::spawn(move || {
+			for line in BufReader::new(fifo).lines() {
+				let Ok(line) = line else { break };
+				if line == close || tx.send(line).is_err() {
+					break;
+				}
+			}
+		});
+
+		Ok(Self {
+			path,
+			close_line,
+			lines: Mutex::new(rx),
+		})
+	}
+
+	/// Filesystem path of the FIFO; write newline-terminated markers to it.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Shell snippet that writes `marker` to the channel.
+	pub fn shell_command(&self, marker: &str) -> String {
+		format!(
+			"printf '%s\\n' {} > {}",
+			shell_single_quote(marker),
+			shell_single_quote(&self.path.to_string_lossy())
+		)
+	}
+
+	/// Block until the next line arrives on the channel.
+	pub fn recv(&self, timeout: Duration) -> Result<String, HarnessError> {
+		let lines = self.lines.lock().unwrap_or_else(|err| err.into_inner());
+		lines.recv_timeout(timeout).map_err(|err| match err {
+			RecvTimeoutError::Timeout => timed_out(&self.path, timeout, "a line"),
+			RecvTimeoutError::Disconnected => HarnessError::io(
+				format!("ready channel {} closed", self.path.display()),
+				std::io::Error::from(std::io::ErrorKind::BrokenPipe),
+			),
+		})
+	}
+
+	/// Block until a line equal to `marker` arrives, discarding other lines.
+	pub fn wait_for(&self, marker: &str, timeout: Duration) -> Result<(), HarnessError> {
+		let start = Instant::now();
+		loop {
+			let remaining = timeout.saturating_sub(start.elapsed());
+			match self.recv(remaining) {
+				Ok(line) if line == marker => return Ok(()),
+				Ok(_) => {}
+				Err(_) if start.elapsed() >= timeout => return Err(timed_out(&self.path, timeout, marker)),
+				Err(err) => return Err(err),
+			}
+		}
+	}
+}
+
+impl Drop for ReadyChannel {
+	fn drop(&mut self) {
+		// Wake the reader thread so it exits, then remove the FIFO.
+		if let Ok(mut fifo) = std::fs::OpenOptions::new().write(true).open(&self.path) {
+			let _ = writeln!(fifo, "{}", self.close_line);
+		}
+		let _ = std::fs::remove_file(&self.path);
+	}
+}
+
+fn timed_out(path: &Path, timeout: Duration, expected: &str) -> HarnessError {
+	HarnessError::io(
+		format!("ready channel {} did not receive {expected:?} within {timeout:?}", path.display()),
+		std::io::Error::from(std::io::ErrorKind::TimedOut),
+	)
+}
+
+/// Event-driven counterpart of [`wait_for_ready_marker`](crate::wait_for_ready_marker).
+///
+/// Types a command into the shell that writes a unique marker to `channel`
+/// and blocks until it arrives, without capturing the screen.
+pub fn wait_for_ready_event(kitty: &KittyHarness, channel: &ReadyChannel, timeout: Duration) -> Result<(), HarnessError> {
+	let idx = NEXT_CHANNEL.fetch_add(1, Ordering::Relaxed);
+	let marker = format!("__KITTY_READY_EVENT_{idx}__");
+	kitty.try_send_text(&format!("{}\n", channel.shell_command(&marker)))?;
+	channel.wait_for(&marker, kitty.clamp_timeout(timeout))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lines_written_to_the_fifo_are_received() {
+		let channel = ReadyChannel::create().expect("create channel");
+		let mut fifo = std::fs::OpenOptions::new().write(true).open(channel.path()).expect("open fifo");
+		writeln!(fifo, "noise\nready").expect("write");
+		channel.wait_for("ready", Duration::from_secs(2)).expect("marker arrives");
+		assert!(channel.recv(Duration::from_millis(20)).is_err());
+
+		let path = channel.path().to_path_buf();
+		drop(channel);
+		assert!(!path.exists());
+	}
+}
@@ -0,0 +1,323 @@
+//! Rasterizing styled captures into images.
+//!
+//! [`render_grid`] draws a [`ScreenGrid`] with a built-in 5x8 bitmap font,
+//! resolving cell colors against a [`Theme`]. The output is an
+//! approximation of what kitty draws (no real fonts, ligatures or images),
+//! meant for visual artifacts such as [`crate::utils::animation`] rather
+//! than pixel-exact output. Printable ASCII and the basic box-drawing
+//! lines have glyphs; other characters are drawn as a hollow box.
+//!
+//! [`compare_images`] diffs two images with a perceptual per-pixel
+//! threshold, for real screenshots as well as renders.
+
+use crate::utils::screen::{Cell, ScreenGrid};
+use crate::utils::theme::{Palette, Rgb, Theme};
+
+/// Width of one cell in pixels.
+pub const CELL_WIDTH: usize = 6;
+/// Height of one cell in pixels.
+pub const CELL_HEIGHT: usize = 10;
+
+/// An RGB image stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+	/// Width in pixels.
+	pub width: usize,
+	/// Height in pixels.
+	pub height: usize,
+	/// `width * height` pixels, top row first.
+	pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+	/// An image filled with `color`.
+	pub fn new(width: usize, height: usize, color: Rgb) -> Self {
+		Self {
+			width,
+			height,
+			pixels: vec![color; width * height],
+		}
+	}
+
+	/// The pixel at (`x`, `y`), or `None` outside the image.
+	pub fn get(&self, x: usize, y: usize) -> Option<Rgb> {
+		(x < self.width && y < self.height).then(|| self.pixels[y * self.width + x])
+	}
+
+	/// Pixels as packed RGB bytes.
+	pub fn to_rgb_bytes(&self) -> Vec<u8> {
+		self.pixels.iter().flat_map(|&(r, g, b)| [r, g, b]).collect()
+	}
+
+	fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+		for row in y..(y + height).min(self.height) {
+			for col in x..(x + width).min(self.width) {
+				self.pixels[row * self.width + col] = color;
+			}
+		}
+	}
+}
+
+/// Result of comparing two images with [`compare_images`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageDiff {
+	/// Pixels whose perceptual difference exceeds the threshold.
+	pub differing_pixels: usize,
+	/// Pixels compared.
+	pub total_pixels: usize,
+	/// Largest perceptual difference found, from 0.0 (identical) to 1.0.
+	pub max_delta: f64,
+	/// Whether the images have different dimensions (then every pixel counts as differing).
+	pub size_mismatch: bool,
+	/// The actual image with differing pixels painted red.
+	pub highlight: Image,
+}
+
+impl ImageDiff {
+	/// Fraction of pixels that differ.
+	pub fn ratio(&self) -> f64 {
+		if self.total_pixels == 0 {
+			return 0.0;
+		}
+		self.differing_pixels as f64 / self.total_pixels as f64
+	}
+}
+
+/// Compare `actual` against `expected` pixel by pixel.
+///
+/// Differences are measured in YIQ space, which weights brightness over
+/// hue like the eye does (the approach used by pixelmatch); a pixel counts
+/// as different when its delta exceeds `threshold` (0.0-1.0, `0.1` ignores
+/// antialiasing noise).
+pub fn compare_images(actual: &Image, expected: &Image, threshold: f64) -> ImageDiff {
+	if (actual.width, actual.height) != (expected.width, expected.height) {
+		let total_pixels = actual.pixels.len().max(expected.pixels.len());
+		return ImageDiff {
+			differing_pixels: total_pixels,
+			total_pixels,
+			max_delta: 1.0,
+			size_mismatch: true,
+			highlight: actual.clone(),
+		};
+	}
+	let mut highlight = actual.clone();
+	let mut differing_pixels = 0;
+	let mut max_delta: f64 = 0.0;
+	for (idx, (a, b)) in actual.pixels.iter().zip(&expected.pixels).enumerate() {
+		let delta = yiq_delta(*a, *b);
+		max_delta = max_delta.max(delta);
+		if delta > threshold {
+			differing_pixels += 1;
+			highlight.pixels[idx] = (255, 0, 0);
+		}
+	}
+	ImageDiff {
+		differing_pixels,
+		total_pixels: actual.pixels.len(),
+		max_delta,
+		size_mismatch: false,
+		highlight,
+	}
+}
+
+/// Normalized YIQ distance between two colors (0.0-1.0).
+fn yiq_delta(a: Rgb, b: Rgb) -> f64 {
+	let yiq = |(r, g, b): Rgb| {
+		let (r, g, b) = (f64::from(r), f64::from(g), f64::from(b));
+		(
+			0.298_895_31 * r + 0.586_622_47 * g + 0.114_482_23 * b,
+			0.595_977_99 * r - 0.274_176_10 * g - 0.321_801_89 * b,
+			0.211_470_17 * r - 0.522_617_55 * g + 0.311_147_38 * b,
+		)
+	};
+	let (y1, i1, q1) = yiq(a);
+	let (y2, i2, q2) = yiq(b);
+	let delta = 0.5053 * (y1 - y2).powi(2) + 0.299 * (i1 - i2).powi(2) + 0.1957 * (q1 - q2).powi(2);
+	// Scale so black against white is 1.0; saturated hue pairs can exceed it and are clamped.
+	let black_white = 0.5053 * 255.0_f64.powi(2);
+	(delta / black_white).sqrt().min(1.0)
+}
+
+/// Render `grid` as a `cols` x `rows` cell image using `theme` for default and palette colors.
+///
+/// Cells beyond a captured row's length are drawn as background.
+pub fn render_grid(grid: &ScreenGrid, cols: usize, rows: usize, theme: &Theme) -> Image {
+	let palette = theme.palette();
+	let mut image = Image::new(cols * CELL_WIDTH, rows * CELL_HEIGHT, theme.background);
+	for (row, cells) in grid.rows().iter().enumerate().take(rows) {
+		for (col, cell) in cells.iter().enumerate().take(cols) {
+			draw_cell(&mut image, col * CELL_WIDTH, row * CELL_HEIGHT, cell, &palette);
+		}
+	}
+	image
+}
+
+fn draw_cell(image: &mut Image, x: usize, y: usize, cell: &Cell, palette: &Palette) {
+	let (fg, bg) = cell.effective_colors(palette);
+	image.fill(x, y, CELL_WIDTH, CELL_HEIGHT, bg);
+
+	match cell.ch {
+		' ' => {}
+		'─' | '━' | '═' => image.fill(x, y + CELL_HEIGHT / 2, CELL_WIDTH, 1, fg),
+		'│' | '┃' | '║' => image.fill(x + CELL_WIDTH / 2, y, 1, CELL_HEIGHT, fg),
+		'┼' | '╋' => {
+			image.fill(x, y + CELL_HEIGHT / 2, CELL_WIDTH, 1, fg);
+			image.fill(x + CELL_WIDTH / 2, y, 1, CELL_HEIGHT, fg);
+		}
+		'█' => image.fill(x, y, CELL_WIDTH, CELL_HEIGHT, fg),
+		ch => {
+			let columns = glyph(ch).unwrap_or([0x7f, 0x41, 0x41, 0x41, 0x7f]);
+			for (dx, bits) in columns.iter().enumerate() {
+				for dy in 0..8 {
+					if bits & (1 << dy) != 0 {
+						image.fill(x + dx, y + 1 + dy, 1 + usize::from(cell.attrs.bold), 1, fg);
+					}
+				}
+			}
+		}
+	}
+	if cell.attrs.underline {
+		image.fill(x, y + CELL_HEIGHT - 1, CELL_WIDTH, 1, palette.resolve(cell.underline_color, fg));
+	}
+	if cell.attrs.strikethrough {
+		image.fill(x, y + CELL_HEIGHT / 2, CELL_WIDTH, 1, fg);
+	}
+}
+
+/// Column bitmaps (least significant bit at the top) of a printable ASCII character.
+fn glyph(ch: char) -> Option<[u8; 5]> {
+	let idx = (ch as u32).checked_sub(0x20)?;
+	FONT.get(idx as usize).copied()
+}
+
+/// 5x8 bitmaps for `' '..='~'`.
+const FONT: [[u8; 5]; 95] = [
+	[0x00, 0x00, 0x00, 0x00, 0x00],
+	[0x00, 0x00, 0x5f, 0x00, 0x00],
+	[0x00, 0x07, 0x00, 0x07, 0x00],
+	[0x14, 0x7f, 0x14, 0x7f, 0x14],
+	[0x24, 0x2a, 0x7f, 0x2a, 0x12],
+	[0x23, 0x13, 0x08, 0x64, 0x62],
+	[0x36, 0x49, 0x56, 0x20, 0x50],
+	[0x00, 0x08, 0x07, 0x03, 0x00],
+	[0x00, 0x1c, 0x22, 0x41, 0x00],
+	[0x00, 0x41, 0x22, 0x1c, 0x00],
+	[0x2a, 0x1c, 0x7f, 0x1c, 0x2a],
+	[0x08, 0x08, 0x3e, 0x08, 0x08],
+	[0x00, 0x80, 0x70, 0x30, 0x00],
+	[0x08, 0x08, 0x08, 0x08, 0x08],
+	[0x00, 0x00, 0x60, 0x60, 0x00],
+	[0x20, 0x10, 0x08, 0x04, 0x02],
+	[0x3e, 0x51, 0x49, 0x45, 0x3e],
+	[0x00, 0x42, 0x7f, 0x40, 0x00],
+	[0x72, 0x49, 0x49, 0x49, 0x46],
+	[0x21, 0x41, 0x49, 0x4d, 0x33],
+	[0x18, 0x14, 0x12, 0x7f, 0x10],
+	[0x27, 0x45, 0x45, 0x45, 0x39],
+	[0x3c, 0x4a, 0x49, 0x49, 0x31],
+	[0x41, 0x21, 0x11, 0x09, 0x07],
+	[0x36, 0x49, 0x49, 0x49, 0x36],
+	[0x46, 0x49, 0x49, 0x29, 0x1e],
+	[0x00, 0x00, 0x14, 0x00, 0x00],
+	[0x00, 0x40, 0x34, 0x00, 0x00],
+	[0x00, 0x08, 0x14, 0x22, 0x41],
+	[0x14, 0x14, 0x14, 0x14, 0x14],
+	[0x00, 0x41, 0x22, 0x14, 0x08],
+	[0x02, 0x01, 0x59, 0x09, 0x06],
+	[0x3e, 0x41, 0x5d, 0x59, 0x4e],
+	[0x7c, 0x12, 0x11, 0x12, 0x7c],
+	[0x7f, 0x49, 0x49, 0x49, 0x36],
+	[0x3e, 0x41, 0x41, 0x41, 0x22],
+	[0x7f, 0x41, 0x41, 0x41, 0x3e],
+	[0x7f, 0x49, 0x49, 0x49, 0x41],
+	[0x7f, 0x09, 0x09, 0x09, 0x01],
+	[0x3e, 0x41, 0x41, 0x51, 0x73],
+	[0x7f, 0x08, 0x08, 0x08, 0x7f],
+	[0x00, 0x41, 0x7f, 0x41, 0x00],
+	[0x20, 0x40, 0x41, 0x3f, 0x01],
+	[0x7f, 0x08, 0x14, 0x22, 0x41],
+	[0x7f, 0x40, 0x40, 0x40, 0x40],
+	[0x7f, 0x02, 0x1c, 0x02, 0x7f],
+	[0x7f, 0x04, 0x08, 0x10, 0x7f],
+	[0x3e, 0x41, 0x41, 0x41, 0x3e],
+	[0x7f, 0x09, 0x09, 0x09, 0x06],
+	[0x3e, 0x41, 0x51, 0x21, 0x5e],
+	[0x7f, 0x09, 0x19, 0x29, 0x46],
+	[0x26, 0x49, 0x49, 0x49, 0x32],
+	[0x03, 0x01, 0x7f, 0x01, 0x03],
+	[0x3f, 0x40, 0x40, 0x40, 0x3f],
+	[0x1f, 0x20, 0x40, 0x20, 0x1f],
+	[0x3f, 0x40, 0x38, 0x40, 0x3f],
+	[0x63, 0x14, 0x08, 0x14, 0x63],
+	[0x03, 0x04, 0x78, 0x04, 0x03],
+	[0x61, 0x59, 0x49, 0x4d, 0x43],
+	[0x00, 0x7f, 0x41, 0x41, 0x41],
+	[0x02, 0x04, 0x08, 0x10, 0x20],
+	[0x00, 0x41, 0x41, 0x41, 0x7f],
+	[0x04, 0x02, 0x01, 0x02, 0x04],
+	[0x40, 0x40, 0x40, 0x40, 0x40],
+	[0x00, 0x03, 0x07, 0x08, 0x00],
+	[0x20, 0x54, 0x54, 0x78, 0x40],
+	[0x7f, 0x28, 0x44, 0x44, 0x38],
+	[0x38, 0x44, 0x44, 0x44, 0x28],
+	[0x38, 0x44, 0x44, 0x28, 0x7f],
+	[0x38, 0x54, 0x54, 0x54, 0x18],
+	[0x00, 0x08, 0x7e, 0x09, 0x02],
+	[0x18, 0xa4, 0xa4, 0x9c, 0x78],
+	[0x7f, 0x08, 0x04, 0x04, 0x78],
+	[0x00, 0x44, 0x7d, 0x40, 0x00],
+	[0x20, 0x40, 0x40, 0x3d, 0x00],
+	[0x7f, 0x10, 0x28, 0x44, 0x00],
+	[0x00, 0x41, 0x7f, 0x40, 0x00],
+	[0x7c, 0x04, 0x78, 0x04, 0x78],
+	[0x7c, 0x08, 0x04, 0x04, 0x78],
+	[0x38, 0x44, 0x44, 0x44, 0x38],
+	[0xfc, 0x18, 0x24, 0x24, 0x18],
+	[0x18, 0x24, 0x24, 0x18, 0xfc],
+	[0x7c, 0x08, 0x04, 0x04, 0x08],
+	[0x48, 0x54, 0x54, 0x54, 0x24],
+	[0x04, 0x04, 0x3f, 0x44, 0x24],
+	[0x3c, 0x40, 0x40, 0x20, 0x7c],
+	[0x1c, 0x20, 0x40, 0x20, 0x1c],
+	[0x3c, 0x40, 0x30, 0x40, 0x3c],
+	[0x44, 0x28, 0x10, 0x28, 0x44],
+	[0x4c, 0x90, 0x90, 0x90, 0x7c],
+	[0x44, 0x64, 0x54, 0x4c, 0x44],
+	[0x00, 0x08, 0x36, 0x41, 0x00],
+	[0x00, 0x00, 0x77, 0x00, 0x00],
+	[0x00, 0x41, 0x36, 0x08, 0x00],
+	[0x02, 0x01, 0x02, 0x04, 0x02],
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn compare_images_counts_pixels_over_threshold() {
+		let expected = Image::new(4, 1, (0, 0, 0));
+		let mut actual = expected.clone();
+		actual.pixels[1] = (255, 255, 255);
+		actual.pixels[2] = (3, 3, 3);
+		let diff = compare_images(&actual, &expected, 0.1);
+		assert_eq!(diff.differing_pixels, 1);
+		assert!((diff.max_delta - 1.0).abs() < 1e-3);
+		assert_eq!(diff.highlight.get(1, 0), Some((255, 0, 0)));
+		assert!(compare_images(&Image::new(2, 2, (0, 0, 0)), &expected, 0.1).size_mismatch);
+	}
+
+	#[test]
+	fn renders_colors_and_glyph_pixels() {
+		let theme = Theme::xterm();
+		let grid = ScreenGrid::parse("\x1b[44mI\x1b[0m\x1b[38;5;196m█");
+		let image = render_grid(&grid, 3, 1, &theme);
+		assert_eq!((image.width, image.height), (3 * CELL_WIDTH, CELL_HEIGHT));
+
+		// Blue background in the corner of the first cell, foreground on the stem of the `I`.
+		assert_eq!(image.get(0, 0), Some(theme.palette[4]));
+		assert_eq!(image.get(2, 4), Some(theme.foreground));
+		// 256-color cube red fills the second cell; the third cell is plain background.
+		assert_eq!(image.get(CELL_WIDTH + 1, 1), Some((255, 0, 0)));
+		assert_eq!(image.get(2 * CELL_WIDTH + 1, 1), Some(theme.background));
+	}
+}
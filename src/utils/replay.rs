@@ -9,8 +9,10 @@
 //! # comments
 //! j                      # key event
 //! C-x                    # key with modifier
+//! hash                   # the `#` key, since `#` starts a comment
 //!                        # blank line = batch boundary
 //! mouse:press left 10,5
+//! mouse:scroll right 10,5
 //! paste:aGVsbG8=
 //! resize:120x50
 //! focus:in
@@ -19,11 +21,13 @@
 use std::time::Duration;
 
 use crate::KittyHarness;
+use crate::utils::keys::parse_key;
 use crate::utils::mouse::{MouseButton, ScrollDirection, encode_mouse_drag, encode_mouse_move, encode_mouse_press, encode_mouse_release, encode_mouse_scroll};
 use crate::utils::resize::resize_window;
 
 /// A parsed replay event.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReplayEvent {
 	/// A batch of key names to be sent as a single `send_text` call.
 	KeyBatch(Vec<String>),
@@ -259,133 +263,141 @@ impl ReplayTiming {
 /// batch is sent as a single `send_text` call. With a non-zero `key_delay`,
 /// keys are sent individually with a pause between each one.
 pub fn replay(kitty: &KittyHarness, events: &[ReplayEvent], timing: ReplayTiming) {
-	use termwiz::escape::csi::KittyKeyboardFlags;
-	use termwiz::input::{KeyCodeEncodeModes, KeyboardEncoding};
+	for event in events {
+		send_event(kitty, event, &timing);
+	}
+}
 
-	let modes = KeyCodeEncodeModes {
-		encoding: KeyboardEncoding::Kitty(KittyKeyboardFlags::empty()),
-		application_cursor_keys: false,
-		newline_mode: false,
-		modify_other_keys: None,
-	};
+/// Sends a single replay event, honouring `timing` for key batches.
+pub(crate) fn send_event(kitty: &KittyHarness, event: &ReplayEvent, timing: &ReplayTiming) {
+	let modes = kitty.key_modes();
+
+	match event {
+		ReplayEvent::KeyBatch(keys) => {
+			if timing.key_delay.is_zero() {
+				// Send entire batch as one string.
+				let mut encoded = String::new();
+				for key_name in keys {
+					if let Some(e) = encode_key_name(key_name, modes) {
+						encoded.push_str(&e);
+					}
+				}
+				if !encoded.is_empty() {
+					kitty.send_text(&encoded);
+				}
+			} else {
+				// Send each key individually with a delay.
+				for key_name in keys {
+					if let Some(e) = encode_key_name(key_name, modes) {
+						kitty.send_text(&e);
+						std::thread::sleep(timing.key_delay);
+					}
+				}
+			}
+			std::thread::sleep(timing.batch_pause);
+		}
+		ReplayEvent::MousePress { button, col, row } => {
+			kitty.send_text(&encode_mouse_press(*button, *col, *row));
+		}
+		ReplayEvent::MouseRelease { col, row } => {
+			// Use Left button for release encoding (button doesn't matter for SGR release trailer)
+			kitty.send_text(&encode_mouse_release(MouseButton::Left, *col, *row));
+		}
+		ReplayEvent::MouseDrag { button, col, row } => {
+			kitty.send_text(&encode_mouse_drag(*button, *col, *row));
+		}
+		ReplayEvent::MouseScroll { direction, col, row } => {
+			kitty.send_text(&encode_mouse_scroll(*direction, *col, *row));
+		}
+		ReplayEvent::MouseMove { col, row } => {
+			kitty.send_text(&encode_mouse_move(*col, *row));
+		}
+		ReplayEvent::Paste(content) => {
+			// Bracketed paste: ESC[200~ ... ESC[201~
+			let paste = format!("\x1b[200~{content}\x1b[201~");
+			kitty.send_text(&paste);
+		}
+		ReplayEvent::Resize { cols, rows } => {
+			resize_window(kitty, *cols, *rows);
+		}
+		ReplayEvent::FocusIn => {
+			// Focus in: ESC[I
+			kitty.send_text("\x1b[I");
+		}
+		ReplayEvent::FocusOut => {
+			// Focus out: ESC[O
+			kitty.send_text("\x1b[O");
+		}
+	}
+}
+
+/// Formats events back into the recording text format.
+///
+/// This is the inverse of [`parse_recording`]: key batches are written one
+/// key per line and separated from each other by blank lines.
+pub fn format_recording(events: &[ReplayEvent]) -> String {
+	use base64::Engine;
+
+	let mut out = String::new();
+	let mut previous_was_batch = false;
 
 	for event in events {
+		let is_batch = matches!(event, ReplayEvent::KeyBatch(_));
+		if is_batch && previous_was_batch {
+			out.push('\n');
+		}
+		previous_was_batch = is_batch;
+
 		match event {
 			ReplayEvent::KeyBatch(keys) => {
-				if timing.key_delay.is_zero() {
-					// Send entire batch as one string.
-					let mut encoded = String::new();
-					for key_name in keys {
-						if let Some(e) = encode_key_name(key_name, modes) {
-							encoded.push_str(&e);
-						}
-					}
-					if !encoded.is_empty() {
-						kitty.send_text(&encoded);
-					}
-				} else {
-					// Send each key individually with a delay.
-					for key_name in keys {
-						if let Some(e) = encode_key_name(key_name, modes) {
-							kitty.send_text(&e);
-							std::thread::sleep(timing.key_delay);
-						}
-					}
+				for key in keys {
+					out.push_str(key);
+					out.push('\n');
 				}
-				std::thread::sleep(timing.batch_pause);
-			}
-			ReplayEvent::MousePress { button, col, row } => {
-				kitty.send_text(&encode_mouse_press(*button, *col, *row));
-			}
-			ReplayEvent::MouseRelease { col, row } => {
-				// Use Left button for release encoding (button doesn't matter for SGR release trailer)
-				kitty.send_text(&encode_mouse_release(MouseButton::Left, *col, *row));
-			}
-			ReplayEvent::MouseDrag { button, col, row } => {
-				kitty.send_text(&encode_mouse_drag(*button, *col, *row));
-			}
-			ReplayEvent::MouseScroll { direction, col, row } => {
-				kitty.send_text(&encode_mouse_scroll(*direction, *col, *row));
-			}
-			ReplayEvent::MouseMove { col, row } => {
-				kitty.send_text(&encode_mouse_move(*col, *row));
+				continue;
 			}
+			ReplayEvent::MousePress { button, col, row } => out.push_str(&format!("mouse:press {} {col},{row}", button_name(*button))),
+			ReplayEvent::MouseRelease { col, row } => out.push_str(&format!("mouse:release {col},{row}")),
+			ReplayEvent::MouseDrag { button, col, row } => out.push_str(&format!("mouse:drag {} {col},{row}", button_name(*button))),
+			ReplayEvent::MouseScroll { direction, col, row } => out.push_str(&format!("mouse:scroll {} {col},{row}", direction_name(*direction))),
+			ReplayEvent::MouseMove { col, row } => out.push_str(&format!("mouse:move {col},{row}")),
 			ReplayEvent::Paste(content) => {
-				// Bracketed paste: ESC[200~ ... ESC[201~
-				let paste = format!("\x1b[200~{content}\x1b[201~");
-				kitty.send_text(&paste);
-			}
-			ReplayEvent::Resize { cols, rows } => {
-				resize_window(kitty, *cols, *rows);
-			}
-			ReplayEvent::FocusIn => {
-				// Focus in: ESC[I
-				kitty.send_text("\x1b[I");
-			}
-			ReplayEvent::FocusOut => {
-				// Focus out: ESC[O
-				kitty.send_text("\x1b[O");
+				out.push_str("paste:");
+				out.push_str(&base64::engine::general_purpose::STANDARD.encode(content));
 			}
+			ReplayEvent::Resize { cols, rows } => out.push_str(&format!("resize:{cols}x{rows}")),
+			ReplayEvent::FocusIn => out.push_str("focus:in"),
+			ReplayEvent::FocusOut => out.push_str("focus:out"),
 		}
+		out.push('\n');
 	}
+
+	out
 }
 
-/// Encodes a key name (from the recording format) to a terminal escape sequence.
-///
-/// Parses the `C-A-S-<code>` notation and encodes via termwiz.
-fn encode_key_name(name: &str, modes: termwiz::input::KeyCodeEncodeModes) -> Option<String> {
-	use termwiz::input::{KeyCode, Modifiers};
-
-	let mut remaining = name;
-	let mut mods = Modifiers::NONE;
-
-	// Parse modifier prefixes
-	loop {
-		if let Some(rest) = remaining.strip_prefix("C-") {
-			mods |= Modifiers::CTRL;
-			remaining = rest;
-		} else if let Some(rest) = remaining.strip_prefix("A-") {
-			mods |= Modifiers::ALT;
-			remaining = rest;
-		} else if let Some(rest) = remaining.strip_prefix("S-") {
-			mods |= Modifiers::SHIFT;
-			remaining = rest;
-		} else {
-			break;
-		}
+fn button_name(button: MouseButton) -> &'static str {
+	match button {
+		MouseButton::Left => "left",
+		MouseButton::Middle => "middle",
+		MouseButton::Right => "right",
 	}
+}
 
-	let keycode = match remaining {
-		"esc" => KeyCode::Escape,
-		"enter" | "ret" => KeyCode::Enter,
-		"tab" => KeyCode::Tab,
-		"backtab" => KeyCode::Tab, // backtab is shift+tab
-		"backspace" | "bs" => KeyCode::Backspace,
-		"del" | "delete" => KeyCode::Delete,
-		"insert" | "ins" => KeyCode::Insert,
-		"home" => KeyCode::Home,
-		"end" => KeyCode::End,
-		"pageup" => KeyCode::PageUp,
-		"pagedown" => KeyCode::PageDown,
-		"up" => KeyCode::UpArrow,
-		"down" => KeyCode::DownArrow,
-		"left" => KeyCode::LeftArrow,
-		"right" => KeyCode::RightArrow,
-		"space" => KeyCode::Char(' '),
-		s if s.starts_with('F') || s.starts_with('f') => {
-			let n: u8 = s[1..].parse().ok()?;
-			KeyCode::Function(n)
-		}
-		s if s.chars().count() == 1 => KeyCode::Char(s.chars().next().unwrap()),
-		_ => return None,
-	};
-
-	// backtab implies shift
-	if remaining == "backtab" {
-		mods |= Modifiers::SHIFT;
+fn direction_name(direction: ScrollDirection) -> &'static str {
+	match direction {
+		ScrollDirection::Up => "up",
+		ScrollDirection::Down => "down",
+		ScrollDirection::Left => "left",
+		ScrollDirection::Right => "right",
 	}
+}
 
-	keycode.encode(mods, modes, true).ok()
+/// Encodes a key name (from the recording format) to a terminal escape sequence.
+///
+/// Parses the `C-A-S-<code>` notation and encodes via termwiz.
+fn encode_key_name(name: &str, modes: termwiz::input::KeyCodeEncodeModes) -> Option<String> {
+	let key = parse_key(name)?;
+	key.key.encode(key.mods, modes, true).ok()
 }
 
 #[cfg(test)]
@@ -428,6 +440,28 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn horizontal_scroll_round_trips() {
+		let input = "mouse:scroll left 3,7\nmouse:scroll right 4,7\n";
+		let events = parse_recording(input);
+		assert_eq!(
+			events,
+			vec![
+				ReplayEvent::MouseScroll {
+					direction: ScrollDirection::Left,
+					col: 3,
+					row: 7
+				},
+				ReplayEvent::MouseScroll {
+					direction: ScrollDirection::Right,
+					col: 4,
+					row: 7
+				},
+			]
+		);
+		assert_eq!(format_recording(&events), input);
+	}
+
 	#[test]
 	fn parse_paste() {
 		let input = "paste:aGVsbG8gd29ybGQ=\n";
@@ -470,6 +504,13 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn format_recording_round_trips() {
+		let input = "j\nk\n\nC-x\nmouse:press left 10,5\nmouse:scroll down 1,2\npaste:aGVsbG8=\nresize:120x50\nfocus:out\n";
+		let events = parse_recording(input);
+		assert_eq!(parse_recording(&format_recording(&events)), events);
+	}
+
 	#[test]
 	fn encode_simple_char() {
 		use termwiz::escape::csi::KittyKeyboardFlags;
@@ -0,0 +1,333 @@
+//! Named test steps and Markdown/HTML test reports.
+//!
+//! [`crate::KittyHarness::step`] runs a closure as a named step: the input
+//! sent and the captures taken while it runs are grouped under the step,
+//! and the screen is captured once more when it finishes, passed or failed.
+//! [`crate::KittyHarness::report`] collects the steps into a [`TestReport`]
+//! that renders as Markdown or HTML, with each step's inputs, outcome,
+//! duration and final screen, ready to attach as sign-off evidence.
+//!
+//! Reports are written to [`REPORT_DIR_ENV`] when set, otherwise to the
+//! session's artifact directory (see [`crate::KittyHarness::write_report`]).
+//!
+//! ```ignore
+//! kitty.step("open file picker", || {
+//!     kitty.send_key_names(&["ctrl+o"]);
+//!     wait_for_clean_contains(&kitty, Duration::from_secs(2), "Open file");
+//! });
+//! kitty.step("filter to notes", || kitty.send_text("notes"));
+//! kitty.write_report("file_picker");
+//! ```
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::utils::capture::ScreenCapture;
+use crate::utils::error::HarnessError;
+use crate::utils::export::{escape_html, grid_to_html_pre};
+use crate::utils::theme::Palette;
+
+/// Environment variable naming a directory to collect reports in, as `<test>.md` and `<test>.html`.
+pub const REPORT_DIR_ENV: &str = "KITTY_TEST_REPORT_DIR";
+
+/// Something that happened in the harness window during a step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StepAction {
+	/// Text, bytes or key names (as `<name>`) sent to the window.
+	Input(String),
+	/// A screen capture, as clean text.
+	Capture(String),
+}
+
+/// One finished step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Step {
+	/// Step name; nested steps are joined with ` / `.
+	pub name: String,
+	/// When the step started.
+	pub started_at: SystemTime,
+	/// How long the step ran.
+	pub duration: Duration,
+	/// Inputs and captures in the order they happened.
+	pub actions: Vec<StepAction>,
+	/// The panic message if the step failed.
+	pub failure: Option<String>,
+	/// The screen when the step finished, if it could still be captured.
+	pub capture: Option<ScreenCapture>,
+}
+
+impl Step {
+	/// Returns true if the step finished without panicking.
+	pub fn passed(&self) -> bool {
+		self.failure.is_none()
+	}
+
+	/// The inputs sent during the step.
+	pub fn inputs(&self) -> impl Iterator<Item = &str> {
+		self.actions.iter().filter_map(|action| match action {
+			StepAction::Input(text) => Some(text.as_str()),
+			StepAction::Capture(_) => None,
+		})
+	}
+
+	fn outcome(&self) -> &'static str {
+		if self.passed() { "passed" } else { "failed" }
+	}
+
+	/// Inputs and captures as one line each, for the report.
+	fn action_lines(&self) -> Vec<String> {
+		self.actions
+			.iter()
+			.map(|action| match action {
+				StepAction::Input(text) => format!("input {text:?}"),
+				StepAction::Capture(_) => "capture".to_string(),
+			})
+			.collect()
+	}
+}
+
+/// The steps of one test, renderable as Markdown or HTML.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestReport {
+	/// Test name, used as the title and file name.
+	pub name: String,
+	/// Finished steps in the order they started.
+	pub steps: Vec<Step>,
+}
+
+impl TestReport {
+	/// A report of `steps` for the test `name`.
+	pub fn new(name: impl Into<String>, steps: Vec<Step>) -> Self {
+		Self { name: name.into(), steps }
+	}
+
+	/// Returns true if every step passed.
+	pub fn passed(&self) -> bool {
+		self.steps.iter().all(Step::passed)
+	}
+
+	/// Render as Markdown: a summary table, then each step's actions and final screen as clean text.
+	pub fn to_markdown(&self) -> String {
+		let mut out = format!("# {}\n\n| # | Step | Result | Duration |\n|---|------|--------|----------|\n", self.name);
+		for (idx, step) in self.steps.iter().enumerate() {
+			let _ = writeln!(
+				out,
+				"| {} | {} | {} | {:.2?} |",
+				idx + 1,
+				step.name.replace('|', "\\|"),
+				step.outcome(),
+				step.duration
+			);
+		}
+		for (idx, step) in self.steps.iter().enumerate() {
+			let _ = write!(out, "\n## {}. {}\n\n**{}** in {:.2?}\n", idx + 1, step.name, step.outcome(), step.duration);
+			if let Some(failure) = &step.failure {
+				let _ = write!(out, "\n```text\n{failure}\n```\n");
+			}
+			let lines = step.action_lines();
+			if !lines.is_empty() {
+				out.push('\n');
+				lines.iter().for_each(|line| {
+					let _ = writeln!(out, "- `{line}`");
+				});
+			}
+			match &step.capture {
+				Some(capture) => {
+					let _ = write!(
+						out,
+						"\nFinal screen ({}x{}):\n\n```text\n{}\n```\n",
+						capture.cols(),
+						capture.rows(),
+						capture.clean
+					);
+				}
+				None => out.push_str("\nNo final screen captured.\n"),
+			}
+		}
+		out
+	}
+
+	/// Render as a standalone HTML document, with each step's final screen in color using `palette`.
+	pub fn to_html(&self, palette: &Palette) -> String {
+		let title = escape_html(&self.name);
+		let mut summary = String::new();
+		let mut sections = String::new();
+		for (idx, step) in self.steps.iter().enumerate() {
+			let (number, name, outcome) = (idx + 1, escape_html(&step.name), step.outcome());
+			let _ = writeln!(
+				summary,
+				"<tr><td>{number}</td><td><a href=\"#step-{number}\">{name}</a></td><td class=\"{outcome}\">{outcome}</td><td>{:.2?}</td></tr>",
+				step.duration
+			);
+			let _ = write!(
+				sections,
+				"<h2 id=\"step-{number}\">{number}. {name}</h2>\n<p><span class=\"{outcome}\">{outcome}</span> in {:.2?}</p>\n",
+				step.duration
+			);
+			if let Some(failure) = &step.failure {
+				let _ = writeln!(sections, "<pre class=\"failure\">{}</pre>", escape_html(failure));
+			}
+			let lines = step.action_lines();
+			if !lines.is_empty() {
+				let items: String = lines.iter().map(|line| format!("<li><code>{}</code></li>", escape_html(line))).collect();
+				let _ = writeln!(sections, "<ul>{items}</ul>");
+			}
+			match &step.capture {
+				Some(capture) => {
+					let _ = writeln!(
+						sections,
+						"<p>Final screen ({}x{}):</p>\n{}",
+						capture.cols(),
+						capture.rows(),
+						grid_to_html_pre(&capture.grid(), palette)
+					);
+				}
+				None => sections.push_str("<p>No final screen captured.</p>\n"),
+			}
+		}
+		format!(
+			"<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>body{{font-family:sans-serif}}table{{border-collapse:collapse}}td,th{{border:1px solid #999;padding:2px 8px}}.passed{{color:#2a7a2a}}.failed{{color:#b00020}}.failure{{color:#b00020}}</style>\n</head>\n<body>\n<h1>{title}</h1>\n<table>\n<tr><th>#</th><th>Step</th><th>Result</th><th>Duration</th></tr>\n{summary}</table>\n{sections}</body>\n</html>\n"
+		)
+	}
+
+	/// Write the Markdown report to `path`.
+	pub fn write_markdown(&self, path: &Path) -> Result<(), HarnessError> {
+		write(path, &self.to_markdown())
+	}
+
+	/// Write the HTML report to `path`, rendering screens with `palette`.
+	pub fn write_html(&self, path: &Path, palette: &Palette) -> Result<(), HarnessError> {
+		write(path, &self.to_html(palette))
+	}
+
+	/// Write `<name>.md` and `<name>.html` into `dir`, rendering screens with `palette`, and return their paths.
+	pub fn write_to(&self, dir: &Path, palette: &Palette) -> Result<(PathBuf, PathBuf), HarnessError> {
+		let (markdown, html) = (dir.join(format!("{}.md", self.name)), dir.join(format!("{}.html", self.name)));
+		self.write_markdown(&markdown)?;
+		self.write_html(&html, palette)?;
+		Ok((markdown, html))
+	}
+}
+
+/// Steps of a harness: the ones running (innermost last) and the finished ones.
+#[derive(Debug, Default)]
+pub(crate) struct StepLog {
+	/// Running steps with the index they take in `finished`, so parents are listed before their nested steps.
+	open: Vec<(usize, Step)>,
+	finished: Vec<Step>,
+}
+
+impl StepLog {
+	/// Start a step nested in the innermost running one.
+	pub(crate) fn begin(&mut self, name: &str) {
+		let name = match self.open.last() {
+			Some((_, parent)) => format!("{} / {name}", parent.name),
+			None => name.to_string(),
+		};
+		let slot = self.finished.len();
+		self.open.push((
+			slot,
+			Step {
+				name,
+				started_at: SystemTime::now(),
+				duration: Duration::ZERO,
+				actions: Vec::new(),
+				failure: None,
+				capture: None,
+			},
+		));
+	}
+
+	/// Record `action` in the innermost running step, if any.
+	pub(crate) fn action(&mut self, action: StepAction) {
+		if let Some((_, step)) = self.open.last_mut() {
+			step.actions.push(action);
+		}
+	}
+
+	/// Finish the innermost running step.
+	pub(crate) fn end(&mut self, failure: Option<String>, capture: Option<ScreenCapture>) {
+		if let Some((slot, mut step)) = self.open.pop() {
+			step.duration = step.started_at.elapsed().unwrap_or_default();
+			step.failure = failure;
+			step.capture = capture;
+			self.finished.insert(slot, step);
+		}
+	}
+
+	/// Finished steps in start order.
+	pub(crate) fn finished(&self) -> &[Step] {
+		&self.finished
+	}
+}
+
+/// The message of a panic payload, as printed by the default panic hook.
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+	payload
+		.downcast_ref::<&str>()
+		.map(|message| message.to_string())
+		.or_else(|| payload.downcast_ref::<String>().cloned())
+		.unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), HarnessError> {
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent).map_err(|err| HarnessError::io(format!("create {}", parent.display()), err))?;
+	}
+	std::fs::write(path, contents).map_err(|err| HarnessError::io(format!("write {}", path.display()), err))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn report() -> TestReport {
+		let mut log = StepLog::default();
+		log.begin("open picker");
+		log.action(StepAction::Input("<ctrl+o>".to_string()));
+		log.begin("type filter");
+		log.action(StepAction::Input("notes\r".to_string()));
+		log.end(None, None);
+		log.action(StepAction::Capture("Open file".to_string()));
+		log.end(
+			Some("expected \"notes.md\"".to_string()),
+			Some(ScreenCapture::new("\x1b[1mOpen file\x1b[0m\n> notes", (20, 2))),
+		);
+		TestReport::new("file_picker", log.finished().to_vec())
+	}
+
+	#[test]
+	fn nested_steps_are_listed_in_start_order() {
+		let report = report();
+		let names: Vec<&str> = report.steps.iter().map(|step| step.name.as_str()).collect();
+		assert_eq!(names, ["open picker", "open picker / type filter"]);
+		assert_eq!(report.steps[0].inputs().collect::<Vec<_>>(), ["<ctrl+o>"]);
+		assert!(!report.passed());
+	}
+
+	#[test]
+	fn markdown_lists_actions_and_final_screen() {
+		let markdown = report().to_markdown();
+		assert!(markdown.starts_with("# file_picker\n\n| # | Step | Result | Duration |"), "{markdown}");
+		assert!(
+			markdown.contains("## 1. open picker\n\n**failed** in ")
+				&& markdown.contains(
+					"```text\nexpected \"notes.md\"\n```\n\n- `input \"<ctrl+o>\"`\n- `capture`\n\nFinal screen (20x2):\n\n```text\nOpen file\n> notes\n```\n"
+				),
+			"{markdown}"
+		);
+		assert!(markdown.contains("- `input \"notes\\r\"`\n\nNo final screen captured.\n"), "{markdown}");
+	}
+
+	#[test]
+	fn html_escapes_text_and_renders_styles() {
+		let html = report().to_html(&Palette::kitty_default());
+		assert!(html.contains("<code>input &quot;&lt;ctrl+o&gt;&quot;</code>"), "{html}");
+		assert!(html.contains("<span style=\"font-weight:bold\">Open file</span>"), "{html}");
+		assert!(html.contains("<td class=\"failed\">failed</td>"), "{html}");
+	}
+}
@@ -1,14 +1,22 @@
 //! Window resize utilities for kitty terminal testing.
 
 use std::process::Command;
+use std::time::{Duration, Instant};
 
 use crate::KittyHarness;
 
+/// How long [`resize_window`] waits for kitty to report the new size.
+const RESIZE_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// Resizes the kitty window to the specified dimensions.
 ///
 /// Uses `kitty @ resize-window` to set the window to the given number of
 /// columns and rows. This sends the appropriate resize signal to the
 /// application running inside the terminal.
+///
+/// Returns once kitty reports the new size, polling with the harness
+/// [`crate::Timing`], or after a second if the size is not applied (for
+/// example under a tiling window manager).
 pub fn resize_window(kitty: &KittyHarness, cols: u16, rows: u16) {
 	let status = Command::new("kitty")
 		.args([
@@ -45,7 +53,12 @@ pub fn resize_window(kitty: &KittyHarness, cols: u16, rows: u16) {
 		.status();
 
 	// Allow the terminal time to process the resize.
-	std::thread::sleep(std::time::Duration::from_millis(100));
+	let timeout = kitty.clamp_timeout(RESIZE_TIMEOUT);
+	let start = Instant::now();
+	let mut delays = kitty.timing().poll_delays();
+	while start.elapsed() < timeout && kitty.try_size().ok() != Some((cols, rows)) {
+		delays.sleep();
+	}
 
 	let _ = status;
 }
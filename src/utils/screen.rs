@@ -4,7 +4,17 @@
 //! raw ANSI terminal output, including:
 //!
 //! - Finding separator characters (│, ─) used in split layouts
-//! - Extracting ANSI color codes for verifying styling changes
+//! - Converting between text offsets and display columns ([`display_col`])
+//! - Extracting ANSI color codes and text attributes for verifying styling changes
+//! - Reading the cursor state appended by `get-text --add-cursor`
+//! - Building a [`ScreenGrid`] of styled cells for positional assertions
+//! - Recovering kitty graphics protocol placements from raw program output
+//! - Extracting OSC 8 hyperlinks from raw captures
+//!
+//! Columns in clean text are display columns: graphemes are measured with
+//! their terminal width, so a CJK character or emoji advances two columns
+//! and combining marks none, matching what kitty reports for the cursor.
+//! [`ScreenGrid`] still stores one cell per `char`.
 //!
 //! # Example
 //!
@@ -24,12 +34,158 @@
 
 use std::collections::HashMap;
 
+use regex::Regex;
+use termwiz::cell::grapheme_column_width;
+use termwiz::escape::apc::KittyImageDelete;
+use termwiz::escape::csi::Cursor as CsiCursor;
+use termwiz::escape::esc::EscCode;
+use termwiz::escape::parser::Parser;
+use termwiz::escape::{Action, CSI, ControlCode, Esc, KittyImage};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::utils::ansi::{TokenKind, split_tokens};
+use crate::utils::style::{ColorSpec, TextStyle, UnderlineStyle, parse_extended_color, parse_extended_semicolon, sgr_params, styled_lines};
+use crate::utils::theme::{Palette, Rgb};
+
 /// Vertical box-drawing character used as a separator in split layouts.
 pub const VERTICAL_SEPARATOR: char = '│'; // U+2502
 
 /// Horizontal box-drawing character used as a separator in split layouts.
 pub const HORIZONTAL_SEPARATOR: char = '─'; // U+2500
 
+/// Display width of `text` in terminal cells.
+///
+/// Wide characters (CJK, most emoji) count two cells and combining marks
+/// none.
+///
+/// ```
+/// use kitty_test_harness::utils::screen::display_width;
+///
+/// assert_eq!(display_width("ab"), 2);
+/// assert_eq!(display_width("日本"), 4);
+/// assert_eq!(display_width("e\u{301}"), 1);
+/// ```
+pub fn display_width(text: &str) -> usize {
+	text.graphemes(true).map(|grapheme| grapheme_column_width(grapheme, None)).sum()
+}
+
+/// Display column at which byte offset `byte_idx` of `line` is drawn.
+///
+/// Use it to turn `str::find` results into screen columns. `byte_idx` past
+/// the end of `line` returns the width of the whole line.
+///
+/// # Panics
+///
+/// Panics if `byte_idx` is not on a character boundary.
+///
+/// ```
+/// use kitty_test_harness::utils::screen::display_col;
+///
+/// let line = "日本 menu";
+/// assert_eq!(display_col(line, line.find("menu").unwrap()), 5);
+/// ```
+pub fn display_col(line: &str, byte_idx: usize) -> usize {
+	display_width(&line[..byte_idx.min(line.len())])
+}
+
+/// Return the row and display column of the first occurrence of `needle` in clean screen text.
+///
+/// Rows are scanned top to bottom; the column accounts for wide characters
+/// before the match, so it can be used directly for mouse clicks and cursor
+/// assertions. A needle spanning lines is never found.
+///
+/// ```
+/// use kitty_test_harness::utils::screen::find_text_position;
+///
+/// let screen = "title\n日本語 [OK] [Cancel]";
+/// assert_eq!(find_text_position(screen, "[OK]"), Some((1, 7)));
+/// assert_eq!(find_text_position(screen, "missing"), None);
+/// ```
+pub fn find_text_position(clean: &str, needle: &str) -> Option<(usize, usize)> {
+	if needle.is_empty() {
+		return None;
+	}
+	clean
+		.lines()
+		.enumerate()
+		.find_map(|(row, line)| line.find(needle).map(|idx| (row, display_col(line, idx))))
+}
+
+/// Return the row and display column of every occurrence of `needle`, in reading order.
+///
+/// Occurrences within a line do not overlap. Use it to count markers or to
+/// pick the Nth match to click.
+///
+/// ```
+/// use kitty_test_harness::utils::screen::find_all_positions;
+///
+/// let screen = "● one\n  two\n● 三 ● four";
+/// assert_eq!(find_all_positions(screen, "●"), vec![(0, 0), (2, 0), (2, 5)]);
+/// ```
+pub fn find_all_positions(clean: &str, needle: &str) -> Vec<(usize, usize)> {
+	if needle.is_empty() {
+		return Vec::new();
+	}
+	clean
+		.lines()
+		.enumerate()
+		.flat_map(|(row, line)| line.match_indices(needle).map(move |(idx, _)| (row, display_col(line, idx))))
+		.collect()
+}
+
+/// Return the row, display column and text of every match of `pattern`, line by line.
+///
+/// Patterns are matched against each line separately, so `^` and `$` refer
+/// to line boundaries. Empty matches are skipped.
+///
+/// ```
+/// use kitty_test_harness::utils::screen::find_all_matches;
+/// use regex::Regex;
+///
+/// let screen = "v1.2 ok\nnext v10.0";
+/// let matches = find_all_matches(screen, &Regex::new(r"v\d+\.\d+").unwrap());
+/// assert_eq!(matches, vec![(0, 0, "v1.2".to_string()), (1, 5, "v10.0".to_string())]);
+/// ```
+pub fn find_all_matches(clean: &str, pattern: &Regex) -> Vec<(usize, usize, String)> {
+	clean
+		.lines()
+		.enumerate()
+		.flat_map(|(row, line)| {
+			pattern
+				.find_iter(line)
+				.filter(|found| !found.is_empty())
+				.map(move |found| (row, display_col(line, found.start()), found.as_str().to_string()))
+		})
+		.collect()
+}
+
+/// Graphemes of `line` paired with the display column they start at.
+pub(crate) fn columns(line: &str) -> impl Iterator<Item = (usize, &str)> {
+	let mut col = 0;
+	line.graphemes(true).map(move |grapheme| {
+		let start = col;
+		col += grapheme_column_width(grapheme, None);
+		(start, grapheme)
+	})
+}
+
+/// One entry per display column: the grapheme starting there, or `""` for
+/// the second half of a wide character.
+pub(crate) fn cells(line: &str) -> Vec<&str> {
+	let mut out = Vec::new();
+	for (col, grapheme) in columns(line) {
+		out.resize(col, "");
+		out.push(grapheme);
+	}
+	out
+}
+
+/// Whether `grapheme` is exactly the character `ch`.
+fn is_char(grapheme: &str, ch: char) -> bool {
+	let mut chars = grapheme.chars();
+	chars.next() == Some(ch) && chars.next().is_none()
+}
+
 /// Find the column position of vertical separators (│) in the screen.
 ///
 /// Scans all lines of the clean (ANSI-stripped) screen output and returns
@@ -63,8 +219,8 @@ pub fn find_vertical_separator_col(clean: &str) -> Option<usize> {
 	let mut col_counts: HashMap<usize, usize> = HashMap::new();
 
 	for line in &lines {
-		for (col, ch) in line.chars().enumerate() {
-			if ch == VERTICAL_SEPARATOR {
+		for (col, grapheme) in columns(line) {
+			if is_char(grapheme, VERTICAL_SEPARATOR) {
 				*col_counts.entry(col).or_insert(0) += 1;
 			}
 		}
@@ -138,7 +294,7 @@ pub fn find_separator_rows_at_col(clean: &str, col: usize) -> Vec<usize> {
 	clean
 		.lines()
 		.enumerate()
-		.filter(|(_, line)| line.chars().nth(col).is_some_and(|c| c == VERTICAL_SEPARATOR))
+		.filter(|(_, line)| columns(line).any(|(start, grapheme)| start == col && is_char(grapheme, VERTICAL_SEPARATOR)))
 		.map(|(row, _)| row)
 		.collect()
 }
@@ -158,77 +314,249 @@ pub fn find_separator_cols_at_row(clean: &str, row: usize) -> Vec<usize> {
 		.lines()
 		.nth(row)
 		.map(|line| {
-			line.chars()
-				.enumerate()
-				.filter(|(_, c)| *c == HORIZONTAL_SEPARATOR)
+			columns(line)
+				.filter(|(_, grapheme)| is_char(grapheme, HORIZONTAL_SEPARATOR))
 				.map(|(col, _)| col)
 				.collect()
 		})
 		.unwrap_or_default()
 }
 
+/// A rectangular screen region in 0-based cell coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+	/// Top row of the region.
+	pub row: usize,
+	/// Leftmost column of the region.
+	pub col: usize,
+	/// Number of columns covered.
+	pub width: usize,
+	/// Number of rows covered.
+	pub height: usize,
+}
+
+impl Rect {
+	/// Create a region from its top-left corner and size.
+	pub fn new(row: usize, col: usize, width: usize, height: usize) -> Self {
+		Self { row, col, width, height }
+	}
+
+	/// Returns true if the cell at (`row`, `col`) lies inside this region.
+	pub fn contains(&self, row: usize, col: usize) -> bool {
+		row >= self.row && row < self.row + self.height && col >= self.col && col < self.col + self.width
+	}
+}
+
+/// Cut the cells inside `rect` out of a clean or raw capture, one line per row.
+///
+/// Columns are display columns, so a pane found with
+/// [`find_vertical_separator_col`] can be extracted and snapshotted on its
+/// own. In raw captures, escape sequences before the end of the region are
+/// kept (including those from rows above it), so the extracted text keeps
+/// its styling. Trailing whitespace is trimmed from each row, and rows past
+/// the end of the capture are empty.
+///
+/// ```
+/// use kitty_test_harness::utils::screen::{Rect, extract_region};
+///
+/// let screen = "files   │ fn main() {\nsrc/    │     run();\nCargo   │ }";
+/// let col = 8;
+/// assert_eq!(extract_region(screen, Rect::new(0, 0, col, 3)), "files\nsrc/\nCargo");
+/// assert_eq!(extract_region(screen, Rect::new(1, col + 2, 20, 2)), "    run();\n}");
+/// ```
+pub fn extract_region(capture: &str, rect: Rect) -> String {
+	let end = rect.col + rect.width;
+	let lines: Vec<&str> = capture.split('\n').collect();
+	let mut carried = String::new();
+	for line in lines.iter().take(rect.row) {
+		for token in split_tokens(line).iter().filter(|token| token.kind == TokenKind::Escape) {
+			carried.push_str(&token.raw);
+		}
+	}
+
+	let mut rows = Vec::with_capacity(rect.height);
+	for row in rect.row..rect.row + rect.height {
+		let tokens = lines.get(row).map(|line| split_tokens(line)).unwrap_or_default();
+		let mut pieces: Vec<(bool, &str)> = Vec::new();
+		let mut col = 0;
+		for token in &tokens {
+			match token.kind {
+				TokenKind::Escape if col < end => pieces.push((false, &token.raw)),
+				TokenKind::Escape => {}
+				TokenKind::Text => {
+					for (start, grapheme) in columns(&token.text) {
+						if (rect.col..end).contains(&(col + start)) {
+							pieces.push((true, grapheme));
+						}
+					}
+					col += display_width(&token.text);
+				}
+			}
+		}
+		let keep = pieces
+			.iter()
+			.rposition(|(is_text, piece)| *is_text && !piece.trim().is_empty())
+			.map_or(0, |idx| idx + 1);
+		let mut out = std::mem::take(&mut carried);
+		for (idx, (is_text, piece)) in pieces.iter().enumerate() {
+			if idx < keep || !is_text {
+				out.push_str(piece);
+			}
+		}
+		rows.push(out);
+	}
+	rows.join("\n")
+}
+
+/// Cursor state reported by `kitty @ get-text --add-cursor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cursor {
+	/// 0-based row of the cursor on the visible screen.
+	pub row: usize,
+	/// 0-based column of the cursor.
+	pub col: usize,
+	/// Whether the cursor is shown (DECTCEM, `CSI ?25h`/`CSI ?25l`).
+	pub visible: bool,
+}
+
+/// Parse the cursor state that `get-text --add-cursor` appends to a capture.
+///
+/// kitty reports the position as a `CSI row;col H` sequence and visibility as
+/// `CSI ?25h`/`CSI ?25l`; the last occurrence of each wins. Returns `None` if
+/// no position sequence is present.
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::utils::screen::{Cursor, parse_cursor};
+///
+/// let raw = "$ ls\n\x1b[?25h\x1b[2;5H";
+/// assert_eq!(parse_cursor(raw), Some(Cursor { row: 1, col: 4, visible: true }));
+/// ```
+pub fn parse_cursor(raw: &str) -> Option<Cursor> {
+	let mut position = None;
+	let mut visible = true;
+	for token in split_tokens(raw) {
+		let Some(body) = token.raw.strip_prefix("\x1b[") else {
+			continue;
+		};
+		match body {
+			"?25h" => visible = true,
+			"?25l" => visible = false,
+			_ => {
+				if let Some(params) = body.strip_suffix('H') {
+					let mut parts = params.splitn(2, ';');
+					let row: usize = parts.next().filter(|p| !p.is_empty()).map_or(Some(1), |p| p.parse().ok())?;
+					let col: usize = parts.next().filter(|p| !p.is_empty()).map_or(Some(1), |p| p.parse().ok())?;
+					position = Some((row.saturating_sub(1), col.saturating_sub(1)));
+				}
+			}
+		}
+	}
+	position.map(|(row, col)| Cursor { row, col, visible })
+}
+
 /// Represents an extracted ANSI color from terminal output.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnsiColor {
 	/// The raw ANSI escape sequence (e.g., "\x1b[38:2:255:128:0m")
 	pub raw: String,
 	/// Whether this is a foreground (true) or background (false) color
 	pub is_foreground: bool,
+	/// Whether this is an underline color (SGR 58/59); `is_foreground` is false for these
+	pub is_underline: bool,
 	/// RGB values if this is a true-color (24-bit) specification
 	pub rgb: Option<(u8, u8, u8)>,
 	/// 256-color palette index if this is an indexed color
 	pub palette_index: Option<u8>,
+	/// The color in any form: basic/bright, palette, true color or the default (39/49).
+	pub color: ColorSpec,
 }
 
 impl AnsiColor {
-	/// Parse an ANSI SGR color sequence into an `AnsiColor` struct.
-	///
-	/// Supports both semicolon-separated (standard) and colon-separated (kitty)
-	/// color specifications.
 	/// Parse an ANSI SGR color sequence into an `AnsiColor`.
+	///
+	/// Supports the basic (`31`, `42`) and bright (`91`, `102`) colors, the
+	/// default-color resets (`39`, `49`, `59`) and extended foreground,
+	/// background and underline (`58`) colors in both semicolon-separated
+	/// (standard) and colon-separated (kitty) forms.
+	/// The first color in a sequence that sets several is returned.
+	///
+	/// ```
+	/// use kitty_test_harness::utils::screen::AnsiColor;
+	/// use kitty_test_harness::utils::style::ColorSpec;
+	///
+	/// let color = AnsiColor::parse_seq("\x1b[1;91m").unwrap();
+	/// assert_eq!((color.is_foreground, color.color), (true, ColorSpec::Basic(9)));
+	/// assert_eq!(AnsiColor::parse_seq("\x1b[49m").unwrap().color, ColorSpec::Default);
+	/// assert_eq!(AnsiColor::parse_seq("\x1b[1m"), None);
+	///
+	/// let underline = AnsiColor::parse_seq("\x1b[4:3;58:2::255:0:0m").unwrap();
+	/// assert!(underline.is_underline);
+	/// assert_eq!(underline.rgb, Some((255, 0, 0)));
+	/// ```
 	pub fn parse_seq(seq: &str) -> Option<Self> {
-		// Check if it's a foreground or background color
-		let is_foreground = seq.contains("38;") || seq.contains("38:");
-		let is_background = seq.contains("48;") || seq.contains("48:");
-
-		if !is_foreground && !is_background {
-			return None;
-		}
-
-		// Try to extract RGB or palette index
-		let mut rgb = None;
-		let mut palette_index = None;
-
-		// Handle RGB colors (38;2;R;G;B or 38:2:R:G:B)
-		if seq.contains(";2;") || seq.contains(":2:") {
-			let parts: Vec<&str> = seq.trim_start_matches("\x1b[").trim_end_matches('m').split([';', ':']).collect();
-
-			// Find the "2" marker and extract R, G, B
-			if let Some(pos) = parts.iter().position(|&p| p == "2")
-				&& parts.len() > pos + 3
-				&& let (Ok(r), Ok(g), Ok(b)) = (parts[pos + 1].parse::<u8>(), parts[pos + 2].parse::<u8>(), parts[pos + 3].parse::<u8>())
-			{
-				rgb = Some((r, g, b));
-			}
+		let groups: Vec<&str> = sgr_params(seq)?.split(';').collect();
+		for (i, group) in groups.iter().enumerate() {
+			let sub: Vec<&str> = group.split(':').collect();
+			let Ok(code) = sub[0].parse::<u8>() else {
+				continue;
+			};
+			let (is_foreground, color) = match code {
+				30..=37 => (true, ColorSpec::Basic(code - 30)),
+				39 => (true, ColorSpec::Default),
+				40..=47 => (false, ColorSpec::Basic(code - 40)),
+				49 | 59 => (false, ColorSpec::Default),
+				90..=97 => (true, ColorSpec::Basic(code - 90 + 8)),
+				100..=107 => (false, ColorSpec::Basic(code - 100 + 8)),
+				38 | 48 | 58 => {
+					let color = if sub.len() > 1 {
+						parse_extended_color(&sub[1..])
+					} else {
+						parse_extended_semicolon(&groups[i + 1..]).0
+					};
+					(code == 38, color?)
+				}
+				_ => continue,
+			};
+			return Some(AnsiColor {
+				raw: seq.to_string(),
+				is_foreground,
+				is_underline: matches!(code, 58 | 59),
+				rgb: match color {
+					ColorSpec::Rgb(r, g, b) => Some((r, g, b)),
+					_ => None,
+				},
+				palette_index: match color {
+					ColorSpec::Palette(idx) => Some(idx),
+					_ => None,
+				},
+				color,
+			});
 		}
-		// Handle 256-color palette (38;5;N or 38:5:N)
-		else if seq.contains(";5;") || seq.contains(":5:") {
-			let parts: Vec<&str> = seq.trim_start_matches("\x1b[").trim_end_matches('m').split([';', ':']).collect();
+		None
+	}
 
-			if let Some(pos) = parts.iter().position(|&p| p == "5")
-				&& parts.len() > pos + 1
-				&& let Ok(idx) = parts[pos + 1].parse::<u8>()
-			{
-				palette_index = Some(idx);
-			}
+	/// Resolve the color to RGB against `palette`.
+	///
+	/// Default colors resolve to the palette's foreground (for foreground
+	/// and underline colors) or background.
+	///
+	/// ```
+	/// use kitty_test_harness::utils::screen::AnsiColor;
+	/// use kitty_test_harness::Palette;
+	///
+	/// let color = AnsiColor::parse_seq("\x1b[38;5;196m").unwrap();
+	/// assert_eq!(color.resolve(&Palette::kitty_default()), (255, 0, 0));
+	/// ```
+	pub fn resolve(&self, palette: &Palette) -> Rgb {
+		if self.is_foreground || self.is_underline {
+			palette.resolve_fg(self.color)
+		} else {
+			palette.resolve_bg(self.color)
 		}
-
-		Some(AnsiColor {
-			raw: seq.to_string(),
-			is_foreground,
-			rgb,
-			palette_index,
-		})
 	}
 }
 
@@ -255,7 +583,10 @@ impl AnsiColor {
 /// - `\x1b[38:2:R:G:Bm` - Kitty RGB foreground
 /// - `\x1b[38;5;Nm` - Standard 256-color foreground
 /// - `\x1b[38:5:Nm` - Kitty 256-color foreground
-/// - `\x1b[48;...]` variants for background colors
+/// - `\x1b[48;...]` and `\x1b[58;...]` variants for background and underline colors
+/// - `\x1b[31m`, `\x1b[91m`, `\x1b[42m`, ... basic and bright colors
+///
+/// Default-color resets (`39`, `49`, `59`) are not reported.
 ///
 /// # Example
 ///
@@ -267,44 +598,17 @@ impl AnsiColor {
 /// assert!(colors.iter().any(|c| c.contains("255")));
 /// ```
 pub fn extract_row_colors(raw: &str, row: usize) -> Vec<String> {
-	let lines: Vec<&str> = raw.lines().collect();
-	if row >= lines.len() {
+	let Some(line) = raw.lines().nth(row) else {
 		return vec![];
-	}
+	};
 
-	let line = lines[row];
 	let mut colors = vec![];
-
-	// Look for ANSI SGR sequences
-	let mut i = 0;
-	let chars: Vec<char> = line.chars().collect();
-	while i < chars.len() {
-		if chars[i] == '\x1b' && i + 1 < chars.len() && chars[i + 1] == '[' {
-			// Find the 'm' that ends the sequence
-			let start = i;
-			while i < chars.len() && chars[i] != 'm' {
-				i += 1;
-			}
-			if i < chars.len() {
-				let seq: String = chars[start..=i].iter().collect();
-				// Check if it's a foreground or background color
-				if (seq.contains("38;2;")
-					|| seq.contains("38;5;")
-					|| seq.contains("38:2:")
-					|| seq.contains("38:5:")
-					|| seq.contains("48;2;")
-					|| seq.contains("48;5;")
-					|| seq.contains("48:2:")
-					|| seq.contains("48:5:"))
-					&& !colors.contains(&seq)
-				{
-					colors.push(seq);
-				}
-			}
+	for token in split_tokens(line) {
+		let is_color = AnsiColor::parse_seq(&token.raw).is_some_and(|color| color.color != ColorSpec::Default);
+		if is_color && !colors.contains(&token.raw) {
+			colors.push(token.raw);
 		}
-		i += 1;
 	}
-
 	colors
 }
 
@@ -376,10 +680,603 @@ pub fn fg_color_at_text(raw_line: &str, needle: &str) -> Option<(u8, u8, u8)> {
 	None
 }
 
+/// Returns the text attributes in effect at the first character of `needle`
+/// in the visible text of a raw ANSI line.
+///
+/// Companion to [`fg_color_at_text`] for bold, dim, italic, underline,
+/// reverse video and strikethrough, e.g. to observe selection highlighting
+/// drawn in reverse video. Returns `None` if `needle` is not found.
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::utils::screen::attrs_at_text;
+///
+/// let line = "  file.txt \x1b[7;1mnotes.md\x1b[27m  todo\x1b[0m";
+/// let attrs = attrs_at_text(line, "notes").unwrap();
+/// assert!(attrs.reverse && attrs.bold);
+/// let attrs = attrs_at_text(line, "todo").unwrap();
+/// assert!(!attrs.reverse && attrs.bold);
+/// ```
+pub fn attrs_at_text(raw_line: &str, needle: &str) -> Option<CellAttrs> {
+	let cells = styled_lines(raw_line).into_iter().next().unwrap_or_default();
+	let text: Vec<char> = cells.iter().map(|(ch, _)| *ch).collect();
+	let needle: Vec<char> = needle.chars().collect();
+	if needle.is_empty() || needle.len() > text.len() {
+		return None;
+	}
+	let start = text.windows(needle.len()).position(|window| window == needle.as_slice())?;
+	Some(CellAttrs::from_style(&cells[start].1))
+}
+
+/// Text attributes of a single screen cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellAttrs {
+	/// Bold / increased intensity.
+	pub bold: bool,
+	/// Dim / decreased intensity.
+	pub dim: bool,
+	/// Italic.
+	pub italic: bool,
+	/// Underlined.
+	pub underline: bool,
+	/// Underline style (curly, dotted, ...).
+	pub underline_style: UnderlineStyle,
+	/// Reverse video.
+	pub reverse: bool,
+	/// Strikethrough.
+	pub strikethrough: bool,
+}
+
+/// A single character cell of a [`ScreenGrid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cell {
+	/// The character drawn in the cell.
+	pub ch: char,
+	/// Foreground color.
+	pub fg: ColorSpec,
+	/// Background color.
+	pub bg: ColorSpec,
+	/// Underline color (SGR 58).
+	pub underline_color: ColorSpec,
+	/// Text attributes.
+	pub attrs: CellAttrs,
+}
+
+impl CellAttrs {
+	fn from_style(style: &TextStyle) -> Self {
+		Self {
+			bold: style.bold,
+			dim: style.dim,
+			italic: style.italic,
+			underline: style.underline,
+			underline_style: style.underline_style,
+			reverse: style.reverse,
+			strikethrough: style.strikethrough,
+		}
+	}
+}
+
+impl Cell {
+	fn new(ch: char, style: &TextStyle) -> Self {
+		Self {
+			ch,
+			fg: style.fg,
+			bg: style.bg,
+			underline_color: style.underline_color,
+			attrs: CellAttrs::from_style(style),
+		}
+	}
+
+	/// Text and background colors the cell is drawn with under `palette`.
+	///
+	/// Reverse video swaps the two and dim blends the text color halfway
+	/// towards the background, the way kitty draws them.
+	pub fn effective_colors(&self, palette: &Palette) -> (Rgb, Rgb) {
+		let mut fg = palette.resolve_fg(self.fg);
+		let mut bg = palette.resolve_bg(self.bg);
+		if self.attrs.reverse {
+			std::mem::swap(&mut fg, &mut bg);
+		}
+		if self.attrs.dim {
+			let mix = |f: u8, b: u8| ((u16::from(f) + u16::from(b)) / 2) as u8;
+			fg = (mix(fg.0, bg.0), mix(fg.1, bg.1), mix(fg.2, bg.2));
+		}
+		(fg, bg)
+	}
+
+	/// Background color the cell is drawn with under `palette`, taking reverse video into account.
+	pub fn effective_bg(&self, palette: &Palette) -> Rgb {
+		self.effective_colors(palette).1
+	}
+}
+
+/// A captured screen as rows of styled cells.
+///
+/// Built from a raw `get-text --ansi` capture, so "the character at (10, 42)
+/// is bold white on blue" can be asserted directly instead of through
+/// regexes over escape sequences. Coordinates are 0-based and rows keep
+/// their captured length (trailing blanks are not padded back in).
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::utils::screen::ScreenGrid;
+/// use kitty_test_harness::utils::style::ColorSpec;
+///
+/// let grid = ScreenGrid::parse("title\n\x1b[1;37;44mOK\x1b[0m done");
+/// let cell = grid.cell(1, 0).unwrap();
+/// assert!(cell.attrs.bold);
+/// assert_eq!((cell.fg, cell.bg), (ColorSpec::Basic(7), ColorSpec::Basic(4)));
+/// assert_eq!(grid.row_text(1), "OK done");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScreenGrid {
+	rows: Vec<Vec<Cell>>,
+}
+
+impl ScreenGrid {
+	/// Build a grid from a raw ANSI capture.
+	pub fn parse(raw: &str) -> Self {
+		let rows = styled_lines(raw)
+			.into_iter()
+			.map(|line| line.into_iter().map(|(ch, style)| Cell::new(ch, &style)).collect())
+			.collect();
+		Self { rows }
+	}
+
+	/// Number of rows in the grid.
+	pub fn height(&self) -> usize {
+		self.rows.len()
+	}
+
+	/// Return the cells of `row`, or `None` if it is out of range.
+	pub fn row(&self, row: usize) -> Option<&[Cell]> {
+		self.rows.get(row).map(Vec::as_slice)
+	}
+
+	/// Return all rows.
+	pub fn rows(&self) -> &[Vec<Cell>] {
+		&self.rows
+	}
+
+	/// Return the cell at (`row`, `col`), or `None` if it is out of range.
+	pub fn cell(&self, row: usize, col: usize) -> Option<&Cell> {
+		self.rows.get(row)?.get(col)
+	}
+
+	/// Return the visible text of `row`, or an empty string if it is out of range.
+	pub fn row_text(&self, row: usize) -> String {
+		self.rows.get(row).map(|cells| cells.iter().map(|cell| cell.ch).collect()).unwrap_or_default()
+	}
+
+	/// Return the visible text inside `rect`, one line per row.
+	pub fn region_text(&self, rect: Rect) -> String {
+		(rect.row..rect.row + rect.height)
+			.map(|row| {
+				let cells = self.row(row).unwrap_or_default();
+				cells.iter().skip(rect.col).take(rect.width).map(|cell| cell.ch).collect::<String>()
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+
+	/// Return the effective background color of every cell on `row`, or an empty vector if it is out of range.
+	///
+	/// Hover and selection highlights that only change the background show
+	/// up here even though the text and foreground stay the same.
+	///
+	/// ```
+	/// use kitty_test_harness::{Palette, ScreenGrid};
+	///
+	/// let grid = ScreenGrid::parse("a \x1b[48;2;40;40;80mhover\x1b[0m \x1b[7msel\x1b[0m");
+	/// let palette = Palette::kitty_default();
+	/// let bg = grid.row_backgrounds(0, &palette);
+	/// assert_eq!(bg[0], palette.background);
+	/// assert_eq!(bg[2], (40, 40, 80));
+	/// assert_eq!(bg[8], palette.foreground);
+	/// ```
+	pub fn row_backgrounds(&self, row: usize, palette: &Palette) -> Vec<Rgb> {
+		self.row(row).unwrap_or_default().iter().map(|cell| cell.effective_bg(palette)).collect()
+	}
+
+	/// Return the effective background color of every cell, row by row.
+	pub fn backgrounds(&self, palette: &Palette) -> Vec<Vec<Rgb>> {
+		(0..self.height()).map(|row| self.row_backgrounds(row, palette)).collect()
+	}
+
+	/// Return the position of the first occurrence of `needle`, scanning rows top to bottom.
+	pub fn find(&self, needle: &str) -> Option<(usize, usize)> {
+		self.rows.iter().enumerate().find_map(|(row, cells)| {
+			let text: Vec<char> = cells.iter().map(|cell| cell.ch).collect();
+			let needle: Vec<char> = needle.chars().collect();
+			if needle.is_empty() || needle.len() > text.len() {
+				return None;
+			}
+			text.windows(needle.len()).position(|window| window == needle.as_slice()).map(|col| (row, col))
+		})
+	}
+}
+
+/// An OSC 8 hyperlink found in a raw capture.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hyperlink {
+	/// Visible link text.
+	pub text: String,
+	/// Link target.
+	pub uri: String,
+	/// 0-based row of the link.
+	pub row: usize,
+	/// 0-based columns covered by the link text.
+	pub col_range: std::ops::Range<usize>,
+}
+
+/// Extract OSC 8 hyperlinks from a raw `get-text --ansi` capture.
+///
+/// A link still open at the end of a line is reported once per row. Other
+/// escape sequences are skipped when counting display columns.
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::utils::screen::extract_hyperlinks;
+///
+/// let raw = "see \x1b]8;;file:///tmp/a.rs\x1b\\a.rs\x1b]8;;\x1b\\ now";
+/// let links = extract_hyperlinks(raw);
+/// assert_eq!(links[0].text, "a.rs");
+/// assert_eq!(links[0].uri, "file:///tmp/a.rs");
+/// assert_eq!((links[0].row, links[0].col_range.clone()), (0, 4..8));
+/// ```
+pub fn extract_hyperlinks(raw: &str) -> Vec<Hyperlink> {
+	let mut links = Vec::new();
+	let mut open_uri: Option<String> = None;
+
+	for (row, line) in raw.split('\n').enumerate() {
+		let chars: Vec<char> = line.chars().collect();
+		let mut col = 0;
+		let mut current: Option<Hyperlink> = open_uri.as_ref().map(|uri| Hyperlink {
+			text: String::new(),
+			uri: uri.clone(),
+			row,
+			col_range: 0..0,
+		});
+		let mut i = 0;
+
+		while i < chars.len() {
+			if chars[i] != '\x1b' {
+				let width = display_width(chars[i].encode_utf8(&mut [0; 4]));
+				if let Some(link) = current.as_mut() {
+					link.text.push(chars[i]);
+					link.col_range.end = col + width;
+				}
+				col += width;
+				i += 1;
+				continue;
+			}
+
+			match chars.get(i + 1) {
+				Some(']') => {
+					let (body, next) = read_osc(&chars, i + 2);
+					i = next;
+					let Some(rest) = body.strip_prefix("8;") else {
+						continue;
+					};
+					let uri = rest.split_once(';').map_or("", |(_params, uri)| uri);
+					if let Some(link) = current.take().filter(|link| !link.text.is_empty()) {
+						links.push(link);
+					}
+					if uri.is_empty() {
+						open_uri = None;
+					} else {
+						open_uri = Some(uri.to_string());
+						current = Some(Hyperlink {
+							text: String::new(),
+							uri: uri.to_string(),
+							row,
+							col_range: col..col,
+						});
+					}
+				}
+				Some('[') => {
+					i += 2;
+					while i < chars.len() && !('\x40'..='\x7e').contains(&chars[i]) {
+						i += 1;
+					}
+					i += 1;
+				}
+				_ => i += 2,
+			}
+		}
+
+		if let Some(link) = current.filter(|link| !link.text.is_empty()) {
+			links.push(link);
+		}
+	}
+
+	links
+}
+
+/// Read an OSC body starting at `start`, returning it and the index after its terminator (BEL or ST).
+fn read_osc(chars: &[char], start: usize) -> (String, usize) {
+	let mut body = String::new();
+	let mut i = start;
+	while i < chars.len() {
+		match chars[i] {
+			'\x07' => return (body, i + 1),
+			'\x1b' if chars.get(i + 1) == Some(&'\\') => return (body, i + 2),
+			ch => body.push(ch),
+		}
+		i += 1;
+	}
+	(body, i)
+}
+
+/// An image placement made with the kitty graphics protocol.
+///
+/// `get-text` only returns text, so placements are recovered from the raw
+/// byte stream the program wrote (see [`parse_graphics_placements`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GraphicsPlacement {
+	/// Image id (`i=`), if the program assigned one.
+	pub image_id: Option<u32>,
+	/// Image number (`I=`), if the program used one instead of an id.
+	pub image_number: Option<u32>,
+	/// Placement id (`p=`), if given.
+	pub placement_id: Option<u32>,
+	/// 0-based row of the top-left cell.
+	pub row: usize,
+	/// 0-based column of the top-left cell.
+	pub col: usize,
+	/// Number of columns the image is scaled to (`c=`), if given.
+	pub columns: Option<u32>,
+	/// Number of rows the image is scaled to (`r=`), if given.
+	pub rows: Option<u32>,
+	/// Stacking order relative to text (`z=`, default 0).
+	pub z_index: i32,
+}
+
+impl GraphicsPlacement {
+	/// Return the cells covered by the placement, if its size in cells is known.
+	pub fn rect(&self) -> Option<Rect> {
+		Some(Rect::new(self.row, self.col, self.columns? as usize, self.rows? as usize))
+	}
+}
+
+/// Replay a raw terminal output stream and return the image placements still displayed at its end.
+///
+/// `stream` is what the program wrote to the terminal (for example recorded
+/// with `script -qfc <command> out.log`), and `cols` is the terminal width
+/// used for line wrapping. The cursor is tracked through printable text,
+/// CR/LF/BS, absolute and relative cursor movement, and DECSC/DECRC, which
+/// is enough to locate placements emitted by typical TUIs. Deletions by id,
+/// number, or of all placements are honoured; positional deletions are not.
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::utils::screen::{Rect, parse_graphics_placements};
+///
+/// let stream = b"\x1b[4;6H\x1b_Ga=T,f=100,i=7,c=36,r=10;AAAA\x1b\\";
+/// let placements = parse_graphics_placements(stream, 80);
+/// assert_eq!(placements[0].image_id, Some(7));
+/// assert_eq!(placements[0].rect(), Some(Rect::new(3, 5, 36, 10)));
+/// ```
+pub fn parse_graphics_placements(stream: &[u8], cols: usize) -> Vec<GraphicsPlacement> {
+	let cols = cols.max(1);
+	let mut placements: Vec<GraphicsPlacement> = Vec::new();
+	let (mut row, mut col) = (0usize, 0usize);
+	let mut saved = (0usize, 0usize);
+
+	let print = |row: &mut usize, col: &mut usize| {
+		if *col >= cols {
+			*col = 0;
+			*row += 1;
+		}
+		*col += 1;
+	};
+
+	Parser::new().parse(stream, |action| match action {
+		Action::Print(_) => print(&mut row, &mut col),
+		Action::PrintString(text) => text.chars().for_each(|_| print(&mut row, &mut col)),
+		Action::Control(ControlCode::LineFeed | ControlCode::VerticalTab | ControlCode::FormFeed) => row += 1,
+		Action::Control(ControlCode::CarriageReturn) => col = 0,
+		Action::Control(ControlCode::Backspace) => col = col.saturating_sub(1),
+		Action::Control(ControlCode::HorizontalTab) => col = ((col / 8 + 1) * 8).min(cols - 1),
+		Action::Esc(Esc::Code(EscCode::DecSaveCursorPosition)) => saved = (row, col),
+		Action::Esc(Esc::Code(EscCode::DecRestoreCursorPosition)) => (row, col) = saved,
+		Action::CSI(CSI::Cursor(cursor)) => match cursor {
+			CsiCursor::Position { line, col: c } | CsiCursor::CharacterAndLinePosition { line, col: c } => {
+				row = line.as_zero_based() as usize;
+				col = c.as_zero_based() as usize;
+			}
+			CsiCursor::CharacterAbsolute(c) | CsiCursor::CharacterPositionAbsolute(c) => col = c.as_zero_based() as usize,
+			CsiCursor::LinePositionAbsolute(line) => row = (line as usize).saturating_sub(1),
+			CsiCursor::Up(n) | CsiCursor::LinePositionBackward(n) => row = row.saturating_sub(n as usize),
+			CsiCursor::Down(n) | CsiCursor::LinePositionForward(n) => row += n as usize,
+			CsiCursor::Left(n) | CsiCursor::CharacterPositionBackward(n) => col = col.saturating_sub(n as usize),
+			CsiCursor::Right(n) | CsiCursor::CharacterPositionForward(n) => col = (col + n as usize).min(cols - 1),
+			CsiCursor::NextLine(n) => (row, col) = (row + n as usize, 0),
+			CsiCursor::PrecedingLine(n) => (row, col) = (row.saturating_sub(n as usize), 0),
+			CsiCursor::SaveCursor => saved = (row, col),
+			CsiCursor::RestoreCursor => (row, col) = saved,
+			_ => {}
+		},
+		Action::KittyImage(image) => {
+			let (image_id, image_number, placement) = match *image {
+				KittyImage::TransmitDataAndDisplay { transmit, placement, .. } => (transmit.image_id, transmit.image_number, placement),
+				KittyImage::Display {
+					image_id,
+					image_number,
+					placement,
+					..
+				} => (image_id, image_number, placement),
+				KittyImage::Delete { what, .. } => {
+					match what {
+						KittyImageDelete::All { .. } => placements.clear(),
+						KittyImageDelete::ByImageId { image_id, placement_id, .. } => {
+							placements.retain(|p| p.image_id != Some(image_id) || placement_id.is_some_and(|id| p.placement_id != Some(id)))
+						}
+						KittyImageDelete::ByImageNumber {
+							image_number, placement_id, ..
+						} => placements.retain(|p| p.image_number != Some(image_number) || placement_id.is_some_and(|id| p.placement_id != Some(id))),
+						_ => {}
+					}
+					return;
+				}
+				_ => return,
+			};
+
+			let placed = GraphicsPlacement {
+				image_id,
+				image_number,
+				placement_id: placement.placement_id,
+				row,
+				col,
+				columns: placement.columns,
+				rows: placement.rows,
+				z_index: placement.z_index.unwrap_or(0),
+			};
+			// A placement with the same image and placement id replaces the previous one.
+			if placed.placement_id.is_some() {
+				placements.retain(|p| (p.image_id, p.image_number, p.placement_id) != (placed.image_id, placed.image_number, placed.placement_id));
+			}
+			placements.push(placed);
+
+			if !placement.do_not_move_cursor
+				&& let (Some(width), Some(height)) = (placement.columns, placement.rows)
+			{
+				row += (height as usize).saturating_sub(1);
+				col = (col + width as usize).min(cols - 1);
+			}
+		}
+		_ => {}
+	});
+
+	placements
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 
+	#[test]
+	fn test_screen_grid_cells_and_text() {
+		let grid = ScreenGrid::parse("ab\n\x1b[1;31mxy\x1b[0mz\n");
+		assert_eq!(grid.height(), 3);
+		assert_eq!(grid.row_text(1), "xyz");
+		assert_eq!(grid.cell(1, 1).unwrap().fg, ColorSpec::Basic(1));
+		assert!(grid.cell(1, 1).unwrap().attrs.bold);
+		assert!(!grid.cell(1, 2).unwrap().attrs.bold);
+		assert_eq!(grid.cell(0, 5), None);
+		assert_eq!(grid.find("yz"), Some((1, 1)));
+		assert_eq!(grid.region_text(Rect::new(0, 1, 2, 2)), "b\nyz");
+	}
+
+	#[test]
+	fn test_graphics_placements_track_cursor_and_deletes() {
+		let stream = b"hello\r\n\x1b_Ga=T,f=100,i=1,c=4,r=2,z=-1;AAAA\x1b\\\x1b[10;20H\x1b_Ga=p,i=2,p=5,C=1\x1b\\";
+		let placements = parse_graphics_placements(stream, 80);
+		assert_eq!(placements.len(), 2);
+		assert_eq!(placements[0].rect(), Some(Rect::new(1, 0, 4, 2)));
+		assert_eq!(placements[0].z_index, -1);
+		assert_eq!((placements[1].row, placements[1].col, placements[1].placement_id), (9, 19, Some(5)));
+		assert_eq!(placements[1].rect(), None);
+
+		let mut deleted = stream.to_vec();
+		deleted.extend_from_slice(b"\x1b_Ga=d,d=i,i=1\x1b\\");
+		let placements = parse_graphics_placements(&deleted, 80);
+		assert_eq!(placements.iter().map(|p| p.image_id).collect::<Vec<_>>(), vec![Some(2)]);
+	}
+
+	#[test]
+	fn test_extract_hyperlinks_across_lines_and_styles() {
+		let raw = "\x1b[1mx\x1b]8;id=1;https://a.example\x07li\x1b[31mnk\nwrap\x1b]8;;\x07 end";
+		let links = extract_hyperlinks(raw);
+		assert_eq!(
+			links,
+			vec![
+				Hyperlink {
+					text: "link".into(),
+					uri: "https://a.example".into(),
+					row: 0,
+					col_range: 1..5
+				},
+				Hyperlink {
+					text: "wrap".into(),
+					uri: "https://a.example".into(),
+					row: 1,
+					col_range: 0..4
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_attrs_at_text() {
+		let line = "\x1b[3;9mold\x1b[23m new\x1b[0m plain";
+		let attrs = attrs_at_text(line, "old").unwrap();
+		assert!(attrs.italic && attrs.strikethrough);
+		let attrs = attrs_at_text(line, "new").unwrap();
+		assert!(!attrs.italic && attrs.strikethrough);
+		assert_eq!(attrs_at_text(line, "plain"), Some(CellAttrs::default()));
+		assert_eq!(attrs_at_text(line, "missing"), None);
+	}
+
+	#[test]
+	fn test_find_text_position() {
+		let screen = "a\n🙂 e\u{301} x x";
+		assert_eq!(find_text_position(screen, "x"), Some((1, 5)));
+		assert_eq!(find_text_position(screen, "a"), Some((0, 0)));
+		assert_eq!(find_text_position(screen, ""), None);
+		assert_eq!(find_text_position(screen, "a\n"), None);
+	}
+
+	#[test]
+	fn test_backgrounds_track_sgr_state_across_lines() {
+		let palette = Palette::kitty_default();
+		let grid = ScreenGrid::parse("x\x1b[44my\nz\x1b[49mw\n\x1b[7;31mr");
+		let blue = palette.color(4);
+		assert_eq!(
+			grid.backgrounds(&palette),
+			vec![vec![palette.background, blue], vec![blue, palette.background], vec![palette.color(1)]]
+		);
+		assert!(grid.row_backgrounds(5, &palette).is_empty());
+	}
+
+	#[test]
+	fn test_extract_region_keeps_styles_from_earlier_rows() {
+		let raw = "\x1b[31mhead\nab日c \x1b[1md   \x1b[0m\nxy";
+		// Escapes past the right edge are dropped, rows past the capture are empty.
+		assert_eq!(extract_region(raw, Rect::new(1, 2, 4, 3)), "\x1b[31m日c\n\n");
+		assert_eq!(extract_region(raw, Rect::new(1, 6, 10, 1)), "\x1b[31m\x1b[1md\x1b[0m");
+	}
+
+	#[test]
+	fn test_find_all_positions_and_matches() {
+		let screen = "aaa\n日a";
+		assert_eq!(find_all_positions(screen, "aa"), vec![(0, 0)]);
+		assert_eq!(find_all_positions(screen, "a"), vec![(0, 0), (0, 1), (0, 2), (1, 2)]);
+		let matches = find_all_matches(screen, &Regex::new("a*").unwrap());
+		assert_eq!(matches, vec![(0, 0, "aaa".to_string()), (1, 2, "a".to_string())]);
+	}
+
+	#[test]
+	fn test_parse_cursor() {
+		assert_eq!(parse_cursor("text\n\x1b[1mbold\x1b[0m"), None);
+		assert_eq!(
+			parse_cursor("a\n\x1b[12;40H\x1b[?25l\x1b[2 q"),
+			Some(Cursor {
+				row: 11,
+				col: 39,
+				visible: false
+			})
+		);
+		assert_eq!(parse_cursor("\x1b[H"), Some(Cursor { row: 0, col: 0, visible: true }));
+	}
+
 	#[test]
 	fn test_find_vertical_separator() {
 		let screen = "left  │right\n\
@@ -406,6 +1303,16 @@ mod tests {
 		assert_eq!(rows, vec![0, 1]);
 	}
 
+	#[test]
+	fn test_separator_columns_use_display_width() {
+		let screen = "日本│x\ne\u{301}e\u{301}e\u{301}e\u{301}│y\n🙂🙂│z\nabcd│w\n────";
+		assert_eq!(find_separator_rows_at_col(screen, 4), vec![0, 1, 2, 3]);
+		assert_eq!(find_separator_cols_at_row("日─", 0), vec![2]);
+		assert_eq!(display_col("🙂 ok", "🙂 ".len()), 3);
+		let links = extract_hyperlinks("日 \x1b]8;;u\x1b\\本\x1b]8;;\x1b\\");
+		assert_eq!(links[0].col_range, 3..5);
+	}
+
 	#[test]
 	fn test_extract_colors_semicolon() {
 		let raw = "text\x1b[38;2;255;128;64mcolored\x1b[m";
@@ -440,6 +1347,34 @@ mod tests {
 		assert_eq!(color.palette_index, Some(196));
 	}
 
+	#[test]
+	fn test_parse_basic_colors() {
+		let color = AnsiColor::parse_seq("\x1b[0;44m").unwrap();
+		assert!(!color.is_foreground);
+		assert_eq!((color.color, color.rgb, color.palette_index), (ColorSpec::Basic(4), None, None));
+		assert_eq!(AnsiColor::parse_seq("\x1b[39m").unwrap().color, ColorSpec::Default);
+		assert_eq!(AnsiColor::parse_seq("\x1b[48:5:17m").unwrap().color, ColorSpec::Palette(17));
+
+		let underline = AnsiColor::parse_seq("\x1b[58;5;3m").unwrap();
+		assert!(underline.is_underline && !underline.is_foreground);
+		assert_eq!(underline.palette_index, Some(3));
+		assert!(AnsiColor::parse_seq("\x1b[59m").unwrap().is_underline);
+		assert!(!AnsiColor::parse_seq("\x1b[48;5;3m").unwrap().is_underline);
+
+		let colors = extract_row_colors("\x1b[31mred\x1b[39m \x1b[1;97mwhite\x1b[31mred", 0);
+		assert_eq!(colors, vec!["\x1b[31m", "\x1b[1;97m"]);
+	}
+
+	#[test]
+	fn test_rect_contains() {
+		let rect = Rect::new(1, 2, 3, 2);
+		assert!(rect.contains(1, 2));
+		assert!(rect.contains(2, 4));
+		assert!(!rect.contains(3, 2));
+		assert!(!rect.contains(1, 5));
+		assert!(!rect.contains(0, 2));
+	}
+
 	#[test]
 	fn test_parse_kitty_format() {
 		let seq = "\x1b[38:2:100:150:200m";
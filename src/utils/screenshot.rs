@@ -0,0 +1,198 @@
+//! Pixel screenshots of the kitty window and image snapshot assertions
+//! (requires the `screenshot` feature).
+//!
+//! kitty has no remote-control command for screenshots, so the capture goes
+//! through the display server. The command comes from
+//! [`SCREENSHOT_COMMAND_ENV`] when set, with `{window}` replaced by the native
+//! window id and `{path}` by the output file. Otherwise the harness uses
+//! `import -window {window} {path}` (ImageMagick) on X11 and `grim {path}` on
+//! Wayland, which captures the whole output because Wayland does not expose
+//! window geometry to clients.
+//!
+//! [`assert_screenshot_matches`] compares a fresh screenshot against a
+//! reference PNG with [`compare_images`]. A missing reference fails like a
+//! mismatch; [`UPDATE_SCREENSHOTS_ENV`] creates or rewrites references.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::KittyHarness;
+use crate::utils::error::HarnessError;
+use crate::utils::render::{Image, ImageDiff, compare_images};
+
+/// Environment variable overriding the screenshot command (`{window}` and `{path}` are substituted).
+pub const SCREENSHOT_COMMAND_ENV: &str = "KITTY_TEST_SCREENSHOT_CMD";
+
+/// Set to `1` to overwrite reference screenshots instead of comparing against them.
+pub const UPDATE_SCREENSHOTS_ENV: &str = "KITTY_TEST_UPDATE_SCREENSHOTS";
+
+/// Capture the OS window of `kitty` as a PNG at `path`.
+pub(crate) fn capture(kitty: &KittyHarness, path: &Path) -> Result<(), HarnessError> {
+	let state = kitty.try_state()?;
+	let window = state
+		.os_window_of(kitty.window_id().0)
+		.and_then(|os_window| os_window.platform_window_id)
+		.map(|id| id.to_string())
+		.unwrap_or_default();
+	let template = std::env::var(SCREENSHOT_COMMAND_ENV).unwrap_or_else(|_| {
+		if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+			"grim {path}".to_string()
+		} else {
+			"import -window {window} {path}".to_string()
+		}
+	});
+	if template.contains("{window}") && window.is_empty() {
+		return Err(HarnessError::Parse {
+			command: "kitty @ ls".into(),
+			message: "no platform_window_id for the harness window".into(),
+		});
+	}
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent).map_err(|err| HarnessError::io(format!("create {}", parent.display()), err))?;
+	}
+	let script = template
+		.replace("{window}", &window)
+		.replace("{path}", &crate::utils::launch::shell_single_quote(&path.to_string_lossy()));
+	let output = Command::new("sh")
+		.args(["-c", &script])
+		.output()
+		.map_err(|err| HarnessError::spawn(&script, err))?;
+	if !output.status.success() {
+		return Err(HarnessError::failed(script, &output));
+	}
+	Ok(())
+}
+
+/// Read an 8-bit RGB or RGBA PNG.
+pub fn read_png(path: &Path) -> Result<Image, HarnessError> {
+	let context = || format!("read png {}", path.display());
+	let file = File::open(path).map_err(|err| HarnessError::io(context(), err))?;
+	let mut decoder = png::Decoder::new(file);
+	decoder.set_transformations(png::Transformations::normalize_to_color8());
+	let mut reader = decoder.read_info().map_err(|err| HarnessError::io(context(), std::io::Error::other(err)))?;
+	let mut buf = vec![0; reader.output_buffer_size()];
+	let info = reader
+		.next_frame(&mut buf)
+		.map_err(|err| HarnessError::io(context(), std::io::Error::other(err)))?;
+	let channels = info.color_type.samples();
+	let pixels = buf[..info.buffer_size()]
+		.chunks(channels)
+		.map(|px| match channels {
+			1 | 2 => (px[0], px[0], px[0]),
+			_ => (px[0], px[1], px[2]),
+		})
+		.collect();
+	Ok(Image {
+		width: info.width as usize,
+		height: info.height as usize,
+		pixels,
+	})
+}
+
+/// Write `image` as an RGB PNG.
+pub fn write_png(image: &Image, path: &Path) -> Result<(), HarnessError> {
+	let context = || format!("write png {}", path.display());
+	if let Some(parent) = path.parent() {
+		std::fs::create_dir_all(parent).map_err(|err| HarnessError::io(format!("create {}", parent.display()), err))?;
+	}
+	let file = File::create(path).map_err(|err| HarnessError::io(context(), err))?;
+	let mut encoder = png::Encoder::new(BufWriter::new(file), image.width as u32, image.height as u32);
+	encoder.set_color(png::ColorType::Rgb);
+	encoder.set_depth(png::BitDepth::Eight);
+	let mut writer = encoder.write_header().map_err(|err| HarnessError::io(context(), std::io::Error::other(err)))?;
+	writer
+		.write_image_data(&image.to_rgb_bytes())
+		.map_err(|err| HarnessError::io(context(), std::io::Error::other(err)))
+}
+
+/// Tolerances for [`assert_screenshot_matches`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageTolerance {
+	/// Per-pixel perceptual threshold (0.0-1.0).
+	pub threshold: f64,
+	/// Fraction of pixels allowed to exceed the threshold.
+	pub max_ratio: f64,
+}
+
+impl Default for ImageTolerance {
+	/// Ignore antialiasing noise (`0.1`) and allow 0.1% of pixels to differ.
+	fn default() -> Self {
+		Self {
+			threshold: 0.1,
+			max_ratio: 0.001,
+		}
+	}
+}
+
+/// Take a screenshot and compare it against the reference PNG at `reference`.
+///
+/// With [`UPDATE_SCREENSHOTS_ENV`]=1 the screenshot is written as the new
+/// reference. Otherwise a missing reference panics after writing
+/// `<reference>.actual.png`, and on mismatch `<reference>.actual.png` and
+/// `<reference>.diff.png` (differences in red) are written next to the
+/// reference and the function panics.
+pub fn assert_screenshot_matches(kitty: &KittyHarness, reference: &Path, tolerance: ImageTolerance) -> ImageDiff {
+	let actual_path = sibling(reference, "actual.png");
+	kitty.screenshot_png(&actual_path);
+	let actual = read_png(&actual_path).unwrap_or_else(|err| panic!("{err}"));
+
+	let update = std::env::var(UPDATE_SCREENSHOTS_ENV).is_ok_and(|value| value == "1");
+	if update {
+		write_png(&actual, reference).unwrap_or_else(|err| panic!("{err}"));
+		let _ = std::fs::remove_file(&actual_path);
+		eprintln!("wrote reference screenshot {}", reference.display());
+		return compare_images(&actual, &actual, tolerance.threshold);
+	}
+	if !reference.exists() {
+		panic!(
+			"reference screenshot {} does not exist; see {} and rerun with {UPDATE_SCREENSHOTS_ENV}=1 to create it",
+			reference.display(),
+			actual_path.display()
+		);
+	}
+
+	let expected = read_png(reference).unwrap_or_else(|err| panic!("{err}"));
+	let diff = compare_images(&actual, &expected, tolerance.threshold);
+	if diff.size_mismatch || diff.ratio() > tolerance.max_ratio {
+		let diff_path = sibling(reference, "diff.png");
+		let _ = write_png(&diff.highlight, &diff_path);
+		panic!(
+			"screenshot differs from {}: {} of {} pixels (max delta {:.3}{}); see {} and {}",
+			reference.display(),
+			diff.differing_pixels,
+			diff.total_pixels,
+			diff.max_delta,
+			if diff.size_mismatch { ", size mismatch" } else { "" },
+			actual_path.display(),
+			diff_path.display()
+		);
+	}
+	let _ = std::fs::remove_file(&actual_path);
+	diff
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+	let mut name = path.file_stem().unwrap_or_default().to_os_string();
+	name.push(".");
+	name.push(suffix);
+	path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn png_round_trip_preserves_pixels() {
+		let mut image = Image::new(3, 2, (10, 20, 30));
+		image.pixels[4] = (255, 0, 128);
+		let path = std::env::temp_dir().join(format!("kitty-test-screenshot-{}.png", std::process::id()));
+		write_png(&image, &path).unwrap();
+		assert_eq!(read_png(&path).unwrap(), image);
+		let _ = std::fs::remove_file(&path);
+
+		assert_eq!(sibling(Path::new("snaps/menu.png"), "diff.png"), Path::new("snaps/menu.diff.png"));
+	}
+}
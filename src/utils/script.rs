@@ -0,0 +1,388 @@
+//! Composable input scripts.
+//!
+//! An [`InputScript`] is a reusable value describing a sequence of input
+//! steps (keys, text, mouse events, pauses, and waits). The same script can
+//! be executed against any harness and converted to and from the replay
+//! recording format, so hand-written interactions and recorded sessions go
+//! through one send path.
+//!
+//! Scripts can also mark where the screen should be snapshotted; the
+//! [`steps`] constructors and [`InputScript::run_snapshots`] back the
+//! `cmd = ..., steps = [...]` form of [`crate::kitty_snapshot_test!`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use kitty_test_harness::utils::script::InputScript;
+//! use kitty_test_harness::{MouseButton, with_kitty_capture};
+//! use termwiz::input::KeyCode;
+//!
+//! let open_menu = InputScript::new()
+//!     .key(KeyCode::Function(2))
+//!     .wait_for("Menu", Duration::from_secs(2))
+//!     .click(MouseButton::Left, 4, 1)
+//!     .text("query")
+//!     .pause(Duration::from_millis(50));
+//!
+//! with_kitty_capture(&kitty_test_harness::manifest_dir(), "my-app", |kitty| {
+//!     open_menu.run(kitty);
+//! });
+//! ```
+
+use std::time::Duration;
+
+use crate::utils::keys::{format_key, parse_key};
+use crate::utils::mouse::{MouseButton, ScrollDirection};
+use crate::utils::replay::{ReplayEvent, ReplayTiming, format_recording, parse_recording, send_event};
+use crate::utils::wait::try_wait_for_clean_contains;
+use crate::{KeyPress, KittyHarness};
+
+/// A single step of an [`InputScript`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScriptStep {
+	/// Encode and send key presses.
+	Keys(Vec<KeyPress>),
+	/// Send text verbatim.
+	Text(String),
+	/// Send a mouse, paste, resize, or focus event.
+	Event(ReplayEvent),
+	/// Sleep for the given duration.
+	Pause(Duration),
+	/// Wait until the clean screen text contains `needle`, panicking after `timeout`.
+	WaitFor {
+		/// Text that must appear on screen.
+		needle: String,
+		/// Maximum time to wait.
+		timeout: Duration,
+	},
+	/// Capture the screen for a snapshot; see [`InputScript::run_snapshots`].
+	Snapshot,
+}
+
+/// Timeout of waits created by [`steps::wait_contains`].
+pub const STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A reusable, composable sequence of input steps.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputScript {
+	steps: Vec<ScriptStep>,
+}
+
+impl InputScript {
+	/// Create an empty script.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Append an arbitrary step.
+	pub fn step(mut self, step: ScriptStep) -> Self {
+		self.steps.push(step);
+		self
+	}
+
+	/// Append a single key press.
+	pub fn key(self, key: impl Into<KeyPress>) -> Self {
+		self.step(ScriptStep::Keys(vec![key.into()]))
+	}
+
+	/// Append several key presses.
+	pub fn keys<K: Into<KeyPress>>(self, keys: impl IntoIterator<Item = K>) -> Self {
+		self.step(ScriptStep::Keys(keys.into_iter().map(Into::into).collect()))
+	}
+
+	/// Append text to send verbatim.
+	pub fn text(self, text: impl Into<String>) -> Self {
+		self.step(ScriptStep::Text(text.into()))
+	}
+
+	/// Append a bracketed paste.
+	pub fn paste(self, content: impl Into<String>) -> Self {
+		self.step(ScriptStep::Event(ReplayEvent::Paste(content.into())))
+	}
+
+	/// Append a mouse click (press followed by release).
+	pub fn click(self, button: MouseButton, col: u16, row: u16) -> Self {
+		self.step(ScriptStep::Event(ReplayEvent::MousePress { button, col, row }))
+			.step(ScriptStep::Event(ReplayEvent::MouseRelease { col, row }))
+	}
+
+	/// Append a single wheel tick.
+	pub fn scroll(self, direction: ScrollDirection, col: u16, row: u16) -> Self {
+		self.step(ScriptStep::Event(ReplayEvent::MouseScroll { direction, col, row }))
+	}
+
+	/// Append a window resize.
+	pub fn resize(self, cols: u16, rows: u16) -> Self {
+		self.step(ScriptStep::Event(ReplayEvent::Resize { cols, rows }))
+	}
+
+	/// Append a pause.
+	pub fn pause(self, duration: Duration) -> Self {
+		self.step(ScriptStep::Pause(duration))
+	}
+
+	/// Append a wait for `needle` to appear in the clean screen text.
+	pub fn wait_for(self, needle: impl Into<String>, timeout: Duration) -> Self {
+		self.step(ScriptStep::WaitFor {
+			needle: needle.into(),
+			timeout,
+		})
+	}
+
+	/// Append a snapshot of the screen.
+	pub fn snapshot(self) -> Self {
+		self.step(ScriptStep::Snapshot)
+	}
+
+	/// Append all steps of another script.
+	pub fn then(mut self, other: &InputScript) -> Self {
+		self.steps.extend(other.steps.iter().cloned());
+		self
+	}
+
+	/// Return the steps of this script.
+	pub fn steps(&self) -> &[ScriptStep] {
+		&self.steps
+	}
+
+	/// Execute the script against a harness.
+	///
+	/// # Panics
+	///
+	/// Panics if a [`ScriptStep::WaitFor`] step times out; the message
+	/// includes the last screen.
+	pub fn run(&self, kitty: &KittyHarness) {
+		self.run_snapshots(kitty);
+	}
+
+	/// Execute the script and return the [`KittyHarness::snapshot_text`] taken at each [`ScriptStep::Snapshot`].
+	///
+	/// # Panics
+	///
+	/// Panics if a [`ScriptStep::WaitFor`] step times out.
+	pub fn run_snapshots(&self, kitty: &KittyHarness) -> Vec<String> {
+		let timing = ReplayTiming::batched(Duration::ZERO);
+		let mut snapshots = Vec::new();
+		for step in &self.steps {
+			match step {
+				ScriptStep::Keys(keys) => crate::send_keys(kitty, keys),
+				ScriptStep::Text(text) => kitty.send_text(text),
+				ScriptStep::Event(event) => send_event(kitty, event, &timing),
+				ScriptStep::Pause(duration) => std::thread::sleep(*duration),
+				ScriptStep::WaitFor { needle, timeout } => {
+					if let Err(err) = try_wait_for_clean_contains(kitty, *timeout, needle) {
+						panic!("input script step failed: {err:#}");
+					}
+				}
+				ScriptStep::Snapshot => snapshots.push(kitty.snapshot_text()),
+			}
+		}
+		snapshots
+	}
+
+	/// Build a script from parsed replay events.
+	///
+	/// Key names that cannot be parsed are skipped, matching [`crate::replay`].
+	pub fn from_events(events: &[ReplayEvent]) -> Self {
+		let steps = events
+			.iter()
+			.map(|event| match event {
+				ReplayEvent::KeyBatch(names) => ScriptStep::Keys(names.iter().filter_map(|name| parse_key(name)).collect()),
+				other => ScriptStep::Event(other.clone()),
+			})
+			.collect();
+		Self { steps }
+	}
+
+	/// Parse a script from the replay recording format.
+	pub fn from_recording(input: &str) -> Self {
+		Self::from_events(&parse_recording(input))
+	}
+
+	/// Convert the script into replay events.
+	///
+	/// Text is expanded into one key per character. Pauses become batch
+	/// boundaries and waits and snapshots are dropped, since the recording format cannot
+	/// express them.
+	pub fn to_events(&self) -> Vec<ReplayEvent> {
+		let mut events = Vec::new();
+		let mut batch: Vec<String> = Vec::new();
+
+		for step in &self.steps {
+			match step {
+				ScriptStep::Keys(keys) => batch.extend(keys.iter().filter_map(|key| format_key(*key))),
+				ScriptStep::Text(text) => batch.extend(text.chars().filter_map(|ch| format_key(KeyPress::from(termwiz::input::KeyCode::Char(ch))))),
+				ScriptStep::Event(event) => {
+					if !batch.is_empty() {
+						events.push(ReplayEvent::KeyBatch(std::mem::take(&mut batch)));
+					}
+					events.push(event.clone());
+				}
+				ScriptStep::Pause(_) | ScriptStep::WaitFor { .. } | ScriptStep::Snapshot => {
+					if !batch.is_empty() {
+						events.push(ReplayEvent::KeyBatch(std::mem::take(&mut batch)));
+					}
+				}
+			}
+		}
+
+		if !batch.is_empty() {
+			events.push(ReplayEvent::KeyBatch(batch));
+		}
+		events
+	}
+
+	/// Convert the script into the replay recording format.
+	pub fn to_recording(&self) -> String {
+		format_recording(&self.to_events())
+	}
+}
+
+impl FromIterator<ScriptStep> for InputScript {
+	fn from_iter<I: IntoIterator<Item = ScriptStep>>(iter: I) -> Self {
+		Self {
+			steps: iter.into_iter().collect(),
+		}
+	}
+}
+
+/// Step constructors for the `steps = [...]` form of [`crate::kitty_snapshot_test!`].
+pub mod steps {
+	use std::time::Duration;
+
+	use super::{STEP_TIMEOUT, ScriptStep};
+	use crate::utils::keys::parse_key;
+
+	/// Send keys given as whitespace-separated names in the replay format (`"F2"`, `"C-x j enter"`).
+	///
+	/// # Panics
+	///
+	/// Panics if a name is not understood by [`parse_key`].
+	pub fn keys(names: &str) -> ScriptStep {
+		ScriptStep::Keys(
+			names
+				.split_whitespace()
+				.map(|name| parse_key(name).unwrap_or_else(|| panic!("unknown key name {name:?}")))
+				.collect(),
+		)
+	}
+
+	/// Send text verbatim.
+	pub fn text(text: &str) -> ScriptStep {
+		ScriptStep::Text(text.to_string())
+	}
+
+	/// Wait up to [`STEP_TIMEOUT`] for `needle` to appear in the clean screen text.
+	pub fn wait_contains(needle: &str) -> ScriptStep {
+		ScriptStep::WaitFor {
+			needle: needle.to_string(),
+			timeout: STEP_TIMEOUT,
+		}
+	}
+
+	/// Sleep for `millis` milliseconds.
+	pub fn pause_ms(millis: u64) -> ScriptStep {
+		ScriptStep::Pause(Duration::from_millis(millis))
+	}
+
+	/// Snapshot the screen.
+	pub fn snapshot() -> ScriptStep {
+		ScriptStep::Snapshot
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use termwiz::input::{KeyCode, Modifiers};
+
+	use super::*;
+
+	#[test]
+	fn builder_collects_steps_in_order() {
+		let script = InputScript::new().key(KeyCode::Enter).text("hi").pause(Duration::from_millis(5));
+		assert_eq!(
+			script.steps(),
+			&[
+				ScriptStep::Keys(vec![KeyPress::from(KeyCode::Enter)]),
+				ScriptStep::Text("hi".into()),
+				ScriptStep::Pause(Duration::from_millis(5)),
+			]
+		);
+	}
+
+	#[test]
+	fn to_recording_expands_text_and_splits_batches() {
+		let script = InputScript::new()
+			.text("a b")
+			.pause(Duration::from_millis(10))
+			.key((KeyCode::Char('x'), Modifiers::CTRL))
+			.click(MouseButton::Left, 3, 4);
+		assert_eq!(script.to_recording(), "a\nspace\nb\n\nC-x\nmouse:press left 3,4\nmouse:release 3,4\n");
+	}
+
+	#[test]
+	fn hash_survives_the_recording_format() {
+		let script = InputScript::new().text("a # b").key((KeyCode::Char('#'), Modifiers::CTRL));
+		let recording = script.to_recording();
+		assert_eq!(recording, "a\nspace\nhash\nspace\nb\nC-hash\n");
+		let mut keys: Vec<KeyPress> = "a # b".chars().map(|ch| KeyPress::from(KeyCode::Char(ch))).collect();
+		keys.push(KeyPress::from((KeyCode::Char('#'), Modifiers::CTRL)));
+		assert_eq!(InputScript::from_recording(&recording).steps(), &[ScriptStep::Keys(keys)]);
+	}
+
+	#[test]
+	fn from_recording_parses_keys_and_events() {
+		let script = InputScript::from_recording("j\nC-x\nfocus:in\n");
+		assert_eq!(
+			script.steps(),
+			&[
+				ScriptStep::Keys(vec![KeyPress::from(KeyCode::Char('j')), KeyPress::from((KeyCode::Char('x'), Modifiers::CTRL))]),
+				ScriptStep::Event(ReplayEvent::FocusIn),
+			]
+		);
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn script_round_trips_through_json() {
+		let script = InputScript::new()
+			.key((KeyCode::Char('x'), Modifiers::CTRL))
+			.key(KeyCode::Char('f'))
+			.key((KeyCode::Char('s'), Modifiers::SUPER))
+			.click(MouseButton::Left, 3, 4)
+			.pause(Duration::from_millis(5))
+			.snapshot();
+		let json = serde_json::to_string(&script).unwrap();
+		assert!(json.contains(r#"{"Keys":["C-x"]},{"Keys":["f"]},{"Keys":["D-s"]}"#), "{json}");
+		assert_eq!(serde_json::from_str::<InputScript>(&json).unwrap(), script);
+		assert!(serde_json::to_string(&InputScript::new().key((KeyCode::Char('a'), Modifiers::LEADER))).is_err());
+	}
+
+	#[test]
+	fn step_constructors_build_a_script() {
+		use steps::*;
+
+		let script: InputScript = [keys("F2 C-x f F"), wait_contains("Menu"), snapshot()].into_iter().collect();
+		assert_eq!(
+			script.steps(),
+			&[
+				ScriptStep::Keys(vec![
+					KeyPress::from(KeyCode::Function(2)),
+					KeyPress::from((KeyCode::Char('x'), Modifiers::CTRL)),
+					KeyPress::from(KeyCode::Char('f')),
+					KeyPress::from(KeyCode::Char('F'))
+				]),
+				ScriptStep::WaitFor {
+					needle: "Menu".into(),
+					timeout: STEP_TIMEOUT,
+				},
+				ScriptStep::Snapshot,
+			]
+		);
+		assert_eq!(script.to_recording(), "F2\nC-x\nf\nF\n");
+	}
+}
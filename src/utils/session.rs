@@ -0,0 +1,94 @@
+//! Per-test resource tracking for harness sessions.
+//!
+//! Every harness launched in this process registers a [`SessionInfo`]
+//! describing the resources it created: the session name (also used as the
+//! kitty window class), the remote control socket, any test logs created
+//! through the harness, and its artifact directory. External tooling such as
+//! CI collectors or cleanup scripts can enumerate them with [`sessions`].
+//!
+//! Session names embed the process id and a per-process counter, so tests
+//! running in parallel (threads or separate test binaries) never share a
+//! socket, log, or artifact path.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Resources owned by a single harness session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+	/// Unique session name (`kitty-test-<pid>-<idx>`), used as the kitty window class.
+	pub name: String,
+	/// Remote control address (`unix:/path/to.sock` or `tcp:host:port`).
+	pub listen_on: String,
+	/// Path of the unix socket used for remote control, if `listen_on` is a unix socket.
+	pub socket_path: Option<PathBuf>,
+	/// Test logs created through the harness.
+	pub log_paths: Vec<PathBuf>,
+	/// Directory where artifacts for this session are written.
+	pub artifact_dir: PathBuf,
+	/// Generated isolated `kitty.conf`, if launch options supplied config fragments.
+	pub config_path: Option<PathBuf>,
+}
+
+static SESSIONS: Mutex<Vec<SessionInfo>> = Mutex::new(Vec::new());
+
+/// Root directory for harness artifacts.
+///
+/// Uses `KITTY_TEST_ARTIFACT_DIR` when set, otherwise `kitty-test-artifacts`
+/// under the system temp directory.
+pub fn artifact_root() -> PathBuf {
+	std::env::var_os("KITTY_TEST_ARTIFACT_DIR")
+		.map(PathBuf::from)
+		.unwrap_or_else(|| std::env::temp_dir().join("kitty-test-artifacts"))
+}
+
+/// Return every session registered in this process, in launch order.
+///
+/// Entries remain after their harness is dropped so that resources can be
+/// collected or cleaned up at the end of a run.
+pub fn sessions() -> Vec<SessionInfo> {
+	SESSIONS.lock().unwrap_or_else(|err| err.into_inner()).clone()
+}
+
+pub(crate) fn register(info: SessionInfo) {
+	SESSIONS.lock().unwrap_or_else(|err| err.into_inner()).push(info);
+}
+
+pub(crate) fn lookup(name: &str) -> Option<SessionInfo> {
+	SESSIONS
+		.lock()
+		.unwrap_or_else(|err| err.into_inner())
+		.iter()
+		.find(|info| info.name == name)
+		.cloned()
+}
+
+pub(crate) fn add_log(name: &str, path: PathBuf) {
+	let mut sessions = SESSIONS.lock().unwrap_or_else(|err| err.into_inner());
+	if let Some(info) = sessions.iter_mut().find(|info| info.name == name) {
+		info.log_paths.push(path);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn registered_sessions_are_listed_with_their_logs() {
+		let name = format!("kitty-test-session-unit-{}", std::process::id());
+		register(SessionInfo {
+			name: name.clone(),
+			listen_on: "unix:/tmp/unit.sock".into(),
+			socket_path: Some(PathBuf::from("/tmp/unit.sock")),
+			log_paths: Vec::new(),
+			artifact_dir: artifact_root().join(&name),
+			config_path: None,
+		});
+		add_log(&name, PathBuf::from("/tmp/unit.log"));
+
+		let info = lookup(&name).expect("session should be registered");
+		assert_eq!(info.log_paths, vec![PathBuf::from("/tmp/unit.log")]);
+		assert!(sessions().iter().any(|s| s.name == name));
+	}
+}
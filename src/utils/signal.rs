@@ -0,0 +1,57 @@
+//! Signals delivered to the program running in a kitty window.
+//!
+//! [`crate::KittyHarness::signal_child`] wraps `kitty @ signal-child`, which
+//! signals the foreground process group of the window's terminal. Unlike
+//! sending Ctrl+C as a key, this does not depend on the terminal line
+//! discipline or on how the application decodes keys.
+
+use std::fmt;
+
+/// A signal accepted by `kitty @ signal-child`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+	/// Interrupt (what Ctrl+C normally sends).
+	Int,
+	/// Polite termination request.
+	Term,
+	/// Hangup, as when the terminal closes.
+	Hup,
+	/// Quit (what Ctrl+\ normally sends).
+	Quit,
+	/// Forced termination.
+	Kill,
+	/// Terminal stop (what Ctrl+Z normally sends).
+	Tstp,
+	/// Continue a stopped process.
+	Cont,
+	/// Window size change.
+	Winch,
+	/// User-defined signal 1.
+	Usr1,
+	/// User-defined signal 2.
+	Usr2,
+}
+
+impl Signal {
+	/// The signal name as kitty expects it, e.g. `SIGINT`.
+	pub fn name(self) -> &'static str {
+		match self {
+			Signal::Int => "SIGINT",
+			Signal::Term => "SIGTERM",
+			Signal::Hup => "SIGHUP",
+			Signal::Quit => "SIGQUIT",
+			Signal::Kill => "SIGKILL",
+			Signal::Tstp => "SIGTSTP",
+			Signal::Cont => "SIGCONT",
+			Signal::Winch => "SIGWINCH",
+			Signal::Usr1 => "SIGUSR1",
+			Signal::Usr2 => "SIGUSR2",
+		}
+	}
+}
+
+impl fmt::Display for Signal {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
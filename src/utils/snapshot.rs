@@ -0,0 +1,149 @@
+//! Stable textual format of styled captures for snapshot tests.
+//!
+//! Raw captures snapshot escape sequences verbatim, which are hard to review
+//! and change whenever an application reorders equivalent SGR codes, while
+//! clean captures lose colors entirely. [`styled_snapshot`] renders a
+//! capture as its text with a marker line under every styled row: each
+//! distinct style gets a letter, and a legend at the end spells out what the
+//! letters mean. Cells in the default style are marked with a space.
+//!
+//! ```text
+//! 0 | Name  Size
+//!   | aaaa  aaaa
+//! 1 | notes 12K
+//! 2 | error 0B
+//!   | bbbbb
+//! --
+//! a: bold underline
+//! b: fg=red
+//! ```
+//!
+//! Letters are assigned in reading order, so the output only changes when
+//! the rendered styling does. Use it through the `styled` form of
+//! [`crate::kitty_snapshot_test!`] or [`crate::KittyHarness::styled_snapshot_text`].
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::utils::snapshot::styled_snapshot;
+//!
+//! let raw = "\x1b[1;4mName\x1b[0m\n\x1b[31merror\x1b[0m ok";
+//! assert_eq!(styled_snapshot(raw), "0 | Name\n  | aaaa\n1 | error ok\n  | bbbbb\n--\na: bold underline\nb: fg=red");
+//! ```
+
+use crate::utils::screen::{Cell, ScreenGrid, display_width};
+use crate::utils::style::{ColorSpec, UnderlineStyle};
+use crate::utils::theme::hex;
+
+/// Marker characters, assigned to styles in order of first appearance.
+const MARKERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// Render a raw ANSI capture in the styled snapshot format.
+pub fn styled_snapshot(raw: &str) -> String {
+	grid_snapshot(&ScreenGrid::parse(raw))
+}
+
+/// Render a [`ScreenGrid`] in the styled snapshot format.
+///
+/// Styles beyond the 62 available letters share the marker `?`; the legend
+/// still lists each of them.
+pub fn grid_snapshot(grid: &ScreenGrid) -> String {
+	let mut legend: Vec<String> = Vec::new();
+	let width = grid.height().saturating_sub(1).to_string().len();
+	let mut out = Vec::new();
+
+	for (row, cells) in grid.rows().iter().enumerate() {
+		let text: String = cells.iter().map(|cell| cell.ch).collect();
+		out.push(format!("{row:>width$} | {text}").trim_end().to_string());
+
+		let mut markers = String::new();
+		for cell in cells {
+			let marker = match describe(cell) {
+				None => ' ',
+				Some(style) => {
+					let idx = legend.iter().position(|known| *known == style).unwrap_or_else(|| {
+						legend.push(style);
+						legend.len() - 1
+					});
+					MARKERS.chars().nth(idx).unwrap_or('?')
+				}
+			};
+			let columns = display_width(cell.ch.encode_utf8(&mut [0; 4])).max(1);
+			markers.extend(std::iter::repeat_n(marker, columns));
+		}
+		let markers = markers.trim_end();
+		if !markers.is_empty() {
+			out.push(format!("{:width$} | {markers}", ""));
+		}
+	}
+
+	if !legend.is_empty() {
+		out.push("--".to_string());
+		for (idx, style) in legend.iter().enumerate() {
+			out.push(format!("{}: {style}", MARKERS.chars().nth(idx).unwrap_or('?')));
+		}
+	}
+	out.join("\n")
+}
+
+/// Describe the style of `cell`, or `None` for the default style.
+fn describe(cell: &Cell) -> Option<String> {
+	let attrs = cell.attrs;
+	let mut parts = Vec::new();
+	if cell.fg != ColorSpec::Default {
+		parts.push(format!("fg={}", color_name(cell.fg)));
+	}
+	if cell.bg != ColorSpec::Default {
+		parts.push(format!("bg={}", color_name(cell.bg)));
+	}
+	for (set, name) in [(attrs.bold, "bold"), (attrs.dim, "dim"), (attrs.italic, "italic")] {
+		if set {
+			parts.push(name.to_string());
+		}
+	}
+	if attrs.underline {
+		parts.push(match attrs.underline_style {
+			UnderlineStyle::Double => "underline=double".to_string(),
+			UnderlineStyle::Curly => "underline=curly".to_string(),
+			UnderlineStyle::Dotted => "underline=dotted".to_string(),
+			UnderlineStyle::Dashed => "underline=dashed".to_string(),
+			UnderlineStyle::None | UnderlineStyle::Single => "underline".to_string(),
+		});
+		if cell.underline_color != ColorSpec::Default {
+			parts.push(format!("underline-color={}", color_name(cell.underline_color)));
+		}
+	}
+	for (set, name) in [(attrs.reverse, "reverse"), (attrs.strikethrough, "strikethrough")] {
+		if set {
+			parts.push(name.to_string());
+		}
+	}
+	(!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// Readable name of a color: basic color names, `color<n>` for the 256-color palette, `#rrggbb` for true color.
+fn color_name(spec: ColorSpec) -> String {
+	const NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+	match spec {
+		ColorSpec::Default => "default".to_string(),
+		ColorSpec::Basic(idx @ 0..=7) => NAMES[usize::from(idx)].to_string(),
+		ColorSpec::Basic(idx) => format!("bright-{}", NAMES[usize::from(idx & 7)]),
+		ColorSpec::Palette(idx) => format!("color{idx}"),
+		ColorSpec::Rgb(r, g, b) => hex((r, g, b)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn marks_styles_by_first_appearance() {
+		let raw = "plain\n\x1b[48;2;32;32;32m  \x1b[0m 日\x1b[92;4:3;58;5;9mx\x1b[0m\n\x1b[48;2;32;32;32mz";
+		assert_eq!(
+			styled_snapshot(raw),
+			"0 | plain\n1 |    日x\n  | aa   b\n2 | z\n  | a\n--\na: bg=#202020\nb: fg=bright-green underline=curly underline-color=color9"
+		);
+		assert_eq!(styled_snapshot("no styles"), "0 | no styles");
+	}
+}
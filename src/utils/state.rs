@@ -0,0 +1,161 @@
+//! Typed model of the `kitty @ ls` window tree.
+//!
+//! [`KittyHarness::state`](crate::KittyHarness::state) returns the whole tree
+//! (OS windows, tabs, windows, their sizes, titles, foreground processes and
+//! focus state) so tests can assert on layout and focus directly. Fields
+//! missing from older kitty versions fall back to their defaults.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// The full window tree of a kitty instance.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(transparent)]
+pub struct KittyState {
+	/// Top-level OS windows.
+	pub os_windows: Vec<OsWindowState>,
+}
+
+/// A top-level OS window.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct OsWindowState {
+	/// kitty OS window id.
+	pub id: u32,
+	/// Whether this is the active OS window.
+	pub is_active: bool,
+	/// Whether this OS window has keyboard focus.
+	pub is_focused: bool,
+	/// Window manager class (the harness session name for launched instances).
+	pub wm_class: String,
+	/// Native window handle (X11 window id), when the platform exposes one.
+	pub platform_window_id: Option<u64>,
+	/// Tabs of this OS window.
+	pub tabs: Vec<TabState>,
+}
+
+/// A tab inside an OS window.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct TabState {
+	/// kitty tab id.
+	pub id: u32,
+	/// Tab title.
+	pub title: String,
+	/// Name of the active layout (e.g. `tall`, `stack`).
+	pub layout: String,
+	/// Whether this is the active tab of its OS window.
+	pub is_active: bool,
+	/// Whether this tab has keyboard focus.
+	pub is_focused: bool,
+	/// Windows of this tab.
+	pub windows: Vec<WindowState>,
+}
+
+/// A single kitty window.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct WindowState {
+	/// kitty window id.
+	pub id: u32,
+	/// Window title, as last set by the program (OSC 0/2) or remote control.
+	pub title: String,
+	/// Whether this is the active window of its tab.
+	pub is_active: bool,
+	/// Whether this window has keyboard focus.
+	pub is_focused: bool,
+	/// Whether this window is the one that ran the `ls` command.
+	pub is_self: bool,
+	/// Number of text columns.
+	pub columns: u32,
+	/// Number of text lines.
+	pub lines: u32,
+	/// Pid of the process kitty started in the window.
+	pub pid: Option<u32>,
+	/// Working directory of the started process.
+	pub cwd: Option<PathBuf>,
+	/// Command line of the started process.
+	pub cmdline: Vec<String>,
+	/// Environment variables kitty passed to the window.
+	pub env: HashMap<String, String>,
+	/// Processes in the foreground process group of the window's tty.
+	pub foreground_processes: Vec<ProcessState>,
+	/// Whether the shell is sitting at a prompt (OSC 133 shell-integration marks).
+	pub at_prompt: bool,
+	/// Command line last reported by shell integration when a command started.
+	pub last_reported_cmdline: String,
+	/// Exit status of the last command reported by shell integration (OSC 133 D).
+	pub last_cmd_exit_status: Option<i32>,
+}
+
+/// A process running in a window.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default)]
+pub struct ProcessState {
+	/// Process id.
+	pub pid: u32,
+	/// Working directory.
+	pub cwd: Option<PathBuf>,
+	/// Command line.
+	pub cmdline: Vec<String>,
+}
+
+impl KittyState {
+	/// Iterate over every tab of every OS window.
+	pub fn tabs(&self) -> impl Iterator<Item = &TabState> {
+		self.os_windows.iter().flat_map(|os_window| os_window.tabs.iter())
+	}
+
+	/// Iterate over every window of every tab.
+	pub fn windows(&self) -> impl Iterator<Item = &WindowState> {
+		self.tabs().flat_map(|tab| tab.windows.iter())
+	}
+
+	/// Find a window by id.
+	pub fn window(&self, id: u32) -> Option<&WindowState> {
+		self.windows().find(|window| window.id == id)
+	}
+
+	/// Find the OS window containing window `id`.
+	pub fn os_window_of(&self, id: u32) -> Option<&OsWindowState> {
+		self.os_windows
+			.iter()
+			.find(|os_window| os_window.tabs.iter().any(|tab| tab.windows.iter().any(|window| window.id == id)))
+	}
+
+	/// Return the focused window, if any window has focus.
+	pub fn focused_window(&self) -> Option<&WindowState> {
+		self.windows().find(|window| window.is_focused)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_ls_output_with_missing_fields() {
+		let json = r#"[{
+			"id": 1, "is_active": true, "is_focused": true, "wm_class": "kitty-test-1-0", "platform_window_id": 42,
+			"tabs": [{
+				"id": 1, "title": "vim", "layout": "stack", "is_active": true, "is_focused": true,
+				"windows": [
+					{ "id": 3, "title": "main.rs", "is_focused": true, "columns": 80, "lines": 24, "pid": 100, "at_prompt": false,
+					  "cmdline": ["bash"], "foreground_processes": [{ "pid": 101, "cmdline": ["vim", "main.rs"] }] },
+					{ "id": 4, "title": "helper" }
+				]
+			}]
+		}]"#;
+		let state: KittyState = serde_json::from_str(json).unwrap();
+		assert_eq!(state.os_windows[0].wm_class, "kitty-test-1-0");
+		assert_eq!(state.os_window_of(4).and_then(|os_window| os_window.platform_window_id), Some(42));
+		assert_eq!(state.windows().count(), 2);
+		let focused = state.focused_window().unwrap();
+		assert_eq!((focused.id, focused.columns, focused.lines), (3, 80, 24));
+		assert_eq!(focused.foreground_processes[0].cmdline, vec!["vim", "main.rs"]);
+		assert_eq!(state.window(4).unwrap().pid, None);
+		assert!(!state.window(4).unwrap().at_prompt);
+	}
+}
@@ -0,0 +1,374 @@
+//! SGR style tracking for raw ANSI captures.
+//!
+//! Walks raw `get-text --ansi` output, applying SGR (`CSI ... m`) sequences
+//! to a running [`TextStyle`], so tests can ask which style each visible
+//! character was drawn with. Both semicolon-separated and kitty's
+//! colon-separated color forms are understood, as are kitty's extended
+//! underline styles (`4:3` curly and friends) and underline colors (SGR 58).
+//!
+//! # Example
+//!
+//! ```
+//! use kitty_test_harness::utils::style::{ColorSpec, assert_no_text_with_style};
+//!
+//! let raw = "all good\n\x1b[32mok\x1b[0m";
+//! assert_no_text_with_style(raw, None, |style| style.fg == ColorSpec::Basic(1) || style.reverse);
+//! ```
+
+use crate::utils::ansi::{TokenKind, split_tokens};
+use crate::utils::screen::Rect;
+
+/// A foreground or background color selected by SGR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorSpec {
+	/// The terminal's default color (SGR 39/49 or reset).
+	#[default]
+	Default,
+	/// One of the 16 basic colors: 0-7 from SGR 30-37/40-47, 8-15 from the bright 90-97/100-107 codes.
+	Basic(u8),
+	/// A 256-color palette index (`38;5;n`).
+	Palette(u8),
+	/// A 24-bit true color (`38;2;r;g;b`).
+	Rgb(u8, u8, u8),
+}
+
+/// Underline style selected by SGR 4 (including kitty's `4:n` sub-parameter), 21 or 24.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnderlineStyle {
+	/// Not underlined (SGR 24 or `4:0`).
+	#[default]
+	None,
+	/// Straight underline (SGR 4 or `4:1`).
+	Single,
+	/// Double underline (SGR 21 or `4:2`).
+	Double,
+	/// Curly underline, as used for spell-check (`4:3`).
+	Curly,
+	/// Dotted underline (`4:4`).
+	Dotted,
+	/// Dashed underline (`4:5`).
+	Dashed,
+}
+
+/// The SGR state in effect for a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextStyle {
+	/// Foreground color.
+	pub fg: ColorSpec,
+	/// Background color.
+	pub bg: ColorSpec,
+	/// Bold / increased intensity (SGR 1).
+	pub bold: bool,
+	/// Dim / decreased intensity (SGR 2).
+	pub dim: bool,
+	/// Italic (SGR 3).
+	pub italic: bool,
+	/// Underlined in any style (SGR 4).
+	pub underline: bool,
+	/// Underline style; [`UnderlineStyle::None`] exactly when `underline` is false.
+	pub underline_style: UnderlineStyle,
+	/// Underline color (SGR 58, reset by 59); [`ColorSpec::Default`] draws in the foreground color.
+	pub underline_color: ColorSpec,
+	/// Reverse video (SGR 7).
+	pub reverse: bool,
+	/// Strikethrough (SGR 9).
+	pub strikethrough: bool,
+}
+
+impl TextStyle {
+	/// Apply the parameters of an SGR sequence (the text between `CSI` and `m`).
+	pub fn apply_sgr(&mut self, params: &str) {
+		if params.is_empty() {
+			*self = TextStyle::default();
+			return;
+		}
+
+		let groups: Vec<&str> = params.split(';').collect();
+		let mut i = 0;
+		while i < groups.len() {
+			let group = groups[i];
+			let sub: Vec<&str> = group.split(':').collect();
+			let code: u16 = sub[0].parse().unwrap_or(0);
+
+			match code {
+				0 => *self = TextStyle::default(),
+				1 => self.bold = true,
+				2 => self.dim = true,
+				3 => self.italic = true,
+				4 => self.set_underline(match sub.get(1).copied() {
+					None | Some("1") => UnderlineStyle::Single,
+					Some("0") => UnderlineStyle::None,
+					Some("2") => UnderlineStyle::Double,
+					Some("3") => UnderlineStyle::Curly,
+					Some("4") => UnderlineStyle::Dotted,
+					Some("5") => UnderlineStyle::Dashed,
+					Some(_) => UnderlineStyle::Single,
+				}),
+				7 => self.reverse = true,
+				9 => self.strikethrough = true,
+				21 => self.set_underline(UnderlineStyle::Double),
+				22 => {
+					self.bold = false;
+					self.dim = false;
+				}
+				23 => self.italic = false,
+				24 => self.set_underline(UnderlineStyle::None),
+				27 => self.reverse = false,
+				29 => self.strikethrough = false,
+				30..=37 => self.fg = ColorSpec::Basic((code - 30) as u8),
+				39 => self.fg = ColorSpec::Default,
+				40..=47 => self.bg = ColorSpec::Basic((code - 40) as u8),
+				49 => self.bg = ColorSpec::Default,
+				59 => self.underline_color = ColorSpec::Default,
+				90..=97 => self.fg = ColorSpec::Basic((code - 90 + 8) as u8),
+				100..=107 => self.bg = ColorSpec::Basic((code - 100 + 8) as u8),
+				38 | 48 | 58 => {
+					let (color, consumed) = if sub.len() > 1 {
+						(parse_extended_color(&sub[1..]), 0)
+					} else {
+						parse_extended_semicolon(&groups[i + 1..])
+					};
+					i += consumed;
+					if let Some(color) = color {
+						match code {
+							38 => self.fg = color,
+							48 => self.bg = color,
+							_ => self.underline_color = color,
+						}
+					}
+				}
+				_ => {}
+			}
+			i += 1;
+		}
+	}
+
+	fn set_underline(&mut self, style: UnderlineStyle) {
+		self.underline = style != UnderlineStyle::None;
+		self.underline_style = style;
+	}
+}
+
+/// Parse colon-separated extended color sub-parameters (after the 38/48).
+pub(crate) fn parse_extended_color(sub: &[&str]) -> Option<ColorSpec> {
+	match *sub.first()? {
+		"5" => sub.get(1)?.parse().ok().map(ColorSpec::Palette),
+		"2" => {
+			// `38:2:r:g:b` or `38:2:<colorspace>:r:g:b`
+			let rgb = if sub.len() >= 5 { &sub[2..5] } else { sub.get(1..4)? };
+			let r = rgb[0].parse().ok()?;
+			let g = rgb[1].parse().ok()?;
+			let b = rgb[2].parse().ok()?;
+			Some(ColorSpec::Rgb(r, g, b))
+		}
+		_ => None,
+	}
+}
+
+/// Parse semicolon-separated extended color parameters, returning how many groups were consumed.
+pub(crate) fn parse_extended_semicolon(rest: &[&str]) -> (Option<ColorSpec>, usize) {
+	match rest.first().copied() {
+		Some("5") => (rest.get(1).and_then(|n| n.parse().ok()).map(ColorSpec::Palette), 2.min(rest.len())),
+		Some("2") if rest.len() >= 4 => {
+			let parsed = (rest[1].parse(), rest[2].parse(), rest[3].parse());
+			match parsed {
+				(Ok(r), Ok(g), Ok(b)) => (Some(ColorSpec::Rgb(r, g, b)), 4),
+				_ => (None, 4),
+			}
+		}
+		Some(_) => (None, 1),
+		None => (None, 0),
+	}
+}
+
+/// Return the SGR parameters of an escape sequence, or `None` if it is not SGR.
+pub(crate) fn sgr_params(seq: &str) -> Option<&str> {
+	seq.strip_prefix("\x1b[")?.strip_suffix('m')
+}
+
+/// Split a raw capture into lines of visible characters paired with their style.
+///
+/// SGR state carries across line breaks, matching terminal semantics.
+pub fn styled_lines(raw: &str) -> Vec<Vec<(char, TextStyle)>> {
+	let mut style = TextStyle::default();
+	raw.split('\n')
+		.map(|line| {
+			let mut cells = Vec::new();
+			for token in split_tokens(line) {
+				match token.kind {
+					TokenKind::Escape => {
+						if let Some(params) = sgr_params(&token.raw) {
+							style.apply_sgr(params);
+						}
+					}
+					TokenKind::Text => cells.extend(token.text.chars().map(|ch| (ch, style))),
+				}
+			}
+			cells
+		})
+		.collect()
+}
+
+/// Find visible (non-whitespace) characters whose style matches `predicate`.
+///
+/// Returns `(row, col, char)` for every match, optionally limited to `region`.
+pub fn find_text_with_style(raw: &str, region: Option<Rect>, predicate: impl Fn(&TextStyle) -> bool) -> Vec<(usize, usize, char)> {
+	let mut hits = Vec::new();
+	for (row, cells) in styled_lines(raw).iter().enumerate() {
+		for (col, (ch, style)) in cells.iter().enumerate() {
+			if ch.is_whitespace() || region.is_some_and(|rect| !rect.contains(row, col)) {
+				continue;
+			}
+			if predicate(style) {
+				hits.push((row, col, *ch));
+			}
+		}
+	}
+	hits
+}
+
+/// Return the position and style of the first character of `needle` in `raw`.
+///
+/// Rows and columns are 0-based, counted in characters like
+/// [`find_text_with_style`].
+pub fn style_at_text(raw: &str, needle: &str) -> Option<(usize, usize, TextStyle)> {
+	let needle: Vec<char> = needle.chars().collect();
+	if needle.is_empty() {
+		return None;
+	}
+	styled_lines(raw).into_iter().enumerate().find_map(|(row, cells)| {
+		let text: Vec<char> = cells.iter().map(|(ch, _)| *ch).collect();
+		let col = text.windows(needle.len()).position(|window| window == needle.as_slice())?;
+		Some((row, col, cells[col].1))
+	})
+}
+
+/// Assert that `needle` appears in `raw` and its first character's style matches `predicate`.
+///
+/// ```
+/// use kitty_test_harness::utils::style::{ColorSpec, UnderlineStyle, assert_text_style};
+///
+/// let raw = "let \x1b[4:3;58:2::255:0:0mtypo\x1b[4:0;59m = 1;";
+/// assert_text_style(raw, "typo", |s| s.underline_style == UnderlineStyle::Curly && s.underline_color == ColorSpec::Rgb(255, 0, 0));
+/// ```
+///
+/// # Panics
+///
+/// Panics if `needle` is missing or its style does not match, printing the style found.
+pub fn assert_text_style(raw: &str, needle: &str, predicate: impl Fn(&TextStyle) -> bool) {
+	match style_at_text(raw, needle) {
+		Some((_, _, style)) if predicate(&style) => {}
+		Some((row, col, style)) => panic!("{needle:?} at row {row}, col {col} has unexpected style {style:?}"),
+		None => panic!("{needle:?} not found in capture"),
+	}
+}
+
+/// Assert that no visible text (optionally within `region`) is drawn with a matching style.
+///
+/// Useful for smoke tests that only need to know nothing rendered as an
+/// error, e.g. `|s| s.fg == ColorSpec::Basic(1)` for red text or
+/// `|s| s.reverse` for reverse video.
+///
+/// # Panics
+///
+/// Panics listing each offending row and the matched text.
+pub fn assert_no_text_with_style(raw: &str, region: Option<Rect>, predicate: impl Fn(&TextStyle) -> bool) {
+	let hits = find_text_with_style(raw, region, predicate);
+	if hits.is_empty() {
+		return;
+	}
+
+	let mut report = String::new();
+	let mut current_row = None;
+	for (row, col, ch) in &hits {
+		if current_row != Some(*row) {
+			report.push_str(&format!("\n  row {row}, col {col}: "));
+			current_row = Some(*row);
+		}
+		report.push(*ch);
+	}
+	panic!("found {} character(s) with forbidden style:{report}", hits.len());
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sgr_basic_and_bright_colors() {
+		let mut style = TextStyle::default();
+		style.apply_sgr("31;102");
+		assert_eq!(style.fg, ColorSpec::Basic(1));
+		assert_eq!(style.bg, ColorSpec::Basic(10));
+		style.apply_sgr("39");
+		assert_eq!(style.fg, ColorSpec::Default);
+	}
+
+	#[test]
+	fn sgr_extended_colors_both_forms() {
+		let mut style = TextStyle::default();
+		style.apply_sgr("38;2;10;20;30;48;5;200");
+		assert_eq!(style.fg, ColorSpec::Rgb(10, 20, 30));
+		assert_eq!(style.bg, ColorSpec::Palette(200));
+
+		style.apply_sgr("38:2:1:2:3");
+		assert_eq!(style.fg, ColorSpec::Rgb(1, 2, 3));
+		style.apply_sgr("38:2::4:5:6");
+		assert_eq!(style.fg, ColorSpec::Rgb(4, 5, 6));
+	}
+
+	#[test]
+	fn sgr_attributes_and_reset() {
+		let mut style = TextStyle::default();
+		style.apply_sgr("1;3;4;7;9");
+		assert!(style.bold && style.italic && style.underline && style.reverse && style.strikethrough);
+		style.apply_sgr("27");
+		assert!(!style.reverse);
+		style.apply_sgr("");
+		assert_eq!(style, TextStyle::default());
+	}
+
+	#[test]
+	fn sgr_extended_underlines_and_color() {
+		let mut style = TextStyle::default();
+		style.apply_sgr("4:3;58;5;196");
+		assert!(style.underline);
+		assert_eq!((style.underline_style, style.underline_color), (UnderlineStyle::Curly, ColorSpec::Palette(196)));
+		style.apply_sgr("4:5");
+		assert_eq!(style.underline_style, UnderlineStyle::Dashed);
+		style.apply_sgr("58:2::1:2:3");
+		assert_eq!(style.underline_color, ColorSpec::Rgb(1, 2, 3));
+		style.apply_sgr("4:0;59");
+		assert!(!style.underline);
+		assert_eq!(style, TextStyle::default());
+		style.apply_sgr("21");
+		assert_eq!(style.underline_style, UnderlineStyle::Double);
+	}
+
+	#[test]
+	fn styled_lines_carry_state_across_lines() {
+		let lines = styled_lines("a\x1b[7mb\nc\x1b[0md");
+		assert!(!lines[0][0].1.reverse);
+		assert!(lines[0][1].1.reverse);
+		assert!(lines[1][0].1.reverse);
+		assert!(!lines[1][1].1.reverse);
+	}
+
+	#[test]
+	fn find_text_with_style_respects_region_and_whitespace() {
+		let raw = "\x1b[31mERR \x1b[0mok\n\x1b[31mbad\x1b[0m";
+		let hits = find_text_with_style(raw, None, |s| s.fg == ColorSpec::Basic(1));
+		assert_eq!(hits.len(), 6);
+		let hits = find_text_with_style(raw, Some(Rect::new(1, 0, 10, 1)), |s| s.fg == ColorSpec::Basic(1));
+		assert_eq!(hits, vec![(1, 0, 'b'), (1, 1, 'a'), (1, 2, 'd')]);
+	}
+
+	#[test]
+	#[should_panic(expected = "forbidden style")]
+	fn assert_no_text_with_style_panics_on_match() {
+		assert_no_text_with_style("fine \x1b[7mselected\x1b[0m", None, |s| s.reverse);
+	}
+}
@@ -0,0 +1,448 @@
+//! Deterministic color themes.
+//!
+//! A [`Theme`] pins the 16-color palette, foreground, background and cursor
+//! colors so color assertions and snapshots do not depend on the local kitty
+//! theme. Apply it at launch with [`crate::LaunchOptions::theme`] or at
+//! runtime with [`crate::KittyHarness::set_colors`].
+//!
+//! A [`Palette`] maps all 256 color indexes to RGB, so indexed colors in a
+//! capture can be compared against expected RGB values. It starts from
+//! kitty's built-in defaults, a [`Theme`], or a kitty theme file.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use kitty_test_harness::{KittyHarness, LaunchOptions, Theme};
+//!
+//! let options = LaunchOptions::new().theme(&Theme::xterm());
+//! let kitty = KittyHarness::launch_with(&kitty_test_harness::manifest_dir(), "my-app", &options);
+//! ```
+
+use std::path::Path;
+
+use crate::utils::error::HarnessError;
+use crate::utils::style::ColorSpec;
+
+/// An RGB color.
+pub type Rgb = (u8, u8, u8);
+
+/// A terminal color theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Theme {
+	/// Default foreground color.
+	pub foreground: Rgb,
+	/// Default background color.
+	pub background: Rgb,
+	/// Cursor color, or `None` to keep kitty's default.
+	pub cursor: Option<Rgb>,
+	/// The 16 basic colors (`color0`-`color15`).
+	pub palette: [Rgb; 16],
+}
+
+impl Theme {
+	/// The classic xterm palette: light gray on black.
+	pub fn xterm() -> Self {
+		Self {
+			foreground: (0xe5, 0xe5, 0xe5),
+			background: (0x00, 0x00, 0x00),
+			cursor: Some((0xe5, 0xe5, 0xe5)),
+			palette: [
+				(0x00, 0x00, 0x00),
+				(0xcd, 0x00, 0x00),
+				(0x00, 0xcd, 0x00),
+				(0xcd, 0xcd, 0x00),
+				(0x00, 0x00, 0xee),
+				(0xcd, 0x00, 0xcd),
+				(0x00, 0xcd, 0xcd),
+				(0xe5, 0xe5, 0xe5),
+				(0x7f, 0x7f, 0x7f),
+				(0xff, 0x00, 0x00),
+				(0x00, 0xff, 0x00),
+				(0xff, 0xff, 0x00),
+				(0x5c, 0x5c, 0xff),
+				(0xff, 0x00, 0xff),
+				(0x00, 0xff, 0xff),
+				(0xff, 0xff, 0xff),
+			],
+		}
+	}
+
+	/// The Tango light palette: dark gray on white.
+	pub fn light() -> Self {
+		Self {
+			foreground: (0x2e, 0x34, 0x36),
+			background: (0xff, 0xff, 0xff),
+			cursor: Some((0x2e, 0x34, 0x36)),
+			palette: [
+				(0x2e, 0x34, 0x36),
+				(0xcc, 0x00, 0x00),
+				(0x4e, 0x9a, 0x06),
+				(0xc4, 0xa0, 0x00),
+				(0x34, 0x65, 0xa4),
+				(0x75, 0x50, 0x7b),
+				(0x06, 0x98, 0x9a),
+				(0xd3, 0xd7, 0xcf),
+				(0x55, 0x57, 0x53),
+				(0xef, 0x29, 0x29),
+				(0x8a, 0xe2, 0x34),
+				(0xfc, 0xe9, 0x4f),
+				(0x72, 0x9f, 0xcf),
+				(0xad, 0x7f, 0xa8),
+				(0x34, 0xe2, 0xe2),
+				(0xee, 0xee, 0xec),
+			],
+		}
+	}
+
+	/// White on black with colors that all reach WCAG AA contrast against the background.
+	///
+	/// color0 is a mid gray rather than black, so text drawn in it stays readable.
+	pub fn high_contrast() -> Self {
+		Self {
+			foreground: (0xff, 0xff, 0xff),
+			background: (0x00, 0x00, 0x00),
+			cursor: Some((0xff, 0xff, 0x00)),
+			palette: [
+				(0x76, 0x76, 0x76),
+				(0xff, 0x5f, 0x5f),
+				(0x5f, 0xff, 0x5f),
+				(0xff, 0xff, 0x5f),
+				(0x5f, 0xaf, 0xff),
+				(0xff, 0x5f, 0xff),
+				(0x5f, 0xff, 0xff),
+				(0xff, 0xff, 0xff),
+				(0xbc, 0xbc, 0xbc),
+				(0xff, 0x87, 0x87),
+				(0x87, 0xff, 0x87),
+				(0xff, 0xff, 0x87),
+				(0x87, 0xd7, 0xff),
+				(0xff, 0x87, 0xff),
+				(0x87, 0xff, 0xff),
+				(0xff, 0xff, 0xff),
+			],
+		}
+	}
+
+	/// Replace the foreground color.
+	pub fn with_foreground(mut self, color: Rgb) -> Self {
+		self.foreground = color;
+		self
+	}
+
+	/// Replace the background color.
+	pub fn with_background(mut self, color: Rgb) -> Self {
+		self.background = color;
+		self
+	}
+
+	/// Replace the cursor color.
+	pub fn with_cursor(mut self, color: Option<Rgb>) -> Self {
+		self.cursor = color;
+		self
+	}
+
+	/// Replace one of the 16 basic colors.
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is not below 16.
+	pub fn with_color(mut self, index: usize, color: Rgb) -> Self {
+		assert!(index < 16, "palette index {index} out of range");
+		self.palette[index] = color;
+		self
+	}
+
+	/// Return `(option, "#rrggbb")` pairs using kitty's color option names.
+	pub fn settings(&self) -> Vec<(String, String)> {
+		let mut settings = vec![
+			("foreground".to_string(), hex(self.foreground)),
+			("background".to_string(), hex(self.background)),
+		];
+		if let Some(cursor) = self.cursor {
+			settings.push(("cursor".to_string(), hex(cursor)));
+		}
+		settings.extend(self.palette.iter().enumerate().map(|(idx, color)| (format!("color{idx}"), hex(*color))));
+		settings
+	}
+
+	/// Render the theme as `kitty.conf` lines.
+	pub fn to_config(&self) -> String {
+		self.settings().iter().map(|(key, value)| format!("{key} {value}\n")).collect()
+	}
+
+	/// The full 256-color palette of this theme.
+	pub fn palette(&self) -> Palette {
+		Palette::from_theme(self)
+	}
+}
+
+/// A 256-color palette with default foreground and background.
+///
+/// Indexes 16-255 default to the standard 6x6x6 color cube and gray ramp
+/// that kitty and xterm use.
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::Palette;
+/// use kitty_test_harness::utils::style::ColorSpec;
+///
+/// let palette = Palette::parse_config("color1 #ff5555\nforeground #f8f8f2").unwrap();
+/// assert_eq!(palette.resolve_fg(ColorSpec::Basic(1)), (0xff, 0x55, 0x55));
+/// assert_eq!(palette.resolve_fg(ColorSpec::Default), (0xf8, 0xf8, 0xf2));
+/// assert_eq!(palette.resolve_fg(ColorSpec::Palette(196)), (0xff, 0x00, 0x00));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Palette {
+	/// Default foreground color.
+	pub foreground: Rgb,
+	/// Default background color.
+	pub background: Rgb,
+	/// Colors for indexes 0-255.
+	pub colors: [Rgb; 256],
+}
+
+impl Palette {
+	/// kitty's built-in default colors.
+	pub fn kitty_default() -> Self {
+		let base = [
+			(0x00, 0x00, 0x00),
+			(0xcc, 0x04, 0x03),
+			(0x19, 0xcb, 0x00),
+			(0xce, 0xcb, 0x00),
+			(0x0d, 0x73, 0xcc),
+			(0xcb, 0x1e, 0xd1),
+			(0x0d, 0xcd, 0xcd),
+			(0xdd, 0xdd, 0xdd),
+			(0x76, 0x76, 0x76),
+			(0xf2, 0x20, 0x1f),
+			(0x23, 0xfd, 0x00),
+			(0xff, 0xfd, 0x00),
+			(0x1a, 0x8f, 0xff),
+			(0xfd, 0x28, 0xff),
+			(0x14, 0xff, 0xff),
+			(0xff, 0xff, 0xff),
+		];
+		Self::with_base((0xdd, 0xdd, 0xdd), (0x00, 0x00, 0x00), base)
+	}
+
+	/// The palette of `theme`: its 16 colors plus the standard extended colors.
+	pub fn from_theme(theme: &Theme) -> Self {
+		Self::with_base(theme.foreground, theme.background, theme.palette)
+	}
+
+	/// Apply the `foreground`, `background` and `colorN` lines of a kitty
+	/// theme or `kitty.conf` on top of [`Palette::kitty_default`].
+	///
+	/// Other options and comments are ignored.
+	pub fn parse_config(config: &str) -> Result<Self, HarnessError> {
+		let mut palette = Self::kitty_default();
+		for (idx, line) in config.lines().enumerate() {
+			let mut parts = line.split_whitespace();
+			let (Some(key), Some(value)) = (parts.next(), parts.next()) else {
+				continue;
+			};
+			let slot = match key {
+				"foreground" => &mut palette.foreground,
+				"background" => &mut palette.background,
+				_ => match key.strip_prefix("color").and_then(|n| n.parse::<u8>().ok()) {
+					Some(n) => &mut palette.colors[usize::from(n)],
+					None => continue,
+				},
+			};
+			*slot = parse_hex(value).ok_or_else(|| HarnessError::Parse {
+				command: "kitty theme".into(),
+				message: format!("line {}: invalid color {value:?} for {key}", idx + 1),
+			})?;
+		}
+		Ok(palette)
+	}
+
+	/// Read a kitty theme file (see [`Palette::parse_config`]).
+	pub fn load(path: &Path) -> Result<Self, HarnessError> {
+		let config = std::fs::read_to_string(path).map_err(|err| HarnessError::io(format!("read theme {}", path.display()), err))?;
+		Self::parse_config(&config)
+	}
+
+	/// RGB of palette index `idx`.
+	pub fn color(&self, idx: u8) -> Rgb {
+		self.colors[usize::from(idx)]
+	}
+
+	/// Resolve a foreground (or underline) color; [`ColorSpec::Default`] is the default foreground.
+	pub fn resolve_fg(&self, spec: ColorSpec) -> Rgb {
+		self.resolve(spec, self.foreground)
+	}
+
+	/// Resolve a background color; [`ColorSpec::Default`] is the default background.
+	pub fn resolve_bg(&self, spec: ColorSpec) -> Rgb {
+		self.resolve(spec, self.background)
+	}
+
+	/// Resolve `spec`, using `default` for [`ColorSpec::Default`].
+	pub(crate) fn resolve(&self, spec: ColorSpec, default: Rgb) -> Rgb {
+		match spec {
+			ColorSpec::Default => default,
+			ColorSpec::Basic(idx) => self.color(idx & 15),
+			ColorSpec::Palette(idx) => self.color(idx),
+			ColorSpec::Rgb(r, g, b) => (r, g, b),
+		}
+	}
+
+	fn with_base(foreground: Rgb, background: Rgb, base: [Rgb; 16]) -> Self {
+		let colors = std::array::from_fn(|idx| match idx {
+			0..=15 => base[idx],
+			_ => extended_color(idx as u8),
+		});
+		Self {
+			foreground,
+			background,
+			colors,
+		}
+	}
+}
+
+impl Default for Palette {
+	fn default() -> Self {
+		Self::kitty_default()
+	}
+}
+
+/// The colors a kitty window is currently drawn with, as reported by `kitty @ get-colors`.
+///
+/// Returned by [`crate::KittyHarness::get_colors`], so tests can check that
+/// a theme switch (by the application or [`crate::KittyHarness::set_colors`])
+/// really changed the terminal colors.
+///
+/// # Example
+///
+/// ```
+/// use kitty_test_harness::TerminalColors;
+///
+/// let colors = TerminalColors::parse("foreground  #dddddd\nbackground  #000000\ncursor      none\ncolor1      #cc0403\nselection_background #fffacd").unwrap();
+/// assert_eq!(colors.palette.color(1), (0xcc, 0x04, 0x03));
+/// assert_eq!(colors.cursor, None);
+/// assert_eq!(colors.get("selection_background"), Some("#fffacd"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalColors {
+	/// Default foreground and background and all 256 indexed colors.
+	pub palette: Palette,
+	/// Cursor color, or `None` when the cursor is drawn in the colors of the cell under it.
+	pub cursor: Option<Rgb>,
+	/// Every reported `(name, value)` pair in order, including selection, tab bar and border colors.
+	pub settings: Vec<(String, String)>,
+}
+
+impl TerminalColors {
+	/// Parse the `name value` lines printed by `kitty @ get-colors`.
+	///
+	/// Colors missing from the output keep kitty's defaults (see [`Palette::parse_config`]).
+	pub fn parse(output: &str) -> Result<Self, HarnessError> {
+		let palette = Palette::parse_config(output)?;
+		let settings: Vec<(String, String)> = output
+			.lines()
+			.filter_map(|line| {
+				let mut parts = line.split_whitespace();
+				Some((parts.next()?.to_string(), parts.next()?.to_string()))
+			})
+			.collect();
+		let cursor = settings.iter().find(|(key, _)| key == "cursor").and_then(|(_, value)| parse_hex(value));
+		Ok(Self { palette, cursor, settings })
+	}
+
+	/// The reported value of color option `name` (e.g. `"selection_background"`), as printed.
+	pub fn get(&self, name: &str) -> Option<&str> {
+		self.settings.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+	}
+
+	/// Returns true if the foreground, background and 16 basic colors are those of `theme`.
+	pub fn matches(&self, theme: &Theme) -> bool {
+		self.palette.foreground == theme.foreground && self.palette.background == theme.background && self.palette.colors[..16] == theme.palette
+	}
+}
+
+/// Standard color of extended index `idx` (16-255): the 6x6x6 cube, then a 24-step gray ramp.
+pub(crate) fn extended_color(idx: u8) -> Rgb {
+	match idx {
+		16..=231 => {
+			let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+			let idx = idx - 16;
+			(level(idx / 36), level(idx / 6 % 6), level(idx % 6))
+		}
+		232..=255 => {
+			let gray = 8 + (idx - 232) * 10;
+			(gray, gray, gray)
+		}
+		_ => (0, 0, 0),
+	}
+}
+
+/// Parse `#rrggbb`, `rrggbb` or `#rgb`.
+fn parse_hex(value: &str) -> Option<Rgb> {
+	let hex = value.strip_prefix('#').unwrap_or(value);
+	let channel = |range: std::ops::Range<usize>| u8::from_str_radix(hex.get(range)?, 16).ok();
+	match hex.len() {
+		6 => Some((channel(0..2)?, channel(2..4)?, channel(4..6)?)),
+		3 => Some((channel(0..1)? * 17, channel(1..2)? * 17, channel(2..3)? * 17)),
+		_ => None,
+	}
+}
+
+impl Default for Theme {
+	fn default() -> Self {
+		Self::xterm()
+	}
+}
+
+pub(crate) fn hex((r, g, b): Rgb) -> String {
+	format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn config_lists_all_colors() {
+		let config = Theme::xterm().with_color(1, (1, 2, 3)).with_cursor(None).to_config();
+		assert!(config.starts_with("foreground #e5e5e5\nbackground #000000\ncolor0 #000000\ncolor1 #010203\n"));
+		assert_eq!(config.lines().count(), 18);
+	}
+
+	#[test]
+	fn palette_resolves_indexes_against_theme_and_config() {
+		let palette = Theme::xterm().palette();
+		assert_eq!(palette.resolve_fg(ColorSpec::Basic(9)), (0xff, 0x00, 0x00));
+		assert_eq!(palette.resolve_bg(ColorSpec::Default), (0x00, 0x00, 0x00));
+		assert_eq!(palette.color(16), (0, 0, 0));
+		assert_eq!(palette.color(67), (0x5f, 0x87, 0xaf));
+		assert_eq!(palette.color(255), (0xee, 0xee, 0xee));
+
+		let palette = Palette::parse_config("# comment\ncolor4 #abc\ncolor100 010203\nfont_size 12").unwrap();
+		assert_eq!(palette.color(4), (0xaa, 0xbb, 0xcc));
+		assert_eq!(palette.color(100), (1, 2, 3));
+		assert_eq!(palette.color(1), Palette::kitty_default().color(1));
+		assert!(matches!(Palette::parse_config("color1 red"), Err(HarnessError::Parse { .. })));
+	}
+
+	#[test]
+	fn terminal_colors_match_the_applied_theme() {
+		let theme = Theme::light();
+		let output: String = theme.settings().iter().map(|(key, value)| format!("{key:<20} {value}\n")).collect();
+		let colors = TerminalColors::parse(&output).unwrap();
+		assert!(colors.matches(&theme));
+		assert!(!colors.matches(&Theme::xterm()));
+		assert_eq!(colors.cursor, theme.cursor);
+		assert_eq!(colors.get("color4"), Some("#3465a4"));
+	}
+
+	#[test]
+	fn high_contrast_colors_are_readable() {
+		let theme = Theme::high_contrast();
+		for (idx, color) in theme.palette.iter().enumerate() {
+			let ratio = crate::utils::contrast::contrast_ratio(*color, theme.background);
+			assert!(ratio >= 4.5, "color{idx} has contrast {ratio:.2}");
+		}
+	}
+}
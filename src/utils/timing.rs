@@ -0,0 +1,131 @@
+//! Polling and post-send timing.
+//!
+//! Waits poll the screen at an interval that starts at
+//! [`Timing::poll_interval`] and grows by [`Timing::backoff`] up to
+//! [`Timing::max_poll_interval`]; sends pause for [`Timing::post_send_delay`]
+//! afterwards so kitty can deliver the input. The defaults keep the historic
+//! fixed 50ms poll and 20ms post-send pause. Tune them per harness with
+//! [`crate::KittyHarness::set_timing`] or [`crate::LaunchOptions::timing`].
+
+use std::time::Duration;
+
+use crate::KittyHarness;
+
+/// Poll interval, backoff and post-send delay used by a harness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+	/// First delay between two polls of a wait.
+	pub poll_interval: Duration,
+	/// Factor the poll delay is multiplied by after every poll (`1.0` disables backoff).
+	pub backoff: f64,
+	/// Upper bound for the poll delay.
+	pub max_poll_interval: Duration,
+	/// Pause after sending text or keys.
+	pub post_send_delay: Duration,
+}
+
+impl Timing {
+	/// Fixed-interval polling with the given post-send delay.
+	pub fn fixed(poll_interval: Duration, post_send_delay: Duration) -> Self {
+		Self {
+			poll_interval,
+			backoff: 1.0,
+			max_poll_interval: poll_interval,
+			post_send_delay,
+		}
+	}
+
+	/// Start polling at `poll_interval` and multiply by `factor` up to `max_poll_interval`.
+	///
+	/// # Panics
+	///
+	/// Panics if `factor` is below `1.0` or not finite.
+	pub fn with_backoff(mut self, factor: f64, max_poll_interval: Duration) -> Self {
+		assert!(factor.is_finite() && factor >= 1.0, "backoff factor must be >= 1.0");
+		self.backoff = factor;
+		self.max_poll_interval = max_poll_interval.max(self.poll_interval);
+		self
+	}
+
+	/// Replace the post-send delay.
+	pub fn with_post_send_delay(mut self, delay: Duration) -> Self {
+		self.post_send_delay = delay;
+		self
+	}
+
+	/// Successive delays between polls of a single wait.
+	pub fn poll_delays(&self) -> PollDelays {
+		PollDelays {
+			next: self.poll_interval,
+			backoff: self.backoff,
+			max: self.max_poll_interval,
+		}
+	}
+}
+
+impl Default for Timing {
+	/// 50ms fixed polling and a 20ms post-send delay.
+	fn default() -> Self {
+		Self::fixed(Duration::from_millis(50), Duration::from_millis(20))
+	}
+}
+
+/// Infinite iterator of poll delays produced by [`Timing::poll_delays`].
+#[derive(Debug, Clone)]
+pub struct PollDelays {
+	next: Duration,
+	backoff: f64,
+	max: Duration,
+}
+
+impl PollDelays {
+	/// Sleep for the next delay.
+	pub fn sleep(&mut self) {
+		if let Some(delay) = self.next() {
+			std::thread::sleep(delay);
+		}
+	}
+}
+
+impl Iterator for PollDelays {
+	type Item = Duration;
+
+	fn next(&mut self) -> Option<Duration> {
+		let delay = self.next;
+		self.next = self.next.mul_f64(self.backoff).min(self.max);
+		Some(delay)
+	}
+}
+
+/// Disables the post-send delay of a harness and restores its timing when dropped, even if the action panics.
+pub(crate) struct RestoreTiming<'a> {
+	kitty: &'a KittyHarness,
+	timing: Timing,
+}
+
+impl<'a> RestoreTiming<'a> {
+	pub(crate) fn zero_post_send_delay(kitty: &'a KittyHarness) -> Self {
+		let timing = kitty.timing();
+		kitty.set_timing(timing.with_post_send_delay(Duration::ZERO));
+		Self { kitty, timing }
+	}
+}
+
+impl Drop for RestoreTiming<'_> {
+	fn drop(&mut self) {
+		self.kitty.set_timing(self.timing);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn backoff_grows_to_the_cap() {
+		let timing = Timing::fixed(Duration::from_millis(10), Duration::ZERO).with_backoff(2.0, Duration::from_millis(50));
+		let delays: Vec<_> = timing.poll_delays().take(5).map(|d| d.as_millis()).collect();
+		assert_eq!(delays, vec![10, 20, 40, 50, 50]);
+		assert!(Timing::default().poll_delays().take(3).all(|d| d == Duration::from_millis(50)));
+	}
+}
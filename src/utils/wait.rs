@@ -1,12 +1,23 @@
 use std::error::Error;
 use std::fmt;
+use std::ops::Not;
+use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 
 use crate::KittyHarness;
+use crate::utils::ansi::strip_ansi;
+use crate::utils::frames::FrameTimeline;
+use crate::utils::log::read_test_log;
+use crate::utils::screen::{Cell, ScreenGrid};
+use crate::utils::state::WindowState;
 
 /// Error returned when waiting for screen content times out.
+///
+/// `Display` gives a one-line summary; the alternate form (`{:#}`) also
+/// prints the last captured screen.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct WaitTimeout {
 	/// Elapsed time before timeout was returned.
 	pub elapsed: Duration,
@@ -16,6 +27,8 @@ pub struct WaitTimeout {
 	pub last_raw: String,
 	/// Last captured cleaned screen text, if applicable.
 	pub last_clean: Option<String>,
+	/// Description of the condition that was being waited for, if known.
+	pub description: Option<String>,
 }
 
 impl WaitTimeout {
@@ -25,22 +38,37 @@ impl WaitTimeout {
 			timeout,
 			last_raw,
 			last_clean: None,
+			description: None,
 		}
 	}
 
-	fn clean(elapsed: Duration, timeout: Duration, last_raw: String, last_clean: String) -> Self {
+	pub(crate) fn clean(elapsed: Duration, timeout: Duration, last_raw: String, last_clean: String) -> Self {
 		Self {
 			elapsed,
 			timeout,
 			last_raw,
 			last_clean: Some(last_clean),
+			description: None,
 		}
 	}
+
+	pub(crate) fn described(mut self, description: impl Into<String>) -> Self {
+		self.description = Some(description.into());
+		self
+	}
 }
 
 impl fmt::Display for WaitTimeout {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		write!(f, "timed out after {:?} (configured timeout: {:?})", self.elapsed, self.timeout)
+		write!(f, "timed out after {:?} (configured timeout: {:?})", self.elapsed, self.timeout)?;
+		if let Some(description) = &self.description {
+			write!(f, " waiting for {description}")?;
+		}
+		if f.alternate() {
+			let last = self.last_clean.as_deref().unwrap_or(&self.last_raw);
+			write!(f, "\n--- last screen ---\n{last}")?;
+		}
+		Ok(())
 	}
 }
 
@@ -53,7 +81,9 @@ pub fn wait_for_screen_text(kitty: &KittyHarness, timeout: Duration, predicate:
 
 /// Wait until the screen text satisfies the given predicate or return a timeout error.
 pub fn wait_for_screen_text_or_timeout(kitty: &KittyHarness, timeout: Duration, predicate: impl Fn(&str) -> bool) -> Result<String, WaitTimeout> {
+	let timeout = kitty.clamp_timeout(timeout);
 	let start = Instant::now();
+	let mut delays = kitty.timing().poll_delays();
 
 	loop {
 		let last = kitty.screen_text();
@@ -66,7 +96,127 @@ pub fn wait_for_screen_text_or_timeout(kitty: &KittyHarness, timeout: Duration,
 			return Err(WaitTimeout::raw(elapsed, timeout, last));
 		}
 
-		std::thread::sleep(Duration::from_millis(50));
+		delays.sleep();
+	}
+}
+
+/// Wait until the screen text satisfies the predicate, returning a [`WaitTimeout`] that names `description` on failure.
+///
+/// Unlike [`wait_for_screen_text`], a timeout surfaces immediately with the
+/// elapsed time, last captures and what was expected, e.g.
+/// `try_wait_for_screen_text(&kitty, timeout, "prompt", |t| t.contains("$ "))?`.
+pub fn try_wait_for_screen_text(kitty: &KittyHarness, timeout: Duration, description: &str, predicate: impl Fn(&str) -> bool) -> Result<String, WaitTimeout> {
+	wait_for_screen_text_clean_or_timeout(kitty, timeout, |raw, _clean| predicate(raw))
+		.map(|(raw, _clean)| raw)
+		.map_err(|err| err.described(description))
+}
+
+/// Wait until the cleaned screen text satisfies the predicate, returning the cleaned text or a described [`WaitTimeout`].
+pub fn try_wait_for_screen_text_clean(
+	kitty: &KittyHarness,
+	timeout: Duration,
+	description: &str,
+	predicate: impl Fn(&str) -> bool,
+) -> Result<String, WaitTimeout> {
+	wait_for_screen_text_clean_or_timeout(kitty, timeout, |_raw, clean| predicate(clean))
+		.map(|(_raw, clean)| clean)
+		.map_err(|err| err.described(description))
+}
+
+/// Wait until the cleaned screen text contains `needle`, returning the cleaned text or a described [`WaitTimeout`].
+pub fn try_wait_for_clean_contains(kitty: &KittyHarness, timeout: Duration, needle: &str) -> Result<String, WaitTimeout> {
+	try_wait_for_screen_text_clean(kitty, timeout, &format!("screen to contain {needle:?}"), |clean| clean.contains(needle))
+}
+
+/// Wait until cleaned screen row `row` satisfies the predicate, returning the row text or a described [`WaitTimeout`].
+pub fn try_wait_for_row(
+	kitty: &KittyHarness,
+	row: usize,
+	timeout: Duration,
+	description: &str,
+	predicate: impl Fn(&str) -> bool,
+) -> Result<String, WaitTimeout> {
+	wait_for_row_or_timeout(kitty, row, timeout, predicate).map_err(|err| err.described(format!("row {row}: {description}")))
+}
+
+/// Wait until a [`WaitCondition`] holds, returning the cleaned text or a [`WaitTimeout`] carrying its description.
+pub fn try_wait_for(kitty: &KittyHarness, timeout: Duration, condition: &WaitCondition) -> Result<String, WaitTimeout> {
+	wait_for_screen_text_clean_or_timeout(kitty, timeout, |raw, clean| condition.holds(raw, clean))
+		.map(|(_raw, clean)| clean)
+		.map_err(|err| err.described(condition.description()))
+}
+
+/// Wait until the harness window's shell sits at a prompt, as reported by kitty shell integration.
+///
+/// kitty tracks the OSC 133 prompt/command marks emitted by its shell
+/// integration (or by any program that prints them) and exposes them as
+/// `at_prompt` in `kitty @ ls`, so this works regardless of what is on
+/// screen. Shell integration must be active in the window, e.g. a bash, zsh
+/// or fish launched by kitty with the default `shell_integration` setting.
+///
+/// Right after typing a command the old prompt may still be reported; use
+/// [`run_command`] to wait for a command to finish.
+pub fn wait_for_prompt(kitty: &KittyHarness, timeout: Duration) -> Result<WindowState, WaitTimeout> {
+	wait_for_window_state(kitty, timeout, "shell prompt (OSC 133)", |window| window.at_prompt)
+}
+
+/// Type `command` into the shell and wait for it to finish, returning its exit status.
+///
+/// Completion and the exit status come from kitty shell integration
+/// (`at_prompt` and `last_cmd_exit_status` in `kitty @ ls`), so nothing is
+/// printed to the screen and no fixed sleep is needed. The status is `None`
+/// if the shell integration does not report it. The whole call, including
+/// waiting for the command to start, shares one `timeout`.
+///
+/// A command identical to the previous one that also finishes within one
+/// poll interval cannot be told apart from the previous run and times out.
+pub fn run_command(kitty: &KittyHarness, command: &str, timeout: Duration) -> Result<Option<i32>, WaitTimeout> {
+	let timeout = kitty.clamp_timeout(timeout);
+	let start = Instant::now();
+	let id = kitty.window_id().0;
+	let baseline = kitty.try_state().ok().and_then(|state| state.window(id).cloned()).unwrap_or_default();
+	let command = command.trim_end_matches('\n');
+	kitty.send_text(&format!("{command}\n"));
+
+	let description = format!("command {command:?} to start");
+	wait_for_window_state(kitty, timeout, &description, |window| {
+		!window.at_prompt
+			|| (window.last_reported_cmdline.trim() == command.trim()
+				&& (window.last_reported_cmdline != baseline.last_reported_cmdline || window.last_cmd_exit_status != baseline.last_cmd_exit_status))
+	})?;
+	let description = format!("command {command:?} to finish");
+	let window = wait_for_window_state(kitty, timeout.saturating_sub(start.elapsed()), &description, |window| window.at_prompt)?;
+	Ok(window.last_cmd_exit_status)
+}
+
+/// Poll `kitty @ ls` until the harness window's state satisfies `predicate`.
+///
+/// Timeouts carry the screen at the time of failure for diagnostics.
+pub fn wait_for_window_state(
+	kitty: &KittyHarness,
+	timeout: Duration,
+	description: &str,
+	predicate: impl Fn(&WindowState) -> bool,
+) -> Result<WindowState, WaitTimeout> {
+	let timeout = kitty.clamp_timeout(timeout);
+	let start = Instant::now();
+	let mut delays = kitty.timing().poll_delays();
+	let id = kitty.window_id().0;
+
+	loop {
+		if let Some(window) = kitty.try_state().ok().and_then(|state| state.window(id).cloned())
+			&& predicate(&window)
+		{
+			return Ok(window);
+		}
+
+		let elapsed = start.elapsed();
+		if elapsed > timeout {
+			let (raw, clean) = kitty.screen_text_clean();
+			return Err(WaitTimeout::clean(elapsed, timeout, raw, clean).described(description));
+		}
+
+		delays.sleep();
 	}
 }
 
@@ -92,7 +242,9 @@ pub fn wait_for_screen_text_clean_or_timeout(
 	timeout: Duration,
 	predicate: impl Fn(&str, &str) -> bool,
 ) -> Result<(String, String), WaitTimeout> {
+	let timeout = kitty.clamp_timeout(timeout);
 	let start = Instant::now();
+	let mut delays = kitty.timing().poll_delays();
 
 	loop {
 		let last = kitty.screen_text_clean();
@@ -105,16 +257,339 @@ pub fn wait_for_screen_text_clean_or_timeout(
 			return Err(WaitTimeout::clean(elapsed, timeout, last.0, last.1));
 		}
 
-		std::thread::sleep(Duration::from_millis(50));
+		delays.sleep();
 	}
 }
 
+/// Wait until the cleaned text of screen row `row` (0-based) satisfies the predicate or the timeout is reached.
+///
+/// Matching a single row avoids false positives from the same text
+/// appearing elsewhere, e.g. in scrolled shell output above a status bar.
+/// Rows past the end of the captured text are empty. Returns the last row text.
+pub fn wait_for_row(kitty: &KittyHarness, row: usize, timeout: Duration, predicate: impl Fn(&str) -> bool) -> String {
+	wait_for_row_or_timeout(kitty, row, timeout, predicate).unwrap_or_else(|err| row_text(err.last_clean.as_deref().unwrap_or_default(), row))
+}
+
+/// Wait until the cleaned text of screen row `row` satisfies the predicate or return a timeout error.
+pub fn wait_for_row_or_timeout(kitty: &KittyHarness, row: usize, timeout: Duration, predicate: impl Fn(&str) -> bool) -> Result<String, WaitTimeout> {
+	wait_for_screen_text_clean_or_timeout(kitty, timeout, |_raw, clean| predicate(&row_text(clean, row))).map(|(_raw, clean)| row_text(&clean, row))
+}
+
+/// Wait until the last screen row (typically a status bar) satisfies the predicate or the timeout is reached.
+pub fn wait_for_last_row(kitty: &KittyHarness, timeout: Duration, predicate: impl Fn(&str) -> bool) -> String {
+	let (_cols, rows) = kitty.size();
+	wait_for_row(kitty, usize::from(rows.saturating_sub(1)), timeout, predicate)
+}
+
+fn row_text(clean: &str, row: usize) -> String {
+	clean.lines().nth(row).unwrap_or_default().to_string()
+}
+
 /// Wait until the cleaned screen text contains the provided substring.
 pub fn wait_for_clean_contains(kitty: &KittyHarness, timeout: Duration, needle: &str) -> String {
 	let (_raw, clean) = wait_for_screen_text_clean(kitty, timeout, |_raw, clean| clean.contains(needle));
 	clean
 }
 
+/// A named condition over a `(raw, clean)` screen capture, composable with [`wait_all`] and [`wait_any`].
+///
+/// Conditions can be negated with `!condition` (or [`WaitCondition::negate`]),
+/// e.g. to wait for a spinner to disappear.
+pub struct WaitCondition {
+	description: String,
+	check: Box<CaptureCheck>,
+}
+
+type CaptureCheck = dyn Fn(&str, &str) -> bool;
+
+impl WaitCondition {
+	/// Build a condition from a description and a `(raw, clean)` predicate.
+	pub fn new(description: impl Into<String>, check: impl Fn(&str, &str) -> bool + 'static) -> Self {
+		Self {
+			description: description.into(),
+			check: Box::new(check),
+		}
+	}
+
+	/// Holds when the cleaned screen text contains `needle`.
+	pub fn contains(needle: impl Into<String>) -> Self {
+		let needle = needle.into();
+		Self::new(format!("screen contains {needle:?}"), move |_raw, clean| clean.contains(&needle))
+	}
+
+	/// Holds when the cleaned screen text does not contain `needle`.
+	pub fn absent(needle: impl Into<String>) -> Self {
+		Self::contains(needle).negate()
+	}
+
+	/// Holds when cleaned screen row `row` (0-based) contains `needle`.
+	pub fn row_contains(row: usize, needle: impl Into<String>) -> Self {
+		let needle = needle.into();
+		Self::new(format!("row {row} contains {needle:?}"), move |_raw, clean| {
+			row_text(clean, row).contains(&needle)
+		})
+	}
+
+	/// Invert the condition.
+	pub fn negate(self) -> Self {
+		let check = self.check;
+		Self {
+			description: format!("not ({})", self.description),
+			check: Box::new(move |raw, clean| !check(raw, clean)),
+		}
+	}
+
+	/// Human-readable description of the condition.
+	pub fn description(&self) -> &str {
+		&self.description
+	}
+
+	/// Evaluate the condition against a capture.
+	pub fn holds(&self, raw: &str, clean: &str) -> bool {
+		(self.check)(raw, clean)
+	}
+}
+
+impl Not for WaitCondition {
+	type Output = Self;
+
+	fn not(self) -> Self {
+		self.negate()
+	}
+}
+
+impl fmt::Debug for WaitCondition {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("WaitCondition").field(&self.description).finish()
+	}
+}
+
+/// Wait until every condition holds on the same capture, polling the screen once per iteration.
+///
+/// Returns the matching `(raw, clean)` capture.
+pub fn wait_all(kitty: &KittyHarness, timeout: Duration, conditions: impl IntoIterator<Item = WaitCondition>) -> Result<(String, String), WaitTimeout> {
+	let conditions: Vec<_> = conditions.into_iter().collect();
+	wait_for_screen_text_clean_or_timeout(kitty, timeout, |raw, clean| conditions.iter().all(|c| c.holds(raw, clean)))
+		.map_err(|err| err.described(join_descriptions(&conditions, " and ")))
+}
+
+/// Wait until any condition holds, polling the screen once per iteration.
+///
+/// Returns the index of the first condition that held along with the `(raw, clean)` capture.
+pub fn wait_any(
+	kitty: &KittyHarness,
+	timeout: Duration,
+	conditions: impl IntoIterator<Item = WaitCondition>,
+) -> Result<(usize, (String, String)), WaitTimeout> {
+	let conditions: Vec<_> = conditions.into_iter().collect();
+	let capture = wait_for_screen_text_clean_or_timeout(kitty, timeout, |raw, clean| conditions.iter().any(|c| c.holds(raw, clean)))
+		.map_err(|err| err.described(join_descriptions(&conditions, " or ")))?;
+	let idx = conditions.iter().position(|c| c.holds(&capture.0, &capture.1)).unwrap_or_default();
+	Ok((idx, capture))
+}
+
+/// Wait until the parsed [`ScreenGrid`] satisfies the predicate, returning the matching grid.
+pub fn wait_for_grid(kitty: &KittyHarness, timeout: Duration, description: &str, predicate: impl Fn(&ScreenGrid) -> bool) -> Result<ScreenGrid, WaitTimeout> {
+	wait_for_screen_text_clean_or_timeout(kitty, timeout, |raw, _clean| predicate(&ScreenGrid::parse(raw)))
+		.map(|(raw, _clean)| ScreenGrid::parse(&raw))
+		.map_err(|err| err.described(description))
+}
+
+/// Wait until the cell at (`row`, `col`) satisfies the predicate, e.g. `|cell| cell.attrs.reverse`.
+///
+/// Out-of-range cells never match.
+pub fn wait_for_cell(kitty: &KittyHarness, row: usize, col: usize, timeout: Duration, predicate: impl Fn(&Cell) -> bool) -> Result<Cell, WaitTimeout> {
+	let description = format!("cell ({row}, {col}) style");
+	let grid = wait_for_grid(kitty, timeout, &description, |grid| grid.cell(row, col).is_some_and(&predicate))?;
+	Ok(*grid.cell(row, col).expect("matched cell is in range"))
+}
+
+/// Wait until `needle` is on screen and every one of its cells satisfies the predicate.
+///
+/// Returns the (row, col) of the styled occurrence. Useful for "the
+/// selected item turns reverse-video" without scraping escape sequences.
+pub fn wait_for_styled_text(kitty: &KittyHarness, needle: &str, timeout: Duration, predicate: impl Fn(&Cell) -> bool) -> Result<(usize, usize), WaitTimeout> {
+	let description = format!("{needle:?} with the expected style");
+	let grid = wait_for_grid(kitty, timeout, &description, |grid| styled_match(grid, needle, &predicate).is_some())?;
+	Ok(styled_match(&grid, needle, &predicate).unwrap_or_default())
+}
+
+fn styled_match(grid: &ScreenGrid, needle: &str, predicate: impl Fn(&Cell) -> bool) -> Option<(usize, usize)> {
+	let needle: Vec<char> = needle.chars().collect();
+	if needle.is_empty() {
+		return None;
+	}
+	grid.rows().iter().enumerate().find_map(|(row, cells)| {
+		cells
+			.windows(needle.len())
+			.position(|window| window.iter().zip(&needle).all(|(cell, ch)| cell.ch == *ch && predicate(cell)))
+			.map(|col| (row, col))
+	})
+}
+
+/// Which side of [`wait_for_log_or_screen`] fired first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogOrScreen {
+	/// A matching line in the log file.
+	Log(String),
+	/// The cleaned screen text that satisfied the screen predicate.
+	Screen(String),
+}
+
+/// Wait for either a matching line in the log at `log_path` or a matching cleaned screen, under one timeout.
+///
+/// The log is checked before each screen capture, so a readiness line the
+/// application writes to its log wins over a screen that matches in the same iteration.
+pub fn wait_for_log_or_screen(
+	kitty: &KittyHarness,
+	log_path: &Path,
+	timeout: Duration,
+	log_predicate: impl Fn(&str) -> bool,
+	screen_predicate: impl Fn(&str) -> bool,
+) -> Result<LogOrScreen, WaitTimeout> {
+	let timeout = kitty.clamp_timeout(timeout);
+	let start = Instant::now();
+	let mut delays = kitty.timing().poll_delays();
+
+	loop {
+		if let Some(line) = read_test_log(log_path).into_iter().find(|line| log_predicate(line)) {
+			return Ok(LogOrScreen::Log(line));
+		}
+		let (raw, clean) = kitty.screen_text_clean();
+		if screen_predicate(&clean) {
+			return Ok(LogOrScreen::Screen(clean));
+		}
+
+		let elapsed = start.elapsed();
+		if elapsed > timeout {
+			return Err(WaitTimeout::clean(elapsed, timeout, raw, clean).described(format!("log line in {} or screen condition", log_path.display())));
+		}
+
+		delays.sleep();
+	}
+}
+
+fn join_descriptions(conditions: &[WaitCondition], sep: &str) -> String {
+	conditions.iter().map(WaitCondition::description).collect::<Vec<_>>().join(sep)
+}
+
+/// Screen state captured by [`KittyHarness::checkpoint`] for later comparison.
+#[derive(Debug, Clone)]
+pub struct ScreenCheckpoint {
+	/// Raw screen text at the time of the checkpoint.
+	pub raw: String,
+	/// When the checkpoint was taken.
+	pub taken_at: Instant,
+}
+
+/// How long [`assert_changed_since`] waits for the screen to change.
+pub const CHANGE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long [`assert_unchanged_since`] watches the screen for unexpected changes.
+pub const SETTLE_WINDOW: Duration = Duration::from_millis(250);
+
+/// Take a checkpoint of the current screen contents.
+pub fn checkpoint(kitty: &KittyHarness) -> ScreenCheckpoint {
+	ScreenCheckpoint {
+		raw: kitty.screen_text(),
+		taken_at: Instant::now(),
+	}
+}
+
+/// Assert that the screen changes from the checkpoint within [`CHANGE_TIMEOUT`].
+///
+/// Returns the first capture that differs from the checkpoint. Changes
+/// inside the harness masks ([`KittyHarness::set_masks`]) are ignored.
+pub fn assert_changed_since(kitty: &KittyHarness, checkpoint: &ScreenCheckpoint) -> String {
+	let baseline = kitty.apply_masks(&checkpoint.raw);
+	match wait_for_screen_text_or_timeout(kitty, CHANGE_TIMEOUT, |text| kitty.apply_masks(text) != baseline) {
+		Ok(text) => text,
+		Err(err) => panic!(
+			"expected screen to change since checkpoint, but it stayed the same for {:?}:\n{}",
+			err.elapsed, checkpoint.raw
+		),
+	}
+}
+
+/// Assert that the screen stays identical to the checkpoint for [`SETTLE_WINDOW`].
+///
+/// Changes inside the harness masks ([`KittyHarness::set_masks`]) are ignored.
+pub fn assert_unchanged_since(kitty: &KittyHarness, checkpoint: &ScreenCheckpoint) {
+	let baseline = kitty.apply_masks(&checkpoint.raw);
+	if let Ok(changed) = wait_for_screen_text_or_timeout(kitty, SETTLE_WINDOW, |text| kitty.apply_masks(text) != baseline) {
+		panic!(
+			"expected screen to stay unchanged since checkpoint\n--- checkpoint ---\n{}\n--- now ---\n{}",
+			checkpoint.raw, changed
+		);
+	}
+}
+
+/// A cleaned screen row that differs between a checkpoint and a later capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowChange {
+	/// 0-based row index.
+	pub row: usize,
+	/// Cleaned row text at the checkpoint (empty past the end of the capture).
+	pub before: String,
+	/// Cleaned row text in the new capture.
+	pub after: String,
+}
+
+/// The capture returned by [`wait_for_screen_change`] and how it differs from the baseline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenChange {
+	/// Raw text of the changed capture.
+	pub raw: String,
+	/// Cleaned text of the changed capture.
+	pub clean: String,
+	/// Rows whose cleaned text differs, in order. Empty if only styling changed.
+	pub rows: Vec<RowChange>,
+}
+
+/// Wait until the screen differs from `baseline`, returning the new capture with a per-row diff.
+///
+/// Unlike [`assert_changed_since`] this takes an explicit timeout and
+/// reports *what* changed, for "press a key, something should happen"
+/// tests that do not know the exact outcome. Changes inside the harness
+/// masks ([`KittyHarness::set_masks`]) neither end the wait nor appear in
+/// `rows`.
+pub fn wait_for_screen_change(kitty: &KittyHarness, baseline: &ScreenCheckpoint, timeout: Duration) -> Result<ScreenChange, WaitTimeout> {
+	let masked = kitty.apply_masks(&baseline.raw);
+	let (raw, clean) = wait_for_screen_text_clean_or_timeout(kitty, timeout, |raw, _clean| kitty.apply_masks(raw) != masked)
+		.map_err(|err| err.described("screen to change since checkpoint"))?;
+	let rows = diff_rows(&strip_ansi(&masked), &kitty.apply_masks(&clean));
+	Ok(ScreenChange { raw, clean, rows })
+}
+
+fn diff_rows(before: &str, after: &str) -> Vec<RowChange> {
+	let count = before.lines().count().max(after.lines().count());
+	(0..count)
+		.map(|row| RowChange {
+			row,
+			before: row_text(before, row),
+			after: row_text(after, row),
+		})
+		.filter(|change| change.before != change.after)
+		.collect()
+}
+
+/// Rapidly sample the screen for a duration into a deduplicated [`FrameTimeline`].
+///
+/// Like [`sample_screen_rapidly`], but identical consecutive captures are
+/// merged, so memory grows with the number of distinct frames rather than
+/// with the sampling rate. Timestamps are taken when each capture returns.
+pub fn sample_frames(kitty: &KittyHarness, duration: Duration) -> FrameTimeline {
+	let start = Instant::now();
+	let mut timeline = FrameTimeline::new();
+
+	while start.elapsed() < duration {
+		let (raw, clean) = kitty.screen_text_clean();
+		timeline.push(start.elapsed(), raw, clean);
+	}
+
+	timeline
+}
+
 /// Rapidly sample the screen for a duration, collecting all captured frames.
 ///
 /// This is useful for catching transient states like animations. The function
@@ -133,3 +608,62 @@ pub fn sample_screen_rapidly(kitty: &KittyHarness, duration: Duration) -> Vec<(D
 
 	samples
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn row_text_picks_one_line() {
+		assert_eq!(row_text("a\nb\nc", 1), "b");
+		assert_eq!(row_text("a\nb", 5), "");
+	}
+
+	#[test]
+	fn conditions_compose() {
+		let ready = WaitCondition::contains("ready");
+		let spinner_gone = !WaitCondition::contains("⠋");
+		assert!(ready.holds("", "ready\n"));
+		assert!(!spinner_gone.holds("", "⠋ loading"));
+		assert_eq!(spinner_gone.description(), "not (screen contains \"⠋\")");
+		assert!(WaitCondition::row_contains(1, "ok").holds("", "x\nok"));
+		assert!(!WaitCondition::absent("x").holds("", "x"));
+	}
+
+	#[test]
+	fn diff_rows_reports_changed_lines() {
+		let rows = diff_rows("a\nb\nc", "a\nB\nc\nd");
+		assert_eq!(
+			rows,
+			vec![
+				RowChange {
+					row: 1,
+					before: "b".into(),
+					after: "B".into()
+				},
+				RowChange {
+					row: 3,
+					before: String::new(),
+					after: "d".into()
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn styled_match_skips_unstyled_occurrences() {
+		let grid = ScreenGrid::parse("item\n\x1b[7mitem\x1b[0m");
+		assert_eq!(styled_match(&grid, "item", |cell| cell.attrs.reverse), Some((1, 0)));
+		assert_eq!(styled_match(&grid, "item", |cell| cell.attrs.bold), None);
+	}
+
+	#[test]
+	fn timeout_display_names_condition_and_last_screen() {
+		let err = WaitTimeout::clean(Duration::from_secs(1), Duration::from_secs(1), "raw".into(), "$ ls".into()).described("screen to contain \"done\"");
+		let message = err.to_string();
+		assert!(message.ends_with("waiting for screen to contain \"done\""), "{message}");
+		assert!(!message.contains('\n'), "{message}");
+		let message = format!("{err:#}");
+		assert!(message.ends_with("--- last screen ---\n$ ls"), "{message}");
+	}
+}
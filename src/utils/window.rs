@@ -1,10 +1,18 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
 use std::process::Command;
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use kitty_remote_bindings::command::{CommandOutput, Ls};
 use kitty_remote_bindings::model::WindowId;
 
+use crate::KittyHarness;
+use crate::utils::error::HarnessError;
+use crate::utils::screen::{Cursor, ScreenGrid};
+use crate::utils::signal::Signal;
+use crate::utils::timing::Timing;
+
 /// Check if we should use kitty panel (requires Wayland with layer-shell).
 /// Falls back to normal window if not on Wayland or if layer-shell is unavailable.
 ///
@@ -37,19 +45,234 @@ pub(crate) fn should_use_panel() -> bool {
 	false
 }
 
-pub(crate) fn wait_for_window(socket_addr: &str) -> WindowId {
-	for _ in 0..40 {
+/// Why kitty remote control could not be used after launch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteControlError {
+	/// Nothing answered on the socket (kitty failed to start or is listening elsewhere).
+	Unreachable {
+		/// Output of the last remote control attempt.
+		stderr: String,
+	},
+	/// kitty answered but refused the command.
+	Rejected {
+		/// Output of the last remote control attempt.
+		stderr: String,
+	},
+	/// Remote control works but kitty reported no windows.
+	NoWindow,
+}
+
+impl fmt::Display for RemoteControlError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RemoteControlError::Unreachable { stderr } => write!(f, "kitty remote control socket not reachable: {}", stderr.trim()),
+			RemoteControlError::Rejected { stderr } => write!(
+				f,
+				"remote control rejected — check allow_remote_control / remote_control_password in your kitty config: {}",
+				stderr.trim()
+			),
+			RemoteControlError::NoWindow => write!(f, "kitty remote control works but no window was found"),
+		}
+	}
+}
+
+impl Error for RemoteControlError {}
+
+/// Classify a failed `kitty @ ls` invocation from its stderr.
+///
+/// Only kitty's own remote control refusals count as rejections; OS errors
+/// such as `Permission denied` on the socket mean it could not be reached.
+pub(crate) fn classify_remote_failure(stderr: &str) -> RemoteControlError {
+	let lower = stderr.to_ascii_lowercase();
+	let rejected = lower.contains("password")
+		|| (lower.contains("remote control") && ["disabled", "rejected", "denied", "not allowed"].iter().any(|word| lower.contains(word)));
+	if rejected {
+		RemoteControlError::Rejected { stderr: stderr.to_string() }
+	} else {
+		RemoteControlError::Unreachable { stderr: stderr.to_string() }
+	}
+}
+
+/// Shut down a kitty instance launched as `session` whose remote control never became usable.
+///
+/// Closes its windows over `socket_addr` when the socket still answers,
+/// then kills any process left with the session's `--class`, and removes
+/// the socket file.
+pub(crate) fn close_failed_launch(socket_addr: &str, session: &str, socket: Option<&Path>) {
+	let _ = Command::new("kitty")
+		.args(["@", "--to", socket_addr, "close-window", "--match", "all"])
+		.output();
+	let _ = Command::new("pkill").args(["-f", "--", &format!("--class {session}( |$)")]).output();
+	if let Some(socket) = socket {
+		let _ = std::fs::remove_file(socket);
+	}
+}
+
+/// How long [`verify_remote_control`] keeps retrying while kitty starts up.
+const REMOTE_CONTROL_STARTUP: Duration = Duration::from_secs(4);
+
+/// Verify that remote control is permitted on `socket_addr` and return the first window id.
+///
+/// Retries every 100ms for a few seconds while kitty starts up, then
+/// reports why the last attempt failed.
+pub fn verify_remote_control(socket_addr: &str) -> Result<WindowId, RemoteControlError> {
+	verify_remote_control_with(socket_addr, &Timing::fixed(Duration::from_millis(100), Duration::ZERO))
+}
+
+/// Like [`verify_remote_control`], but spaces the retries with the poll delays of `timing`.
+pub(crate) fn verify_remote_control_with(socket_addr: &str, timing: &Timing) -> Result<WindowId, RemoteControlError> {
+	let start = Instant::now();
+	let mut delays = timing.poll_delays();
+	let mut last_error = RemoteControlError::Unreachable { stderr: String::new() };
+	while start.elapsed() < REMOTE_CONTROL_STARTUP {
 		let ls = Ls::new().to(socket_addr.to_string());
 		let mut cmd: Command = (&ls).into();
-		if let Ok(output) = cmd.output()
-			&& let Ok(os_windows) = Ls::result(&output)
-			&& let Some(id) = first_window_id(os_windows)
-		{
-			return id;
+		match cmd.output() {
+			Ok(output) if output.status.success() => match Ls::result(&output).ok().and_then(first_window_id) {
+				Some(id) => return Ok(id),
+				None => last_error = RemoteControlError::NoWindow,
+			},
+			Ok(output) => {
+				last_error = classify_remote_failure(&String::from_utf8_lossy(&output.stderr));
+				// A rejection will not fix itself by retrying.
+				if matches!(last_error, RemoteControlError::Rejected { .. }) {
+					return Err(last_error);
+				}
+			}
+			Err(err) => last_error = RemoteControlError::Unreachable { stderr: err.to_string() },
 		}
-		thread::sleep(Duration::from_millis(100));
+		delays.sleep();
 	}
-	panic!("kitty remote control not reachable or window not found");
+	Err(last_error)
+}
+
+/// A handle to one window of a harness's kitty instance.
+///
+/// Returned by [`KittyHarness::launch_window`] for windows the test opens and
+/// by [`KittyHarness::window`] for windows the application opened itself
+/// (file pickers, helper panes). All windows are closed when the harness is
+/// dropped.
+#[derive(Clone, Copy)]
+pub struct KittyWindow<'a> {
+	harness: &'a KittyHarness,
+	id: WindowId,
+}
+
+impl<'a> KittyWindow<'a> {
+	pub(crate) fn new(harness: &'a KittyHarness, id: WindowId) -> Self {
+		Self { harness, id }
+	}
+
+	/// Return the kitty window id.
+	pub fn id(&self) -> WindowId {
+		self.id
+	}
+
+	/// Return the harness that owns this window.
+	pub fn harness(&self) -> &'a KittyHarness {
+		self.harness
+	}
+
+	/// Send raw text to this window.
+	pub fn send_text(&self, text: &str) {
+		self.harness.send_text_to_window(self.id, text)
+	}
+
+	/// Like [`KittyWindow::send_text`], but returns an error instead of panicking.
+	pub fn try_send_text(&self, text: &str) -> Result<(), HarnessError> {
+		self.harness.try_send_text_to_window(self.id, text)
+	}
+
+	/// Send arbitrary bytes (not necessarily UTF-8) to this window.
+	pub fn send_bytes(&self, bytes: &[u8]) {
+		self.harness.send_bytes_to_window(self.id, bytes)
+	}
+
+	/// Like [`KittyWindow::send_bytes`], but returns an error instead of panicking.
+	pub fn try_send_bytes(&self, bytes: &[u8]) -> Result<(), HarnessError> {
+		self.harness.try_send_bytes_to_window(self.id, bytes)
+	}
+
+	/// Capture this window's screen as ANSI text.
+	pub fn screen_text(&self) -> String {
+		self.harness.screen_text_for_window(self.id)
+	}
+
+	/// Like [`KittyWindow::screen_text`], but returns an error instead of panicking.
+	pub fn try_screen_text(&self) -> Result<String, HarnessError> {
+		self.harness.try_screen_text_for_window(self.id)
+	}
+
+	/// Capture this window's screen text and a variant with ANSI escapes stripped.
+	pub fn screen_text_clean(&self) -> (String, String) {
+		self.harness.screen_text_clean_for_window(self.id)
+	}
+
+	/// Like [`KittyWindow::screen_text_clean`], but returns an error instead of panicking.
+	pub fn try_screen_text_clean(&self) -> Result<(String, String), HarnessError> {
+		self.harness.try_screen_text_clean_for_window(self.id)
+	}
+
+	/// Capture this window's screen as a [`ScreenGrid`].
+	pub fn screen_grid(&self) -> ScreenGrid {
+		ScreenGrid::parse(&self.screen_text())
+	}
+
+	/// Return this window's cursor position and visibility.
+	pub fn cursor(&self) -> Cursor {
+		self.harness.cursor_for_window(self.id)
+	}
+
+	/// Return this window's size in cells as `(cols, rows)`.
+	pub fn size(&self) -> (u16, u16) {
+		self.harness.size_for_window(self.id)
+	}
+
+	/// Return this window's title.
+	pub fn title(&self) -> String {
+		self.harness.title_for_window(self.id)
+	}
+
+	/// Set this window's title (see [`KittyHarness::set_title`]).
+	pub fn set_title(&self, title: &str) {
+		self.harness.set_title_for_window(self.id, title)
+	}
+
+	/// Send `signal` to this window's foreground process.
+	pub fn signal_child(&self, signal: Signal) {
+		self.try_signal_child(signal).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyWindow::signal_child`], but returns an error instead of panicking.
+	pub fn try_signal_child(&self, signal: Signal) -> Result<(), HarnessError> {
+		self.harness.try_signal_child_for_window(self.id, signal)
+	}
+
+	/// Close this window.
+	pub fn close(self) {
+		self.try_close().unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// Like [`KittyWindow::close`], but returns an error instead of panicking.
+	pub fn try_close(self) -> Result<(), HarnessError> {
+		self.harness
+			.try_remote(["close-window".to_string(), "--match".to_string(), format!("id:{}", self.id.0)])
+			.map(|_| ())
+	}
+}
+
+impl std::fmt::Debug for KittyWindow<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("KittyWindow")
+			.field("session", &self.harness.session_name())
+			.field("id", &self.id.0)
+			.finish()
+	}
+}
+
+/// Parse the window id printed by `kitty @ launch`.
+pub(crate) fn parse_launched_window_id(stdout: &[u8]) -> Option<WindowId> {
+	String::from_utf8_lossy(stdout).trim().parse().ok().map(WindowId)
 }
 
 pub(crate) fn first_window_id(ls: kitty_remote_bindings::model::OsWindows) -> Option<WindowId> {
@@ -58,3 +281,41 @@ pub(crate) fn first_window_id(ls: kitty_remote_bindings::model::OsWindows) -> Op
 		.and_then(|tab| tab.windows.first())
 		.map(|win| win.id)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn launched_window_id_is_parsed_from_stdout() {
+		assert_eq!(parse_launched_window_id(b"7\n"), Some(WindowId(7)));
+		assert_eq!(parse_launched_window_id(b""), None);
+	}
+
+	#[test]
+	fn classify_rejections() {
+		for stderr in [
+			"Error: Remote control is disabled, add allow_remote_control to kitty.conf",
+			"This remote control request requires a password",
+			"Remote control is not allowed for this window",
+		] {
+			assert!(matches!(classify_remote_failure(stderr), RemoteControlError::Rejected { .. }), "{stderr}");
+		}
+	}
+
+	#[test]
+	fn classify_unreachable() {
+		for stderr in [
+			"Failed to connect to unix:/tmp/x.sock: Connection refused",
+			"Failed to connect to unix:/tmp/x.sock: [Errno 13] Permission denied",
+		] {
+			assert!(matches!(classify_remote_failure(stderr), RemoteControlError::Unreachable { .. }), "{stderr}");
+		}
+	}
+
+	#[test]
+	fn rejected_message_mentions_config() {
+		let message = RemoteControlError::Rejected { stderr: "nope".into() }.to_string();
+		assert!(message.contains("allow_remote_control"));
+	}
+}
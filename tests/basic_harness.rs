@@ -5,7 +5,10 @@
 use std::path::PathBuf;
 use std::time::Duration;
 
-use kitty_test_harness::{KeyPress, kitty_send_keys, wait_for_ready_marker, wait_for_screen_text, wait_for_screen_text_clean, with_kitty_capture};
+use kitty_test_harness::{
+	KeyPress, KittyPool, LaunchOptions, Signal, kitty_send_keys, run_command, wait_for_prompt, wait_for_ready_marker, wait_for_screen_text,
+	wait_for_screen_text_clean, with_kitty_capture, with_kitty_capture_options,
+};
 use termwiz::input::KeyCode;
 
 #[test]
@@ -97,3 +100,229 @@ fn key_press_with_modifiers() {
 		assert!(output.contains("^C"), "expected ^C marker after sending Ctrl+C, got:\n{output}");
 	});
 }
+
+#[test]
+#[ignore = "example test"]
+fn checkpoint_detects_changes() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "bash", |kitty| {
+		wait_for_ready_marker(kitty);
+
+		let before = kitty.checkpoint();
+		kitty.assert_unchanged_since(&before);
+
+		kitty.send_text("echo changed\n");
+		let after = kitty.assert_changed_since(&before);
+		assert_ne!(after, before.raw);
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn cursor_follows_typed_text() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "bash", |kitty| {
+		wait_for_ready_marker(kitty);
+
+		let before = kitty.cursor();
+		kitty.send_text("abc");
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.contains("abc"));
+		let after = kitty.cursor();
+		assert_eq!(after.row, before.row);
+		assert_eq!(after.col, before.col + 3);
+		assert!(after.visible);
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn helper_window_is_driven_separately() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "bash", |kitty| {
+		wait_for_ready_marker(kitty);
+
+		let helper = kitty.launch_window("bash");
+		helper.send_text("echo from-helper\n");
+		std::thread::sleep(Duration::from_millis(300));
+
+		assert!(helper.screen_text_clean().1.contains("from-helper"));
+		assert!(!kitty.screen_text_clean().1.contains("from-helper"));
+		helper.close();
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn osc_title_is_reported() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "bash", |kitty| {
+		wait_for_ready_marker(kitty);
+		kitty.send_text("printf '\\033]2;notes.txt\\007'\n");
+		kitty.wait_for_title(Duration::from_secs(2), |title| title == "notes.txt");
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn clipboard_round_trip() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture_options(&working_dir, "bash", &LaunchOptions::new().clipboard(), |kitty| {
+		wait_for_ready_marker(kitty);
+		kitty.set_clipboard("copied from test");
+		assert_eq!(kitty.get_clipboard(), "copied from test");
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn pooled_instances_are_reused() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+	let pool = KittyPool::new(Default::default());
+
+	let first_session = {
+		let kitty = pool.acquire(&working_dir, "bash");
+		wait_for_ready_marker(&kitty);
+		kitty.send_text("echo first\n");
+		kitty.session_name().to_string()
+	};
+	assert_eq!(pool.idle_count(), 1);
+
+	let kitty = pool.acquire(&working_dir, "bash");
+	wait_for_ready_marker(&kitty);
+	assert_eq!(kitty.session_name(), first_session);
+	assert!(!kitty.screen_text_clean().1.contains("first"));
+}
+
+#[test]
+#[ignore = "example test"]
+fn size_matches_stty() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "bash", |kitty| {
+		wait_for_ready_marker(kitty);
+		let (cols, rows) = kitty.size();
+		kitty.send_text("stty size\n");
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.contains(&format!("{rows} {cols}")));
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn raw_bytes_reach_the_application() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "od -An -tx1", |kitty| {
+		kitty.send_bytes(b"\xff\xfe\x1b[\n");
+		// Ctrl+D ends od's input so it prints the dump.
+		kitty.send_text("\x04");
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.contains("ff fe 1b 5b"));
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn click_text_targets_the_label() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	// Enable SGR mouse reporting and echo the raw click sequence back.
+	let script = r"printf '\e[?1000h\e[?1006h   [ Save ]\n'; stty -echo raw; head -c 12 | od -An -c";
+	with_kitty_capture(&working_dir, script, |kitty| {
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.contains("Save"));
+		let (col, row) = kitty.click_text("Save");
+		assert_eq!((col, row), (6, 0));
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.contains("0   ;   7   ;   1"));
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn signal_child_reaches_trap() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "trap 'echo got-term' TERM; echo ready; while :; do sleep 0.1; done", |kitty| {
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.contains("ready"));
+		kitty.signal_child(Signal::Term);
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.contains("got-term"));
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn scrollback_keeps_lines_scrolled_off_screen() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "seq -f 'line-%g' 1 200; sleep 30", |kitty| {
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.contains("line-200"));
+		assert!(!kitty.screen_text().contains("line-1\n"));
+		kitty.scroll_to_start();
+		let history = kitty.scrollback_text();
+		assert!(history.contains("line-1\n"), "scrollback should keep the first lines:\n{history}");
+		kitty.scroll_to_end();
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn run_command_reports_exit_status() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	// Needs kitty shell integration in the launched bash.
+	with_kitty_capture(&working_dir, "bash", |kitty| {
+		wait_for_prompt(kitty, Duration::from_secs(5)).expect("shell integration prompt");
+		assert_eq!(run_command(kitty, "true", Duration::from_secs(5)).unwrap(), Some(0));
+		assert_eq!(run_command(kitty, "(exit 3)", Duration::from_secs(5)).unwrap(), Some(3));
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn argv_command_preserves_arguments() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	let output = with_kitty_capture(&working_dir, ["printf", "%s|%s\\n", "it's $HOME", "a  b"], |kitty| {
+		wait_for_screen_text(kitty, Duration::from_secs(2), |text| text.contains('|'))
+	});
+
+	assert!(output.contains("it's $HOME|a  b"), "argv should pass arguments verbatim, got:\n{output}");
+}
+
+#[test]
+#[ignore = "example test"]
+fn watch_screen_while_typing() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "bash", |kitty| {
+		wait_for_ready_marker(kitty);
+
+		let (watcher, frames) = kitty.watch_screen_channel(Duration::from_millis(20));
+		kitty_test_harness::type_string(kitty, "echo watched\n");
+		wait_for_screen_text(kitty, Duration::from_secs(2), |text| text.contains("watched"));
+		let delivered = watcher.stop();
+
+		let frames: Vec<_> = frames.try_iter().collect();
+		assert_eq!(frames.len(), delivered);
+		assert!(
+			frames.iter().all(|(_, _, clean)| !clean.is_empty()),
+			"screen should never go blank while typing"
+		);
+	});
+}
+
+#[test]
+#[ignore = "example test"]
+fn expect_screen_chains_matchers() {
+	let working_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+
+	with_kitty_capture(&working_dir, "bash", |kitty| {
+		wait_for_ready_marker(kitty);
+
+		kitty.send_text("clear; printf 'ready'\n");
+		wait_for_screen_text_clean(kitty, Duration::from_secs(2), |_raw, clean| clean.starts_with("ready"));
+		kitty_test_harness::expect_screen(kitty).contains("ready").at(0, 0).not_contains("ERROR");
+	});
+}